@@ -1,35 +1,477 @@
+use axum::extract::rejection::JsonRejection;
 use axum::extract::{
-    Path, State,
+    DefaultBodyLimit, FromRequest, Path, Query, Request, State,
     ws::{Message, WebSocket, WebSocketUpgrade},
 };
-use axum::http::StatusCode;
-use axum::response::IntoResponse;
-use axum::routing::{delete, get};
+use axum::http::{HeaderValue, StatusCode, header};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post, put};
 use axum::{Json, Router};
-use serde::{Deserialize, Serialize};
-use sqlx::FromRow;
-use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions};
+use regex::Regex;
+use serde::{Deserialize, Serialize, Serializer};
+use sqlx::sqlite::{
+    Sqlite, SqliteConnectOptions, SqliteJournalMode, SqlitePool, SqlitePoolOptions,
+    SqliteSynchronous,
+};
+use sqlx::{FromRow, QueryBuilder};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
 use std::net::SocketAddr;
 use std::path::PathBuf;
 use std::str::FromStr;
+use std::sync::Arc;
 use std::time::Duration;
-use time::OffsetDateTime;
+use subtle::ConstantTimeEq;
+use time::{Date, Month, OffsetDateTime};
 use tokio::signal;
 use tokio::sync::broadcast;
-use tracing::{Level, info};
+use tracing::{Instrument, Level, info, info_span, warn};
+use tower_http::timeout::TimeoutLayer;
+use tracing_subscriber::EnvFilter;
+use tracing_subscriber::util::SubscriberInitExt;
 use uuid::Uuid;
 
+#[cfg(test)]
+mod tests;
+
 type AppResult<T> = Result<Json<T>, (StatusCode, String)>;
+// For create/update handlers that report every invalid field at once.
+type FormResult<T> = Result<Json<T>, FormError>;
+
+enum FormError {
+    Status(StatusCode, String),
+    // 422 with `{ "errors": { field: message } }`.
+    Fields(BTreeMap<&'static str, String>),
+}
+
+impl From<(StatusCode, String)> for FormError {
+    fn from((status, message): (StatusCode, String)) -> Self {
+        FormError::Status(status, message)
+    }
+}
+
+impl IntoResponse for FormError {
+    fn into_response(self) -> Response {
+        match self {
+            FormError::Status(status, message) => (status, message).into_response(),
+            FormError::Fields(errors) => (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                Json(serde_json::json!({ "errors": errors })),
+            )
+                .into_response(),
+        }
+    }
+}
+
+// Collects every field's first error instead of stopping at the first bad field.
+#[derive(Default)]
+struct FieldErrors(BTreeMap<&'static str, String>);
+
+impl FieldErrors {
+    fn add(&mut self, field: &'static str, message: String) {
+        self.0.entry(field).or_insert(message);
+    }
+
+    fn check<T>(&mut self, field: &'static str, result: Result<T, (StatusCode, String)>) -> Option<T> {
+        match result {
+            Ok(value) => Some(value),
+            Err((_, message)) => {
+                self.add(field, message);
+                None
+            }
+        }
+    }
+
+    fn finish(self) -> Result<(), FormError> {
+        if self.0.is_empty() {
+            Ok(())
+        } else {
+            Err(FormError::Fields(self.0))
+        }
+    }
+}
+
+// Json extractor whose rejections name the offending field.
+struct ValidJson<T>(T);
+
+impl<T, S> FromRequest<S> for ValidJson<T>
+where
+    Json<T>: FromRequest<S, Rejection = JsonRejection>,
+    S: Send + Sync,
+{
+    type Rejection = (StatusCode, String);
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        match Json::<T>::from_request(req, state).await {
+            Ok(Json(value)) => Ok(ValidJson(value)),
+            Err(JsonRejection::JsonDataError(err)) => {
+                let detail = std::error::Error::source(&err)
+                    .map(|source| source.to_string())
+                    .unwrap_or_else(|| err.body_text());
+                Err((
+                    StatusCode::UNPROCESSABLE_ENTITY,
+                    format!("invalid request body: {detail}"),
+                ))
+            }
+            Err(rejection) => Err((rejection.status(), rejection.body_text())),
+        }
+    }
+}
 
 #[derive(Clone)]
 struct AppState {
     pool: SqlitePool,
     notifier: broadcast::Sender<ServerEvent>,
+    admin_enabled: bool,
+    // Set by READ_ONLY; every request that could change data is refused.
+    read_only: bool,
+    // Set by API_KEY; requests must then present it (see api_key_layer).
+    api_key: Option<String>,
+    snapshot_dir: PathBuf,
+    money: MoneyFormat,
+    balance_mode: BalanceMode,
+    // Cap on rows one GET /transactions returns.
+    max_transaction_list: i64,
+}
+
+// Cached keeps accounts.balance up to date; Computed derives balances from history.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum BalanceMode {
+    Cached,
+    Computed,
+}
+
+impl BalanceMode {
+    fn column(self) -> &'static str {
+        match self {
+            BalanceMode::Cached => "accounts.balance",
+            BalanceMode::Computed => COMPUTED_BALANCE_SQL,
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
 enum ServerEvent {
     DataChanged,
+    AccountsChanged,
+    TransactionsChanged,
+    Alert(AlertEvent),
+}
+
+#[derive(Clone, Debug, Serialize)]
+struct AlertEvent {
+    rule_id: String,
+    transaction_id: String,
+    direction: String,
+    amount: f64,
+    threshold: f64,
+}
+
+// How monetary fields are written in JSON responses (`?amount_format=`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum AmountFormat {
+    Number,
+    String,
+    Display,
+}
+
+// Per-request presentation of amounts (`?amount_sign=`, `?amount_format=`).
+#[derive(Clone, Debug)]
+struct AmountStyle {
+    format: AmountFormat,
+    signed: bool,
+    accounts: Vec<String>,
+    money: MoneyFormat,
+    currencies: Arc<BTreeMap<String, String>>,
+}
+
+impl Default for AmountStyle {
+    fn default() -> Self {
+        Self {
+            format: AmountFormat::Number,
+            signed: false,
+            accounts: Vec::new(),
+            money: MoneyFormat::default(),
+            currencies: Arc::default(),
+        }
+    }
+}
+
+tokio::task_local! {
+    static AMOUNT_STYLE: AmountStyle;
+}
+
+fn amount_style() -> AmountStyle {
+    AMOUNT_STYLE.try_with(AmountStyle::clone).unwrap_or_default()
+}
+
+// Currencies of the record being serialized: its own and a transfer's destination.
+#[derive(Clone, Default)]
+struct RecordCurrencies {
+    own: Option<String>,
+    received: Option<String>,
+}
+
+thread_local! {
+    static RECORD_CURRENCIES: RefCell<RecordCurrencies> = RefCell::default();
+}
+
+// Serialization never yields, so the thread-local scope can't leak across requests.
+fn serialize_in_currency<S: Serializer>(
+    currencies: RecordCurrencies,
+    serializer: S,
+    record: impl FnOnce(S) -> Result<S::Ok, S::Error>,
+) -> Result<S::Ok, S::Error> {
+    let outer = RECORD_CURRENCIES.replace(currencies);
+    let result = record(serializer);
+    RECORD_CURRENCIES.set(outer);
+    result
+}
+
+fn currency_of(account_id: &str) -> Option<String> {
+    amount_style().currencies.get(account_id).cloned()
+}
+
+fn serialize_amount<S: Serializer>(value: &f64, serializer: S) -> Result<S::Ok, S::Error> {
+    write_amount(*value, false, serializer)
+}
+
+// What a transfer delivers, in the destination account's currency.
+fn serialize_received_amount<S: Serializer>(
+    value: &Option<f64>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match value {
+        Some(v) => write_amount(*v, true, serializer),
+        None => serializer.serialize_none(),
+    }
+}
+
+fn write_amount<S: Serializer>(value: f64, received: bool, serializer: S) -> Result<S::Ok, S::Error> {
+    let style = amount_style();
+    let currency = || {
+        RECORD_CURRENCIES
+            .with_borrow(|record| if received { record.received.clone() } else { record.own.clone() })
+            .unwrap_or_else(|| style.money.currency.clone())
+    };
+    match style.format {
+        AmountFormat::Number => serializer.serialize_f64(value),
+        AmountFormat::String => {
+            let currency = currency();
+            let digits = currency_minor_units(&currency) as usize;
+            serializer.serialize_str(&format!("{:.digits$}", round_in_currency(value, &currency)))
+        }
+        AmountFormat::Display => serializer.serialize_str(&style.money.format_in(value, &currency())),
+    }
+}
+
+// Serializes a record with amounts in the currency of its account field(s).
+macro_rules! serialize_in_account_currency {
+    ($record:ty, $account:ident $(, $to_account:ident)?) => {
+        impl Serialize for $record {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                let to_account_id: Option<&str> = None $(.or(self.$to_account.as_deref()))?;
+                let currencies = RecordCurrencies {
+                    own: currency_of(&self.$account),
+                    received: to_account_id.and_then(currency_of),
+                };
+                serialize_in_currency(currencies, serializer, |serializer| {
+                    <$record>::serialize(self, serializer)
+                })
+            }
+        }
+    };
+}
+
+// Number conventions for human-facing money text.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Locale {
+    EnUs,
+    EnGb,
+    DeDe,
+    FrFr,
+}
+
+impl Locale {
+    fn parse(tag: &str) -> Option<Self> {
+        match tag.trim().replace('_', "-").to_ascii_lowercase().as_str() {
+            "en-us" => Some(Locale::EnUs),
+            "en-gb" => Some(Locale::EnGb),
+            "de-de" => Some(Locale::DeDe),
+            "fr-fr" => Some(Locale::FrFr),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            Locale::EnUs => "en-US",
+            Locale::EnGb => "en-GB",
+            Locale::DeDe => "de-DE",
+            Locale::FrFr => "fr-FR",
+        }
+    }
+
+    // (thousands separator, decimal separator, symbol goes after the number)
+    fn conventions(&self) -> (char, char, bool) {
+        match self {
+            Locale::EnUs | Locale::EnGb => (',', '.', false),
+            Locale::DeDe => ('.', ',', true),
+            Locale::FrFr => ('\u{a0}', ',', true),
+        }
+    }
+}
+
+// The configured base currency and locale.
+#[derive(Clone, Debug)]
+struct MoneyFormat {
+    currency: String,
+    locale: Locale,
+}
+
+impl Default for MoneyFormat {
+    fn default() -> Self {
+        Self {
+            currency: DEFAULT_CURRENCY.to_string(),
+            locale: Locale::EnUs,
+        }
+    }
+}
+
+impl MoneyFormat {
+    fn format_in(&self, amount: f64, currency: &str) -> String {
+        let (thousands, decimal, symbol_after) = self.locale.conventions();
+        let digits = currency_minor_units(currency) as usize;
+        let fixed = format!("{:.digits$}", amount.abs());
+        let (whole, minor) = fixed.split_once('.').unwrap_or((&fixed, ""));
+        let mut grouped = String::new();
+        for (idx, digit) in whole.chars().enumerate() {
+            if idx > 0 && (whole.len() - idx) % 3 == 0 {
+                grouped.push(thousands);
+            }
+            grouped.push(digit);
+        }
+        let number = if minor.is_empty() {
+            grouped
+        } else {
+            format!("{grouped}{decimal}{minor}")
+        };
+        let rounds_to_zero = fixed.chars().all(|c| c == '0' || c == '.');
+        let sign = if amount < 0.0 && !rounds_to_zero { "-" } else { "" };
+        match (currency_symbol(currency), symbol_after) {
+            (Some(symbol), false) => format!("{sign}{symbol}{number}"),
+            (None, false) => format!("{sign}{currency} {number}"),
+            (symbol, true) => format!("{sign}{number} {}", symbol.unwrap_or(currency)),
+        }
+    }
+
+    // Reads back what `format_in` writes, so exported CSV can be imported again.
+    fn parse(&self, text: &str) -> Option<f64> {
+        let text = text.trim();
+        if let Ok(value) = text.parse::<f64>() {
+            return Some(value);
+        }
+        let (thousands, decimal, _) = self.locale.conventions();
+        let mut number = String::new();
+        for c in text.chars() {
+            match c {
+                '0'..='9' | '-' => number.push(c),
+                c if c == decimal => number.push('.'),
+                c if c == thousands => {}
+                // Currency symbols and codes.
+                c if c.is_alphabetic() || c.is_whitespace() || "$€£¥".contains(c) => {}
+                _ => return None,
+            }
+        }
+        number.parse().ok()
+    }
+}
+
+fn currency_symbol(code: &str) -> Option<&'static str> {
+    match code {
+        "USD" | "CAD" | "AUD" => Some("$"),
+        "EUR" => Some("€"),
+        "GBP" => Some("£"),
+        "JPY" => Some("¥"),
+        _ => None,
+    }
+}
+
+// A transaction amount with its direction, for signed presentation.
+#[derive(Clone, Debug)]
+struct DirectedAmount {
+    value: f64,
+    flow: Flow,
+}
+
+#[derive(Clone, Debug)]
+enum Flow {
+    In,
+    Out,
+    // Leaves `from` as the amount plus fee and arrives in `to` as `received`.
+    Transfer { from: String, to: String, received: f64, fee: f64 },
+}
+
+impl DirectedAmount {
+    fn new(value: f64, direction: &TransactionDirection) -> Self {
+        let flow = match direction {
+            TransactionDirection::Expense => Flow::Out,
+            _ => Flow::In,
+        };
+        Self { value, flow }
+    }
+
+    fn transfer(value: f64, from: &str, to: &str, received: Option<f64>, fee: f64) -> Self {
+        Self {
+            value,
+            flow: Flow::Transfer {
+                from: from.to_string(),
+                to: to.to_string(),
+                received: received.unwrap_or(value),
+                fee,
+            },
+        }
+    }
+
+    // Transfers count from the source side unless filtered to the destination alone.
+    fn signed(&self, accounts: &[String]) -> f64 {
+        match &self.flow {
+            Flow::In => self.value,
+            Flow::Out => -self.value,
+            Flow::Transfer { received, .. } if self.seen_from_destination(accounts) => *received,
+            Flow::Transfer { fee, .. } => -(self.value + fee),
+        }
+    }
+
+    fn seen_from_destination(&self, accounts: &[String]) -> bool {
+        match &self.flow {
+            Flow::Transfer { from, to, .. } => accounts.contains(to) && !accounts.contains(from),
+            _ => false,
+        }
+    }
+}
+
+impl Serialize for DirectedAmount {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let style = amount_style();
+        if style.signed {
+            let received = self.seen_from_destination(&style.accounts);
+            write_amount(self.signed(&style.accounts), received, serializer)
+        } else {
+            write_amount(self.value, false, serializer)
+        }
+    }
+}
+
+fn serialize_optional_amount<S: Serializer>(
+    value: &Option<f64>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match value {
+        Some(v) => serialize_amount(v, serializer),
+        None => serializer.serialize_none(),
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -58,6 +500,8 @@ enum TransactionDirection {
     Income,
     Expense,
     Transfer,
+    // Money coming back for an earlier expense.
+    Refund,
 }
 
 impl TransactionDirection {
@@ -66,17 +510,70 @@ impl TransactionDirection {
             TransactionDirection::Income => "income",
             TransactionDirection::Expense => "expense",
             TransactionDirection::Transfer => "transfer",
+            TransactionDirection::Refund => "refund",
         }
     }
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug, FromRow)]
+// How an entry came to be recorded; import and recurring are set by those paths only.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+enum TransactionSource {
+    #[default]
+    Manual,
+    Import,
+    Recurring,
+    Api,
+}
+
+impl TransactionSource {
+    fn as_str(self) -> &'static str {
+        match self {
+            TransactionSource::Manual => "manual",
+            TransactionSource::Import => "import",
+            TransactionSource::Recurring => "recurring",
+            TransactionSource::Api => "api",
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Debug, FromRow)]
+#[serde(remote = "Self")]
 struct Account {
     id: String,
     name: String,
     kind: String,
+    currency: String,
+    #[serde(serialize_with = "serialize_amount")]
     balance: f64,
+    position: i64,
+    color: Option<String>,
     created_at: String,
+    #[sqlx(skip)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    default_direction: Option<TransactionDirection>,
+    // Filled in when accounts are listed.
+    #[sqlx(skip)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    transaction_count: Option<i64>,
+    #[sqlx(skip)]
+    #[serde(
+        serialize_with = "serialize_optional_amount",
+        skip_serializing_if = "Option::is_none"
+    )]
+    month_net: Option<f64>,
+}
+
+impl Serialize for Account {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let currencies = RecordCurrencies {
+            own: Some(self.currency.clone()),
+            received: None,
+        };
+        serialize_in_currency(currencies, serializer, |serializer| {
+            Account::serialize(self, serializer)
+        })
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, FromRow)]
@@ -86,29 +583,86 @@ struct Category {
     created_at: String,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+// A merchant or counterparty transactions can point at.
+#[derive(Serialize, Deserialize, Clone, Debug, FromRow)]
+struct Payee {
+    id: String,
+    name: String,
+    created_at: String,
+}
+
+// A free-form label; a transaction can carry any number of them.
+#[derive(Serialize, Deserialize, Clone, Debug, FromRow)]
+struct Tag {
+    id: String,
+    name: String,
+    created_at: String,
+}
+
+#[derive(Deserialize)]
+struct CreateTag {
+    name: String,
+}
+
+// Body of PUT /transactions/{id}/tags; unknown names become new tags.
+#[derive(Deserialize)]
+struct ReplaceTags {
+    tags: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct CreatePayee {
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct PayeeQuery {
+    q: Option<String>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(remote = "Self")]
 struct Transaction {
     id: String,
     account_id: String,
     to_account_id: Option<String>,
-    amount: f64,
+    amount: DirectedAmount,
+    #[serde(serialize_with = "serialize_received_amount")]
+    to_amount: Option<f64>,
+    #[serde(serialize_with = "serialize_amount")]
+    fee: f64,
     direction: TransactionDirection,
     description: Option<String>,
+    payee_id: Option<String>,
+    external_id: Option<String>,
     occurred_at: String,
+    scheduled: bool,
+    reconciled: bool,
+    source: String,
     splits: Vec<TransactionSplit>,
+    tags: Vec<String>,
     created_at: String,
     updated_at: String,
 }
 
+serialize_in_account_currency!(Transaction, account_id, to_account_id);
+
 #[derive(Serialize, Deserialize, Clone, Debug, FromRow)]
 struct TransactionRow {
     id: String,
     account_id: String,
     to_account_id: Option<String>,
     amount: f64,
+    to_amount: Option<f64>,
+    fee: f64,
     direction: String,
     description: Option<String>,
+    payee_id: Option<String>,
+    external_id: Option<String>,
     occurred_at: String,
+    scheduled: bool,
+    reconciled: bool,
+    source: String,
     created_at: String,
     updated_at: String,
 }
@@ -117,13 +671,27 @@ struct TransactionRow {
 struct TransactionSplit {
     transaction_id: String,
     category_id: String,
+    #[serde(serialize_with = "serialize_amount")]
     amount: f64,
 }
 
+#[derive(Deserialize)]
+struct ReorderAccounts {
+    ids: Vec<String>,
+}
+
 #[derive(Deserialize)]
 struct CreateAccount {
     name: String,
     kind: AccountKind,
+    currency: Option<String>,
+    color: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct UpdateAccount {
+    name: Option<String>,
+    color: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -134,704 +702,5666 @@ struct CreateCategory {
 #[derive(Deserialize, Clone)]
 struct SplitInput {
     category_id: String,
+    #[serde(default)]
     amount: f64,
+    #[serde(default)]
+    remainder: bool,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Clone)]
 struct CreateTransaction {
     account_id: String,
     to_account_id: Option<String>,
     amount: f64,
+    to_amount: Option<f64>,
+    // Charged to the source account of a transfer on top of the amount moved.
+    fee: Option<f64>,
     direction: TransactionDirection,
     description: Option<String>,
+    payee_id: Option<String>,
+    // Identifier from the source system (e.g. a bank export); unique when present.
+    external_id: Option<String>,
     occurred_at: Option<String>,
     splits: Option<Vec<SplitInput>>,
+    // Shorthand for equal splits across these categories; resolved into `splits`.
+    split_evenly: Option<Vec<String>>,
+    #[serde(default)]
+    source: TransactionSource,
 }
 
-#[tokio::main]
-async fn main() -> anyhow::Result<()> {
-    dotenvy::dotenv().ok();
-    tracing_subscriber::fmt()
-        .with_max_level(Level::INFO)
-        .with_target(false)
-        .with_env_filter("info")
-        .init();
-
-    let database_url =
-        std::env::var("DATABASE_URL").unwrap_or_else(|_| "sqlite://finance.db".to_string());
-    let pool = build_pool(&database_url).await?;
-    let (notifier, _) = broadcast::channel(32);
-    init_db(&pool).await?;
-    seed_defaults(&pool).await?;
-
-    let state = AppState { pool, notifier };
+#[derive(Deserialize, Clone, Default)]
+struct PatchTransaction {
+    account_id: Option<String>,
+    to_account_id: Option<String>,
+    amount: Option<f64>,
+    to_amount: Option<f64>,
+    fee: Option<f64>,
+    direction: Option<TransactionDirection>,
+    description: Option<String>,
+    payee_id: Option<String>,
+    occurred_at: Option<String>,
+    splits: Option<Vec<SplitInput>>,
+    split_evenly: Option<Vec<String>>,
+}
 
-    let app = Router::new()
-        .route("/health", get(health))
-        .route("/accounts", get(list_accounts).post(create_account))
-        .route("/accounts/{id}", delete(delete_account))
-        .route("/categories", get(list_categories).post(create_category))
-        .route(
-            "/transactions",
-            get(list_transactions).post(create_transaction),
-        )
-        .route(
-            "/transactions/{id}",
-            get(get_transaction).put(update_transaction).delete(delete_transaction),
-        )
-        .route("/events", get(events_ws))
-        .with_state(state);
+#[derive(Deserialize, Clone)]
+struct ReplaceSplits {
+    splits: Vec<SplitInput>,
+}
 
-    let addr: SocketAddr = "0.0.0.0:8080".parse()?;
-    info!("Backend running at http://{}", addr);
-    axum::serve(tokio::net::TcpListener::bind(addr).await?, app)
-        .with_graceful_shutdown(shutdown_signal())
-        .await?;
+#[derive(Serialize)]
+struct Summary {
+    #[serde(serialize_with = "serialize_amount")]
+    net_worth: f64,
+    account_count: i64,
+    #[serde(serialize_with = "serialize_amount")]
+    month_income: f64,
+    #[serde(serialize_with = "serialize_amount")]
+    month_expense: f64,
+    #[serde(serialize_with = "serialize_amount")]
+    month_net: f64,
+    top_categories: Vec<CategoryTotal>,
+}
 
-    Ok(())
+#[derive(Serialize, FromRow)]
+struct CategoryTotal {
+    category_id: String,
+    name: String,
+    #[serde(serialize_with = "serialize_amount")]
+    total: f64,
 }
 
-async fn shutdown_signal() {
-    let ctrl_c = async {
-        signal::ctrl_c()
-            .await
-            .expect("failed to install Ctrl+C handler")
-    };
-    ctrl_c.await;
-    info!("signal received, shutting down");
+#[derive(Deserialize, Default)]
+struct AccountQuery {
+    created_from: Option<String>,
+    created_to: Option<String>,
+    sort: Option<String>,
 }
 
-async fn health() -> &'static str {
-    "ok"
+#[derive(Deserialize)]
+struct CategoryQuery {
+    order: Option<String>,
 }
 
-async fn events_ws(State(state): State<AppState>, ws: WebSocketUpgrade) -> impl IntoResponse {
-    ws.on_upgrade(move |socket| ws_handler(socket, state.notifier.subscribe()))
+#[derive(Deserialize)]
+struct ReportQuery {
+    bucket: Option<String>,
+    fiscal_year_start_month: Option<u8>,
+    fiscal_year: Option<i32>,
+    from: Option<String>,
+    to: Option<String>,
 }
 
-async fn ws_handler(mut socket: WebSocket, mut rx: broadcast::Receiver<ServerEvent>) {
-    while let Ok(event) = rx.recv().await {
-        match event {
-            ServerEvent::DataChanged => {
-                if socket.send(Message::Text("refresh".into())).await.is_err() {
-                    break;
-                }
-            }
-        }
-    }
+#[derive(Serialize)]
+struct ReportBucket {
+    period: String,
+    #[serde(serialize_with = "serialize_amount")]
+    income: f64,
+    #[serde(serialize_with = "serialize_amount")]
+    expense: f64,
+    #[serde(serialize_with = "serialize_amount")]
+    net: f64,
 }
 
-async fn list_accounts(State(state): State<AppState>) -> AppResult<Vec<Account>> {
-    let rows = sqlx::query_as::<_, Account>(
-        r#"
-        SELECT
-            id,
+#[derive(FromRow)]
+struct ExportRow {
+    id: String,
+    occurred_at: String,
+    account: Option<String>,
+    account_currency: Option<String>,
+    to_account: Option<String>,
+    to_currency: Option<String>,
+    direction: String,
+    amount: f64,
+    to_amount: Option<f64>,
+    fee: f64,
+    description: Option<String>,
+    scheduled: bool,
+    reconciled: bool,
+}
+
+#[derive(Deserialize)]
+struct TransferQuery {
+    from: Option<String>,
+    to: Option<String>,
+}
+
+// `to_account_id`/`to_account` are null when the destination has been deleted.
+#[derive(Serialize, FromRow)]
+#[serde(remote = "Self")]
+struct TransferView {
+    id: String,
+    from_account_id: String,
+    from_account: Option<String>,
+    to_account_id: Option<String>,
+    to_account: Option<String>,
+    #[serde(serialize_with = "serialize_amount")]
+    amount: f64,
+    #[serde(serialize_with = "serialize_received_amount")]
+    to_amount: Option<f64>,
+    description: Option<String>,
+    occurred_at: String,
+    scheduled: bool,
+}
+
+serialize_in_account_currency!(TransferView, from_account_id, to_account_id);
+
+#[derive(Deserialize)]
+struct MerchantQuery {
+    from: Option<String>,
+    to: Option<String>,
+    limit: Option<i64>,
+}
+
+#[derive(Deserialize)]
+struct AverageSpendQuery {
+    window: Option<String>,
+}
+
+// Expense net of refunds, plus transfer fees, averaged per day.
+#[derive(Serialize)]
+struct AverageSpend {
+    window: String,
+    days: i64,
+    #[serde(serialize_with = "serialize_amount")]
+    total: f64,
+    #[serde(serialize_with = "serialize_amount")]
+    daily: f64,
+    #[serde(serialize_with = "serialize_amount")]
+    weekly: f64,
+    #[serde(serialize_with = "serialize_amount")]
+    projected_monthly: f64,
+}
+
+#[derive(Deserialize)]
+struct AsOfQuery {
+    as_of: String,
+}
+
+#[derive(Serialize)]
+#[serde(remote = "Self")]
+struct AccountBalance {
+    account_id: String,
+    as_of: String,
+    #[serde(serialize_with = "serialize_amount")]
+    balance: f64,
+}
+
+serialize_in_account_currency!(AccountBalance, account_id);
+
+#[derive(Deserialize)]
+struct BalanceHistoryQuery {
+    days: Option<i64>,
+}
+
+#[derive(Serialize)]
+#[serde(remote = "Self")]
+struct BalanceHistory {
+    account_id: String,
+    points: Vec<DailyBalance>,
+}
+
+serialize_in_account_currency!(BalanceHistory, account_id);
+
+#[derive(Serialize)]
+struct DailyBalance {
+    date: String,
+    #[serde(serialize_with = "serialize_amount")]
+    balance: f64,
+}
+
+#[derive(Serialize, FromRow)]
+#[serde(remote = "Self")]
+struct CategoryAccount {
+    account_id: String,
+    name: String,
+    kind: String,
+    #[serde(serialize_with = "serialize_amount")]
+    total: f64,
+    count: i64,
+    #[serde(skip)]
+    currency: String,
+}
+
+serialize_in_account_currency!(CategoryAccount, account_id);
+
+#[derive(Serialize, FromRow)]
+struct MerchantTotal {
+    payee_id: Option<String>,
+    merchant: String,
+    #[serde(serialize_with = "serialize_amount")]
+    total: f64,
+    count: i64,
+}
+
+// Credit balances go negative as the card is used, so `owed` is the negated balance.
+#[derive(Serialize, FromRow)]
+#[serde(remote = "Self")]
+struct NetWorthAccount {
+    id: String,
+    name: String,
+    kind: String,
+    #[serde(serialize_with = "serialize_amount")]
+    balance: f64,
+    #[serde(serialize_with = "serialize_amount")]
+    owed: f64,
+    #[serde(skip)]
+    currency: String,
+}
+
+serialize_in_account_currency!(NetWorthAccount, id);
+
+#[derive(Serialize)]
+struct NetWorthReport {
+    #[serde(serialize_with = "serialize_amount")]
+    assets: f64,
+    #[serde(serialize_with = "serialize_amount")]
+    liabilities: f64,
+    #[serde(serialize_with = "serialize_amount")]
+    net_worth: f64,
+    accounts: Vec<NetWorthAccount>,
+}
+
+#[derive(Serialize)]
+struct KindTotal {
+    kind: &'static str,
+    account_count: i64,
+    #[serde(serialize_with = "serialize_amount")]
+    balance: f64,
+}
+
+#[derive(Clone, Copy)]
+enum Bucket {
+    Month,
+    IsoWeek,
+    FiscalYear,
+}
+
+#[derive(Deserialize)]
+struct ForceParam {
+    #[serde(default)]
+    force: bool,
+}
+
+#[derive(Deserialize)]
+struct DuplicateParam {
+    #[serde(default)]
+    allow_duplicate: bool,
+}
+
+#[derive(Deserialize)]
+struct MergeRequest {
+    into: String,
+}
+
+// Categories differing only in case or spacing; `suggested_id` is the one to keep.
+#[derive(Serialize)]
+struct CategoryDuplicates {
+    normalized_name: String,
+    suggested_id: String,
+    categories: Vec<DuplicateCategory>,
+}
+
+#[derive(Serialize, FromRow)]
+struct DuplicateCategory {
+    id: String,
+    name: String,
+    created_at: String,
+    split_count: i64,
+}
+
+#[derive(Deserialize)]
+struct TransactionQuery {
+    period: Option<String>,
+    scheduled: Option<bool>,
+    account_id: Option<String>,
+    uncategorized: Option<bool>,
+    source: Option<String>,
+    updated_since: Option<String>,
+    created_since: Option<String>,
+    // Totals are sent back in X-Total-Count and X-Total-Amount.
+    limit: Option<i64>,
+    offset: Option<i64>,
+}
+
+impl TransactionQuery {
+    fn account_ids(&self) -> Vec<String> {
+        let mut ids: Vec<String> = Vec::new();
+        for id in self.account_id.iter().flat_map(|ids| ids.split(',')) {
+            let id = id.trim();
+            if !id.is_empty() && !ids.iter().any(|seen| seen == id) {
+                ids.push(id.to_string());
+            }
+        }
+        ids
+    }
+}
+
+#[derive(Serialize, Clone, Debug, FromRow)]
+#[serde(remote = "Self")]
+struct Template {
+    id: String,
+    name: String,
+    account_id: String,
+    to_account_id: Option<String>,
+    #[serde(serialize_with = "serialize_amount")]
+    amount: f64,
+    direction: String,
+    category_id: Option<String>,
+    description: Option<String>,
+    created_at: String,
+}
+
+serialize_in_account_currency!(Template, account_id);
+
+#[derive(Serialize, FromRow)]
+struct AlertRule {
+    id: String,
+    direction: String,
+    #[serde(serialize_with = "serialize_amount")]
+    threshold: f64,
+    created_at: String,
+}
+
+#[derive(Deserialize)]
+struct CreateAlertRule {
+    direction: TransactionDirection,
+    threshold: f64,
+}
+
+#[derive(Serialize, FromRow)]
+struct CategorizationRule {
+    id: String,
+    pattern: String,
+    is_regex: bool,
+    category_id: String,
+    created_at: String,
+}
+
+#[derive(Deserialize)]
+struct CreateCategorizationRule {
+    pattern: String,
+    #[serde(default)]
+    is_regex: bool,
+    category_id: String,
+}
+
+enum RuleMatcher {
+    Substring(String),
+    Regex(Regex),
+}
+
+impl RuleMatcher {
+    fn matches(&self, description: &str) -> bool {
+        match self {
+            RuleMatcher::Substring(needle) => description.to_lowercase().contains(needle),
+            RuleMatcher::Regex(re) => re.is_match(description),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ApplyRulesResult {
+    categorized: u64,
+    dry_run: bool,
+    assignments: Vec<RuleAssignment>,
+    skipped_reconciled: u64,
+}
+
+#[derive(Serialize)]
+struct RuleAssignment {
+    transaction_id: String,
+    description: String,
+    category_id: String,
+}
+
+#[derive(Deserialize)]
+struct NormalizeDescriptions {
+    rules: Vec<DescriptionRule>,
+}
+
+// With `regex`, `replace` may use `$1`-style capture references.
+#[derive(Deserialize)]
+struct DescriptionRule {
+    find: String,
+    replace: String,
+    #[serde(default)]
+    regex: bool,
+}
+
+#[derive(Serialize)]
+struct NormalizeResult {
+    changed: u64,
+    dry_run: bool,
+    changes: Vec<DescriptionChange>,
+    skipped_reconciled: u64,
+}
+
+#[derive(Serialize)]
+struct DescriptionChange {
+    transaction_id: String,
+    before: String,
+    after: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ApiKeyParam {
+    api_key: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct BulkEditParams {
+    #[serde(default)]
+    dry_run: bool,
+    #[serde(default)]
+    force: bool,
+}
+
+#[derive(Deserialize)]
+struct CreateTemplate {
+    name: String,
+    account_id: String,
+    to_account_id: Option<String>,
+    amount: f64,
+    direction: TransactionDirection,
+    category_id: Option<String>,
+    description: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+enum Cadence {
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+impl Cadence {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Cadence::Daily => "daily",
+            Cadence::Weekly => "weekly",
+            Cadence::Monthly => "monthly",
+        }
+    }
+
+    // Monthly runs clamp to the month's last day, e.g. the 31st becomes Feb 28.
+    fn advance(&self, date: Date) -> Date {
+        match self {
+            Cadence::Daily => date + time::Duration::days(1),
+            Cadence::Weekly => date + time::Duration::days(7),
+            Cadence::Monthly => {
+                let (year, month) = match date.month() {
+                    Month::December => (date.year() + 1, Month::January),
+                    month => (date.year(), month.next()),
+                };
+                let day = date.day().min(month.length(year));
+                Date::from_calendar_date(year, month, day).unwrap_or(date)
+            }
+        }
+    }
+}
+
+// Paused schedules keep their place but generate nothing.
+#[derive(Serialize, FromRow)]
+struct RecurringTransaction {
+    id: String,
+    template_id: String,
+    cadence: String,
+    next_run_at: String,
+    paused: bool,
+    created_at: String,
+}
+
+#[derive(Deserialize)]
+struct CreateRecurring {
+    template_id: String,
+    cadence: Cadence,
+    start_on: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+struct ApplyTemplate {
+    account_id: Option<String>,
+    amount: Option<f64>,
+    to_amount: Option<f64>,
+    description: Option<String>,
+    occurred_at: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct BatchDelete {
+    ids: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct BatchDeleteResult {
+    deleted: usize,
+    not_found: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct HistoryEntry {
+    action: String,
+    changes: serde_json::Value,
+    recorded_at: String,
+}
+
+#[derive(Serialize)]
+struct ImportResult {
+    inserted: usize,
+    skipped: usize,
+    errors: Vec<ImportRowError>,
+}
+
+// Line numbers are 1-based, the header being line 1.
+#[derive(Serialize)]
+struct ImportRowError {
+    line: usize,
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct ResetRequest {
+    confirm: String,
+}
+
+#[derive(Serialize)]
+#[serde(remote = "Self")]
+struct BalanceDrift {
+    account_id: String,
+    name: String,
+    #[serde(serialize_with = "serialize_amount")]
+    stored: f64,
+    #[serde(serialize_with = "serialize_amount")]
+    computed: f64,
+    #[serde(serialize_with = "serialize_amount")]
+    delta: f64,
+}
+
+serialize_in_account_currency!(BalanceDrift, account_id);
+
+#[derive(Serialize)]
+struct SnapshotInfo {
+    path: String,
+    size_bytes: u64,
+}
+
+#[derive(Serialize)]
+struct ResetSummary {
+    accounts_removed: u64,
+    categories_removed: u64,
+    transactions_removed: u64,
+    splits_removed: u64,
+}
+
+const API_VERSION: &str = "v1";
+const RESET_CONFIRM_TOKEN: &str = "RESET";
+const MAX_RECURRING_CATCH_UP: usize = 31;
+const BALANCE_DRIFT_TOLERANCE: f64 = 0.005;
+const DEFAULT_SNAPSHOT_DIR: &str = "snapshots";
+const DEFAULT_CURRENCY: &str = "USD";
+const DEFAULT_LOCALE: &str = "en-US";
+const NAMED_COLORS: [&str; 16] = [
+    "black",
+    "red",
+    "green",
+    "yellow",
+    "blue",
+    "magenta",
+    "cyan",
+    "gray",
+    "darkgray",
+    "lightred",
+    "lightgreen",
+    "lightyellow",
+    "lightblue",
+    "lightmagenta",
+    "lightcyan",
+    "white",
+];
+const DEFAULT_ACCOUNT_NAMES: [&str; 3] = ["Main Checking", "Savings", "Credit Card"];
+const DEFAULT_MAX_BODY_BYTES: usize = 2 * 1024 * 1024;
+const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+const MAX_NAME_LEN: usize = 100;
+const MAX_DESCRIPTION_LEN: usize = 500;
+const DEFAULT_MERCHANT_LIMIT: i64 = 10;
+const MAX_MERCHANT_LIMIT: i64 = 100;
+const DEFAULT_MAX_TRANSACTION_LIST: i64 = 10_000;
+const TOTAL_COUNT_HEADER: &str = "x-total-count";
+const TRUNCATED_HEADER: &str = "x-truncated";
+const TOTAL_AMOUNT_HEADER: &str = "x-total-amount";
+const HIDE_DELETED_ACCOUNTS: &str = " AND NOT EXISTS (SELECT 1 FROM accounts hidden \
+     WHERE hidden.deleted_at IS NOT NULL \
+     AND hidden.id IN (transactions.account_id, transactions.to_account_id))";
+const UNLABELED_MERCHANT: &str = "Unlabeled";
+const REQUEST_ID_HEADER: &str = "x-request-id";
+const API_KEY_HEADER: &str = "x-api-key";
+const MAX_REQUEST_ID_LEN: usize = 128;
+const ARCHIVE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+const COMPUTED_BALANCE_SQL: &str = "CAST(accounts.opening_balance + COALESCE((SELECT SUM(CASE WHEN t.account_id = accounts.id AND t.direction IN ('income', 'refund') THEN t.amount WHEN t.account_id = accounts.id THEN -(t.amount + t.fee) ELSE COALESCE(t.to_amount, t.amount) END) FROM transactions t WHERE (t.account_id = accounts.id OR t.to_account_id = accounts.id) AND t.scheduled = 0), 0) AS REAL)";
+const SPLIT_SUM_TOLERANCE: f64 = 0.005;
+const DUPLICATE_WINDOW: Duration = Duration::from_secs(5 * 60);
+const MAX_IMPORT_BYTES: usize = 10 * 1024 * 1024;
+const DEMO_SEED_MONTHS: u32 = 3;
+const DEFAULT_DIRECTION_SAMPLE: i64 = 20;
+const DEFAULT_HISTORY_DAYS: i64 = 30;
+const DEFAULT_SPEND_WINDOW: &str = "30d";
+const DAYS_PER_MONTH: f64 = 365.25 / 12.0;
+const MAX_HISTORY_DAYS: i64 = 366;
+const BUSY_MESSAGE: &str = "database is busy, try again";
+const BUSY_RETRY_ATTEMPTS: u32 = 5;
+const BUSY_RETRY_BASE_DELAY: Duration = Duration::from_millis(20);
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+// Columns the queries rely on, checked at startup.
+const EXPECTED_SCHEMA: &[(&str, &[&str])] = &[
+    (
+        "accounts",
+        &[
+            "id",
+            "name",
+            "kind",
+            "currency",
+            "balance",
+            "position",
+            "opening_balance",
+            "color",
+            "created_at",
+            "deleted_at",
+        ],
+    ),
+    ("categories", &["id", "name", "created_at"]),
+    ("payees", &["id", "name", "created_at"]),
+    ("tags", &["id", "name", "created_at"]),
+    ("transaction_tags", &["transaction_id", "tag_id"]),
+    (
+        "transactions",
+        &[
+            "id",
+            "account_id",
+            "to_account_id",
+            "amount",
+            "to_amount",
+            "fee",
+            "direction",
+            "description",
+            "payee_id",
+            "external_id",
+            "occurred_at",
+            "scheduled",
+            "reconciled",
+            "source",
+            "created_at",
+            "updated_at",
+        ],
+    ),
+    (
+        "archived_transactions",
+        &[
+            "id",
+            "account_id",
+            "to_account_id",
+            "amount",
+            "to_amount",
+            "fee",
+            "direction",
+            "description",
+            "payee_id",
+            "external_id",
+            "occurred_at",
+            "scheduled",
+            "reconciled",
+            "source",
+            "created_at",
+            "updated_at",
+            "archived_at",
+        ],
+    ),
+    ("archived_transaction_splits", &["id", "transaction_id", "category_id", "amount"]),
+    ("alert_rules", &["id", "direction", "threshold", "created_at"]),
+    (
+        "categorization_rules",
+        &["id", "pattern", "is_regex", "category_id", "created_at"],
+    ),
+    (
+        "templates",
+        &[
+            "id",
+            "name",
+            "account_id",
+            "to_account_id",
+            "amount",
+            "direction",
+            "category_id",
+            "description",
+            "created_at",
+        ],
+    ),
+    ("transaction_splits", &["id", "transaction_id", "category_id", "amount"]),
+    (
+        "transaction_history",
+        &["id", "transaction_id", "action", "changes", "recorded_at"],
+    ),
+    (
+        "recurring_transactions",
+        &["id", "template_id", "cadence", "next_run_at", "paused", "created_at"],
+    ),
+];
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    dotenvy::dotenv().ok();
+    init_tracing(&std::env::var("LOG_FORMAT").unwrap_or_default());
+
+    let database_url =
+        std::env::var("DATABASE_URL").unwrap_or_else(|_| "sqlite://finance.db".to_string());
+    let pool = build_pool(&database_url).await?;
+    let (notifier, _) = broadcast::channel(32);
+    let money = money_format_from_env();
+    init_db(&pool).await?;
+    verify_schema(&pool).await?;
+    seed_defaults(&pool, &money.currency).await?;
+
+    let admin_enabled = std::env::var("ADMIN_ENABLED")
+        .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+        .unwrap_or(false);
+    let read_only = std::env::var("READ_ONLY")
+        .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+        .unwrap_or(false);
+    let api_key = std::env::var("API_KEY").ok().filter(|key| !key.is_empty());
+    let seed_demo_enabled = std::env::var("SEED_DEMO")
+        .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+        .unwrap_or(false);
+    let snapshot_dir = std::env::var("SNAPSHOT_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from(DEFAULT_SNAPSHOT_DIR));
+    let balance_mode = match std::env::var("BALANCE_MODE").as_deref() {
+        Ok("computed") => BalanceMode::Computed,
+        Ok("cached") | Err(_) => BalanceMode::Cached,
+        Ok(other) => {
+            warn!("unknown BALANCE_MODE '{}', using cached", other);
+            BalanceMode::Cached
+        }
+    };
+    let max_transaction_list = std::env::var("MAX_TRANSACTION_LIST")
+        .ok()
+        .and_then(|v| v.parse::<i64>().ok())
+        .filter(|max| *max > 0)
+        .unwrap_or(DEFAULT_MAX_TRANSACTION_LIST);
+    let state = AppState {
+        pool,
+        notifier,
+        admin_enabled,
+        read_only,
+        api_key,
+        snapshot_dir,
+        money,
+        balance_mode,
+        max_transaction_list,
+    };
+    if seed_demo_enabled && read_only {
+        warn!("SEED_DEMO is ignored in read-only mode");
+    } else if seed_demo_enabled {
+        seed_demo(&state).await?;
+    }
+
+    let max_body_bytes = std::env::var("MAX_BODY_BYTES")
+        .ok()
+        .and_then(|v| v.parse::<usize>().ok())
+        .unwrap_or(DEFAULT_MAX_BODY_BYTES);
+
+    let request_timeout = std::env::var("REQUEST_TIMEOUT_SECS")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .map(Duration::from_secs)
+        .unwrap_or(DEFAULT_REQUEST_TIMEOUT);
+
+    let retention_days = std::env::var("RETENTION_DAYS")
+        .ok()
+        .and_then(|v| v.parse::<i64>().ok())
+        .filter(|days| *days > 0);
+    if state.read_only {
+        info!("read-only mode: scheduled, recurring and archiving jobs are paused");
+    }
+    if let Some(days) = retention_days.filter(|_| !state.read_only) {
+        let archive_state = state.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(ARCHIVE_INTERVAL);
+            loop {
+                interval.tick().await;
+                if let Err((_, message)) = archive_old_transactions(&archive_state, days).await {
+                    warn!("failed to archive old transactions: {}", message);
+                }
+            }
+        });
+    }
+
+    if !state.read_only {
+        let scheduler_state = state.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(60));
+            loop {
+                interval.tick().await;
+                if let Err((_, message)) = activate_due_transactions(&scheduler_state).await {
+                    warn!("failed to activate scheduled transactions: {}", message);
+                }
+                if let Err((_, message)) = run_due_recurring(&scheduler_state).await {
+                    warn!("failed to run recurring transactions: {}", message);
+                }
+            }
+        });
+    }
+
+    let app = build_router(state, max_body_bytes, request_timeout);
+
+    let addr: SocketAddr = "0.0.0.0:8080".parse()?;
+    info!("Backend running at http://{}", addr);
+    axum::serve(tokio::net::TcpListener::bind(addr).await?, app)
+        .with_graceful_shutdown(shutdown_signal())
+        .await?;
+
+    Ok(())
+}
+
+fn build_router(state: AppState, max_body_bytes: usize, request_timeout: Duration) -> Router {
+    let timed = Router::new()
+        .route("/health", get(health))
+        .route("/accounts", get(list_accounts).post(create_account))
+        .route("/accounts/reorder", put(reorder_accounts))
+        .route("/accounts/{id}", put(update_account).delete(delete_account))
+        .route("/accounts/{id}/merge", post(merge_account))
+        .route("/accounts/{id}/restore", post(restore_account))
+        .route("/accounts/{id}/balance", get(account_balance_as_of))
+        .route("/accounts/{id}/balance-history", get(account_balance_history))
+        .route("/categories", get(list_categories).post(create_category))
+        .route("/categories/duplicates", get(category_duplicates))
+        .route("/categories/{id}/accounts", get(category_accounts))
+        .route("/categories/{id}/merge", post(merge_category))
+        .route("/payees", get(list_payees).post(create_payee))
+        .route("/payees/{id}", put(update_payee).delete(delete_payee))
+        .route("/tags", get(list_tags).post(create_tag))
+        .route(
+            "/transactions",
+            get(list_transactions).post(create_transaction),
+        )
+        .route("/transactions/delete", post(delete_transactions_batch))
+        .route("/transactions/archived", get(list_archived_transactions))
+        .route("/transactions/apply-rules", post(apply_categorization_rules))
+        .route("/transactions/normalize-descriptions", post(normalize_descriptions))
+        .route("/transfers", get(list_transfers))
+        .route("/transactions/{id}/splits", put(replace_splits))
+        .route("/transactions/{id}/tags", put(replace_tags))
+        .route("/transactions/{id}/history", get(transaction_history))
+        .route("/transactions/{id}/reconcile", post(reconcile_transaction))
+        .route("/transactions/{id}/unreconcile", post(unreconcile_transaction))
+        .route(
+            "/transactions/{id}",
+            get(get_transaction)
+                .put(update_transaction)
+                .patch(patch_transaction)
+                .delete(delete_transaction),
+        )
+        .route("/templates", get(list_templates).post(create_template))
+        .route(
+            "/templates/{id}",
+            get(get_template).put(update_template).delete(delete_template),
+        )
+        .route("/templates/{id}/apply", post(apply_template))
+        .route("/recurring", get(list_recurring).post(create_recurring))
+        .route("/recurring/{id}", axum::routing::delete(delete_recurring))
+        .route("/recurring/{id}/skip", post(skip_recurring))
+        .route("/recurring/{id}/pause", post(pause_recurring))
+        .route("/recurring/{id}/resume", post(resume_recurring))
+        .route("/alerts", get(list_alert_rules).post(create_alert_rule))
+        .route("/alerts/{id}", put(update_alert_rule).delete(delete_alert_rule))
+        .route(
+            "/categorization-rules",
+            get(list_categorization_rules).post(create_categorization_rule),
+        )
+        .route(
+            "/categorization-rules/{id}",
+            put(update_categorization_rule).delete(delete_categorization_rule),
+        )
+        .route("/admin/reset", post(admin_reset))
+        .route("/admin/snapshot", post(admin_snapshot))
+        .route("/admin/integrity", get(admin_integrity))
+        .route("/summary", get(summary))
+        .route("/reports/monthly", get(monthly_report))
+        .route("/reports/trend", get(trend_report))
+        .route("/reports/merchants", get(merchant_report))
+        .route("/reports/networth", get(net_worth_report))
+        .route("/reports/by-kind", get(kind_report))
+        .route("/reports/average-spend", get(average_spend_report))
+        .route("/export/transactions.csv", get(export_transactions_csv))
+        .route(
+            "/import/transactions.csv",
+            post(import_transactions_csv).layer(DefaultBodyLimit::max(MAX_IMPORT_BYTES)),
+        );
+    let untimed = Router::new().route("/events", get(events_ws));
+    let api = with_request_timeout(timed, untimed, request_timeout);
+
+    // Unversioned paths alias /v1 for one release.
+    Router::new()
+        .nest(&format!("/{API_VERSION}"), api.clone())
+        .merge(api.layer(middleware::map_response(mark_unversioned)))
+        .layer(DefaultBodyLimit::max(max_body_bytes))
+        .layer(middleware::from_fn_with_state(state.clone(), read_only_layer))
+        .layer(middleware::from_fn_with_state(state.clone(), api_key_layer))
+        .layer(middleware::from_fn_with_state(state.clone(), amount_style_layer))
+        .layer(middleware::from_fn(request_id_layer))
+        .with_state(state)
+}
+
+fn with_request_timeout(
+    timed: Router<AppState>,
+    untimed: Router<AppState>,
+    timeout: Duration,
+) -> Router<AppState> {
+    timed
+        .layer(TimeoutLayer::with_status_code(StatusCode::REQUEST_TIMEOUT, timeout))
+        .merge(untimed)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum LogFormat {
+    Pretty,
+    Json,
+}
+
+impl LogFormat {
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "json" => Some(LogFormat::Json),
+            "" | "pretty" => Some(LogFormat::Pretty),
+            _ => None,
+        }
+    }
+}
+
+fn log_subscriber<W>(format: LogFormat, writer: W) -> Box<dyn tracing::Subscriber + Send + Sync>
+where
+    W: for<'a> tracing_subscriber::fmt::MakeWriter<'a> + Send + Sync + 'static,
+{
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let builder = tracing_subscriber::fmt()
+        .with_max_level(Level::INFO)
+        .with_target(false)
+        .with_env_filter(filter)
+        .with_writer(writer);
+    match format {
+        LogFormat::Json => Box::new(builder.json().finish()),
+        LogFormat::Pretty => Box::new(builder.finish()),
+    }
+}
+
+fn init_tracing(log_format: &str) {
+    let format = LogFormat::parse(log_format);
+    log_subscriber(format.unwrap_or(LogFormat::Pretty), std::io::stdout).init();
+    if format.is_none() {
+        warn!("unknown LOG_FORMAT '{}', using pretty output", log_format);
+    }
+}
+
+// BASE_CURRENCY (default USD) and LOCALE (default en-US).
+fn money_format_from_env() -> MoneyFormat {
+    let mut money = MoneyFormat::default();
+    if let Ok(code) = std::env::var("BASE_CURRENCY") {
+        match normalize_currency(&code) {
+            Ok(code) => money.currency = code,
+            Err(_) => warn!("invalid BASE_CURRENCY '{}', using {}", code, DEFAULT_CURRENCY),
+        }
+    }
+    if let Ok(tag) = std::env::var("LOCALE") {
+        match Locale::parse(&tag) {
+            Some(locale) => money.locale = locale,
+            None => warn!("unsupported LOCALE '{}', using {}", tag, DEFAULT_LOCALE),
+        }
+    }
+    money
+}
+
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        signal::ctrl_c()
+            .await
+            .expect("failed to install Ctrl+C handler")
+    };
+    ctrl_c.await;
+    info!("signal received, shutting down");
+}
+
+async fn amount_style_layer(State(state): State<AppState>, req: Request, next: Next) -> Response {
+    let mut style = AmountStyle {
+        money: state.money.clone(),
+        ..AmountStyle::default()
+    };
+    for pair in req.uri().query().unwrap_or_default().split('&') {
+        match pair {
+            "amount_format=string" => style.format = AmountFormat::String,
+            "amount_format=display" => style.format = AmountFormat::Display,
+            "amount_sign=signed" => style.signed = true,
+            _ => {
+                if let Some(ids) = pair.strip_prefix("account_id=") {
+                    style.accounts.extend(ids.split(',').map(|id| id.trim().to_string()));
+                }
+            }
+        }
+    }
+    if style.format != AmountFormat::Number {
+        // Deleted accounts included: their transactions can still be listed.
+        let currencies: Vec<(String, String)> = sqlx::query_as("SELECT id, currency FROM accounts")
+            .fetch_all(&state.pool)
+            .await
+            .unwrap_or_default();
+        style.currencies = Arc::new(currencies.into_iter().collect());
+    }
+    AMOUNT_STYLE.scope(style, next.run(req)).await
+}
+
+async fn read_only_layer(State(state): State<AppState>, req: Request, next: Next) -> Response {
+    if state.read_only && !req.method().is_safe() {
+        return (
+            StatusCode::FORBIDDEN,
+            "server is read-only; changes are disabled".to_string(),
+        )
+            .into_response();
+    }
+    next.run(req).await
+}
+
+// API_KEY requires X-Api-Key on everything but /health; /events also takes ?api_key=.
+async fn api_key_layer(State(state): State<AppState>, req: Request, next: Next) -> Response {
+    let Some(expected) = state.api_key.as_deref() else {
+        return next.run(req).await;
+    };
+    let path = req.uri().path();
+    if path.ends_with("/health") {
+        return next.run(req).await;
+    }
+    let header_key = req
+        .headers()
+        .get(API_KEY_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let query_key = if path.ends_with("/events") {
+        Query::<ApiKeyParam>::try_from_uri(req.uri())
+            .ok()
+            .and_then(|Query(param)| param.api_key)
+    } else {
+        None
+    };
+    let matches = header_key
+        .or(query_key)
+        .is_some_and(|key| bool::from(key.as_bytes().ct_eq(expected.as_bytes())));
+    if !matches {
+        return (
+            StatusCode::UNAUTHORIZED,
+            "missing or invalid API key".to_string(),
+        )
+            .into_response();
+    }
+    next.run(req).await
+}
+
+// Tags every request with an X-Request-Id, the client's or a fresh UUID.
+async fn request_id_layer(req: Request, next: Next) -> Response {
+    let request_id = req
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|value| !value.is_empty() && value.len() <= MAX_REQUEST_ID_LEN)
+        .map(str::to_string)
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+    let span = info_span!(
+        "request",
+        request_id = %request_id,
+        method = %req.method(),
+        path = %req.uri().path(),
+    );
+
+    let response = next.run(req).instrument(span).await;
+    let status = response.status();
+    let mut response = if status.is_client_error() || status.is_server_error() {
+        warn!(request_id = %request_id, status = %status, "request failed");
+        append_request_id(response, &request_id).await
+    } else {
+        response
+    };
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    response
+}
+
+async fn append_request_id(response: Response, request_id: &str) -> Response {
+    let content_type = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    let is_text = content_type.starts_with("text/plain");
+    if !is_text && !content_type.starts_with("application/json") {
+        return response;
+    }
+    let (mut parts, body) = response.into_parts();
+    let Ok(bytes) = axum::body::to_bytes(body, usize::MAX).await else {
+        return Response::from_parts(parts, axum::body::Body::empty());
+    };
+    let body = if is_text {
+        let message = String::from_utf8_lossy(&bytes);
+        format!("{message} (request id: {request_id})").into_bytes()
+    } else {
+        match serde_json::from_slice::<serde_json::Value>(&bytes) {
+            Ok(serde_json::Value::Object(mut fields)) => {
+                fields.insert("request_id".into(), request_id.into());
+                serde_json::Value::Object(fields).to_string().into_bytes()
+            }
+            _ => bytes.to_vec(),
+        }
+    };
+    parts.headers.remove(header::CONTENT_LENGTH);
+    Response::from_parts(parts, axum::body::Body::from(body))
+}
+
+async fn mark_unversioned(mut response: Response) -> Response {
+    response
+        .headers_mut()
+        .insert("deprecation", HeaderValue::from_static("true"));
+    response
+}
+
+async fn health(State(state): State<AppState>) -> Json<serde_json::Value> {
+    Json(serde_json::json!({
+        "status": "ok",
+        "api_version": API_VERSION,
+        "version": env!("CARGO_PKG_VERSION"),
+        "currency": state.money.currency,
+        "locale": state.money.locale.as_str(),
+        "read_only": state.read_only,
+    }))
+}
+
+fn notify_ledger_changed(state: &AppState) {
+    let _ = state.notifier.send(ServerEvent::TransactionsChanged);
+    let _ = state.notifier.send(ServerEvent::AccountsChanged);
+}
+
+async fn events_ws(State(state): State<AppState>, ws: WebSocketUpgrade) -> impl IntoResponse {
+    ws.on_upgrade(move |socket| ws_handler(socket, state.notifier.subscribe()))
+}
+
+async fn ws_handler(mut socket: WebSocket, mut rx: broadcast::Receiver<ServerEvent>) {
+    while let Ok(event) = rx.recv().await {
+        match event {
+            ServerEvent::DataChanged => {
+                if socket.send(Message::Text("refresh".into())).await.is_err() {
+                    break;
+                }
+            }
+            ServerEvent::AccountsChanged => {
+                if socket.send(Message::Text("accounts".into())).await.is_err() {
+                    break;
+                }
+            }
+            ServerEvent::TransactionsChanged => {
+                if socket.send(Message::Text("transactions".into())).await.is_err() {
+                    break;
+                }
+            }
+            ServerEvent::Alert(alert) => {
+                let text = serde_json::json!({ "event": "alert", "alert": alert }).to_string();
+                if socket.send(Message::Text(text.into())).await.is_err() {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+async fn list_accounts(
+    State(state): State<AppState>,
+    Query(params): Query<AccountQuery>,
+) -> AppResult<Vec<Account>> {
+    let order = match params.sort.as_deref().unwrap_or("position") {
+        "position" => "position ASC, created_at DESC",
+        "name" => "name COLLATE NOCASE ASC, created_at DESC",
+        "created" => "created_at DESC, name ASC",
+        "balance" => "balance DESC, name ASC",
+        other => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                format!("unknown sort '{other}'; expected position, name, created or balance"),
+            ));
+        }
+    };
+    let created_from = created_bound("created_from", &params.created_from)?;
+    let created_to = created_bound("created_to", &params.created_to)?;
+    if let (Some(from), Some(to)) = (&created_from, &created_to)
+        && from > to
+    {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "created_from must not be after created_to".to_string(),
+        ));
+    }
+    activate_due_transactions(&state).await?;
+    let rows = sqlx::query_as::<_, Account>(&format!(
+        r#"
+        SELECT
+            id,
             name,
             kind,
-            balance,
+            currency,
+            {} AS balance,
+            position,
+            color,
             created_at
         FROM accounts
-        ORDER BY created_at DESC
+        WHERE deleted_at IS NULL
+          AND (?1 IS NULL OR created_at >= ?1)
+          AND (?2 IS NULL OR created_at <= ?2)
+        ORDER BY {order}
+        "#,
+        state.balance_mode.column()
+    ))
+    .bind(created_from)
+    .bind(created_to)
+    .fetch_all(&state.pool)
+    .await
+    .map_err(internal_error)?;
+
+    let usage: Vec<(String, i64, i64)> = sqlx::query_as(
+        r#"
+        SELECT account_id, SUM(direction = 'income'), SUM(direction = 'expense')
+        FROM (
+            SELECT account_id, direction, ROW_NUMBER() OVER (
+                PARTITION BY account_id ORDER BY occurred_at DESC, created_at DESC
+            ) AS recency
+            FROM transactions
+            WHERE direction IN ('income', 'expense') AND scheduled = 0
+        )
+        WHERE recency <= ?1
+        GROUP BY account_id
+        "#,
+    )
+    .bind(DEFAULT_DIRECTION_SAMPLE)
+    .fetch_all(&state.pool)
+    .await
+    .map_err(internal_error)?;
+
+    let month_start = format_rfc3339(period_start("mtd", OffsetDateTime::now_utc())?);
+    let activity: Vec<(String, i64, f64)> = sqlx::query_as(
+        r#"
+        SELECT a.id, COUNT(t.id), CAST(COALESCE(SUM(CASE
+            WHEN t.occurred_at < ?1 THEN 0
+            WHEN t.account_id = a.id AND t.direction IN ('income', 'refund') THEN t.amount
+            WHEN t.account_id = a.id THEN -(t.amount + t.fee)
+            ELSE COALESCE(t.to_amount, t.amount)
+        END), 0) AS REAL)
+        FROM accounts a
+        JOIN transactions t ON a.id IN (t.account_id, t.to_account_id) AND t.scheduled = 0
+        WHERE a.deleted_at IS NULL
+        GROUP BY a.id
+        "#,
+    )
+    .bind(&month_start)
+    .fetch_all(&state.pool)
+    .await
+    .map_err(internal_error)?;
+    let rows = rows
+        .into_iter()
+        .map(|account| {
+            let (income, expense) = usage
+                .iter()
+                .find(|(id, _, _)| *id == account.id)
+                .map(|(_, income, expense)| (*income, *expense))
+                .unwrap_or((0, 0));
+            let (count, month_net) = activity
+                .iter()
+                .find(|(id, _, _)| *id == account.id)
+                .map(|(_, count, net)| (*count, *net))
+                .unwrap_or((0, 0.0));
+            Account {
+                default_direction: Some(suggested_direction(&account.kind, income, expense)),
+                transaction_count: Some(count),
+                month_net: Some(round_in_currency(month_net, &account.currency)),
+                balance: round_in_currency(account.balance, &account.currency),
+                ..account
+            }
+        })
+        .collect();
+    Ok(Json(rows))
+}
+
+// Timestamps are stored in UTC, so bounds are restated in UTC before comparing.
+fn created_bound(
+    param: &str,
+    value: &Option<String>,
+) -> Result<Option<String>, (StatusCode, String)> {
+    value
+        .as_deref()
+        .map(|value| {
+            OffsetDateTime::parse(value, &time::format_description::well_known::Rfc3339)
+                .map(|at| format_rfc3339(at.to_offset(time::UtcOffset::UTC)))
+                .map_err(|_| {
+                    (
+                        StatusCode::BAD_REQUEST,
+                        format!("{param} must be an RFC 3339 timestamp"),
+                    )
+                })
+        })
+        .transpose()
+}
+
+// Leans towards the account's recent entries, else what its kind usually sees.
+fn suggested_direction(kind: &str, recent_income: i64, recent_expense: i64) -> TransactionDirection {
+    match recent_income.cmp(&recent_expense) {
+        std::cmp::Ordering::Greater => TransactionDirection::Income,
+        std::cmp::Ordering::Less => TransactionDirection::Expense,
+        std::cmp::Ordering::Equal if matches!(kind, "savings" | "investment") => {
+            TransactionDirection::Income
+        }
+        std::cmp::Ordering::Equal => TransactionDirection::Expense,
+    }
+}
+
+async fn create_account(
+    State(state): State<AppState>,
+    ValidJson(payload): ValidJson<CreateAccount>,
+) -> FormResult<Account> {
+    let mut errors = FieldErrors::default();
+    errors.check("name", check_name(&payload.name));
+    let currency = match &payload.currency {
+        Some(code) => errors.check("currency", normalize_currency(code)),
+        None => Some(state.money.currency.clone()),
+    };
+    let color = errors.check("color", payload.color.as_deref().map(normalize_color).transpose());
+    errors.finish()?;
+    let currency = currency.unwrap_or_default();
+    let color = color.flatten().flatten();
+    let id = Uuid::new_v4().to_string();
+    let now = OffsetDateTime::now_utc()
+        .format(&time::format_description::well_known::Rfc3339)
+        .unwrap();
+    let (position,): (i64,) =
+        sqlx::query_as("SELECT COALESCE(MAX(position) + 1, 0) FROM accounts")
+            .fetch_one(&state.pool)
+            .await
+            .map_err(internal_error)?;
+    sqlx::query(
+        "INSERT INTO accounts (id, name, kind, currency, balance, position, color, created_at) VALUES (?1, ?2, ?3, ?4, 0.0, ?5, ?6, ?7)",
+    )
+    .bind(&id)
+    .bind(&payload.name)
+    .bind(payload.kind.as_str())
+    .bind(&currency)
+    .bind(position)
+    .bind(&color)
+    .bind(&now)
+    .execute(&state.pool)
+    .await
+    .map_err(internal_error)?;
+
+    let account = Account {
+        id,
+        name: payload.name,
+        kind: payload.kind.as_str().to_string(),
+        currency,
+        balance: 0.0,
+        position,
+        color,
+        created_at: now,
+        default_direction: Some(suggested_direction(payload.kind.as_str(), 0, 0)),
+        transaction_count: Some(0),
+        month_net: Some(0.0),
+    };
+    let _ = state.notifier.send(ServerEvent::AccountsChanged);
+    Ok(Json(account))
+}
+
+// Listed accounts move to the top; the rest keep their order after them.
+async fn reorder_accounts(
+    State(state): State<AppState>,
+    ValidJson(payload): ValidJson<ReorderAccounts>,
+) -> AppResult<Vec<Account>> {
+    let mut tx = state.pool.begin().await.map_err(internal_error)?;
+    let current: Vec<(String,)> =
+        sqlx::query_as("SELECT id FROM accounts WHERE deleted_at IS NULL ORDER BY position ASC, created_at DESC")
+            .fetch_all(&mut *tx)
+            .await
+            .map_err(internal_error)?;
+    let current: Vec<String> = current.into_iter().map(|(id,)| id).collect();
+
+    let mut order: Vec<&String> = Vec::with_capacity(current.len());
+    for id in &payload.ids {
+        if !current.contains(id) {
+            return Err((StatusCode::BAD_REQUEST, format!("account {id} does not exist")));
+        }
+        if order.contains(&id) {
+            return Err((StatusCode::BAD_REQUEST, format!("account {id} is listed twice")));
+        }
+        order.push(id);
+    }
+    order.extend(current.iter().filter(|id| !payload.ids.contains(id)));
+
+    for (position, id) in order.into_iter().enumerate() {
+        sqlx::query("UPDATE accounts SET position = ?1 WHERE id = ?2")
+            .bind(position as i64)
+            .bind(id)
+            .execute(&mut *tx)
+            .await
+            .map_err(internal_error)?;
+    }
+    tx.commit().await.map_err(internal_error)?;
+
+    let _ = state.notifier.send(ServerEvent::AccountsChanged);
+    list_accounts(State(state), Query(AccountQuery::default())).await
+}
+
+async fn update_account(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(payload): Json<UpdateAccount>,
+) -> FormResult<Account> {
+    let mut errors = FieldErrors::default();
+    if let Some(name) = &payload.name {
+        errors.check("name", check_name(name));
+    }
+    let color = errors.check("color", payload.color.as_deref().map(normalize_color).transpose());
+    errors.finish()?;
+    let color = color.flatten();
+
+    let mut tx = state.pool.begin().await.map_err(internal_error)?;
+    let existing = fetch_account(&mut *tx, state.balance_mode, &id)
+        .await?
+        .ok_or((StatusCode::NOT_FOUND, "account not found".to_string()))?;
+    if payload.name.as_ref().is_some_and(|name| *name != existing.name)
+        && DEFAULT_ACCOUNT_NAMES.contains(&existing.name.as_str())
+    {
+        return Err((StatusCode::CONFLICT, "default accounts cannot be renamed".to_string()).into());
+    }
+    let name = payload.name.unwrap_or(existing.name);
+    let color = color.unwrap_or(existing.color);
+    sqlx::query("UPDATE accounts SET name = ?1, color = ?2 WHERE id = ?3")
+        .bind(&name)
+        .bind(&color)
+        .bind(&id)
+        .execute(&mut *tx)
+        .await
+        .map_err(internal_error)?;
+    tx.commit().await.map_err(internal_error)?;
+
+    let _ = state.notifier.send(ServerEvent::AccountsChanged);
+    Ok(Json(Account {
+        name,
+        color,
+        ..existing
+    }))
+}
+
+// Soft delete; POST /accounts/{id}/restore undoes it.
+async fn delete_account(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let existing = fetch_account(&state.pool, state.balance_mode, &id).await?;
+
+    let Some(account) = existing else {
+        return Err((StatusCode::NOT_FOUND, "account not found".into()));
+    };
+
+    if DEFAULT_ACCOUNT_NAMES.contains(&account.name.as_str()) {
+        return Err((StatusCode::CONFLICT, "default accounts cannot be deleted".into()));
+    }
+
+    sqlx::query("UPDATE accounts SET deleted_at = ?1 WHERE id = ?2")
+        .bind(format_rfc3339(OffsetDateTime::now_utc()))
+        .bind(&id)
+        .execute(&state.pool)
+        .await
+        .map_err(internal_error)?;
+
+    notify_ledger_changed(&state);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn restore_account(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> AppResult<Account> {
+    let restored = sqlx::query("UPDATE accounts SET deleted_at = NULL WHERE id = ?1 AND deleted_at IS NOT NULL")
+        .bind(&id)
+        .execute(&state.pool)
+        .await
+        .map_err(internal_error)?
+        .rows_affected();
+    let account = fetch_account(&state.pool, state.balance_mode, &id)
+        .await?
+        .ok_or((StatusCode::NOT_FOUND, "account not found".to_string()))?;
+    if restored == 0 {
+        return Err((StatusCode::CONFLICT, "account is not deleted".into()));
+    }
+
+    notify_ledger_changed(&state);
+    Ok(Json(account))
+}
+
+async fn merge_account(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(payload): Json<MergeRequest>,
+) -> AppResult<Account> {
+    if id == payload.into {
+        return Err((StatusCode::BAD_REQUEST, "cannot merge an account into itself".into()));
+    }
+
+    let mut tx = state.pool.begin().await.map_err(internal_error)?;
+    let source = fetch_account(&mut *tx, state.balance_mode, &id)
+        .await?
+        .ok_or((StatusCode::NOT_FOUND, "account not found".to_string()))?;
+    let target = fetch_account(&mut *tx, state.balance_mode, &payload.into)
+        .await?
+        .ok_or((StatusCode::NOT_FOUND, "target account not found".to_string()))?;
+    if DEFAULT_ACCOUNT_NAMES.contains(&source.name.as_str()) {
+        return Err((StatusCode::CONFLICT, "default accounts cannot be merged away".into()));
+    }
+    if source.currency != target.currency {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "accounts with different currencies cannot be merged".into(),
+        ));
+    }
+
+    // Transfers between the two would become self-transfers; the user resolves them first.
+    let (between,): (i64,) = sqlx::query_as(
+        "SELECT COUNT(*) FROM transactions WHERE direction = 'transfer' AND ((account_id = ?1 AND to_account_id = ?2) OR (account_id = ?2 AND to_account_id = ?1))",
+    )
+    .bind(&source.id)
+    .bind(&target.id)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(internal_error)?;
+    if between > 0 {
+        return Err((
+            StatusCode::CONFLICT,
+            format!("{between} transfer(s) between these accounts must be deleted or re-pointed before merging"),
+        ));
+    }
+    let now = format_rfc3339(OffsetDateTime::now_utc());
+    sqlx::query("UPDATE transactions SET account_id = ?2, updated_at = ?3 WHERE account_id = ?1")
+        .bind(&source.id)
+        .bind(&target.id)
+        .bind(&now)
+        .execute(&mut *tx)
+        .await
+        .map_err(internal_error)?;
+    sqlx::query("UPDATE transactions SET to_account_id = ?2, updated_at = ?3 WHERE to_account_id = ?1")
+        .bind(&source.id)
+        .bind(&target.id)
+        .bind(&now)
+        .execute(&mut *tx)
+        .await
+        .map_err(internal_error)?;
+    sqlx::query("UPDATE templates SET account_id = ?2 WHERE account_id = ?1")
+        .bind(&source.id)
+        .bind(&target.id)
+        .execute(&mut *tx)
+        .await
+        .map_err(internal_error)?;
+    sqlx::query("UPDATE templates SET to_account_id = ?2 WHERE to_account_id = ?1")
+        .bind(&source.id)
+        .bind(&target.id)
+        .execute(&mut *tx)
+        .await
+        .map_err(internal_error)?;
+    sqlx::query("UPDATE archived_transactions SET account_id = ?2 WHERE account_id = ?1")
+        .bind(&source.id)
+        .bind(&target.id)
+        .execute(&mut *tx)
+        .await
+        .map_err(internal_error)?;
+    sqlx::query("UPDATE archived_transactions SET to_account_id = ?2 WHERE to_account_id = ?1")
+        .bind(&source.id)
+        .bind(&target.id)
+        .execute(&mut *tx)
+        .await
+        .map_err(internal_error)?;
+    sqlx::query(
+        "UPDATE accounts SET opening_balance = opening_balance + (SELECT opening_balance FROM accounts WHERE id = ?1) WHERE id = ?2",
+    )
+    .bind(&source.id)
+    .bind(&target.id)
+    .execute(&mut *tx)
+    .await
+    .map_err(internal_error)?;
+
+    let balance = computed_balance(&mut *tx, &target.id).await?;
+    if state.balance_mode == BalanceMode::Cached {
+        sqlx::query("UPDATE accounts SET balance = ?1 WHERE id = ?2")
+            .bind(balance)
+            .bind(&target.id)
+            .execute(&mut *tx)
+            .await
+            .map_err(internal_error)?;
+    }
+    sqlx::query("DELETE FROM accounts WHERE id = ?1")
+        .bind(&source.id)
+        .execute(&mut *tx)
+        .await
+        .map_err(internal_error)?;
+    tx.commit().await.map_err(internal_error)?;
+
+    notify_ledger_changed(&state);
+    Ok(Json(Account { balance, ..target }))
+}
+
+async fn fetch_account<'c, E>(
+    executor: E,
+    mode: BalanceMode,
+    id: &str,
+) -> Result<Option<Account>, (StatusCode, String)>
+where
+    E: sqlx::Executor<'c, Database = Sqlite>,
+{
+    sqlx::query_as(&format!(
+        "SELECT id, name, kind, currency, {} AS balance, position, color, created_at FROM accounts WHERE id = ?1 AND deleted_at IS NULL",
+        mode.column()
+    ))
+        .bind(id)
+        .fetch_optional(executor)
+        .await
+        .map(|account: Option<Account>| {
+            account.map(|account| Account {
+                balance: round_in_currency(account.balance, &account.currency),
+                ..account
+            })
+        })
+        .map_err(internal_error)
+}
+
+// Balance derived from the transaction history rather than the cached column.
+async fn computed_balance<'c, E>(executor: E, account_id: &str) -> Result<f64, (StatusCode, String)>
+where
+    E: sqlx::Executor<'c, Database = Sqlite>,
+{
+    let (balance,): (f64,) = sqlx::query_as(
+        r#"
+        SELECT CAST(COALESCE(SUM(
+            CASE
+                WHEN account_id = ?1 AND direction IN ('income', 'refund') THEN amount
+                WHEN account_id = ?1 THEN -(amount + fee)
+                WHEN to_account_id = ?1 THEN COALESCE(to_amount, amount)
+                ELSE 0
+            END
+        ), 0) + COALESCE((SELECT opening_balance FROM accounts WHERE id = ?1), 0) AS REAL)
+        FROM transactions
+        WHERE (account_id = ?1 OR to_account_id = ?1) AND scheduled = 0
+        "#,
+    )
+    .bind(account_id)
+    .fetch_one(executor)
+    .await
+    .map_err(internal_error)?;
+    Ok(balance)
+}
+
+// Archived rows are already in the opening balance, so later ones are taken back out.
+async fn account_balance_as_of(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(params): Query<AsOfQuery>,
+) -> AppResult<AccountBalance> {
+    let as_of = match parse_date_prefix(&params.as_of) {
+        Some(day) if params.as_of.len() == 10 => format!("{day}T23:59:59.999Z"),
+        _ => OffsetDateTime::parse(&params.as_of, &time::format_description::well_known::Rfc3339)
+            .map(|at| format_rfc3339(at.to_offset(time::UtcOffset::UTC)))
+            .map_err(|_| {
+                (
+                    StatusCode::BAD_REQUEST,
+                    "as_of must be a date or an RFC 3339 timestamp".to_string(),
+                )
+            })?,
+    };
+    let leg = "CASE \
+        WHEN account_id = ?1 AND direction IN ('income', 'refund') THEN amount \
+        WHEN account_id = ?1 THEN -(amount + fee) \
+        WHEN to_account_id = ?1 THEN COALESCE(to_amount, amount) \
+        ELSE 0 END";
+    let balance: Option<(f64, String)> = sqlx::query_as(&format!(
+        "SELECT CAST(opening_balance \
+            + COALESCE((SELECT SUM({leg}) FROM transactions \
+                WHERE (account_id = ?1 OR to_account_id = ?1) AND scheduled = 0 \
+                AND julianday(occurred_at) <= julianday(?2)), 0) \
+            - COALESCE((SELECT SUM({leg}) FROM archived_transactions \
+                WHERE (account_id = ?1 OR to_account_id = ?1) \
+                AND julianday(occurred_at) > julianday(?2)), 0) AS REAL), currency \
+         FROM accounts WHERE id = ?1 AND deleted_at IS NULL"
+    ))
+    .bind(&id)
+    .bind(&as_of)
+    .fetch_optional(&state.pool)
+    .await
+    .map_err(internal_error)?;
+    let Some((balance, currency)) = balance else {
+        return Err((StatusCode::NOT_FOUND, "account not found".into()));
+    };
+    Ok(Json(AccountBalance {
+        account_id: id,
+        as_of,
+        balance: round_in_currency(balance, &currency),
+    }))
+}
+
+// End-of-day balances for the last `?days=` days (default 30).
+async fn account_balance_history(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(params): Query<BalanceHistoryQuery>,
+) -> AppResult<BalanceHistory> {
+    let days = params.days.unwrap_or(DEFAULT_HISTORY_DAYS);
+    if !(1..=MAX_HISTORY_DAYS).contains(&days) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("days must be between 1 and {MAX_HISTORY_DAYS}"),
+        ));
+    }
+    let first_day = OffsetDateTime::now_utc().date() - time::Duration::days(days - 1);
+    let window_start = format!("{first_day}T00:00:00Z");
+    let leg = "CASE \
+        WHEN account_id = ?1 AND direction IN ('income', 'refund') THEN amount \
+        WHEN account_id = ?1 THEN -(amount + fee) \
+        WHEN to_account_id = ?1 THEN COALESCE(to_amount, amount) \
+        ELSE 0 END";
+    let opening: Option<(f64, String)> = sqlx::query_as(&format!(
+        "SELECT CAST(opening_balance \
+            + COALESCE((SELECT SUM({leg}) FROM transactions \
+                WHERE (account_id = ?1 OR to_account_id = ?1) AND scheduled = 0 \
+                AND julianday(occurred_at) < julianday(?2)), 0) \
+            - COALESCE((SELECT SUM({leg}) FROM archived_transactions \
+                WHERE (account_id = ?1 OR to_account_id = ?1) \
+                AND julianday(occurred_at) >= julianday(?2)), 0) AS REAL), currency \
+         FROM accounts WHERE id = ?1 AND deleted_at IS NULL"
+    ))
+    .bind(&id)
+    .bind(&window_start)
+    .fetch_optional(&state.pool)
+    .await
+    .map_err(internal_error)?;
+    let Some((mut balance, currency)) = opening else {
+        return Err((StatusCode::NOT_FOUND, "account not found".into()));
+    };
+    let changes: Vec<(String, f64)> = sqlx::query_as(&format!(
+        "SELECT day, CAST(SUM(change) AS REAL) FROM ( \
+            SELECT date(occurred_at) AS day, {leg} AS change FROM transactions \
+                WHERE (account_id = ?1 OR to_account_id = ?1) AND scheduled = 0 \
+                AND julianday(occurred_at) >= julianday(?2) \
+            UNION ALL \
+            SELECT date(occurred_at) AS day, {leg} AS change FROM archived_transactions \
+                WHERE (account_id = ?1 OR to_account_id = ?1) \
+                AND julianday(occurred_at) >= julianday(?2)) \
+         GROUP BY day"
+    ))
+    .bind(&id)
+    .bind(&window_start)
+    .fetch_all(&state.pool)
+    .await
+    .map_err(internal_error)?;
+    let changes: BTreeMap<String, f64> = changes.into_iter().collect();
+    let points = (0..days)
+        .map(|offset| {
+            let date = (first_day + time::Duration::days(offset)).to_string();
+            balance += changes.get(&date).copied().unwrap_or(0.0);
+            DailyBalance {
+                date,
+                balance: round_in_currency(balance, &currency),
+            }
+        })
+        .collect();
+    Ok(Json(BalanceHistory {
+        account_id: id,
+        points,
+    }))
+}
+
+async fn list_categories(
+    State(state): State<AppState>,
+    Query(params): Query<CategoryQuery>,
+) -> AppResult<Vec<Category>> {
+    let sql = match params.order.as_deref().unwrap_or("name") {
+        "name" => "SELECT * FROM categories ORDER BY name ASC",
+        "created" => "SELECT * FROM categories ORDER BY created_at ASC, name ASC",
+        "usage" => {
+            "SELECT c.id, c.name, c.created_at FROM categories c \
+             LEFT JOIN transaction_splits s ON s.category_id = c.id \
+             GROUP BY c.id ORDER BY COUNT(s.transaction_id) DESC, c.name ASC"
+        }
+        other => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                format!("unknown order '{other}'; expected name, created or usage"),
+            ));
+        }
+    };
+    let rows = sqlx::query_as::<_, Category>(sql)
+        .fetch_all(&state.pool)
+        .await
+        .map_err(internal_error)?;
+    Ok(Json(rows))
+}
+
+async fn create_category(
+    State(state): State<AppState>,
+    ValidJson(payload): ValidJson<CreateCategory>,
+) -> FormResult<Category> {
+    let mut errors = FieldErrors::default();
+    errors.check("name", check_name(&payload.name));
+    errors.finish()?;
+    let id = Uuid::new_v4().to_string();
+    let now = OffsetDateTime::now_utc()
+        .format(&time::format_description::well_known::Rfc3339)
+        .unwrap();
+    sqlx::query("INSERT INTO categories (id, name, created_at) VALUES (?1, ?2, ?3)")
+        .bind(&id)
+        .bind(&payload.name)
+        .bind(&now)
+        .execute(&state.pool)
+        .await
+        .map_err(|e| map_conflict(e, "category already exists"))?;
+
+    let category = Category {
+        id,
+        name: payload.name,
+        created_at: now,
+    };
+    let _ = state.notifier.send(ServerEvent::DataChanged);
+    Ok(Json(category))
+}
+
+async fn list_payees(
+    State(state): State<AppState>,
+    Query(params): Query<PayeeQuery>,
+) -> AppResult<Vec<Payee>> {
+    let q = params.q.unwrap_or_default().trim().to_lowercase();
+    let rows = sqlx::query_as::<_, Payee>(
+        r#"
+        SELECT id, name, created_at FROM payees
+        WHERE INSTR(LOWER(name), ?1) > 0
+        ORDER BY INSTR(LOWER(name), ?1) = 1 DESC, name COLLATE NOCASE ASC
+        "#,
+    )
+    .bind(&q)
+    .fetch_all(&state.pool)
+    .await
+    .map_err(internal_error)?;
+    Ok(Json(rows))
+}
+
+async fn create_payee(
+    State(state): State<AppState>,
+    ValidJson(payload): ValidJson<CreatePayee>,
+) -> AppResult<Payee> {
+    let name = payload.name.trim().to_string();
+    if name.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "payee name cannot be empty".into()));
+    }
+    check_length("name", &name, MAX_NAME_LEN)?;
+    let payee = Payee {
+        id: Uuid::new_v4().to_string(),
+        name,
+        created_at: format_rfc3339(OffsetDateTime::now_utc()),
+    };
+    sqlx::query("INSERT INTO payees (id, name, created_at) VALUES (?1, ?2, ?3)")
+        .bind(&payee.id)
+        .bind(&payee.name)
+        .bind(&payee.created_at)
+        .execute(&state.pool)
+        .await
+        .map_err(|e| map_conflict(e, "payee already exists"))?;
+    let _ = state.notifier.send(ServerEvent::DataChanged);
+    Ok(Json(payee))
+}
+
+async fn update_payee(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    ValidJson(payload): ValidJson<CreatePayee>,
+) -> AppResult<Payee> {
+    let name = payload.name.trim().to_string();
+    if name.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "payee name cannot be empty".into()));
+    }
+    check_length("name", &name, MAX_NAME_LEN)?;
+    let payee = sqlx::query_as::<_, Payee>("UPDATE payees SET name = ?1 WHERE id = ?2 RETURNING *")
+        .bind(&name)
+        .bind(&id)
+        .fetch_optional(&state.pool)
+        .await
+        .map_err(|e| map_conflict(e, "payee already exists"))?
+        .ok_or((StatusCode::NOT_FOUND, "payee not found".into()))?;
+    let _ = state.notifier.send(ServerEvent::DataChanged);
+    Ok(Json(payee))
+}
+
+async fn delete_payee(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let mut tx = state.pool.begin().await.map_err(internal_error)?;
+    sqlx::query("UPDATE transactions SET payee_id = NULL, updated_at = ?2 WHERE payee_id = ?1")
+        .bind(&id)
+        .bind(format_rfc3339(OffsetDateTime::now_utc()))
+        .execute(&mut *tx)
+        .await
+        .map_err(internal_error)?;
+    let affected = sqlx::query("DELETE FROM payees WHERE id = ?1")
+        .bind(&id)
+        .execute(&mut *tx)
+        .await
+        .map_err(internal_error)?
+        .rows_affected();
+    if affected == 0 {
+        return Err((StatusCode::NOT_FOUND, "payee not found".into()));
+    }
+    tx.commit().await.map_err(internal_error)?;
+    let _ = state.notifier.send(ServerEvent::DataChanged);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn list_tags(State(state): State<AppState>) -> AppResult<Vec<Tag>> {
+    let rows = sqlx::query_as::<_, Tag>(
+        "SELECT id, name, created_at FROM tags ORDER BY name COLLATE NOCASE ASC",
+    )
+    .fetch_all(&state.pool)
+    .await
+    .map_err(internal_error)?;
+    Ok(Json(rows))
+}
+
+async fn create_tag(
+    State(state): State<AppState>,
+    ValidJson(payload): ValidJson<CreateTag>,
+) -> AppResult<Tag> {
+    let name = payload.name.trim().to_string();
+    if name.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "tag name cannot be empty".into()));
+    }
+    check_length("name", &name, MAX_NAME_LEN)?;
+    let tag = Tag {
+        id: Uuid::new_v4().to_string(),
+        name,
+        created_at: format_rfc3339(OffsetDateTime::now_utc()),
+    };
+    sqlx::query("INSERT INTO tags (id, name, created_at) VALUES (?1, ?2, ?3)")
+        .bind(&tag.id)
+        .bind(&tag.name)
+        .bind(&tag.created_at)
+        .execute(&state.pool)
+        .await
+        .map_err(|e| map_conflict(e, "tag already exists"))?;
+    let _ = state.notifier.send(ServerEvent::DataChanged);
+    Ok(Json(tag))
+}
+
+async fn replace_tags(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    ValidJson(body): ValidJson<ReplaceTags>,
+) -> AppResult<Transaction> {
+    let mut names: Vec<String> = Vec::new();
+    for name in body.tags {
+        let name = name.trim().to_string();
+        if name.is_empty() {
+            return Err((StatusCode::BAD_REQUEST, "tag name cannot be empty".into()));
+        }
+        check_length("name", &name, MAX_NAME_LEN)?;
+        if !names.iter().any(|n| n.eq_ignore_ascii_case(&name)) {
+            names.push(name);
+        }
+    }
+
+    let mut tx = state.pool.begin().await.map_err(internal_error)?;
+    let row = sqlx::query_as::<_, TransactionRow>("SELECT * FROM transactions WHERE id = ?1")
+        .bind(&id)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(internal_error)?
+        .ok_or((StatusCode::NOT_FOUND, "transaction not found".to_string()))?;
+    let now = format_rfc3339(OffsetDateTime::now_utc());
+    let before = history_snapshot(&mut tx, &id).await?;
+    sqlx::query("DELETE FROM transaction_tags WHERE transaction_id = ?1")
+        .bind(&id)
+        .execute(&mut *tx)
+        .await
+        .map_err(internal_error)?;
+    let mut created_tag = false;
+    for name in &names {
+        created_tag |= sqlx::query("INSERT OR IGNORE INTO tags (id, name, created_at) VALUES (?1, ?2, ?3)")
+            .bind(Uuid::new_v4().to_string())
+            .bind(name)
+            .bind(&now)
+            .execute(&mut *tx)
+            .await
+            .map_err(internal_error)?
+            .rows_affected()
+            > 0;
+        sqlx::query("INSERT INTO transaction_tags (transaction_id, tag_id) SELECT ?1, id FROM tags WHERE name = ?2")
+            .bind(&id)
+            .bind(name)
+            .execute(&mut *tx)
+            .await
+            .map_err(internal_error)?;
+    }
+    sqlx::query("UPDATE transactions SET updated_at = ?2 WHERE id = ?1")
+        .bind(&id)
+        .bind(&now)
+        .execute(&mut *tx)
+        .await
+        .map_err(internal_error)?;
+    let after = history_snapshot(&mut tx, &id).await?;
+    record_history(&mut tx, &id, "updated", Some(&before), Some(&after)).await?;
+    tx.commit().await.map_err(internal_error)?;
+
+    let updated = hydrate_transaction(
+        &state.pool,
+        TransactionRow {
+            updated_at: now,
+            ..row
+        },
+    )
+    .await?;
+    let event = if created_tag {
+        ServerEvent::DataChanged
+    } else {
+        ServerEvent::TransactionsChanged
+    };
+    let _ = state.notifier.send(event);
+    Ok(Json(updated))
+}
+
+async fn tag_names(pool: &SqlitePool, txn_id: &str) -> Result<Vec<String>, (StatusCode, String)> {
+    let rows: Vec<(String,)> = sqlx::query_as(
+        "SELECT g.name FROM transaction_tags tt JOIN tags g ON g.id = tt.tag_id \
+         WHERE tt.transaction_id = ?1 ORDER BY g.name COLLATE NOCASE ASC",
+    )
+    .bind(txn_id)
+    .fetch_all(pool)
+    .await
+    .map_err(internal_error)?;
+    Ok(rows.into_iter().map(|(name,)| name).collect())
+}
+
+async fn category_accounts(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> AppResult<Vec<CategoryAccount>> {
+    let exists: Option<(String,)> = sqlx::query_as("SELECT id FROM categories WHERE id = ?1")
+        .bind(&id)
+        .fetch_optional(&state.pool)
+        .await
+        .map_err(internal_error)?;
+    if exists.is_none() {
+        return Err((StatusCode::NOT_FOUND, "category not found".into()));
+    }
+
+    let rows = sqlx::query_as::<_, CategoryAccount>(
+        r#"
+        SELECT a.id AS account_id, a.name AS name, a.kind AS kind,
+            CAST(SUM(CASE WHEN t.direction = 'refund' THEN -s.amount ELSE s.amount END) AS REAL) AS total,
+            COUNT(DISTINCT t.id) AS count, a.currency AS currency
+        FROM transaction_splits s
+        JOIN transactions t ON t.id = s.transaction_id
+        JOIN accounts a ON a.id = t.account_id
+        WHERE s.category_id = ?1 AND a.deleted_at IS NULL
+        GROUP BY a.id, a.name, a.kind, a.currency
+        ORDER BY total DESC, a.name ASC
+        "#,
+    )
+    .bind(&id)
+    .fetch_all(&state.pool)
+    .await
+    .map_err(internal_error)?;
+    Ok(Json(
+        rows.into_iter()
+            .map(|a| CategoryAccount {
+                total: round_in_currency(a.total, &a.currency),
+                ..a
+            })
+            .collect(),
+    ))
+}
+
+async fn category_duplicates(State(state): State<AppState>) -> AppResult<Vec<CategoryDuplicates>> {
+    let rows = sqlx::query_as::<_, DuplicateCategory>(
+        r#"
+        SELECT c.id, c.name, c.created_at, COUNT(s.transaction_id) AS split_count
+        FROM categories c
+        LEFT JOIN transaction_splits s ON s.category_id = c.id
+        GROUP BY c.id
+        ORDER BY split_count DESC, c.created_at ASC, c.id ASC
+        "#,
+    )
+    .fetch_all(&state.pool)
+    .await
+    .map_err(internal_error)?;
+
+    let mut groups: BTreeMap<String, Vec<DuplicateCategory>> = BTreeMap::new();
+    for row in rows {
+        groups.entry(row.name.trim().to_lowercase()).or_default().push(row);
+    }
+    let duplicates = groups
+        .into_iter()
+        .filter(|(_, categories)| categories.len() > 1)
+        .map(|(normalized_name, categories)| CategoryDuplicates {
+            normalized_name,
+            suggested_id: categories[0].id.clone(),
+            categories,
+        })
+        .collect();
+    Ok(Json(duplicates))
+}
+
+async fn merge_category(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(payload): Json<MergeRequest>,
+) -> AppResult<Category> {
+    if id == payload.into {
+        return Err((StatusCode::BAD_REQUEST, "cannot merge a category into itself".into()));
+    }
+
+    let mut tx = state.pool.begin().await.map_err(internal_error)?;
+    let source: Option<Category> = sqlx::query_as("SELECT * FROM categories WHERE id = ?1")
+        .bind(&id)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(internal_error)?;
+    if source.is_none() {
+        return Err((StatusCode::NOT_FOUND, "category not found".into()));
+    }
+    let target: Category = sqlx::query_as("SELECT * FROM categories WHERE id = ?1")
+        .bind(&payload.into)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(internal_error)?
+        .ok_or((StatusCode::NOT_FOUND, "target category not found".to_string()))?;
+
+    sqlx::query("UPDATE transactions SET updated_at = ?2 WHERE id IN (SELECT transaction_id FROM transaction_splits WHERE category_id = ?1)")
+        .bind(&id)
+        .bind(format_rfc3339(OffsetDateTime::now_utc()))
+        .execute(&mut *tx)
+        .await
+        .map_err(internal_error)?;
+    // Fold the source split into the target's where a transaction has both.
+    sqlx::query(
+        "UPDATE transaction_splits SET amount = amount + (SELECT SUM(s.amount) FROM transaction_splits s WHERE s.transaction_id = transaction_splits.transaction_id AND s.category_id = ?1) \
+         WHERE id IN (SELECT MIN(t.id) FROM transaction_splits t WHERE t.category_id = ?2 AND t.transaction_id IN (SELECT transaction_id FROM transaction_splits WHERE category_id = ?1) GROUP BY t.transaction_id)",
+    )
+    .bind(&id)
+    .bind(&target.id)
+    .execute(&mut *tx)
+    .await
+    .map_err(internal_error)?;
+    sqlx::query("DELETE FROM transaction_splits WHERE category_id = ?1 AND transaction_id IN (SELECT transaction_id FROM transaction_splits WHERE category_id = ?2)")
+        .bind(&id)
+        .bind(&target.id)
+        .execute(&mut *tx)
+        .await
+        .map_err(internal_error)?;
+    sqlx::query("UPDATE transaction_splits SET category_id = ?2 WHERE category_id = ?1")
+        .bind(&id)
+        .bind(&target.id)
+        .execute(&mut *tx)
+        .await
+        .map_err(internal_error)?;
+    sqlx::query("UPDATE templates SET category_id = ?2 WHERE category_id = ?1")
+        .bind(&id)
+        .bind(&target.id)
+        .execute(&mut *tx)
+        .await
+        .map_err(internal_error)?;
+    sqlx::query("UPDATE categorization_rules SET category_id = ?2 WHERE category_id = ?1")
+        .bind(&id)
+        .bind(&target.id)
+        .execute(&mut *tx)
+        .await
+        .map_err(internal_error)?;
+    sqlx::query("DELETE FROM categories WHERE id = ?1")
+        .bind(&id)
+        .execute(&mut *tx)
+        .await
+        .map_err(internal_error)?;
+    tx.commit().await.map_err(internal_error)?;
+
+    let _ = state.notifier.send(ServerEvent::DataChanged);
+    Ok(Json(target))
+}
+
+async fn list_transactions(
+    State(state): State<AppState>,
+    Query(params): Query<TransactionQuery>,
+) -> Result<([(&'static str, String); 3], Json<Vec<Transaction>>), (StatusCode, String)> {
+    activate_due_transactions(&state).await?;
+    if params.limit.is_some_and(|limit| limit < 1) {
+        return Err((StatusCode::BAD_REQUEST, "limit must be at least 1".into()));
+    }
+    if params.offset.is_some_and(|offset| offset < 0) {
+        return Err((StatusCode::BAD_REQUEST, "offset must not be negative".into()));
+    }
+    let limit = params
+        .limit
+        .unwrap_or(i64::MAX)
+        .min(state.max_transaction_list);
+    let offset = params.offset.unwrap_or(0);
+    let account_ids = params.account_ids();
+    check_account_ids(&state.pool, &account_ids).await?;
+
+    // Signed total over every matching row, not just this page.
+    let mut count = QueryBuilder::<Sqlite>::new(
+        "SELECT COUNT(*), CAST(COALESCE(SUM(CASE WHEN direction IN ('income', 'refund') THEN amount \
+         WHEN direction = 'expense' THEN -amount ",
+    );
+    if account_ids.is_empty() {
+        count.push("ELSE -fee END), 0) AS REAL)");
+    } else {
+        count.push("ELSE (CASE WHEN to_account_id IN ");
+        push_id_list(&mut count, &account_ids);
+        count.push(" THEN COALESCE(to_amount, amount) ELSE 0 END) - (CASE WHEN account_id IN ");
+        push_id_list(&mut count, &account_ids);
+        count.push(" THEN amount + fee ELSE 0 END) END), 0) AS REAL)");
+    }
+    count.push(" FROM transactions WHERE 1 = 1");
+    push_transaction_filters(&mut count, &params)?;
+    let (total, total_amount): (i64, f64) = count
+        .build_query_as()
+        .fetch_one(&state.pool)
+        .await
+        .map_err(internal_error)?;
+
+    let mut query = QueryBuilder::<Sqlite>::new("SELECT * FROM transactions WHERE 1 = 1");
+    push_transaction_filters(&mut query, &params)?;
+    query
+        .push(" ORDER BY occurred_at DESC, created_at DESC LIMIT ")
+        .push_bind(limit)
+        .push(" OFFSET ")
+        .push_bind(offset);
+
+    let base_rows = query
+        .build_query_as::<TransactionRow>()
+        .fetch_all(&state.pool)
+        .await
+        .map_err(internal_error)?;
+
+    let mut results = Vec::with_capacity(base_rows.len());
+    for row in base_rows {
+        results.push(hydrate_transaction(&state.pool, row).await?);
+    }
+    let truncated = limit < params.limit.unwrap_or(i64::MAX) && offset + limit < total;
+    let currencies: Vec<(String,)> = if account_ids.is_empty() {
+        Vec::new()
+    } else {
+        let mut query = QueryBuilder::<Sqlite>::new("SELECT DISTINCT currency FROM accounts WHERE id IN ");
+        push_id_list(&mut query, &account_ids);
+        query
+            .build_query_as()
+            .fetch_all(&state.pool)
+            .await
+            .map_err(internal_error)?
+    };
+    let currency = match currencies.as_slice() {
+        [(currency,)] => currency.as_str(),
+        _ => state.money.currency.as_str(),
+    };
+    let digits = currency_minor_units(currency) as usize;
+    let total_amount = round_in_currency(total_amount, currency);
+    Ok((
+        [
+            (TOTAL_COUNT_HEADER, total.to_string()),
+            (TRUNCATED_HEADER, truncated.to_string()),
+            (TOTAL_AMOUNT_HEADER, format!("{total_amount:.digits$}")),
+        ],
+        Json(results),
+    ))
+}
+
+fn push_id_list(query: &mut QueryBuilder<'_, Sqlite>, ids: &[String]) {
+    query.push("(");
+    let mut list = query.separated(", ");
+    for id in ids {
+        list.push_bind(id.clone());
+    }
+    list.push_unseparated(")");
+}
+
+async fn check_account_ids(pool: &SqlitePool, ids: &[String]) -> Result<(), (StatusCode, String)> {
+    if ids.is_empty() {
+        return Ok(());
+    }
+    let mut query = QueryBuilder::<Sqlite>::new("SELECT id FROM accounts WHERE deleted_at IS NULL AND id IN ");
+    push_id_list(&mut query, ids);
+    let found: Vec<(String,)> = query
+        .build_query_as()
+        .fetch_all(pool)
+        .await
+        .map_err(internal_error)?;
+    match ids.iter().find(|id| !found.iter().any(|(found,)| found == *id)) {
+        Some(missing) => Err((
+            StatusCode::BAD_REQUEST,
+            format!("unknown account_id '{missing}'"),
+        )),
+        None => Ok(()),
+    }
+}
+
+fn push_transaction_filters(
+    query: &mut QueryBuilder<'_, Sqlite>,
+    params: &TransactionQuery,
+) -> Result<(), (StatusCode, String)> {
+    if let Some(period) = &params.period {
+        let since = period_start(period, OffsetDateTime::now_utc())?;
+        query.push(" AND occurred_at >= ").push_bind(format_rfc3339(since));
+    }
+    if let Some(scheduled) = params.scheduled {
+        query.push(" AND scheduled = ").push_bind(scheduled);
+    }
+    query.push(HIDE_DELETED_ACCOUNTS);
+    let account_ids = params.account_ids();
+    if !account_ids.is_empty() {
+        query.push(" AND (account_id IN ");
+        push_id_list(query, &account_ids);
+        query.push(" OR to_account_id IN ");
+        push_id_list(query, &account_ids);
+        query.push(")");
+    }
+    if let Some(source) = &params.source {
+        if !["manual", "import", "recurring", "api"].contains(&source.as_str()) {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                format!("unknown source '{source}'; expected manual, import, recurring or api"),
+            ));
+        }
+        query.push(" AND source = ").push_bind(source.clone());
+    }
+    if params.uncategorized == Some(true) {
+        query.push(
+            " AND direction <> 'transfer' AND NOT EXISTS \
+             (SELECT 1 FROM transaction_splits s WHERE s.transaction_id = transactions.id)",
+        );
+    }
+    for (param, column, since) in [
+        ("updated_since", "updated_at", &params.updated_since),
+        ("created_since", "created_at", &params.created_since),
+    ] {
+        if let Some(since) = created_bound(param, since)? {
+            query
+                .push(format!(" AND julianday({column}) >= julianday("))
+                .push_bind(since)
+                .push(")");
+        }
+    }
+    Ok(())
+}
+
+async fn get_transaction(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> AppResult<Transaction> {
+    let row = sqlx::query_as::<_, TransactionRow>("SELECT * FROM transactions WHERE id = ?1")
+        .bind(&id)
+        .fetch_optional(&state.pool)
+        .await
+        .map_err(internal_error)?
+        .ok_or((StatusCode::NOT_FOUND, "transaction not found".to_string()))?;
+
+    let txn = hydrate_transaction(&state.pool, row).await?;
+    Ok(Json(txn))
+}
+
+async fn hydrate_transaction(
+    pool: &SqlitePool,
+    row: TransactionRow,
+) -> Result<Transaction, (StatusCode, String)> {
+    hydrate_from(pool, row, "transaction_splits").await
+}
+
+fn directed_amount(
+    direction: &TransactionDirection,
+    amount: f64,
+    account_id: &str,
+    to_account_id: Option<&str>,
+    to_amount: Option<f64>,
+    fee: f64,
+) -> DirectedAmount {
+    match (direction, to_account_id) {
+        (TransactionDirection::Transfer, Some(to)) => {
+            DirectedAmount::transfer(amount, account_id, to, to_amount, fee)
+        }
+        _ => DirectedAmount::new(amount, direction),
+    }
+}
+
+async fn hydrate_from(
+    pool: &SqlitePool,
+    row: TransactionRow,
+    splits_table: &str,
+) -> Result<Transaction, (StatusCode, String)> {
+    let splits = sqlx::query_as::<_, TransactionSplit>(&format!(
+        "SELECT transaction_id, category_id, amount FROM {splits_table} WHERE transaction_id = ?1"
+    ))
+    .bind(&row.id)
+    .fetch_all(pool)
+    .await
+    .map_err(internal_error)?;
+    let tags = tag_names(pool, &row.id).await?;
+
+    let direction = parse_direction(&row.direction)?;
+    let amount = directed_amount(&direction, row.amount, &row.account_id, row.to_account_id.as_deref(), row.to_amount, row.fee);
+    Ok(Transaction {
+        id: row.id,
+        account_id: row.account_id,
+        to_account_id: row.to_account_id,
+        amount,
+        to_amount: row.to_amount,
+        fee: row.fee,
+        direction,
+        description: row.description,
+        payee_id: row.payee_id,
+        external_id: row.external_id,
+        occurred_at: row.occurred_at,
+        scheduled: row.scheduled,
+        reconciled: row.reconciled,
+        source: row.source,
+        splits,
+        tags,
+        created_at: row.created_at,
+        updated_at: row.updated_at,
+    })
+}
+
+async fn create_transaction(
+    State(state): State<AppState>,
+    Query(params): Query<DuplicateParam>,
+    ValidJson(payload): ValidJson<CreateTransaction>,
+) -> FormResult<Transaction> {
+    if !matches!(payload.source, TransactionSource::Manual | TransactionSource::Api) {
+        let mut errors = FieldErrors::default();
+        errors.add("source", "source must be manual or api".into());
+        errors.finish()?;
+    }
+    check_transaction_fields(&state.pool, &payload).await?;
+    if !params.allow_duplicate {
+        check_duplicate(&state.pool, &payload).await?;
+    }
+    let created = retry_busy(|| insert_transaction(&state, payload.clone())).await?;
+    notify_ledger_changed(&state);
+    raise_alerts(&state, &created).await;
+    Ok(Json(created))
+}
+
+// Rejects an entry matching an existing one within DUPLICATE_WINDOW.
+async fn check_duplicate(
+    pool: &SqlitePool,
+    payload: &CreateTransaction,
+) -> Result<(), (StatusCode, String)> {
+    let occurred_at = payload
+        .occurred_at
+        .clone()
+        .unwrap_or_else(|| format_rfc3339(OffsetDateTime::now_utc()));
+    let existing: Option<(String,)> = sqlx::query_as(
+        "SELECT id FROM transactions WHERE account_id = ?1 AND amount = ?2 AND direction = ?3 AND ABS(julianday(occurred_at) - julianday(?4)) * 86400 <= ?5 LIMIT 1",
+    )
+    .bind(&payload.account_id)
+    .bind(payload.amount)
+    .bind(payload.direction.as_str())
+    .bind(&occurred_at)
+    .bind(DUPLICATE_WINDOW.as_secs() as i64)
+    .fetch_optional(pool)
+    .await
+    .map_err(internal_error)?;
+    match existing {
+        Some((id,)) => Err((
+            StatusCode::CONFLICT,
+            format!("possible duplicate of transaction {id}; retry with allow_duplicate=true to record it anyway"),
+        )),
+        None => Ok(()),
+    }
+}
+
+// Stored timestamps compare as strings, so they must share the UTC offset.
+fn occurred_at_utc(
+    value: Option<&str>,
+    now: OffsetDateTime,
+) -> Result<OffsetDateTime, (StatusCode, String)> {
+    match value {
+        Some(value) => OffsetDateTime::parse(value, &time::format_description::well_known::Rfc3339)
+            .map(|at| at.to_offset(time::UtcOffset::UTC))
+            .map_err(|_| {
+                (
+                    StatusCode::BAD_REQUEST,
+                    "occurred_at must be an RFC 3339 timestamp".to_string(),
+                )
+            }),
+        None => Ok(now),
+    }
+}
+
+async fn insert_transaction(
+    state: &AppState,
+    mut payload: CreateTransaction,
+) -> Result<Transaction, (StatusCode, String)> {
+    resolve_amounts(&state.pool, &mut payload).await?;
+    check_accounts_live(&state.pool, &payload).await?;
+    let txn_id = Uuid::new_v4().to_string();
+    let now_at = OffsetDateTime::now_utc();
+    let now = format_rfc3339(now_at);
+    let occurred = occurred_at_utc(payload.occurred_at.as_deref(), now_at)?;
+    let occurred_at = format_rfc3339(occurred);
+    let scheduled = occurred > now_at;
+    let direction = payload.direction.clone();
+    if payload.amount < 0.0 {
+        return Err((StatusCode::BAD_REQUEST, "amount must be non-negative".into()));
+    }
+    if let Some(description) = &payload.description {
+        check_length("description", description, MAX_DESCRIPTION_LEN)?;
+    }
+    check_distinct_splits(payload.splits.as_deref().unwrap_or_default())?;
+    check_payee(&state.pool, payload.payee_id.as_deref()).await?;
+    let fee = check_fee(payload.fee, &payload.direction)?;
+
+    let (to_account_id, to_amount) = match direction {
+        TransactionDirection::Transfer => {
+            let dest = payload
+                .to_account_id
+                .clone()
+                .ok_or((StatusCode::BAD_REQUEST, "transfer requires destination account".into()))?;
+            if dest == payload.account_id {
+                return Err((StatusCode::BAD_REQUEST, "source and destination cannot match".into()));
+            }
+            let to_amount = resolve_to_amount(
+                &state.pool,
+                &payload.account_id,
+                &dest,
+                payload.amount,
+                payload.to_amount,
+            )
+            .await?;
+            (Some(dest), to_amount)
+        }
+        _ => (None, None),
+    };
+
+    let mut tx = state.pool.begin().await.map_err(internal_error)?;
+    sqlx::query("INSERT INTO transactions (id, account_id, to_account_id, amount, to_amount, fee, direction, description, payee_id, external_id, occurred_at, scheduled, source, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)")
+        .bind(&txn_id)
+        .bind(&payload.account_id)
+        .bind(&to_account_id)
+        .bind(payload.amount)
+        .bind(to_amount)
+        .bind(fee)
+        .bind(direction.as_str())
+        .bind(&payload.description)
+        .bind(&payload.payee_id)
+        .bind(&payload.external_id)
+        .bind(&occurred_at)
+        .bind(scheduled)
+        .bind(payload.source.as_str())
+        .bind(&now)
+        .bind(&now)
+        .execute(&mut *tx)
+        .await
+        .map_err(|err| map_conflict(err, "a transaction with this external_id already exists"))?;
+
+    let splits = if direction == TransactionDirection::Transfer {
+        Vec::new()
+    } else {
+        payload
+            .splits
+            .unwrap_or_default()
+            .into_iter()
+            .map(|s| TransactionSplit {
+                transaction_id: txn_id.clone(),
+                category_id: s.category_id,
+                amount: s.amount,
+            })
+            .collect::<Vec<_>>()
+    };
+
+    for split in &splits {
+        sqlx::query("INSERT INTO transaction_splits (transaction_id, category_id, amount) VALUES (?1, ?2, ?3)")
+            .bind(&split.transaction_id)
+            .bind(&split.category_id)
+            .bind(split.amount)
+            .execute(&mut *tx)
+            .await
+            .map_err(internal_error)?;
+    }
+
+    if !scheduled {
+        apply_balance(
+            &mut tx,
+            state.balance_mode,
+            &direction,
+            &payload.account_id,
+            to_account_id
+                .as_deref()
+                .map(|dest| (dest, to_amount.unwrap_or(payload.amount))),
+            payload.amount,
+            fee,
+        )
+        .await?;
+    }
+
+    let after = history_snapshot(&mut tx, &txn_id).await?;
+    record_history(&mut tx, &txn_id, "created", None, Some(&after)).await?;
+
+    tx.commit().await.map_err(internal_error)?;
+
+    let amount = directed_amount(&direction, payload.amount, &payload.account_id, to_account_id.as_deref(), to_amount, fee);
+    let created = Transaction {
+        id: txn_id,
+        account_id: payload.account_id,
+        to_account_id,
+        amount,
+        to_amount,
+        fee,
+        direction,
+        description: payload.description,
+        payee_id: payload.payee_id,
+        external_id: payload.external_id,
+        occurred_at,
+        scheduled,
+        reconciled: false,
+        source: payload.source.as_str().to_string(),
+        splits,
+        tags: Vec::new(),
+        created_at: now.clone(),
+        updated_at: now,
+    };
+    Ok(created)
+}
+
+// The transaction row must already be written, so computed mode sees it.
+async fn apply_balance(
+    tx: &mut sqlx::Transaction<'_, Sqlite>,
+    mode: BalanceMode,
+    direction: &TransactionDirection,
+    account_id: &str,
+    destination: Option<(&str, f64)>,
+    amount: f64,
+    fee: f64,
+) -> Result<(), (StatusCode, String)> {
+    match direction {
+        TransactionDirection::Income | TransactionDirection::Refund => {
+            if !adjust_balance(tx, mode, account_id, amount, false).await? {
+                return Err((StatusCode::NOT_FOUND, "source account not found".into()));
+            }
+        }
+        TransactionDirection::Expense => {
+            if !adjust_balance(tx, mode, account_id, -amount, true).await? {
+                return Err((StatusCode::BAD_REQUEST, "insufficient funds or account not found".into()));
+            }
+        }
+        TransactionDirection::Transfer => {
+            if let Some((dest, received)) = destination {
+                if !adjust_balance(tx, mode, account_id, -(amount + fee), true).await? {
+                    return Err((StatusCode::BAD_REQUEST, "insufficient funds or account not found".into()));
+                }
+                if !adjust_balance(tx, mode, dest, received, false).await? {
+                    return Err((StatusCode::NOT_FOUND, "destination account not found".into()));
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// Returns false if the account is missing or a guarded change would overdraw it.
+async fn adjust_balance(
+    tx: &mut sqlx::Transaction<'_, Sqlite>,
+    mode: BalanceMode,
+    account_id: &str,
+    delta: f64,
+    guarded: bool,
+) -> Result<bool, (StatusCode, String)> {
+    if mode == BalanceMode::Computed {
+        let kind: Option<(String,)> = sqlx::query_as("SELECT kind FROM accounts WHERE id = ?1")
+            .bind(account_id)
+            .fetch_optional(&mut **tx)
+            .await
+            .map_err(internal_error)?;
+        return match kind {
+            None => Ok(false),
+            Some((kind,)) if !guarded || kind == "credit" || kind == "investment" => Ok(true),
+            Some(_) => Ok(computed_balance(&mut **tx, account_id).await? > -BALANCE_DRIFT_TOLERANCE),
+        };
+    }
+
+    let sql = if guarded {
+        "UPDATE accounts SET balance = balance + ?1 WHERE id = ?2 AND (kind IN ('credit', 'investment') OR balance + ?1 >= 0)"
+    } else {
+        "UPDATE accounts SET balance = balance + ?1 WHERE id = ?2"
+    };
+    let affected = sqlx::query(sql)
+        .bind(delta)
+        .bind(account_id)
+        .execute(&mut **tx)
+        .await
+        .map_err(internal_error)?
+        .rows_affected();
+    Ok(affected > 0)
+}
+
+// Posts scheduled transactions whose date has arrived; failures stay scheduled.
+async fn activate_due_transactions(state: &AppState) -> Result<(), (StatusCode, String)> {
+    if state.read_only {
+        return Ok(());
+    }
+    let now = format_rfc3339(OffsetDateTime::now_utc());
+    let due = sqlx::query_as::<_, TransactionRow>(
+        &format!("SELECT * FROM transactions WHERE scheduled = 1 AND occurred_at <= ?1{HIDE_DELETED_ACCOUNTS} ORDER BY occurred_at ASC"),
+    )
+    .bind(&now)
+    .fetch_all(&state.pool)
+    .await
+    .map_err(internal_error)?;
+
+    let mut activated = 0;
+    for row in due {
+        let direction = parse_direction(&row.direction)?;
+        let mut tx = state.pool.begin().await.map_err(internal_error)?;
+        // Only the request that flips the flag applies the balance.
+        let flipped = sqlx::query("UPDATE transactions SET scheduled = 0, updated_at = ?2 WHERE id = ?1 AND scheduled = 1")
+            .bind(&row.id)
+            .bind(format_rfc3339(OffsetDateTime::now_utc()))
+            .execute(&mut *tx)
+            .await
+            .map_err(internal_error)?;
+        if flipped.rows_affected() == 0 {
+            continue;
+        }
+        let applied = apply_balance(
+            &mut tx,
+            state.balance_mode,
+            &direction,
+            &row.account_id,
+            row.to_account_id
+                .as_deref()
+                .map(|dest| (dest, row.to_amount.unwrap_or(row.amount))),
+            row.amount,
+            row.fee,
+        )
+        .await;
+        if let Err((_, message)) = applied {
+            warn!("scheduled transaction {} not activated: {}", row.id, message);
+            continue;
+        }
+        tx.commit().await.map_err(internal_error)?;
+        activated += 1;
+    }
+
+    if activated > 0 {
+        info!("activated {} scheduled transaction(s)", activated);
+        notify_ledger_changed(state);
+    }
+    Ok(())
+}
+
+// Archived rows are folded into opening_balance so balances stay the same.
+async fn archive_old_transactions(state: &AppState, days: i64) -> Result<u64, (StatusCode, String)> {
+    let now = OffsetDateTime::now_utc();
+    let cutoff = format_rfc3339(now - time::Duration::days(days));
+    let archived_at = format_rfc3339(now);
+
+    let mut tx = state.pool.begin().await.map_err(internal_error)?;
+    sqlx::query(
+        r#"
+        UPDATE accounts SET opening_balance = opening_balance + (
+            SELECT COALESCE(SUM(
+                CASE
+                    WHEN t.account_id = accounts.id AND t.direction IN ('income', 'refund') THEN t.amount
+                    WHEN t.account_id = accounts.id THEN -(t.amount + t.fee)
+                    WHEN t.to_account_id = accounts.id THEN COALESCE(t.to_amount, t.amount)
+                    ELSE 0
+                END
+            ), 0)
+            FROM transactions t
+            WHERE (t.account_id = accounts.id OR t.to_account_id = accounts.id)
+                AND t.scheduled = 0 AND julianday(t.occurred_at) < julianday(?1)
+        )
+        "#,
+    )
+    .bind(&cutoff)
+    .execute(&mut *tx)
+    .await
+    .map_err(internal_error)?;
+    sqlx::query(
+        r#"
+        INSERT INTO archived_transaction_splits (transaction_id, category_id, amount)
+        SELECT s.transaction_id, s.category_id, s.amount
+        FROM transaction_splits s JOIN transactions t ON t.id = s.transaction_id
+        WHERE t.scheduled = 0 AND julianday(t.occurred_at) < julianday(?1)
+        "#,
+    )
+    .bind(&cutoff)
+    .execute(&mut *tx)
+    .await
+    .map_err(internal_error)?;
+    sqlx::query(
+        r#"
+        INSERT INTO archived_transactions (
+            id, account_id, to_account_id, amount, to_amount, fee, direction, description,
+            payee_id, external_id, occurred_at, scheduled, reconciled, source, created_at,
+            updated_at, archived_at
+        )
+        SELECT
+            id, account_id, to_account_id, amount, to_amount, fee, direction, description,
+            payee_id, external_id, occurred_at, scheduled, reconciled, source, created_at,
+            updated_at, ?2
+        FROM transactions
+        WHERE scheduled = 0 AND julianday(occurred_at) < julianday(?1)
         "#,
     )
+    .bind(&cutoff)
+    .bind(&archived_at)
+    .execute(&mut *tx)
+    .await
+    .map_err(internal_error)?;
+    sqlx::query(
+        "DELETE FROM transaction_splits WHERE transaction_id IN (SELECT id FROM transactions WHERE scheduled = 0 AND julianday(occurred_at) < julianday(?1))",
+    )
+    .bind(&cutoff)
+    .execute(&mut *tx)
+    .await
+    .map_err(internal_error)?;
+    sqlx::query(
+        "DELETE FROM transaction_tags WHERE transaction_id IN (SELECT id FROM transactions WHERE scheduled = 0 AND julianday(occurred_at) < julianday(?1))",
+    )
+    .bind(&cutoff)
+    .execute(&mut *tx)
+    .await
+    .map_err(internal_error)?;
+    let archived = sqlx::query("DELETE FROM transactions WHERE scheduled = 0 AND julianday(occurred_at) < julianday(?1)")
+        .bind(&cutoff)
+        .execute(&mut *tx)
+        .await
+        .map_err(internal_error)?
+        .rows_affected();
+    tx.commit().await.map_err(internal_error)?;
+
+    if archived > 0 {
+        info!("archived {} transaction(s) older than {} days", archived, days);
+        let _ = state.notifier.send(ServerEvent::TransactionsChanged);
+    }
+    Ok(archived)
+}
+
+async fn list_archived_transactions(State(state): State<AppState>) -> AppResult<Vec<Transaction>> {
+    let rows = sqlx::query_as::<_, TransactionRow>(
+        "SELECT * FROM archived_transactions ORDER BY occurred_at DESC, created_at DESC",
+    )
+    .fetch_all(&state.pool)
+    .await
+    .map_err(internal_error)?;
+
+    let mut results = Vec::with_capacity(rows.len());
+    for row in rows {
+        results.push(hydrate_from(&state.pool, row, "archived_transaction_splits").await?);
+    }
+    Ok(Json(results))
+}
+
+async fn delete_transaction(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(params): Query<ForceParam>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    retry_busy(|| delete_transaction_by_id(&state, &id, params.force)).await?;
+    notify_ledger_changed(&state);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn delete_transaction_by_id(
+    state: &AppState,
+    id: &str,
+    force: bool,
+) -> Result<(), (StatusCode, String)> {
+    let mut tx = state.pool.begin().await.map_err(internal_error)?;
+    let existing: Option<TransactionRow> =
+        sqlx::query_as("SELECT * FROM transactions WHERE id = ?1")
+            .bind(id)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(internal_error)?;
+    let Some(row) = existing else {
+        return Err((StatusCode::NOT_FOUND, "transaction not found".into()));
+    };
+    ensure_unlocked(&row, force)?;
+
+    remove_transaction(&mut tx, state.balance_mode, &row).await?;
+
+    tx.commit().await.map_err(internal_error)?;
+    Ok(())
+}
+
+async fn delete_transactions_batch(
+    State(state): State<AppState>,
+    Query(params): Query<ForceParam>,
+    Json(payload): Json<BatchDelete>,
+) -> AppResult<BatchDeleteResult> {
+    let result = retry_busy(|| delete_transaction_ids(&state, &payload.ids, params.force)).await?;
+    if result.deleted > 0 {
+        notify_ledger_changed(&state);
+    }
+    Ok(Json(result))
+}
+
+async fn delete_transaction_ids(
+    state: &AppState,
+    ids: &[String],
+    force: bool,
+) -> Result<BatchDeleteResult, (StatusCode, String)> {
+    let mut tx = state.pool.begin().await.map_err(internal_error)?;
+    let mut deleted = 0;
+    let mut not_found = Vec::new();
+
+    for id in ids {
+        let existing: Option<TransactionRow> =
+            sqlx::query_as("SELECT * FROM transactions WHERE id = ?1")
+                .bind(id)
+                .fetch_optional(&mut *tx)
+                .await
+                .map_err(internal_error)?;
+        let Some(row) = existing else {
+            not_found.push(id.clone());
+            continue;
+        };
+        ensure_unlocked(&row, force)?;
+        remove_transaction(&mut tx, state.balance_mode, &row).await?;
+        deleted += 1;
+    }
+
+    tx.commit().await.map_err(internal_error)?;
+    Ok(BatchDeleteResult { deleted, not_found })
+}
+
+fn ensure_unlocked(row: &TransactionRow, force: bool) -> Result<(), (StatusCode, String)> {
+    if row.reconciled && !force {
+        return Err((
+            StatusCode::CONFLICT,
+            format!("transaction {} is reconciled; pass force=true to modify it", row.id),
+        ));
+    }
+    Ok(())
+}
+
+async fn reconcile_transaction(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> AppResult<Transaction> {
+    set_reconciled(&state, &id, true).await
+}
+
+async fn unreconcile_transaction(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> AppResult<Transaction> {
+    set_reconciled(&state, &id, false).await
+}
+
+async fn set_reconciled(state: &AppState, id: &str, reconciled: bool) -> AppResult<Transaction> {
+    let affected = sqlx::query("UPDATE transactions SET reconciled = ?1, updated_at = ?2 WHERE id = ?3")
+        .bind(reconciled)
+        .bind(format_rfc3339(OffsetDateTime::now_utc()))
+        .bind(id)
+        .execute(&state.pool)
+        .await
+        .map_err(internal_error)?
+        .rows_affected();
+    if affected == 0 {
+        return Err((StatusCode::NOT_FOUND, "transaction not found".into()));
+    }
+
+    let row = sqlx::query_as::<_, TransactionRow>("SELECT * FROM transactions WHERE id = ?1")
+        .bind(id)
+        .fetch_one(&state.pool)
+        .await
+        .map_err(internal_error)?;
+    let txn = hydrate_transaction(&state.pool, row).await?;
+    let _ = state.notifier.send(ServerEvent::TransactionsChanged);
+    Ok(Json(txn))
+}
+
+async fn transaction_history(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> AppResult<Vec<HistoryEntry>> {
+    let rows: Vec<(String, String, String)> = sqlx::query_as(
+        "SELECT action, changes, recorded_at FROM transaction_history WHERE transaction_id = ?1 ORDER BY id ASC",
+    )
+    .bind(&id)
+    .fetch_all(&state.pool)
+    .await
+    .map_err(internal_error)?;
+    if rows.is_empty() {
+        let exists: Option<(String,)> = sqlx::query_as(
+            "SELECT id FROM transactions WHERE id = ?1 UNION ALL SELECT id FROM archived_transactions WHERE id = ?1",
+        )
+        .bind(&id)
+        .fetch_optional(&state.pool)
+        .await
+        .map_err(internal_error)?;
+        if exists.is_none() {
+            return Err((StatusCode::NOT_FOUND, "transaction not found".into()));
+        }
+    }
+    let entries = rows
+        .into_iter()
+        .map(|(action, changes, recorded_at)| HistoryEntry {
+            action,
+            changes: serde_json::from_str(&changes).unwrap_or_default(),
+            recorded_at,
+        })
+        .collect();
+    Ok(Json(entries))
+}
+
+async fn history_snapshot(
+    tx: &mut sqlx::Transaction<'_, Sqlite>,
+    id: &str,
+) -> Result<serde_json::Map<String, serde_json::Value>, (StatusCode, String)> {
+    let row = sqlx::query_as::<_, TransactionRow>("SELECT * FROM transactions WHERE id = ?1")
+        .bind(id)
+        .fetch_one(&mut **tx)
+        .await
+        .map_err(internal_error)?;
+    let splits = sqlx::query_as::<_, TransactionSplit>(
+        "SELECT transaction_id, category_id, amount FROM transaction_splits WHERE transaction_id = ?1 ORDER BY category_id ASC",
+    )
+    .bind(id)
+    .fetch_all(&mut **tx)
+    .await
+    .map_err(internal_error)?;
+    let tags: Vec<(String,)> = sqlx::query_as(
+        "SELECT g.name FROM transaction_tags tt JOIN tags g ON g.id = tt.tag_id \
+         WHERE tt.transaction_id = ?1 ORDER BY g.name COLLATE NOCASE ASC",
+    )
+    .bind(id)
+    .fetch_all(&mut **tx)
+    .await
+    .map_err(internal_error)?;
+    let mut fields = match serde_json::to_value(&row) {
+        Ok(serde_json::Value::Object(fields)) => fields,
+        _ => serde_json::Map::new(),
+    };
+    for key in ["id", "scheduled", "reconciled", "created_at", "updated_at"] {
+        fields.remove(key);
+    }
+    let splits = splits
+        .into_iter()
+        .map(|s| serde_json::json!({ "category_id": s.category_id, "amount": s.amount }))
+        .collect();
+    fields.insert("splits".into(), serde_json::Value::Array(splits));
+    let tags = tags.into_iter().map(|(name,)| serde_json::Value::String(name)).collect();
+    fields.insert("tags".into(), serde_json::Value::Array(tags));
+    Ok(fields)
+}
+
+async fn record_history(
+    tx: &mut sqlx::Transaction<'_, Sqlite>,
+    transaction_id: &str,
+    action: &str,
+    before: Option<&serde_json::Map<String, serde_json::Value>>,
+    after: Option<&serde_json::Map<String, serde_json::Value>>,
+) -> Result<(), (StatusCode, String)> {
+    let empty = serde_json::Map::new();
+    let (before, after) = (before.unwrap_or(&empty), after.unwrap_or(&empty));
+    let mut changes = serde_json::Map::new();
+    for field in before.keys().chain(after.keys()) {
+        let old = before.get(field).unwrap_or(&serde_json::Value::Null);
+        let new = after.get(field).unwrap_or(&serde_json::Value::Null);
+        if old != new && !changes.contains_key(field) {
+            changes.insert(field.clone(), serde_json::json!({ "before": old, "after": new }));
+        }
+    }
+    if changes.is_empty() && action == "updated" {
+        return Ok(());
+    }
+    sqlx::query(
+        "INSERT INTO transaction_history (transaction_id, action, changes, recorded_at) VALUES (?1, ?2, ?3, ?4)",
+    )
+    .bind(transaction_id)
+    .bind(action)
+    .bind(serde_json::Value::Object(changes).to_string())
+    .bind(format_rfc3339(OffsetDateTime::now_utc()))
+    .execute(&mut **tx)
+    .await
+    .map_err(internal_error)?;
+    Ok(())
+}
+
+async fn remove_transaction(
+    tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+    mode: BalanceMode,
+    row: &TransactionRow,
+) -> Result<(), (StatusCode, String)> {
+    let direction = parse_direction(&row.direction)?;
+    let before = history_snapshot(tx, &row.id).await?;
+    record_history(tx, &row.id, "deleted", Some(&before), None).await?;
+
+    sqlx::query("DELETE FROM transaction_splits WHERE transaction_id = ?1")
+        .bind(&row.id)
+        .execute(&mut **tx)
+        .await
+        .map_err(internal_error)?;
+    sqlx::query("DELETE FROM transaction_tags WHERE transaction_id = ?1")
+        .bind(&row.id)
+        .execute(&mut **tx)
+        .await
+        .map_err(internal_error)?;
+    sqlx::query("DELETE FROM transactions WHERE id = ?1")
+        .bind(&row.id)
+        .execute(&mut **tx)
+        .await
+        .map_err(internal_error)?;
+
+    match direction {
+        _ if row.scheduled => {}
+        TransactionDirection::Income | TransactionDirection::Refund => {
+            if !adjust_balance(tx, mode, &row.account_id, -row.amount, true).await? {
+                return Err((StatusCode::BAD_REQUEST, "insufficient funds to remove income or account missing".into()));
+            }
+        }
+        TransactionDirection::Expense => {
+            if !adjust_balance(tx, mode, &row.account_id, row.amount, false).await? {
+                return Err((StatusCode::NOT_FOUND, "source account not found".into()));
+            }
+        }
+        TransactionDirection::Transfer => {
+            if let Some(dest) = &row.to_account_id {
+                let to_amount = row.to_amount.unwrap_or(row.amount);
+                if !adjust_balance(tx, mode, dest, -to_amount, true).await? {
+                    return Err((StatusCode::BAD_REQUEST, "insufficient funds on destination to rollback transfer or account missing".into()));
+                }
+            }
+            if !adjust_balance(tx, mode, &row.account_id, row.amount + row.fee, false).await? {
+                return Err((StatusCode::NOT_FOUND, "source account not found".into()));
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn update_transaction(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(params): Query<ForceParam>,
+    Json(payload): Json<CreateTransaction>,
+) -> FormResult<Transaction> {
+    check_transaction_fields(&state.pool, &payload).await?;
+    let updated =
+        retry_busy(|| replace_transaction(&state, &id, payload.clone(), params.force)).await?;
+    notify_ledger_changed(&state);
+    Ok(Json(updated))
+}
+
+async fn patch_transaction(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(params): Query<ForceParam>,
+    ValidJson(patch): ValidJson<PatchTransaction>,
+) -> FormResult<Transaction> {
+    let row = sqlx::query_as::<_, TransactionRow>("SELECT * FROM transactions WHERE id = ?1")
+        .bind(&id)
+        .fetch_optional(&state.pool)
+        .await
+        .map_err(internal_error)?
+        .ok_or((StatusCode::NOT_FOUND, "transaction not found".to_string()))?;
+    let current = hydrate_transaction(&state.pool, row).await?;
+    let payload = merge_patch(current, patch)?;
+    check_transaction_fields(&state.pool, &payload).await?;
+    let updated =
+        retry_busy(|| replace_transaction(&state, &id, payload.clone(), params.force)).await?;
+    notify_ledger_changed(&state);
+    Ok(Json(updated))
+}
+
+// Unlike a full update, the splits must add up to the amount.
+async fn replace_splits(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(params): Query<ForceParam>,
+    ValidJson(mut body): ValidJson<ReplaceSplits>,
+) -> AppResult<Transaction> {
+    let row = sqlx::query_as::<_, TransactionRow>("SELECT * FROM transactions WHERE id = ?1")
+        .bind(&id)
+        .fetch_optional(&state.pool)
+        .await
+        .map_err(internal_error)?
+        .ok_or((StatusCode::NOT_FOUND, "transaction not found".to_string()))?;
+    let current = hydrate_transaction(&state.pool, row).await?;
+    if current.direction == TransactionDirection::Transfer {
+        return Err((StatusCode::BAD_REQUEST, "transfers have no splits".into()));
+    }
+    let currency = account_currency(&state.pool, &current.account_id).await?;
+    resolve_remainder_split(&mut body.splits, current.amount.value, &currency)?;
+    let allocated: f64 = body.splits.iter().map(|s| s.amount).sum();
+    if (allocated - current.amount.value).abs() > SPLIT_SUM_TOLERANCE {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!(
+                "splits add up to {:.2} but the transaction amount is {:.2}",
+                allocated, current.amount.value
+            ),
+        ));
+    }
+
+    let patch = PatchTransaction {
+        splits: Some(body.splits),
+        ..Default::default()
+    };
+    let payload = merge_patch(current, patch)?;
+    let updated =
+        retry_busy(|| replace_transaction(&state, &id, payload.clone(), params.force)).await?;
+    let _ = state.notifier.send(ServerEvent::TransactionsChanged);
+    Ok(Json(updated))
+}
+
+// A lone split follows a changed amount; several splits must be replaced.
+fn merge_patch(
+    current: Transaction,
+    patch: PatchTransaction,
+) -> Result<CreateTransaction, (StatusCode, String)> {
+    let old_amount = current.amount.value;
+    let amount = patch.amount.unwrap_or(old_amount);
+    let splits = match (patch.splits, &patch.split_evenly) {
+        (Some(splits), _) => Some(splits),
+        (None, Some(_)) => None,
+        (None, None) => {
+            let mut splits: Vec<SplitInput> = current
+                .splits
+                .into_iter()
+                .map(|s| SplitInput {
+                    category_id: s.category_id,
+                    amount: s.amount,
+                    remainder: false,
+                })
+                .collect();
+            match splits.as_mut_slice() {
+                [only] => only.amount = amount,
+                [] => {}
+                _ if amount != old_amount => {
+                    return Err((
+                        StatusCode::BAD_REQUEST,
+                        "changing the amount of a split transaction requires splits or split_evenly".into(),
+                    ));
+                }
+                _ => {}
+            }
+            (!splits.is_empty()).then_some(splits)
+        }
+    };
+    let direction = patch.direction.unwrap_or(current.direction);
+    Ok(CreateTransaction {
+        account_id: patch.account_id.unwrap_or(current.account_id),
+        to_account_id: patch.to_account_id.or(current.to_account_id),
+        amount,
+        to_amount: patch.to_amount.or(if patch.amount.is_some() {
+            None
+        } else {
+            current.to_amount
+        }),
+        fee: patch.fee.or((direction == TransactionDirection::Transfer).then_some(current.fee)),
+        direction,
+        description: patch.description.or(current.description),
+        payee_id: patch.payee_id.or(current.payee_id),
+        external_id: current.external_id,
+        occurred_at: Some(patch.occurred_at.unwrap_or(current.occurred_at)),
+        splits,
+        split_evenly: patch.split_evenly,
+        source: TransactionSource::default(),
+    })
+}
+
+async fn replace_transaction(
+    state: &AppState,
+    id: &str,
+    mut payload: CreateTransaction,
+    force: bool,
+) -> Result<Transaction, (StatusCode, String)> {
+    resolve_amounts(&state.pool, &mut payload).await?;
+    check_accounts_live(&state.pool, &payload).await?;
+    if payload.amount < 0.0 {
+        return Err((StatusCode::BAD_REQUEST, "amount must be non-negative".into()));
+    }
+    if let Some(description) = &payload.description {
+        check_length("description", description, MAX_DESCRIPTION_LEN)?;
+    }
+    check_distinct_splits(payload.splits.as_deref().unwrap_or_default())?;
+    check_payee(&state.pool, payload.payee_id.as_deref()).await?;
+    let fee = check_fee(payload.fee, &payload.direction)?;
+
+    let direction = payload.direction.clone();
+    let mut tx = state.pool.begin().await.map_err(internal_error)?;
+    let existing: Option<TransactionRow> =
+        sqlx::query_as("SELECT * FROM transactions WHERE id = ?1")
+            .bind(id)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(internal_error)?;
+    let Some(old) = existing else {
+        return Err((StatusCode::NOT_FOUND, "transaction not found".into()));
+    };
+    ensure_unlocked(&old, force)?;
+    let before = history_snapshot(&mut tx, id).await?;
+
+    let (to_account_id, to_amount) = match direction {
+        TransactionDirection::Transfer => {
+            let dest = payload
+                .to_account_id
+                .clone()
+                .ok_or((StatusCode::BAD_REQUEST, "transfer requires destination account".into()))?;
+            if dest == payload.account_id {
+                return Err((StatusCode::BAD_REQUEST, "source and destination cannot match".into()));
+            }
+            let to_amount = resolve_to_amount(
+                &mut *tx,
+                &payload.account_id,
+                &dest,
+                payload.amount,
+                payload.to_amount,
+            )
+            .await?;
+            (Some(dest), to_amount)
+        }
+        _ => (None, None),
+    };
+
+    // Replace splits with new set
+    sqlx::query("DELETE FROM transaction_splits WHERE transaction_id = ?1")
+        .bind(id)
+        .execute(&mut *tx)
+        .await
+        .map_err(internal_error)?;
+
+    let now_at = OffsetDateTime::now_utc();
+    let occurred = occurred_at_utc(payload.occurred_at.as_deref(), now_at)?;
+    let occurred_at = format_rfc3339(occurred);
+    let updated_at = format_rfc3339(now_at);
+    let scheduled = occurred > now_at;
+
+    sqlx::query("UPDATE transactions SET account_id = ?1, to_account_id = ?2, amount = ?3, to_amount = ?4, fee = ?5, direction = ?6, description = ?7, payee_id = ?8, occurred_at = ?9, scheduled = ?10, updated_at = ?11 WHERE id = ?12")
+        .bind(&payload.account_id)
+        .bind(&to_account_id)
+        .bind(payload.amount)
+        .bind(to_amount)
+        .bind(fee)
+        .bind(direction.as_str())
+        .bind(&payload.description)
+        .bind(&payload.payee_id)
+        .bind(&occurred_at)
+        .bind(scheduled)
+        .bind(&updated_at)
+        .bind(id)
+        .execute(&mut *tx)
+        .await
+        .map_err(internal_error)?;
+
+    let splits = if direction == TransactionDirection::Transfer {
+        Vec::new()
+    } else {
+        payload
+            .splits
+            .clone()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|s| TransactionSplit {
+                transaction_id: id.to_string(),
+                category_id: s.category_id,
+                amount: s.amount,
+            })
+            .collect::<Vec<_>>()
+    };
+
+    for split in &splits {
+        sqlx::query("INSERT INTO transaction_splits (transaction_id, category_id, amount) VALUES (?1, ?2, ?3)")
+            .bind(&split.transaction_id)
+            .bind(&split.category_id)
+            .bind(split.amount)
+            .execute(&mut *tx)
+            .await
+            .map_err(internal_error)?;
+    }
+
+    // Apply balance deltas atomically to avoid transient negative checks.
+    let mut deltas: HashMap<String, f64> = HashMap::new();
+
+    let mut add_delta = |account_id: &str, delta: f64| {
+        let entry = deltas.entry(account_id.to_string()).or_insert(0.0);
+        *entry += delta;
+    };
+
+    let old_direction = parse_direction(&old.direction)?;
+    match old_direction {
+        _ if old.scheduled => {}
+        TransactionDirection::Income | TransactionDirection::Refund => {
+            add_delta(&old.account_id, -old.amount)
+        }
+        TransactionDirection::Expense => add_delta(&old.account_id, old.amount),
+        TransactionDirection::Transfer => {
+            add_delta(&old.account_id, old.amount + old.fee);
+            if let Some(dest) = &old.to_account_id {
+                add_delta(dest, -old.to_amount.unwrap_or(old.amount));
+            }
+        }
+    }
+
+    match direction {
+        _ if scheduled => {}
+        TransactionDirection::Income | TransactionDirection::Refund => {
+            add_delta(&payload.account_id, payload.amount)
+        }
+        TransactionDirection::Expense => add_delta(&payload.account_id, -payload.amount),
+        TransactionDirection::Transfer => {
+            add_delta(&payload.account_id, -(payload.amount + fee));
+            if let Some(dest) = &to_account_id {
+                add_delta(dest, to_amount.unwrap_or(payload.amount));
+            }
+        }
+    }
+
+    for (acct, delta) in deltas {
+        if delta == 0.0 {
+            continue;
+        }
+        if !adjust_balance(&mut tx, state.balance_mode, &acct, delta, true).await? {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                "insufficient funds for update or account not found".into(),
+            ));
+        }
+    }
+
+    let after = history_snapshot(&mut tx, id).await?;
+    record_history(&mut tx, id, "updated", Some(&before), Some(&after)).await?;
+
+    tx.commit().await.map_err(internal_error)?;
+    let tags = tag_names(&state.pool, id).await?;
+    let amount = directed_amount(&direction, payload.amount, &payload.account_id, to_account_id.as_deref(), to_amount, fee);
+    let updated = Transaction {
+        id: id.to_string(),
+        account_id: payload.account_id,
+        to_account_id,
+        amount,
+        to_amount,
+        fee,
+        direction,
+        description: payload.description,
+        payee_id: payload.payee_id,
+        external_id: old.external_id,
+        occurred_at,
+        scheduled,
+        reconciled: old.reconciled,
+        source: old.source,
+        splits,
+        tags,
+        created_at: old.created_at,
+        updated_at,
+    };
+    Ok(updated)
+}
+
+async fn list_templates(State(state): State<AppState>) -> AppResult<Vec<Template>> {
+    let rows = sqlx::query_as::<_, Template>("SELECT * FROM templates ORDER BY name ASC")
+        .fetch_all(&state.pool)
+        .await
+        .map_err(internal_error)?;
+    Ok(Json(rows))
+}
+
+async fn get_template(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> AppResult<Template> {
+    let template = fetch_template(&state.pool, &id).await?;
+    Ok(Json(template))
+}
+
+async fn create_template(
+    State(state): State<AppState>,
+    Json(payload): Json<CreateTemplate>,
+) -> AppResult<Template> {
+    validate_template(&payload)?;
+    let id = Uuid::new_v4().to_string();
+    let now = OffsetDateTime::now_utc()
+        .format(&time::format_description::well_known::Rfc3339)
+        .unwrap();
+    let to_account_id = template_destination(&payload);
+    sqlx::query("INSERT INTO templates (id, name, account_id, to_account_id, amount, direction, category_id, description, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)")
+        .bind(&id)
+        .bind(&payload.name)
+        .bind(&payload.account_id)
+        .bind(&to_account_id)
+        .bind(payload.amount)
+        .bind(payload.direction.as_str())
+        .bind(&payload.category_id)
+        .bind(&payload.description)
+        .bind(&now)
+        .execute(&state.pool)
+        .await
+        .map_err(map_foreign_key)?;
+
+    let template = Template {
+        id,
+        name: payload.name,
+        account_id: payload.account_id,
+        to_account_id,
+        amount: payload.amount,
+        direction: payload.direction.as_str().to_string(),
+        category_id: payload.category_id,
+        description: payload.description,
+        created_at: now,
+    };
+    let _ = state.notifier.send(ServerEvent::DataChanged);
+    Ok(Json(template))
+}
+
+async fn update_template(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(payload): Json<CreateTemplate>,
+) -> AppResult<Template> {
+    validate_template(&payload)?;
+    let to_account_id = template_destination(&payload);
+    let affected = sqlx::query("UPDATE templates SET name = ?1, account_id = ?2, to_account_id = ?3, amount = ?4, direction = ?5, category_id = ?6, description = ?7 WHERE id = ?8")
+        .bind(&payload.name)
+        .bind(&payload.account_id)
+        .bind(&to_account_id)
+        .bind(payload.amount)
+        .bind(payload.direction.as_str())
+        .bind(&payload.category_id)
+        .bind(&payload.description)
+        .bind(&id)
+        .execute(&state.pool)
+        .await
+        .map_err(map_foreign_key)?
+        .rows_affected();
+    if affected == 0 {
+        return Err((StatusCode::NOT_FOUND, "template not found".into()));
+    }
+
+    let template = fetch_template(&state.pool, &id).await?;
+    let _ = state.notifier.send(ServerEvent::DataChanged);
+    Ok(Json(template))
+}
+
+async fn delete_template(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let affected = sqlx::query("DELETE FROM templates WHERE id = ?1")
+        .bind(&id)
+        .execute(&state.pool)
+        .await
+        .map_err(internal_error)?
+        .rows_affected();
+    if affected == 0 {
+        return Err((StatusCode::NOT_FOUND, "template not found".into()));
+    }
+    let _ = state.notifier.send(ServerEvent::DataChanged);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn list_alert_rules(State(state): State<AppState>) -> AppResult<Vec<AlertRule>> {
+    let rows = sqlx::query_as::<_, AlertRule>(
+        "SELECT * FROM alert_rules ORDER BY direction ASC, threshold ASC",
+    )
+    .fetch_all(&state.pool)
+    .await
+    .map_err(internal_error)?;
+    Ok(Json(rows))
+}
+
+async fn create_alert_rule(
+    State(state): State<AppState>,
+    Json(payload): Json<CreateAlertRule>,
+) -> AppResult<AlertRule> {
+    if payload.threshold < 0.0 {
+        return Err((StatusCode::BAD_REQUEST, "threshold must be non-negative".into()));
+    }
+    let rule = AlertRule {
+        id: Uuid::new_v4().to_string(),
+        direction: payload.direction.as_str().to_string(),
+        threshold: payload.threshold,
+        created_at: format_rfc3339(OffsetDateTime::now_utc()),
+    };
+    sqlx::query(
+        "INSERT INTO alert_rules (id, direction, threshold, created_at) VALUES (?1, ?2, ?3, ?4)",
+    )
+    .bind(&rule.id)
+    .bind(&rule.direction)
+    .bind(rule.threshold)
+    .bind(&rule.created_at)
+    .execute(&state.pool)
+    .await
+    .map_err(internal_error)?;
+    let _ = state.notifier.send(ServerEvent::DataChanged);
+    Ok(Json(rule))
+}
+
+async fn update_alert_rule(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(payload): Json<CreateAlertRule>,
+) -> AppResult<AlertRule> {
+    if payload.threshold < 0.0 {
+        return Err((StatusCode::BAD_REQUEST, "threshold must be non-negative".into()));
+    }
+    let rule = sqlx::query_as::<_, AlertRule>(
+        "UPDATE alert_rules SET direction = ?1, threshold = ?2 WHERE id = ?3 RETURNING *",
+    )
+    .bind(payload.direction.as_str())
+    .bind(payload.threshold)
+    .bind(&id)
+    .fetch_optional(&state.pool)
+    .await
+    .map_err(internal_error)?
+    .ok_or((StatusCode::NOT_FOUND, "alert rule not found".to_string()))?;
+    let _ = state.notifier.send(ServerEvent::DataChanged);
+    Ok(Json(rule))
+}
+
+async fn delete_alert_rule(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let affected = sqlx::query("DELETE FROM alert_rules WHERE id = ?1")
+        .bind(&id)
+        .execute(&state.pool)
+        .await
+        .map_err(internal_error)?
+        .rows_affected();
+    if affected == 0 {
+        return Err((StatusCode::NOT_FOUND, "alert rule not found".into()));
+    }
+    let _ = state.notifier.send(ServerEvent::DataChanged);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn list_categorization_rules(
+    State(state): State<AppState>,
+) -> AppResult<Vec<CategorizationRule>> {
+    let rows = sqlx::query_as::<_, CategorizationRule>(
+        "SELECT * FROM categorization_rules ORDER BY created_at ASC, id ASC",
+    )
+    .fetch_all(&state.pool)
+    .await
+    .map_err(internal_error)?;
+    Ok(Json(rows))
+}
+
+async fn create_categorization_rule(
+    State(state): State<AppState>,
+    Json(payload): Json<CreateCategorizationRule>,
+) -> AppResult<CategorizationRule> {
+    check_rule_pattern(&payload)?;
+    let rule = CategorizationRule {
+        id: Uuid::new_v4().to_string(),
+        pattern: payload.pattern,
+        is_regex: payload.is_regex,
+        category_id: payload.category_id,
+        created_at: format_rfc3339(OffsetDateTime::now_utc()),
+    };
+    sqlx::query(
+        "INSERT INTO categorization_rules (id, pattern, is_regex, category_id, created_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+    )
+    .bind(&rule.id)
+    .bind(&rule.pattern)
+    .bind(rule.is_regex)
+    .bind(&rule.category_id)
+    .bind(&rule.created_at)
+    .execute(&state.pool)
+    .await
+    .map_err(map_foreign_key)?;
+    Ok(Json(rule))
+}
+
+async fn update_categorization_rule(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(payload): Json<CreateCategorizationRule>,
+) -> AppResult<CategorizationRule> {
+    check_rule_pattern(&payload)?;
+    sqlx::query_as::<_, CategorizationRule>(
+        "UPDATE categorization_rules SET pattern = ?1, is_regex = ?2, category_id = ?3 WHERE id = ?4 RETURNING *",
+    )
+    .bind(&payload.pattern)
+    .bind(payload.is_regex)
+    .bind(&payload.category_id)
+    .bind(&id)
+    .fetch_optional(&state.pool)
+    .await
+    .map_err(map_foreign_key)?
+    .map(Json)
+    .ok_or((StatusCode::NOT_FOUND, "categorization rule not found".into()))
+}
+
+async fn delete_categorization_rule(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let affected = sqlx::query("DELETE FROM categorization_rules WHERE id = ?1")
+        .bind(&id)
+        .execute(&state.pool)
+        .await
+        .map_err(internal_error)?
+        .rows_affected();
+    if affected == 0 {
+        return Err((StatusCode::NOT_FOUND, "categorization rule not found".into()));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+fn check_rule_pattern(payload: &CreateCategorizationRule) -> Result<(), (StatusCode, String)> {
+    if payload.pattern.trim().is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "pattern cannot be empty".into()));
+    }
+    check_length("pattern", &payload.pattern, MAX_DESCRIPTION_LEN)?;
+    if payload.is_regex {
+        Regex::new(&payload.pattern)
+            .map_err(|err| (StatusCode::BAD_REQUEST, format!("invalid regex: {err}")))?;
+    }
+    Ok(())
+}
+
+// Reconciled transactions are left alone unless `force=true`.
+async fn normalize_descriptions(
+    State(state): State<AppState>,
+    Query(params): Query<BulkEditParams>,
+    ValidJson(payload): ValidJson<NormalizeDescriptions>,
+) -> AppResult<NormalizeResult> {
+    if payload.rules.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "rules must not be empty".into()));
+    }
+    let mut rules = Vec::with_capacity(payload.rules.len());
+    for (idx, rule) in payload.rules.into_iter().enumerate() {
+        if rule.find.is_empty() {
+            return Err((StatusCode::BAD_REQUEST, format!("rule {}: find must not be empty", idx + 1)));
+        }
+        let pattern = if rule.regex {
+            rule.find.clone()
+        } else {
+            regex::escape(&rule.find)
+        };
+        let re = Regex::new(&pattern).map_err(|err| {
+            (StatusCode::BAD_REQUEST, format!("rule {}: invalid regex: {err}", idx + 1))
+        })?;
+        let replace = if rule.regex {
+            rule.replace
+        } else {
+            rule.replace.replace('$', "$$")
+        };
+        rules.push((re, replace));
+    }
+
+    let candidates: Vec<(String, String, bool)> = sqlx::query_as(
+        "SELECT id, description, reconciled FROM transactions WHERE description IS NOT NULL",
+    )
+    .fetch_all(&state.pool)
+    .await
+    .map_err(internal_error)?;
+
+    let mut changes = Vec::new();
+    let mut skipped_reconciled = 0;
+    let now = format_rfc3339(OffsetDateTime::now_utc());
+    let mut tx = state.pool.begin().await.map_err(internal_error)?;
+    for (txn_id, before, reconciled) in candidates {
+        let mut rewritten = before.clone();
+        for (re, replace) in &rules {
+            rewritten = re.replace_all(&rewritten, replace.as_str()).into_owned();
+        }
+        let rewritten = rewritten.trim();
+        if rewritten == before {
+            continue;
+        }
+        if reconciled && !params.force {
+            skipped_reconciled += 1;
+            continue;
+        }
+        check_length("description", rewritten, MAX_DESCRIPTION_LEN)
+            .map_err(|(status, message)| (status, format!("transaction {txn_id}: {message}")))?;
+        let after = (!rewritten.is_empty()).then(|| rewritten.to_string());
+        if !params.dry_run {
+            let snapshot = history_snapshot(&mut tx, &txn_id).await?;
+            sqlx::query("UPDATE transactions SET description = ?1, updated_at = ?2 WHERE id = ?3")
+                .bind(&after)
+                .bind(&now)
+                .bind(&txn_id)
+                .execute(&mut *tx)
+                .await
+                .map_err(internal_error)?;
+            let updated = history_snapshot(&mut tx, &txn_id).await?;
+            record_history(&mut tx, &txn_id, "updated", Some(&snapshot), Some(&updated)).await?;
+        }
+        changes.push(DescriptionChange {
+            transaction_id: txn_id,
+            before,
+            after,
+        });
+    }
+    if params.dry_run {
+        tx.rollback().await.map_err(internal_error)?;
+    } else {
+        tx.commit().await.map_err(internal_error)?;
+    }
+
+    let changed = if params.dry_run { 0 } else { changes.len() as u64 };
+    if changed > 0 {
+        let _ = state.notifier.send(ServerEvent::TransactionsChanged);
+    }
+    Ok(Json(NormalizeResult {
+        changed,
+        dry_run: params.dry_run,
+        changes,
+        skipped_reconciled,
+    }))
+}
+
+// Uses the oldest matching rule; reconciled rows are skipped unless `force=true`.
+async fn apply_categorization_rules(
+    State(state): State<AppState>,
+    Query(params): Query<BulkEditParams>,
+) -> AppResult<ApplyRulesResult> {
+    let rules = sqlx::query_as::<_, CategorizationRule>(
+        "SELECT * FROM categorization_rules ORDER BY created_at ASC, id ASC",
+    )
+    .fetch_all(&state.pool)
+    .await
+    .map_err(internal_error)?;
+    let mut matchers = Vec::with_capacity(rules.len());
+    for rule in rules {
+        let matcher = if rule.is_regex {
+            match Regex::new(&rule.pattern) {
+                Ok(re) => RuleMatcher::Regex(re),
+                Err(err) => {
+                    warn!("skipping categorization rule {}: {}", rule.id, err);
+                    continue;
+                }
+            }
+        } else {
+            RuleMatcher::Substring(rule.pattern.to_lowercase())
+        };
+        matchers.push((matcher, rule.category_id));
+    }
+
+    let candidates: Vec<(String, f64, String, bool)> = sqlx::query_as(
+        "SELECT id, amount, description, reconciled FROM transactions t \
+         WHERE direction <> 'transfer' AND description IS NOT NULL \
+         AND NOT EXISTS (SELECT 1 FROM transaction_splits s WHERE s.transaction_id = t.id)",
+    )
+    .fetch_all(&state.pool)
+    .await
+    .map_err(internal_error)?;
+
+    let mut assignments = Vec::new();
+    let mut skipped_reconciled = 0;
+    let now = format_rfc3339(OffsetDateTime::now_utc());
+    let mut tx = state.pool.begin().await.map_err(internal_error)?;
+    for (txn_id, amount, description, reconciled) in candidates {
+        let Some((_, category_id)) = matchers.iter().find(|(m, _)| m.matches(&description)) else {
+            continue;
+        };
+        if reconciled && !params.force {
+            skipped_reconciled += 1;
+            continue;
+        }
+        assignments.push(RuleAssignment {
+            transaction_id: txn_id.clone(),
+            description,
+            category_id: category_id.clone(),
+        });
+        if params.dry_run {
+            continue;
+        }
+        let before = history_snapshot(&mut tx, &txn_id).await?;
+        sqlx::query("INSERT INTO transaction_splits (transaction_id, category_id, amount) VALUES (?1, ?2, ?3)")
+            .bind(&txn_id)
+            .bind(category_id)
+            .bind(amount)
+            .execute(&mut *tx)
+            .await
+            .map_err(internal_error)?;
+        sqlx::query("UPDATE transactions SET updated_at = ?1 WHERE id = ?2")
+            .bind(&now)
+            .bind(&txn_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(internal_error)?;
+        let after = history_snapshot(&mut tx, &txn_id).await?;
+        record_history(&mut tx, &txn_id, "updated", Some(&before), Some(&after)).await?;
+    }
+    if params.dry_run {
+        tx.rollback().await.map_err(internal_error)?;
+    } else {
+        tx.commit().await.map_err(internal_error)?;
+    }
+
+    let categorized = if params.dry_run { 0 } else { assignments.len() as u64 };
+    if categorized > 0 {
+        let _ = state.notifier.send(ServerEvent::TransactionsChanged);
+    }
+    Ok(Json(ApplyRulesResult {
+        categorized,
+        dry_run: params.dry_run,
+        assignments,
+        skipped_reconciled,
+    }))
+}
+
+// The transaction is already committed, so failures are only logged.
+async fn raise_alerts(state: &AppState, txn: &Transaction) {
+    let direction = txn.direction.as_str();
+    let rules = sqlx::query_as::<_, AlertRule>(
+        "SELECT * FROM alert_rules WHERE direction = ?1 AND threshold < ?2",
+    )
+    .bind(direction)
+    .bind(txn.amount.value)
+    .fetch_all(&state.pool)
+    .await;
+    let rules = match rules {
+        Ok(rules) => rules,
+        Err(err) => {
+            warn!("failed to evaluate alert rules: {}", err);
+            return;
+        }
+    };
+    for rule in rules {
+        info!("transaction {} triggered alert rule {}", txn.id, rule.id);
+        let _ = state.notifier.send(ServerEvent::Alert(AlertEvent {
+            rule_id: rule.id,
+            transaction_id: txn.id.clone(),
+            direction: direction.to_string(),
+            amount: txn.amount.value,
+            threshold: rule.threshold,
+        }));
+    }
+}
+
+async fn apply_template(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    overrides: Option<Json<ApplyTemplate>>,
+) -> AppResult<Transaction> {
+    let template = fetch_template(&state.pool, &id).await?;
+    let overrides = overrides.map(|Json(o)| o).unwrap_or_default();
+    let payload = template_payload(template, overrides)?;
+
+    let created = insert_transaction(&state, payload).await?;
+    notify_ledger_changed(&state);
+    raise_alerts(&state, &created).await;
+    Ok(Json(created))
+}
+
+fn template_payload(
+    template: Template,
+    overrides: ApplyTemplate,
+) -> Result<CreateTransaction, (StatusCode, String)> {
+    let direction = parse_direction(&template.direction)?;
+    let amount = overrides.amount.unwrap_or(template.amount);
+
+    let splits = template.category_id.map(|category_id| {
+        vec![SplitInput {
+            category_id,
+            amount,
+            remainder: false,
+        }]
+    });
+    Ok(CreateTransaction {
+        account_id: overrides.account_id.unwrap_or(template.account_id),
+        to_account_id: template.to_account_id,
+        amount,
+        to_amount: overrides.to_amount,
+        fee: None,
+        direction,
+        description: overrides.description.or(template.description),
+        payee_id: None,
+        external_id: None,
+        occurred_at: overrides.occurred_at,
+        splits,
+        split_evenly: None,
+        source: TransactionSource::Manual,
+    })
+}
+
+async fn list_recurring(State(state): State<AppState>) -> AppResult<Vec<RecurringTransaction>> {
+    let rows = sqlx::query_as::<_, RecurringTransaction>(
+        "SELECT * FROM recurring_transactions ORDER BY next_run_at ASC, created_at ASC",
+    )
     .fetch_all(&state.pool)
     .await
     .map_err(internal_error)?;
     Ok(Json(rows))
 }
 
-async fn create_account(
+async fn create_recurring(
     State(state): State<AppState>,
-    Json(payload): Json<CreateAccount>,
-) -> AppResult<Account> {
-    let id = Uuid::new_v4().to_string();
-    let now = OffsetDateTime::now_utc()
-        .format(&time::format_description::well_known::Rfc3339)
-        .unwrap();
+    ValidJson(payload): ValidJson<CreateRecurring>,
+) -> AppResult<RecurringTransaction> {
+    let start_on = match &payload.start_on {
+        Some(value) => parse_date_prefix(value)
+            .ok_or((StatusCode::BAD_REQUEST, "start_on must be a YYYY-MM-DD date".to_string()))?,
+        None => OffsetDateTime::now_utc().date(),
+    };
+    fetch_template(&state.pool, &payload.template_id).await?;
+    let recurring = RecurringTransaction {
+        id: Uuid::new_v4().to_string(),
+        template_id: payload.template_id,
+        cadence: payload.cadence.as_str().to_string(),
+        next_run_at: run_timestamp(start_on),
+        paused: false,
+        created_at: format_rfc3339(OffsetDateTime::now_utc()),
+    };
     sqlx::query(
-        "INSERT INTO accounts (id, name, kind, balance, created_at) VALUES (?1, ?2, ?3, 0.0, ?4)",
+        "INSERT INTO recurring_transactions (id, template_id, cadence, next_run_at, paused, created_at) VALUES (?1, ?2, ?3, ?4, 0, ?5)",
     )
-    .bind(&id)
-    .bind(&payload.name)
-    .bind(payload.kind.as_str())
-    .bind(&now)
+    .bind(&recurring.id)
+    .bind(&recurring.template_id)
+    .bind(&recurring.cadence)
+    .bind(&recurring.next_run_at)
+    .bind(&recurring.created_at)
     .execute(&state.pool)
     .await
     .map_err(internal_error)?;
+    Ok(Json(recurring))
+}
 
-    let account = Account {
-        id,
-        name: payload.name,
-        kind: payload.kind.as_str().to_string(),
-        balance: 0.0,
-        created_at: now,
+async fn delete_recurring(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let affected = sqlx::query("DELETE FROM recurring_transactions WHERE id = ?1")
+        .bind(&id)
+        .execute(&state.pool)
+        .await
+        .map_err(internal_error)?
+        .rows_affected();
+    if affected == 0 {
+        return Err((StatusCode::NOT_FOUND, "recurring transaction not found".into()));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn skip_recurring(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> AppResult<RecurringTransaction> {
+    let recurring = fetch_recurring(&state.pool, &id).await?;
+    let next = next_run_after(&recurring)?;
+    update_recurring(&state.pool, &id, &next, recurring.paused).await
+}
+
+async fn pause_recurring(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> AppResult<RecurringTransaction> {
+    let recurring = fetch_recurring(&state.pool, &id).await?;
+    update_recurring(&state.pool, &id, &recurring.next_run_at, true).await
+}
+
+async fn resume_recurring(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> AppResult<RecurringTransaction> {
+    let mut recurring = fetch_recurring(&state.pool, &id).await?;
+    let today = run_timestamp(OffsetDateTime::now_utc().date());
+    while recurring.next_run_at < today {
+        recurring.next_run_at = next_run_after(&recurring)?;
+    }
+    update_recurring(&state.pool, &id, &recurring.next_run_at, false).await
+}
+
+async fn fetch_recurring(
+    pool: &SqlitePool,
+    id: &str,
+) -> Result<RecurringTransaction, (StatusCode, String)> {
+    sqlx::query_as::<_, RecurringTransaction>("SELECT * FROM recurring_transactions WHERE id = ?1")
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+        .map_err(internal_error)?
+        .ok_or((StatusCode::NOT_FOUND, "recurring transaction not found".to_string()))
+}
+
+async fn update_recurring(
+    pool: &SqlitePool,
+    id: &str,
+    next_run_at: &str,
+    paused: bool,
+) -> AppResult<RecurringTransaction> {
+    sqlx::query_as::<_, RecurringTransaction>(
+        "UPDATE recurring_transactions SET next_run_at = ?1, paused = ?2 WHERE id = ?3 RETURNING *",
+    )
+    .bind(next_run_at)
+    .bind(paused)
+    .bind(id)
+    .fetch_optional(pool)
+    .await
+    .map_err(internal_error)?
+    .map(Json)
+    .ok_or((StatusCode::NOT_FOUND, "recurring transaction not found".into()))
+}
+
+fn next_run_after(recurring: &RecurringTransaction) -> Result<String, (StatusCode, String)> {
+    let cadence = match recurring.cadence.as_str() {
+        "daily" => Cadence::Daily,
+        "weekly" => Cadence::Weekly,
+        "monthly" => Cadence::Monthly,
+        _ => {
+            return Err((StatusCode::INTERNAL_SERVER_ERROR, "invalid cadence".into()));
+        }
     };
-    let _ = state.notifier.send(ServerEvent::DataChanged);
-    Ok(Json(account))
+    let date = parse_date_prefix(&recurring.next_run_at).ok_or((
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "invalid next_run_at".to_string(),
+    ))?;
+    Ok(run_timestamp(cadence.advance(date)))
+}
+
+fn run_timestamp(date: Date) -> String {
+    format!("{date}T00:00:00Z")
+}
+
+// A failed run stays due and is retried on the next tick.
+async fn run_due_recurring(state: &AppState) -> Result<(), (StatusCode, String)> {
+    let now = format_rfc3339(OffsetDateTime::now_utc());
+    let due = sqlx::query_as::<_, RecurringTransaction>(
+        "SELECT * FROM recurring_transactions WHERE paused = 0 AND next_run_at <= ?1",
+    )
+    .bind(&now)
+    .fetch_all(&state.pool)
+    .await
+    .map_err(internal_error)?;
+
+    let mut created_any = false;
+    for mut recurring in due {
+        for _ in 0..MAX_RECURRING_CATCH_UP {
+            if recurring.next_run_at > now {
+                break;
+            }
+            let template = fetch_template(&state.pool, &recurring.template_id).await?;
+            let overrides = ApplyTemplate {
+                occurred_at: Some(recurring.next_run_at.clone()),
+                ..Default::default()
+            };
+            let payload = CreateTransaction {
+                source: TransactionSource::Recurring,
+                ..template_payload(template, overrides)?
+            };
+            let created = match insert_transaction(state, payload).await {
+                Ok(created) => created,
+                Err((_, message)) => {
+                    warn!("recurring transaction {} not created: {}", recurring.id, message);
+                    break;
+                }
+            };
+            recurring.next_run_at = next_run_after(&recurring)?;
+            sqlx::query("UPDATE recurring_transactions SET next_run_at = ?1 WHERE id = ?2")
+                .bind(&recurring.next_run_at)
+                .bind(&recurring.id)
+                .execute(&state.pool)
+                .await
+                .map_err(internal_error)?;
+            raise_alerts(state, &created).await;
+            created_any = true;
+        }
+    }
+    if created_any {
+        notify_ledger_changed(state);
+    }
+    Ok(())
+}
+
+async fn fetch_template(pool: &SqlitePool, id: &str) -> Result<Template, (StatusCode, String)> {
+    sqlx::query_as::<_, Template>("SELECT * FROM templates WHERE id = ?1")
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+        .map_err(internal_error)?
+        .ok_or((StatusCode::NOT_FOUND, "template not found".to_string()))
+}
+
+fn validate_template(payload: &CreateTemplate) -> Result<(), (StatusCode, String)> {
+    if payload.name.trim().is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "template name cannot be empty".into()));
+    }
+    check_length("name", &payload.name, MAX_NAME_LEN)?;
+    if payload.amount < 0.0 {
+        return Err((StatusCode::BAD_REQUEST, "amount must be non-negative".into()));
+    }
+    if let Some(description) = &payload.description {
+        check_length("description", description, MAX_DESCRIPTION_LEN)?;
+    }
+    if payload.direction == TransactionDirection::Transfer && payload.to_account_id.is_none() {
+        return Err((StatusCode::BAD_REQUEST, "transfer requires destination account".into()));
+    }
+    Ok(())
+}
+
+fn template_destination(payload: &CreateTemplate) -> Option<String> {
+    match payload.direction {
+        TransactionDirection::Transfer => payload.to_account_id.clone(),
+        _ => None,
+    }
+}
+
+async fn summary(State(state): State<AppState>) -> AppResult<Summary> {
+    activate_due_transactions(&state).await?;
+    let month_start = format_rfc3339(period_start("mtd", OffsetDateTime::now_utc())?);
+
+    let (net_worth, account_count): (f64, i64) = sqlx::query_as(&format!(
+        "SELECT CAST(COALESCE(SUM({}), 0) AS REAL), COUNT(1) FROM accounts WHERE deleted_at IS NULL",
+        state.balance_mode.column()
+    ))
+            .fetch_one(&state.pool)
+            .await
+            .map_err(internal_error)?;
+
+    let (month_income, month_expense): (f64, f64) = sqlx::query_as(&format!(
+        r#"
+        SELECT
+            CAST(COALESCE(SUM(CASE WHEN direction = 'income' THEN amount ELSE 0 END), 0) AS REAL),
+            CAST(COALESCE(SUM(CASE
+                WHEN direction = 'expense' THEN amount
+                WHEN direction = 'refund' THEN -amount
+                WHEN direction = 'transfer' THEN fee
+                ELSE 0
+            END), 0) AS REAL)
+        FROM transactions
+        WHERE occurred_at >= ?1 AND scheduled = 0{HIDE_DELETED_ACCOUNTS}
+        "#,
+    ))
+    .bind(&month_start)
+    .fetch_one(&state.pool)
+    .await
+    .map_err(internal_error)?;
+
+    let top_categories = sqlx::query_as::<_, CategoryTotal>(
+        r#"
+        SELECT c.id AS category_id, c.name AS name,
+            CAST(SUM(CASE WHEN t.direction = 'refund' THEN -s.amount ELSE s.amount END) AS REAL) AS total
+        FROM transaction_splits s
+        JOIN transactions t ON t.id = s.transaction_id
+        JOIN categories c ON c.id = s.category_id
+        WHERE t.direction IN ('expense', 'refund') AND t.occurred_at >= ?1 AND t.scheduled = 0
+            AND NOT EXISTS (SELECT 1 FROM accounts hidden WHERE hidden.deleted_at IS NOT NULL
+                AND hidden.id IN (t.account_id, t.to_account_id))
+        GROUP BY c.id, c.name
+        HAVING total > 0
+        ORDER BY total DESC
+        LIMIT 3
+        "#,
+    )
+    .bind(&month_start)
+    .fetch_all(&state.pool)
+    .await
+    .map_err(internal_error)?;
+
+    let round = |value: f64| round_in_currency(value, &state.money.currency);
+    let (month_income, month_expense) = (round(month_income), round(month_expense));
+    Ok(Json(Summary {
+        net_worth: round(net_worth),
+        account_count,
+        month_income,
+        month_expense,
+        month_net: round(month_income - month_expense),
+        top_categories: top_categories
+            .into_iter()
+            .map(|c| CategoryTotal { total: round(c.total), ..c })
+            .collect(),
+    }))
+}
+
+async fn monthly_report(
+    State(state): State<AppState>,
+    Query(params): Query<ReportQuery>,
+) -> AppResult<Vec<ReportBucket>> {
+    let fy_start = fiscal_start_month(params.fiscal_year_start_month)?;
+    let fiscal_year = params
+        .fiscal_year
+        .unwrap_or_else(|| fiscal_year_of(OffsetDateTime::now_utc().date(), fy_start));
+    let (mut year, mut month) = if fy_start == Month::January {
+        (fiscal_year, fy_start)
+    } else {
+        (fiscal_year - 1, fy_start)
+    };
+
+    let mut buckets = Vec::with_capacity(12);
+    for _ in 0..12 {
+        buckets.push(ReportBucket {
+            period: format!("{:04}-{:02}", year, u8::from(month)),
+            income: 0.0,
+            expense: 0.0,
+            net: 0.0,
+        });
+        if month == Month::December {
+            year += 1;
+        }
+        month = month.next();
+    }
+
+    for (date, direction, amount) in report_rows(&state.pool, None, None).await? {
+        if fiscal_year_of(date, fy_start) != fiscal_year {
+            continue;
+        }
+        let label = bucket_label(date, Bucket::Month, fy_start);
+        if let Some(bucket) = buckets.iter_mut().find(|b| b.period == label) {
+            add_to_bucket(bucket, &direction, amount);
+        }
+    }
+    Ok(Json(
+        buckets
+            .into_iter()
+            .map(|bucket| bucket.rounded(&state.money.currency))
+            .collect(),
+    ))
+}
+
+async fn trend_report(
+    State(state): State<AppState>,
+    Query(params): Query<ReportQuery>,
+) -> AppResult<Vec<ReportBucket>> {
+    let fy_start = fiscal_start_month(params.fiscal_year_start_month)?;
+    let bucket = match params.bucket.as_deref().unwrap_or("month") {
+        "month" => Bucket::Month,
+        "iso_week" | "week" => Bucket::IsoWeek,
+        "fiscal_year" => Bucket::FiscalYear,
+        other => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                format!("unknown bucket '{other}'; expected month, iso_week or fiscal_year"),
+            ));
+        }
+    };
+
+    let mut grouped: BTreeMap<String, ReportBucket> = BTreeMap::new();
+    for (date, direction, amount) in
+        report_rows(&state.pool, params.from.as_deref(), params.to.as_deref()).await?
+    {
+        let label = bucket_label(date, bucket, fy_start);
+        let entry = grouped.entry(label.clone()).or_insert(ReportBucket {
+            period: label,
+            income: 0.0,
+            expense: 0.0,
+            net: 0.0,
+        });
+        add_to_bucket(entry, &direction, amount);
+    }
+    Ok(Json(
+        grouped
+            .into_values()
+            .map(|bucket| bucket.rounded(&state.money.currency))
+            .collect(),
+    ))
+}
+
+// Splits are packed into one column as "Category:amount;Category:amount".
+async fn export_transactions_csv(
+    State(state): State<AppState>,
+) -> Result<Response, (StatusCode, String)> {
+    let rows: Vec<ExportRow> = sqlx::query_as(
+        r#"
+        SELECT
+            t.id,
+            t.occurred_at,
+            a.name AS account,
+            a.currency AS account_currency,
+            d.name AS to_account,
+            d.currency AS to_currency,
+            t.direction,
+            t.amount,
+            t.to_amount,
+            t.fee,
+            t.description,
+            t.scheduled,
+            t.reconciled
+        FROM transactions t
+        LEFT JOIN accounts a ON a.id = t.account_id
+        LEFT JOIN accounts d ON d.id = t.to_account_id
+        WHERE a.deleted_at IS NULL AND d.deleted_at IS NULL
+        ORDER BY t.occurred_at ASC, t.created_at ASC
+        "#,
+    )
+    .fetch_all(&state.pool)
+    .await
+    .map_err(internal_error)?;
+
+    let split_rows: Vec<(String, String, f64)> = sqlx::query_as(
+        "SELECT s.transaction_id, c.name, s.amount FROM transaction_splits s JOIN categories c ON c.id = s.category_id ORDER BY c.name ASC",
+    )
+    .fetch_all(&state.pool)
+    .await
+    .map_err(internal_error)?;
+    let mut splits_by_txn: HashMap<String, Vec<(String, f64)>> = HashMap::new();
+    for (transaction_id, name, amount) in split_rows {
+        splits_by_txn.entry(transaction_id).or_default().push((name, amount));
+    }
+
+    let money = &state.money;
+    let mut body = String::from(
+        "id,occurred_at,account,to_account,direction,amount,to_amount,fee,categories,description,scheduled,reconciled\n",
+    );
+    for row in rows {
+        let currency = row.account_currency.as_deref().unwrap_or(&money.currency);
+        let to_currency = row.to_currency.as_deref().unwrap_or(currency);
+        let categories = splits_by_txn
+            .get(&row.id)
+            .map(|splits| {
+                splits
+                    .iter()
+                    .map(|(name, amount)| format!("{name}:{}", money.format_in(*amount, currency)))
+                    .collect::<Vec<_>>()
+                    .join(";")
+            })
+            .unwrap_or_default();
+        let fields = [
+            row.id,
+            row.occurred_at,
+            row.account.unwrap_or_default(),
+            row.to_account.unwrap_or_default(),
+            row.direction,
+            money.format_in(row.amount, currency),
+            row.to_amount
+                .map(|v| money.format_in(v, to_currency))
+                .unwrap_or_default(),
+            money.format_in(row.fee, currency),
+            categories,
+            row.description.unwrap_or_default(),
+            row.scheduled.to_string(),
+            row.reconciled.to_string(),
+        ];
+        let line: Vec<String> = fields.iter().map(|f| csv_field(f)).collect();
+        body.push_str(&line.join(","));
+        body.push('\n');
+    }
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/csv; charset=utf-8"),
+            (header::CONTENT_DISPOSITION, "attachment; filename=\"transactions.csv\""),
+        ],
+        body,
+    )
+        .into_response())
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+// Rows whose external_id is already on record are skipped, so re-imports only add new rows.
+async fn import_transactions_csv(
+    State(state): State<AppState>,
+    body: String,
+) -> AppResult<ImportResult> {
+    let mut records = parse_csv(&body).into_iter();
+    let header: Vec<String> = records
+        .next()
+        .ok_or((StatusCode::BAD_REQUEST, "CSV body is empty".into()))?
+        .iter()
+        .map(|name| name.trim().to_ascii_lowercase())
+        .collect();
+    let column = |name: &str| header.iter().position(|h| h == name);
+    let required = |name: &str| {
+        column(name).ok_or((StatusCode::BAD_REQUEST, format!("CSV header is missing the {name} column")))
+    };
+    let occurred_col = required("occurred_at")?;
+    let account_col = required("account")?;
+    let direction_col = required("direction")?;
+    let amount_col = required("amount")?;
+    let to_account_col = column("to_account");
+    let to_amount_col = column("to_amount");
+    let fee_col = column("fee");
+    let category_col = column("category");
+    let categories_col = column("categories");
+    let description_col = column("description");
+    let external_id_col = column("external_id");
+
+    let accounts: Vec<(String, String)> =
+        sqlx::query_as("SELECT id, name FROM accounts WHERE deleted_at IS NULL")
+        .fetch_all(&state.pool)
+        .await
+        .map_err(internal_error)?;
+    let categories: Vec<(String, String)> = sqlx::query_as("SELECT id, name FROM categories")
+        .fetch_all(&state.pool)
+        .await
+        .map_err(internal_error)?;
+    let find = |list: &[(String, String)], name: &str| {
+        list.iter().find(|(_, n)| n.eq_ignore_ascii_case(name)).map(|(id, _)| id.clone())
+    };
+
+    let mut payloads = Vec::new();
+    let mut errors = Vec::new();
+    for (idx, record) in records.enumerate() {
+        let line = idx + 2;
+        if record.iter().all(|value| value.trim().is_empty()) {
+            continue;
+        }
+        let field = |col: Option<usize>| {
+            col.and_then(|c| record.get(c)).map(|v| v.trim()).filter(|v| !v.is_empty())
+        };
+        let parse_row = || -> Result<CreateTransaction, String> {
+            let occurred_at = match field(Some(occurred_col)) {
+                Some(value) if parse_date_prefix(value).is_some() && value.len() == 10 => {
+                    format!("{value}T00:00:00Z")
+                }
+                Some(value) if parse_date_prefix(value).is_some() => value.to_string(),
+                _ => return Err("occurred_at must start with a date like 2024-01-31".into()),
+            };
+            let account_name = field(Some(account_col)).ok_or("account is required")?;
+            let account_id = find(&accounts, account_name)
+                .ok_or_else(|| format!("unknown account \"{account_name}\""))?;
+            let direction = parse_direction(&field(Some(direction_col)).unwrap_or_default().to_ascii_lowercase())
+                .map_err(|(_, message)| message)?;
+            let money_field = |col: Option<usize>, name: &str| -> Result<Option<f64>, String> {
+                match field(col) {
+                    Some(value) => state
+                        .money
+                        .parse(value)
+                        .filter(|amount| amount.is_finite() && *amount >= 0.0)
+                        .map(Some)
+                        .ok_or_else(|| format!("{name} must be a non-negative number")),
+                    None => Ok(None),
+                }
+            };
+            let amount = money_field(Some(amount_col), "amount")?
+                .ok_or("amount must be a non-negative number")?;
+            let to_amount = money_field(to_amount_col, "to_amount")?;
+            let fee = money_field(fee_col, "fee")?.filter(|fee| *fee > 0.0);
+            let to_account_id = match field(to_account_col) {
+                Some(name) => Some(
+                    find(&accounts, name).ok_or_else(|| format!("unknown account \"{name}\""))?,
+                ),
+                None => None,
+            };
+            let category_id = |name: &str| {
+                find(&categories, name).ok_or_else(|| format!("unknown category \"{name}\""))
+            };
+            let splits = match (field(category_col), field(categories_col)) {
+                _ if direction == TransactionDirection::Transfer => None,
+                (Some(name), _) => Some(vec![SplitInput {
+                    category_id: category_id(name)?,
+                    amount,
+                    remainder: false,
+                }]),
+                (None, Some(list)) => {
+                    let mut splits = Vec::new();
+                    for part in list.split(';') {
+                        let (name, split_amount) = match part.rsplit_once(':') {
+                            Some((name, value)) => (
+                                name.trim(),
+                                state.money.parse(value).ok_or_else(|| {
+                                    format!("categories has an unreadable amount in \"{part}\"")
+                                })?,
+                            ),
+                            None => (part.trim(), amount),
+                        };
+                        splits.push(SplitInput {
+                            category_id: category_id(name)?,
+                            amount: split_amount,
+                            remainder: false,
+                        });
+                    }
+                    Some(splits)
+                }
+                (None, None) => None,
+            };
+            Ok(CreateTransaction {
+                account_id,
+                to_account_id,
+                amount,
+                to_amount: to_amount.filter(|_| direction == TransactionDirection::Transfer),
+                fee,
+                direction,
+                description: field(description_col).map(str::to_string),
+                payee_id: None,
+                external_id: field(external_id_col).map(str::to_string),
+                occurred_at: Some(occurred_at),
+                splits,
+                split_evenly: None,
+                source: TransactionSource::Import,
+            })
+        };
+        match parse_row() {
+            Ok(payload) => payloads.push((line, payload)),
+            Err(message) => errors.push(ImportRowError { line, message }),
+        }
+    }
+
+    let mut inserted = 0;
+    let mut skipped = 0;
+    for (line, payload) in payloads {
+        if let Some(external_id) = &payload.external_id
+            && external_id_exists(&state.pool, external_id).await?
+        {
+            skipped += 1;
+            continue;
+        }
+        match retry_busy(|| insert_transaction(&state, payload.clone())).await {
+            Ok(_) => inserted += 1,
+            Err((status, message)) if status == StatusCode::CONFLICT => {
+                // Only an external_id clash means the row is already in.
+                match &payload.external_id {
+                    Some(external_id) if external_id_exists(&state.pool, external_id).await? => {
+                        skipped += 1;
+                    }
+                    _ => errors.push(ImportRowError { line, message }),
+                }
+            }
+            Err((status, message)) if status.is_client_error() => {
+                errors.push(ImportRowError { line, message });
+            }
+            Err((status, message)) => {
+                if inserted > 0 {
+                    notify_ledger_changed(&state);
+                }
+                return Err((
+                    status,
+                    format!("row {line}: {message} ({inserted} row(s) imported before it)"),
+                ));
+            }
+        }
+    }
+    if inserted > 0 {
+        notify_ledger_changed(&state);
+    }
+    errors.sort_by_key(|error| error.line);
+    Ok(Json(ImportResult {
+        inserted,
+        skipped,
+        errors,
+    }))
 }
 
-async fn delete_account(
-    State(state): State<AppState>,
-    Path(id): Path<String>,
-) -> Result<StatusCode, (StatusCode, String)> {
-    let default_names = ["Main Checking", "Savings", "Credit Card"];
-    let existing: Option<Account> = sqlx::query_as(
-        "SELECT id, name, kind, balance, created_at FROM accounts WHERE id = ?1",
+async fn external_id_exists(pool: &SqlitePool, external_id: &str) -> Result<bool, (StatusCode, String)> {
+    let found: Option<(String,)> = sqlx::query_as(
+        "SELECT id FROM transactions WHERE external_id = ?1 UNION ALL SELECT id FROM archived_transactions WHERE external_id = ?1 LIMIT 1",
     )
-    .bind(&id)
-    .fetch_optional(&state.pool)
+    .bind(external_id)
+    .fetch_optional(pool)
     .await
     .map_err(internal_error)?;
+    Ok(found.is_some())
+}
 
-    let Some(account) = existing else {
-        return Err((StatusCode::NOT_FOUND, "account not found".into()));
-    };
-
-    if default_names.iter().any(|n| n == &account.name) {
-        return Err((StatusCode::CONFLICT, "default accounts cannot be deleted".into()));
+fn parse_csv(text: &str) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = text.trim_start_matches('\u{feff}').chars().peekable();
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => in_quotes = false,
+                _ => field.push(c),
+            }
+            continue;
+        }
+        match c {
+            '"' => in_quotes = true,
+            ',' => record.push(std::mem::take(&mut field)),
+            '\r' => {}
+            '\n' => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            _ => field.push(c),
+        }
     }
-
-    sqlx::query("DELETE FROM accounts WHERE id = ?1")
-        .bind(&id)
-        .execute(&state.pool)
-        .await
-        .map_err(internal_error)?;
-
-    let _ = state.notifier.send(ServerEvent::DataChanged);
-    Ok(StatusCode::NO_CONTENT)
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    records
 }
 
-async fn list_categories(State(state): State<AppState>) -> AppResult<Vec<Category>> {
-    let rows = sqlx::query_as::<_, Category>("SELECT * FROM categories ORDER BY name ASC")
+async fn list_transfers(
+    State(state): State<AppState>,
+    Query(params): Query<TransferQuery>,
+) -> AppResult<Vec<TransferView>> {
+    let from = created_bound("from", &params.from)?;
+    let to = created_bound("to", &params.to)?;
+    activate_due_transactions(&state).await?;
+    let mut query = QueryBuilder::<Sqlite>::new(
+        "SELECT t.id, t.account_id AS from_account_id, a.name AS from_account, \
+         t.to_account_id, d.name AS to_account, t.amount, t.to_amount, t.description, \
+         t.occurred_at, t.scheduled \
+         FROM transactions t \
+         LEFT JOIN accounts a ON a.id = t.account_id \
+         LEFT JOIN accounts d ON d.id = t.to_account_id \
+         WHERE t.direction = 'transfer' \
+           AND NOT EXISTS (SELECT 1 FROM accounts hidden WHERE hidden.deleted_at IS NOT NULL \
+               AND hidden.id IN (t.account_id, t.to_account_id))",
+    );
+    if let Some(from) = from {
+        query.push(" AND julianday(t.occurred_at) >= julianday(").push_bind(from).push(")");
+    }
+    if let Some(to) = to {
+        query.push(" AND julianday(t.occurred_at) <= julianday(").push_bind(to).push(")");
+    }
+    query.push(" ORDER BY t.occurred_at DESC, t.created_at DESC");
+
+    let rows = query
+        .build_query_as::<TransferView>()
         .fetch_all(&state.pool)
         .await
         .map_err(internal_error)?;
     Ok(Json(rows))
 }
 
-async fn create_category(
-    State(state): State<AppState>,
-    Json(payload): Json<CreateCategory>,
-) -> AppResult<Category> {
-    let id = Uuid::new_v4().to_string();
-    let now = OffsetDateTime::now_utc()
-        .format(&time::format_description::well_known::Rfc3339)
-        .unwrap();
-    sqlx::query("INSERT INTO categories (id, name, created_at) VALUES (?1, ?2, ?3)")
-        .bind(&id)
-        .bind(&payload.name)
-        .bind(&now)
-        .execute(&state.pool)
-        .await
-        .map_err(|e| map_conflict(e, "category already exists"))?;
-
-    let category = Category {
-        id,
-        name: payload.name,
-        created_at: now,
-    };
-    let _ = state.notifier.send(ServerEvent::DataChanged);
-    Ok(Json(category))
-}
-
-async fn list_transactions(State(state): State<AppState>) -> AppResult<Vec<Transaction>> {
-    let base_rows = sqlx::query_as::<_, TransactionRow>(
-        "SELECT * FROM transactions ORDER BY occurred_at DESC, created_at DESC",
-    )
+async fn kind_report(State(state): State<AppState>) -> AppResult<Vec<KindTotal>> {
+    activate_due_transactions(&state).await?;
+    let rows: Vec<(String, i64, f64)> = sqlx::query_as(&format!(
+        "SELECT kind, COUNT(1), CAST(SUM({}) AS REAL) FROM accounts WHERE deleted_at IS NULL GROUP BY kind",
+        state.balance_mode.column()
+    ))
     .fetch_all(&state.pool)
     .await
     .map_err(internal_error)?;
 
-    let mut results = Vec::with_capacity(base_rows.len());
-    for row in base_rows {
-        let splits = sqlx::query_as::<_, TransactionSplit>(
-            "SELECT transaction_id, category_id, amount FROM transaction_splits WHERE transaction_id = ?1",
-        )
-        .bind(&row.id)
-        .fetch_all(&state.pool)
-        .await
-        .map_err(internal_error)?;
-
-        let txn = Transaction {
-            id: row.id,
-            account_id: row.account_id,
-            to_account_id: row.to_account_id,
-            amount: row.amount,
-            direction: parse_direction(&row.direction)?,
-            description: row.description,
-            occurred_at: row.occurred_at,
-            splits,
-            created_at: row.created_at,
-            updated_at: row.updated_at,
-        };
-        results.push(txn);
-    }
-    Ok(Json(results))
+    let totals = [
+        AccountKind::Checking,
+        AccountKind::Savings,
+        AccountKind::Credit,
+        AccountKind::Investment,
+    ]
+    .into_iter()
+    .map(|kind| {
+        let (account_count, balance) = rows
+            .iter()
+            .find(|(name, _, _)| name == kind.as_str())
+            .map_or((0, 0.0), |(_, count, balance)| (*count, *balance));
+        KindTotal {
+            kind: kind.as_str(),
+            account_count,
+            balance: round_in_currency(balance, &state.money.currency),
+        }
+    })
+    .collect();
+    Ok(Json(totals))
 }
 
-async fn get_transaction(
+// Grouped by payee when one is attached, otherwise by description.
+async fn merchant_report(
     State(state): State<AppState>,
-    Path(id): Path<String>,
-) -> AppResult<Transaction> {
-    let row = sqlx::query_as::<_, TransactionRow>("SELECT * FROM transactions WHERE id = ?1")
-        .bind(&id)
-        .fetch_optional(&state.pool)
+    Query(params): Query<MerchantQuery>,
+) -> AppResult<Vec<MerchantTotal>> {
+    let limit = params.limit.unwrap_or(DEFAULT_MERCHANT_LIMIT);
+    if !(1..=MAX_MERCHANT_LIMIT).contains(&limit) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("limit must be between 1 and {MAX_MERCHANT_LIMIT}"),
+        ));
+    }
+    let from = created_bound("from", &params.from)?;
+    let to = created_bound("to", &params.to)?;
+    activate_due_transactions(&state).await?;
+
+    let mut query = QueryBuilder::<Sqlite>::new(
+        "SELECT p.id AS payee_id, COALESCE(p.name, NULLIF(LOWER(TRIM(t.description)), ''), ",
+    );
+    query
+        .push_bind(UNLABELED_MERCHANT)
+        .push(
+            ") AS merchant, \
+             SUM(CASE WHEN t.direction = 'refund' THEN -t.amount ELSE t.amount END) AS total, \
+             COUNT(*) AS count FROM transactions t LEFT JOIN payees p ON p.id = t.payee_id \
+             WHERE t.scheduled = 0 AND t.direction IN ('expense', 'refund') \
+               AND NOT EXISTS (SELECT 1 FROM accounts hidden WHERE hidden.deleted_at IS NOT NULL \
+                   AND hidden.id IN (t.account_id, t.to_account_id))",
+        );
+    if let Some(from) = from {
+        query.push(" AND julianday(t.occurred_at) >= julianday(").push_bind(from).push(")");
+    }
+    if let Some(to) = to {
+        query.push(" AND julianday(t.occurred_at) <= julianday(").push_bind(to).push(")");
+    }
+    query
+        .push(" GROUP BY p.id, merchant ORDER BY total DESC, merchant ASC LIMIT ")
+        .push_bind(limit);
+
+    let rows = query
+        .build_query_as::<MerchantTotal>()
+        .fetch_all(&state.pool)
         .await
-        .map_err(internal_error)?
-        .ok_or((StatusCode::NOT_FOUND, "transaction not found".to_string()))?;
+        .map_err(internal_error)?;
+    Ok(Json(
+        rows.into_iter()
+            .map(|m| MerchantTotal {
+                total: round_in_currency(m.total, &state.money.currency),
+                ..m
+            })
+            .collect(),
+    ))
+}
 
-    let splits = sqlx::query_as::<_, TransactionSplit>(
-        "SELECT transaction_id, category_id, amount FROM transaction_splits WHERE transaction_id = ?1",
-    )
-    .bind(&row.id)
+async fn net_worth_report(State(state): State<AppState>) -> AppResult<NetWorthReport> {
+    activate_due_transactions(&state).await?;
+    let balance = state.balance_mode.column();
+    let accounts = sqlx::query_as::<_, NetWorthAccount>(&format!(
+        "SELECT id, name, kind, {balance} AS balance, CAST(CASE WHEN kind = 'credit' THEN -({balance}) ELSE 0 END AS REAL) AS owed, currency FROM accounts WHERE deleted_at IS NULL ORDER BY position ASC, created_at DESC",
+    ))
     .fetch_all(&state.pool)
     .await
     .map_err(internal_error)?;
+    // Sum the rounded figures so the totals reconcile.
+    let accounts: Vec<NetWorthAccount> = accounts
+        .into_iter()
+        .map(|a| NetWorthAccount {
+            balance: round_in_currency(a.balance, &a.currency),
+            owed: round_in_currency(a.owed, &a.currency),
+            ..a
+        })
+        .collect();
 
-    let txn = Transaction {
-        id: row.id,
-        account_id: row.account_id,
-        to_account_id: row.to_account_id,
-        amount: row.amount,
-        direction: parse_direction(&row.direction)?,
-        description: row.description,
-        occurred_at: row.occurred_at,
-        splits,
-        created_at: row.created_at,
-        updated_at: row.updated_at,
-    };
-    Ok(Json(txn))
+    let liabilities: f64 = accounts.iter().map(|a| a.owed).sum();
+    let assets: f64 = accounts
+        .iter()
+        .filter(|a| a.kind != "credit")
+        .map(|a| a.balance)
+        .sum();
+    Ok(Json(NetWorthReport {
+        assets: round_in_currency(assets, &state.money.currency),
+        liabilities: round_in_currency(liabilities, &state.money.currency),
+        net_worth: round_in_currency(assets - liabilities, &state.money.currency),
+        accounts,
+    }))
 }
 
-async fn create_transaction(
+// Transfer principal is left out but its fee counts.
+async fn average_spend_report(
     State(state): State<AppState>,
-    Json(payload): Json<CreateTransaction>,
-) -> AppResult<Transaction> {
-    let txn_id = Uuid::new_v4().to_string();
-    let now = OffsetDateTime::now_utc()
-        .format(&time::format_description::well_known::Rfc3339)
-        .unwrap();
-    let occurred_at = payload.occurred_at.unwrap_or_else(|| now.clone());
-    let direction = payload.direction.clone();
-    if payload.amount < 0.0 {
-        return Err((StatusCode::BAD_REQUEST, "amount must be non-negative".into()));
-    }
+    Query(params): Query<AverageSpendQuery>,
+) -> AppResult<AverageSpend> {
+    let window = params.window.unwrap_or_else(|| DEFAULT_SPEND_WINDOW.to_string());
+    let days = window_days(&window)?;
+    let since = OffsetDateTime::now_utc() - time::Duration::days(days);
+    activate_due_transactions(&state).await?;
 
-    let to_account_id = match direction {
-        TransactionDirection::Transfer => {
-            let dest = payload
-                .to_account_id
-                .clone()
-                .ok_or((StatusCode::BAD_REQUEST, "transfer requires destination account".into()))?;
-            if dest == payload.account_id {
-                return Err((StatusCode::BAD_REQUEST, "source and destination cannot match".into()));
-            }
-            let exists: Option<(String,)> = sqlx::query_as("SELECT id FROM accounts WHERE id = ?1")
-                .bind(&dest)
-                .fetch_optional(&state.pool)
-                .await
-                .map_err(internal_error)?;
-            if exists.is_none() {
-                return Err((StatusCode::NOT_FOUND, "destination account not found".into()));
-            }
-            Some(dest)
-        }
-        _ => None,
-    };
+    let (total,): (f64,) = sqlx::query_as(&format!(
+        r#"
+        SELECT CAST(COALESCE(SUM(CASE
+            WHEN direction = 'expense' THEN amount
+            WHEN direction = 'refund' THEN -amount
+            WHEN direction = 'transfer' THEN fee
+            ELSE 0
+        END), 0) AS REAL)
+        FROM transactions
+        WHERE occurred_at >= ?1 AND scheduled = 0{HIDE_DELETED_ACCOUNTS}
+        "#,
+    ))
+    .bind(format_rfc3339(since))
+    .fetch_one(&state.pool)
+    .await
+    .map_err(internal_error)?;
 
-    let mut tx = state.pool.begin().await.map_err(internal_error)?;
-    sqlx::query("INSERT INTO transactions (id, account_id, to_account_id, amount, direction, description, occurred_at, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)")
-        .bind(&txn_id)
-        .bind(&payload.account_id)
-        .bind(&to_account_id)
-        .bind(payload.amount)
-        .bind(direction.as_str())
-        .bind(&payload.description)
-        .bind(&occurred_at)
-        .bind(&now)
-        .bind(&now)
-        .execute(&mut *tx)
+    let daily = total / days as f64;
+    let round = |value: f64| round_in_currency(value, &state.money.currency);
+    Ok(Json(AverageSpend {
+        window,
+        days,
+        total: round(total),
+        daily: round(daily),
+        weekly: round(daily * 7.0),
+        projected_monthly: round(daily * DAYS_PER_MONTH),
+    }))
+}
+
+// A transfer only contributes its fee, as an expense.
+async fn report_rows(
+    pool: &SqlitePool,
+    from: Option<&str>,
+    to: Option<&str>,
+) -> Result<Vec<(Date, TransactionDirection, f64)>, (StatusCode, String)> {
+    let mut query = QueryBuilder::<Sqlite>::new(
+        "SELECT occurred_at, \
+             CASE WHEN direction = 'transfer' THEN 'expense' ELSE direction END, \
+             CASE WHEN direction = 'transfer' THEN fee ELSE amount END \
+         FROM transactions \
+         WHERE scheduled = 0 AND (direction IN ('income', 'expense', 'refund') OR (direction = 'transfer' AND fee > 0))",
+    );
+    query.push(HIDE_DELETED_ACCOUNTS);
+    if let Some(from) = from {
+        query.push(" AND occurred_at >= ").push_bind(from);
+    }
+    if let Some(to) = to {
+        query.push(" AND occurred_at <= ").push_bind(to);
+    }
+    let rows: Vec<(String, String, f64)> = query
+        .build_query_as()
+        .fetch_all(pool)
         .await
         .map_err(internal_error)?;
 
-    let splits = if direction == TransactionDirection::Transfer {
-        Vec::new()
-    } else {
-        payload
-            .splits
-            .unwrap_or_default()
-            .into_iter()
-            .map(|s| TransactionSplit {
-                transaction_id: txn_id.clone(),
-                category_id: s.category_id,
-                amount: s.amount,
-            })
-            .collect::<Vec<_>>()
-    };
-
-    for split in &splits {
-        sqlx::query("INSERT INTO transaction_splits (transaction_id, category_id, amount) VALUES (?1, ?2, ?3)")
-            .bind(&split.transaction_id)
-            .bind(&split.category_id)
-            .bind(split.amount)
-            .execute(&mut *tx)
-            .await
-            .map_err(internal_error)?;
+    let mut results = Vec::with_capacity(rows.len());
+    for (occurred_at, direction, amount) in rows {
+        let Some(date) = parse_date_prefix(&occurred_at) else {
+            warn!("skipping transaction with unparseable date {}", occurred_at);
+            continue;
+        };
+        results.push((date, parse_direction(&direction)?, amount));
     }
+    Ok(results)
+}
 
+fn add_to_bucket(bucket: &mut ReportBucket, direction: &TransactionDirection, amount: f64) {
     match direction {
-        TransactionDirection::Income => {
-            let affected = sqlx::query("UPDATE accounts SET balance = balance + ?1 WHERE id = ?2")
-                .bind(payload.amount)
-                .bind(&payload.account_id)
-                .execute(&mut *tx)
-                .await
-                .map_err(internal_error)?
-                .rows_affected();
-
-            if affected == 0 {
-                return Err((StatusCode::NOT_FOUND, "source account not found".into()));
-            }
-        }
-        TransactionDirection::Expense => {
-            let affected = sqlx::query(
-                "UPDATE accounts SET balance = balance - ?1 WHERE id = ?2 AND (kind IN ('credit', 'investment') OR balance >= ?1)",
-            )
-            .bind(payload.amount)
-            .bind(&payload.account_id)
-            .execute(&mut *tx)
-            .await
-            .map_err(internal_error)?
-            .rows_affected();
+        TransactionDirection::Income => bucket.income += amount,
+        TransactionDirection::Expense => bucket.expense += amount,
+        TransactionDirection::Refund => bucket.expense -= amount,
+        TransactionDirection::Transfer => {}
+    }
+    bucket.net = bucket.income - bucket.expense;
+}
 
-            if affected == 0 {
-                return Err((StatusCode::BAD_REQUEST, "insufficient funds or account not found".into()));
-            }
+impl ReportBucket {
+    fn rounded(self, currency: &str) -> Self {
+        let income = round_in_currency(self.income, currency);
+        let expense = round_in_currency(self.expense, currency);
+        Self {
+            income,
+            expense,
+            net: round_in_currency(income - expense, currency),
+            ..self
         }
-        TransactionDirection::Transfer => {
-            if let Some(dest) = &to_account_id {
-                let debited = sqlx::query(
-                    "UPDATE accounts SET balance = balance - ?1 WHERE id = ?2 AND (kind IN ('credit', 'investment') OR balance >= ?1)",
-                )
-                .bind(payload.amount)
-                .bind(&payload.account_id)
-                .execute(&mut *tx)
-                .await
-                .map_err(internal_error)?
-                .rows_affected();
+    }
+}
 
-                if debited == 0 {
-                    return Err((StatusCode::BAD_REQUEST, "insufficient funds or account not found".into()));
-                }
+// Only used for the demo seed.
+fn round_cents(value: f64) -> f64 {
+    round_to_digits(value, 2)
+}
 
-                let credited = sqlx::query(
-                    "UPDATE accounts SET balance = balance + ?1 WHERE id = ?2",
-                )
-                .bind(payload.amount)
-                .bind(dest)
-                .execute(&mut *tx)
-                .await
-                .map_err(internal_error)?
-                .rows_affected();
+fn round_in_currency(value: f64, currency: &str) -> f64 {
+    round_to_digits(value, currency_minor_units(currency))
+}
 
-                if credited == 0 {
-                    return Err((StatusCode::NOT_FOUND, "destination account not found".into()));
-                }
-            }
-        }
+// Uses the shortest decimal form, so 1.005 rounds up as written.
+fn round_to_digits(value: f64, digits: u32) -> f64 {
+    let digits = digits as usize;
+    let text = value.abs().to_string();
+    let (whole, fraction) = text.split_once('.').unwrap_or((&text, ""));
+    let kept = &fraction[..fraction.len().min(digits)];
+    let Ok(mut units) = format!("{whole}{kept:0<digits$}").parse::<i128>() else {
+        return value;
+    };
+    if fraction.as_bytes().get(digits).is_some_and(|next| *next >= b'5') {
+        units += 1;
     }
+    let rounded = units as f64 / 10f64.powi(digits as i32);
+    if value < 0.0 { -rounded + 0.0 } else { rounded }
+}
 
-    tx.commit().await.map_err(internal_error)?;
+fn parse_date_prefix(value: &str) -> Option<Date> {
+    let mut parts = value.get(..10)?.split('-');
+    let year: i32 = parts.next()?.parse().ok()?;
+    let month: u8 = parts.next()?.parse().ok()?;
+    let day: u8 = parts.next()?.parse().ok()?;
+    Date::from_calendar_date(year, Month::try_from(month).ok()?, day).ok()
+}
 
-    let created = Transaction {
-        id: txn_id,
-        account_id: payload.account_id,
-        to_account_id,
-        amount: payload.amount,
-        direction,
-        description: payload.description,
-        occurred_at,
-        splits,
-        created_at: now.clone(),
-        updated_at: now,
-    };
-    let _ = state.notifier.send(ServerEvent::DataChanged);
-    Ok(Json(created))
+fn fiscal_start_month(value: Option<u8>) -> Result<Month, (StatusCode, String)> {
+    Month::try_from(value.unwrap_or(1)).map_err(|_| {
+        (
+            StatusCode::BAD_REQUEST,
+            "fiscal_year_start_month must be between 1 and 12".to_string(),
+        )
+    })
 }
 
-async fn delete_transaction(
+// Fiscal years are named after the calendar year they end in.
+fn fiscal_year_of(date: Date, fy_start: Month) -> i32 {
+    if fy_start != Month::January && u8::from(date.month()) >= u8::from(fy_start) {
+        date.year() + 1
+    } else {
+        date.year()
+    }
+}
+
+fn bucket_label(date: Date, bucket: Bucket, fy_start: Month) -> String {
+    match bucket {
+        Bucket::Month => format!("{:04}-{:02}", date.year(), u8::from(date.month())),
+        Bucket::IsoWeek => {
+            let (year, week, _) = date.to_iso_week_date();
+            format!("{:04}-W{:02}", year, week)
+        }
+        Bucket::FiscalYear => format!("FY{}", fiscal_year_of(date, fy_start)),
+    }
+}
+
+async fn admin_reset(
     State(state): State<AppState>,
-    Path(id): Path<String>,
-) -> Result<StatusCode, (StatusCode, String)> {
+    Json(payload): Json<ResetRequest>,
+) -> AppResult<ResetSummary> {
+    if !state.admin_enabled {
+        return Err((StatusCode::FORBIDDEN, "admin endpoints are disabled".into()));
+    }
+    if payload.confirm != RESET_CONFIRM_TOKEN {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("confirm must be \"{RESET_CONFIRM_TOKEN}\" to reset all data"),
+        ));
+    }
+
     let mut tx = state.pool.begin().await.map_err(internal_error)?;
-    let existing: Option<TransactionRow> =
-        sqlx::query_as("SELECT * FROM transactions WHERE id = ?1")
-            .bind(&id)
-            .fetch_optional(&mut *tx)
+    // Clear tables explicitly rather than relying on foreign-key cascades.
+    for table in [
+        "transaction_tags",
+        "recurring_transactions",
+        "templates",
+        "categorization_rules",
+        "alert_rules",
+    ] {
+        sqlx::query(&format!("DELETE FROM {table}"))
+            .execute(&mut *tx)
             .await
             .map_err(internal_error)?;
-    let Some(row) = existing else {
-        return Err((StatusCode::NOT_FOUND, "transaction not found".into()));
-    };
+    }
+    let mut removed = [0u64; 4];
+    for (slot, table) in removed.iter_mut().zip([
+        "transaction_splits",
+        "transactions",
+        "categories",
+        "accounts",
+    ]) {
+        *slot = sqlx::query(&format!("DELETE FROM {table}"))
+            .execute(&mut *tx)
+            .await
+            .map_err(internal_error)?
+            .rows_affected();
+    }
+    for table in [
+        "archived_transaction_splits",
+        "archived_transactions",
+        "transaction_history",
+        "payees",
+        "tags",
+    ] {
+        sqlx::query(&format!("DELETE FROM {table}"))
+            .execute(&mut *tx)
+            .await
+            .map_err(internal_error)?;
+    }
+    tx.commit().await.map_err(internal_error)?;
 
-    let direction = parse_direction(&row.direction)?;
+    seed_defaults(&state.pool, &state.money.currency)
+        .await
+        .map_err(internal_error)?;
 
-    match direction {
-        TransactionDirection::Income => {
-            let affected = sqlx::query("UPDATE accounts SET balance = balance - ?1 WHERE id = ?2 AND (kind IN ('credit', 'investment') OR balance >= ?1)")
-                .bind(row.amount)
-                .bind(&row.account_id)
-                .execute(&mut *tx)
-                .await
-                .map_err(internal_error)?
-                .rows_affected();
-            if affected == 0 {
-                return Err((StatusCode::BAD_REQUEST, "insufficient funds to remove income or account missing".into()));
-            }
-        }
-        TransactionDirection::Expense => {
-            let affected = sqlx::query("UPDATE accounts SET balance = balance + ?1 WHERE id = ?2")
-                .bind(row.amount)
-                .bind(&row.account_id)
-                .execute(&mut *tx)
-                .await
-                .map_err(internal_error)?
-                .rows_affected();
-            if affected == 0 {
-                return Err((StatusCode::NOT_FOUND, "source account not found".into()));
-            }
-        }
-        TransactionDirection::Transfer => {
-            if let Some(dest) = &row.to_account_id {
-                let dest_affected = sqlx::query("UPDATE accounts SET balance = balance - ?1 WHERE id = ?2 AND (kind IN ('credit', 'investment') OR balance >= ?1)")
-                    .bind(row.amount)
-                    .bind(dest)
-                    .execute(&mut *tx)
-                    .await
-                    .map_err(internal_error)?
-                    .rows_affected();
-                if dest_affected == 0 {
-                    return Err((StatusCode::BAD_REQUEST, "insufficient funds on destination to rollback transfer or account missing".into()));
-                }
-            }
-            let src_affected = sqlx::query("UPDATE accounts SET balance = balance + ?1 WHERE id = ?2")
-                .bind(row.amount)
-                .bind(&row.account_id)
-                .execute(&mut *tx)
-                .await
-                .map_err(internal_error)?
-                .rows_affected();
-            if src_affected == 0 {
-                return Err((StatusCode::NOT_FOUND, "source account not found".into()));
-            }
+    let [splits_removed, transactions_removed, categories_removed, accounts_removed] = removed;
+    let _ = state.notifier.send(ServerEvent::DataChanged);
+    Ok(Json(ResetSummary {
+        accounts_removed,
+        categories_removed,
+        transactions_removed,
+        splits_removed,
+    }))
+}
+
+async fn admin_integrity(State(state): State<AppState>) -> AppResult<Vec<BalanceDrift>> {
+    if !state.admin_enabled {
+        return Err((StatusCode::FORBIDDEN, "admin endpoints are disabled".into()));
+    }
+    if state.balance_mode == BalanceMode::Computed {
+        return Ok(Json(Vec::new()));
+    }
+
+    let accounts: Vec<(String, String, f64)> = sqlx::query_as(
+        "SELECT id, name, balance FROM accounts ORDER BY position ASC, created_at DESC",
+    )
+    .fetch_all(&state.pool)
+    .await
+    .map_err(internal_error)?;
+    let mut drifts = Vec::new();
+    for (account_id, name, stored) in accounts {
+        let computed = computed_balance(&state.pool, &account_id).await?;
+        let delta = stored - computed;
+        if delta.abs() >= BALANCE_DRIFT_TOLERANCE {
+            warn!(
+                "balance drift on account {}: stored {} computed {}",
+                account_id, stored, computed
+            );
+            drifts.push(BalanceDrift {
+                account_id,
+                name,
+                stored,
+                computed,
+                delta,
+            });
         }
     }
+    Ok(Json(drifts))
+}
 
-    sqlx::query("DELETE FROM transaction_splits WHERE transaction_id = ?1")
-        .bind(&row.id)
-        .execute(&mut *tx)
+async fn admin_snapshot(State(state): State<AppState>) -> AppResult<SnapshotInfo> {
+    if !state.admin_enabled {
+        return Err((StatusCode::FORBIDDEN, "admin endpoints are disabled".into()));
+    }
+
+    tokio::fs::create_dir_all(&state.snapshot_dir)
         .await
         .map_err(internal_error)?;
-    sqlx::query("DELETE FROM transactions WHERE id = ?1")
-        .bind(&row.id)
-        .execute(&mut *tx)
+    let now = OffsetDateTime::now_utc();
+    let file_name = format!(
+        "finance-{:04}{:02}{:02}T{:02}{:02}{:02}{:03}Z.db",
+        now.year(),
+        u8::from(now.month()),
+        now.day(),
+        now.hour(),
+        now.minute(),
+        now.second(),
+        now.millisecond()
+    );
+    let path = state.snapshot_dir.join(file_name);
+    let path_str = path.to_string_lossy().into_owned();
+
+    sqlx::query("VACUUM INTO ?1")
+        .bind(&path_str)
+        .execute(&state.pool)
         .await
         .map_err(internal_error)?;
+    let size_bytes = tokio::fs::metadata(&path)
+        .await
+        .map_err(internal_error)?
+        .len();
 
-    tx.commit().await.map_err(internal_error)?;
-    let _ = state.notifier.send(ServerEvent::DataChanged);
-    Ok(StatusCode::NO_CONTENT)
+    info!("wrote database snapshot to {} ({} bytes)", path_str, size_bytes);
+    Ok(Json(SnapshotInfo {
+        path: path_str,
+        size_bytes,
+    }))
 }
 
-async fn update_transaction(
-    State(state): State<AppState>,
-    Path(id): Path<String>,
-    Json(payload): Json<CreateTransaction>,
-) -> AppResult<Transaction> {
-    if payload.amount < 0.0 {
-        return Err((StatusCode::BAD_REQUEST, "amount must be non-negative".into()));
-    }
+async fn build_pool(database_url: &str) -> anyhow::Result<SqlitePool> {
+    let encryption_key = std::env::var("DB_ENCRYPTION_KEY")
+        .ok()
+        .filter(|key| !key.is_empty());
+    build_pool_with_key(database_url, encryption_key.as_deref()).await
+}
 
-    let direction = payload.direction.clone();
-    let mut tx = state.pool.begin().await.map_err(internal_error)?;
-    let existing: Option<TransactionRow> =
-        sqlx::query_as("SELECT * FROM transactions WHERE id = ?1")
-            .bind(&id)
-            .fetch_optional(&mut *tx)
-            .await
-            .map_err(internal_error)?;
-    let Some(old) = existing else {
-        return Err((StatusCode::NOT_FOUND, "transaction not found".into()));
+async fn build_pool_with_key(
+    database_url: &str,
+    encryption_key: Option<&str>,
+) -> anyhow::Result<SqlitePool> {
+    let opts = connect_options(database_url)?;
+    if !database_url.contains(":memory:") && !database_url.contains("mode=memory") {
+        prepare_database_file(&opts.clone().get_filename())?;
+    }
+    let journal_mode = SqliteJournalMode::from_str(
+        &std::env::var("SQLITE_JOURNAL_MODE").unwrap_or_else(|_| "wal".to_string()),
+    )?;
+    let synchronous = SqliteSynchronous::from_str(
+        &std::env::var("SQLITE_SYNCHRONOUS").unwrap_or_else(|_| "normal".to_string()),
+    )?;
+    let opts = opts
+        .busy_timeout(BUSY_TIMEOUT)
+        .journal_mode(journal_mode)
+        .synchronous(synchronous);
+    // sqlx issues the `key` pragma first on each new connection.
+    let opts = match encryption_key {
+        Some(key) => opts.pragma("key", format!("'{}'", key.replace('\'', "''"))),
+        None => opts,
     };
 
-    let to_account_id = match direction {
-        TransactionDirection::Transfer => {
-            let dest = payload
-                .to_account_id
-                .clone()
-                .ok_or((StatusCode::BAD_REQUEST, "transfer requires destination account".into()))?;
-            if dest == payload.account_id {
-                return Err((StatusCode::BAD_REQUEST, "source and destination cannot match".into()));
-            }
-            let exists: Option<(String,)> = sqlx::query_as("SELECT id FROM accounts WHERE id = ?1")
-                .bind(&dest)
-                .fetch_optional(&mut *tx)
-                .await
-                .map_err(internal_error)?;
-            if exists.is_none() {
-                return Err((StatusCode::NOT_FOUND, "destination account not found".into()));
-            }
-            Some(dest)
+    let pool = SqlitePoolOptions::new()
+        .acquire_timeout(Duration::from_secs(5))
+        .max_connections(5)
+        .connect_with(opts)
+        .await
+        .map_err(|err| match encryption_key {
+            Some(_) => anyhow::anyhow!(
+                "could not open database; is DB_ENCRYPTION_KEY correct and the file encrypted? ({err})"
+            ),
+            None => err.into(),
+        })?;
+
+    // Plain SQLite ignores `PRAGMA key`, which would leave the data unencrypted.
+    if encryption_key.is_some() {
+        let cipher: Option<(String,)> = sqlx::query_as("PRAGMA cipher_version")
+            .fetch_optional(&pool)
+            .await?;
+        if cipher.is_none() {
+            anyhow::bail!(
+                "DB_ENCRYPTION_KEY is set but this build uses plain SQLite; rebuild the backend with `--features sqlcipher`"
+            );
         }
-        _ => None,
-    };
+    }
 
-    // Replace splits with new set
-    sqlx::query("DELETE FROM transaction_splits WHERE transaction_id = ?1")
-        .bind(&id)
-        .execute(&mut *tx)
-        .await
-        .map_err(internal_error)?;
+    // In-memory databases ignore WAL.
+    let (active_mode,): (String,) = sqlx::query_as("PRAGMA journal_mode")
+        .fetch_one(&pool)
+        .await?;
+    info!("sqlite journal_mode={} synchronous={:?}", active_mode, synchronous);
+    Ok(pool)
+}
 
-    let occurred_at = payload
-        .occurred_at
-        .clone()
-        .unwrap_or_else(|| OffsetDateTime::now_utc()
-            .format(&time::format_description::well_known::Rfc3339)
-            .unwrap());
-    let updated_at = OffsetDateTime::now_utc()
-        .format(&time::format_description::well_known::Rfc3339)
-        .unwrap();
+// Windows paths like sqlite://d:/path/finance.db bypass URL parsing.
+fn connect_options(database_url: &str) -> anyhow::Result<SqliteConnectOptions> {
+    if database_url.starts_with("sqlite://") && !database_url.starts_with("sqlite::") {
+        let path_str = database_url.trim_start_matches("sqlite://");
+        if path_str.contains('?') {
+            anyhow::bail!(
+                "invalid DATABASE_URL {database_url:?}: sqlite://<path> takes no query parameters; use sqlite:<path>?<params> instead"
+            );
+        }
+        Ok(SqliteConnectOptions::default()
+            .filename(PathBuf::from(path_str))
+            .create_if_missing(true))
+    } else {
+        Ok(SqliteConnectOptions::from_str(database_url)
+            .map_err(|err| anyhow::anyhow!("invalid DATABASE_URL {database_url:?}: {err}"))?
+            .create_if_missing(true))
+    }
+}
 
-    sqlx::query("UPDATE transactions SET account_id = ?1, to_account_id = ?2, amount = ?3, direction = ?4, description = ?5, occurred_at = ?6, updated_at = ?7 WHERE id = ?8")
-        .bind(&payload.account_id)
-        .bind(&to_account_id)
-        .bind(payload.amount)
-        .bind(direction.as_str())
-        .bind(&payload.description)
-        .bind(&occurred_at)
-        .bind(&updated_at)
-        .bind(&id)
-        .execute(&mut *tx)
+// SQLite's own error for an unusable path says little, so check it up front.
+fn prepare_database_file(path: &std::path::Path) -> anyhow::Result<()> {
+    let display = path.display();
+    if path.as_os_str().is_empty() || path.file_name().is_none() {
+        anyhow::bail!("invalid database file path {display:?}: expected a path to a file");
+    }
+    if path.is_dir() {
+        anyhow::bail!("invalid database file path {display:?}: it is a directory");
+    }
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        if !parent.exists() {
+            std::fs::create_dir_all(parent).map_err(|err| match err.kind() {
+                std::io::ErrorKind::PermissionDenied => anyhow::anyhow!(
+                    "permission denied creating database directory {}",
+                    parent.display()
+                ),
+                _ => anyhow::anyhow!(
+                    "database directory {} doesn't exist and could not be created: {err}",
+                    parent.display()
+                ),
+            })?;
+            info!("created database directory {}", parent.display());
+        } else if !parent.is_dir() {
+            anyhow::bail!("database directory {} is not a directory", parent.display());
+        }
+    }
+    std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)
+        .map_err(|err| match err.kind() {
+            std::io::ErrorKind::PermissionDenied => {
+                anyhow::anyhow!("permission denied opening database file {display}")
+            }
+            _ => anyhow::anyhow!("cannot open database file {display}: {err}"),
+        })?;
+    Ok(())
+}
+
+async fn resolve_amounts(pool: &SqlitePool, payload: &mut CreateTransaction) -> Result<(), (StatusCode, String)> {
+    let currency = account_currency(pool, &payload.account_id).await?;
+    resolve_even_splits(payload, &currency)?;
+    if let Some(splits) = payload.splits.as_mut() {
+        resolve_remainder_split(splits, payload.amount, &currency)?;
+    }
+
+    check_precision("amount", payload.amount, &currency)?;
+    if let Some(fee) = payload.fee {
+        check_precision("fee", fee, &currency)?;
+    }
+    for split in payload.splits.iter().flatten() {
+        check_precision("split amount", split.amount, &currency)?;
+    }
+    if let (Some(to_amount), Some(dest)) = (payload.to_amount, &payload.to_account_id) {
+        check_precision("to_amount", to_amount, &account_currency(pool, dest).await?)?;
+    }
+    Ok(())
+}
+
+async fn check_accounts_live(pool: &SqlitePool, payload: &CreateTransaction) -> Result<(), (StatusCode, String)> {
+    let deleted: Option<(String,)> = sqlx::query_as(
+        "SELECT name FROM accounts WHERE deleted_at IS NOT NULL AND id IN (?1, ?2) LIMIT 1",
+    )
+    .bind(&payload.account_id)
+    .bind(&payload.to_account_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(internal_error)?;
+    match deleted {
+        Some((name,)) => Err((
+            StatusCode::BAD_REQUEST,
+            format!("account \"{name}\" is deleted; restore it first"),
+        )),
+        None => Ok(()),
+    }
+}
+
+async fn account_currency(pool: &SqlitePool, account_id: &str) -> Result<String, (StatusCode, String)> {
+    let currency: Option<(String,)> = sqlx::query_as("SELECT currency FROM accounts WHERE id = ?1")
+        .bind(account_id)
+        .fetch_optional(pool)
         .await
         .map_err(internal_error)?;
+    Ok(currency.map_or_else(|| DEFAULT_CURRENCY.to_string(), |(code,)| code))
+}
 
-    let splits = if direction == TransactionDirection::Transfer {
-        Vec::new()
-    } else {
-        payload
-            .splits
-            .clone()
-            .unwrap_or_default()
-            .into_iter()
-            .map(|s| TransactionSplit {
-                transaction_id: id.clone(),
-                category_id: s.category_id,
-                amount: s.amount,
-            })
-            .collect::<Vec<_>>()
-    };
-
-    for split in &splits {
-        sqlx::query("INSERT INTO transaction_splits (transaction_id, category_id, amount) VALUES (?1, ?2, ?3)")
-            .bind(&split.transaction_id)
-            .bind(&split.category_id)
-            .bind(split.amount)
-            .execute(&mut *tx)
-            .await
-            .map_err(internal_error)?;
+fn currency_minor_units(code: &str) -> u32 {
+    match code {
+        "JPY" | "KRW" | "VND" | "CLP" | "ISK" | "UGX" | "PYG" | "XAF" | "XOF" => 0,
+        "BHD" | "KWD" | "OMR" | "JOD" | "TND" | "LYD" | "IQD" => 3,
+        _ => 2,
     }
+}
 
-    // Apply balance deltas atomically to avoid transient negative checks.
-    use std::collections::HashMap;
-    let mut deltas: HashMap<String, f64> = HashMap::new();
+fn to_minor_units(amount: f64, currency: &str) -> i64 {
+    (amount * 10f64.powi(currency_minor_units(currency) as i32)).round() as i64
+}
 
-    let mut add_delta = |account_id: &str, delta: f64| {
-        let entry = deltas.entry(account_id.to_string()).or_insert(0.0);
-        *entry += delta;
+fn from_minor_units(units: i64, currency: &str) -> f64 {
+    units as f64 / 10f64.powi(currency_minor_units(currency) as i32)
+}
+
+fn check_precision(field: &str, value: f64, currency: &str) -> Result<(), (StatusCode, String)> {
+    let digits = currency_minor_units(currency);
+    let scaled = value * 10f64.powi(digits as i32);
+    // Absorbs float noise such as 0.1 * 100.
+    if (scaled - scaled.round()).abs() > 1e-3 {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("{field} {value} has more decimal places than {currency} allows ({digits})"),
+        ));
+    }
+    Ok(())
+}
+
+// The first category absorbs the rounding remainder.
+fn resolve_even_splits(payload: &mut CreateTransaction, currency: &str) -> Result<(), (StatusCode, String)> {
+    let Some(category_ids) = payload.split_evenly.take() else {
+        return Ok(());
     };
+    if payload.splits.is_some() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "use either splits or split_evenly, not both".into(),
+        ));
+    }
+    if category_ids.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "split_evenly needs at least one category".into()));
+    }
 
-    let old_direction = parse_direction(&old.direction)?;
-    match old_direction {
-        TransactionDirection::Income => add_delta(&old.account_id, -old.amount),
-        TransactionDirection::Expense => add_delta(&old.account_id, old.amount),
-        TransactionDirection::Transfer => {
-            add_delta(&old.account_id, old.amount);
-            if let Some(dest) = &old.to_account_id {
-                add_delta(dest, -old.amount);
+    let total_units = to_minor_units(payload.amount, currency);
+    let count = category_ids.len() as i64;
+    let share = total_units / count;
+    let remainder = total_units - share * count;
+    let splits = category_ids
+        .into_iter()
+        .enumerate()
+        .map(|(idx, category_id)| {
+            let units = if idx == 0 { share + remainder } else { share };
+            SplitInput {
+                category_id,
+                amount: from_minor_units(units, currency),
+                remainder: false,
             }
+        })
+        .collect();
+    payload.splits = Some(splits);
+    Ok(())
+}
+
+fn resolve_remainder_split(
+    splits: &mut [SplitInput],
+    amount: f64,
+    currency: &str,
+) -> Result<(), (StatusCode, String)> {
+    let mut marked = splits.iter().filter(|s| s.remainder);
+    if marked.next().is_none() {
+        return Ok(());
+    }
+    if marked.next().is_some() {
+        return Err((StatusCode::BAD_REQUEST, "only one split can be the remainder".into()));
+    }
+
+    let allocated_units: i64 = splits
+        .iter()
+        .filter(|s| !s.remainder)
+        .map(|s| to_minor_units(s.amount, currency))
+        .sum();
+    let remainder_units = to_minor_units(amount, currency) - allocated_units;
+    if remainder_units < 0 {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!(
+                "splits add up to {}, more than the transaction amount of {}",
+                from_minor_units(allocated_units, currency),
+                amount
+            ),
+        ));
+    }
+    for split in splits.iter_mut().filter(|s| s.remainder) {
+        split.amount = from_minor_units(remainder_units, currency);
+    }
+    Ok(())
+}
+
+fn check_distinct_splits(splits: &[SplitInput]) -> Result<(), (StatusCode, String)> {
+    for (idx, split) in splits.iter().enumerate() {
+        if splits[..idx]
+            .iter()
+            .any(|earlier| earlier.category_id == split.category_id)
+        {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                format!("category {} appears in more than one split", split.category_id),
+            ));
         }
     }
+    Ok(())
+}
 
-    match direction {
-        TransactionDirection::Income => add_delta(&payload.account_id, payload.amount),
-        TransactionDirection::Expense => add_delta(&payload.account_id, -payload.amount),
-        TransactionDirection::Transfer => {
-            add_delta(&payload.account_id, -payload.amount);
-            if let Some(dest) = &to_account_id {
-                add_delta(dest, payload.amount);
+fn check_fee(fee: Option<f64>, direction: &TransactionDirection) -> Result<f64, (StatusCode, String)> {
+    let fee = fee.unwrap_or(0.0);
+    if fee < 0.0 {
+        return Err((StatusCode::BAD_REQUEST, "fee must be non-negative".into()));
+    }
+    if fee > 0.0 && *direction != TransactionDirection::Transfer {
+        return Err((StatusCode::BAD_REQUEST, "fee is only allowed on transfers".into()));
+    }
+    Ok(fee)
+}
+
+async fn check_payee(pool: &SqlitePool, payee_id: Option<&str>) -> Result<(), (StatusCode, String)> {
+    let Some(payee_id) = payee_id else {
+        return Ok(());
+    };
+    let exists: Option<(String,)> = sqlx::query_as("SELECT id FROM payees WHERE id = ?1")
+        .bind(payee_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(internal_error)?;
+    if exists.is_none() {
+        return Err((StatusCode::BAD_REQUEST, "payee not found".into()));
+    }
+    Ok(())
+}
+
+fn check_name(name: &str) -> Result<(), (StatusCode, String)> {
+    if name.trim().is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "name must not be empty".into()));
+    }
+    check_length("name", name, MAX_NAME_LEN)
+}
+
+async fn check_transaction_fields(pool: &SqlitePool, payload: &CreateTransaction) -> Result<(), FormError> {
+    let mut errors = FieldErrors::default();
+    if payload.amount < 0.0 {
+        errors.add("amount", "amount must be non-negative".into());
+    }
+    if let Some(description) = &payload.description {
+        errors.check("description", check_length("description", description, MAX_DESCRIPTION_LEN));
+    }
+    errors.check("fee", check_fee(payload.fee, &payload.direction));
+    if payload.direction == TransactionDirection::Transfer {
+        match &payload.to_account_id {
+            None => errors.add("to_account_id", "transfer requires destination account".into()),
+            Some(dest) if *dest == payload.account_id => {
+                errors.add("to_account_id", "source and destination cannot match".into())
             }
+            Some(_) => {}
         }
     }
-
-    for (acct, delta) in deltas {
-        if delta == 0.0 {
-            continue;
+    if let Some(splits) = &payload.splits {
+        errors.check("splits", check_distinct_splits(splits));
+    }
+    match check_payee(pool, payload.payee_id.as_deref()).await {
+        Err(err) if err.0.is_server_error() => return Err(err.into()),
+        result => {
+            errors.check("payee_id", result);
         }
-        let affected = sqlx::query(
-            "UPDATE accounts SET balance = balance + ?1 WHERE id = ?2 AND (kind IN ('credit', 'investment') OR balance + ?1 >= 0)",
-        )
-        .bind(delta)
-        .bind(&acct)
-        .execute(&mut *tx)
-        .await
-        .map_err(internal_error)?
-        .rows_affected();
+    }
+    errors.finish()
+}
+
+fn check_length(field: &str, value: &str, max: usize) -> Result<(), (StatusCode, String)> {
+    if value.chars().count() > max {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("{field} must be at most {max} characters"),
+        ));
+    }
+    Ok(())
+}
 
-        if affected == 0 {
-            return Err((
-                StatusCode::BAD_REQUEST,
-                "insufficient funds for update or account not found".into(),
-            ));
-        }
+fn normalize_currency(code: &str) -> Result<String, (StatusCode, String)> {
+    let code = code.trim().to_ascii_uppercase();
+    if code.len() != 3 || !code.chars().all(|c| c.is_ascii_alphabetic()) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "currency must be a 3-letter ISO 4217 code".into(),
+        ));
     }
+    Ok(code)
+}
 
-    tx.commit().await.map_err(internal_error)?;
-    let updated = Transaction {
-        id,
-        account_id: payload.account_id,
-        to_account_id,
-        amount: payload.amount,
-        direction,
-        description: payload.description,
-        occurred_at,
-        splits,
-        created_at: old.created_at,
-        updated_at,
-    };
-    let _ = state.notifier.send(ServerEvent::DataChanged);
-    Ok(Json(updated))
+fn normalize_color(color: &str) -> Result<Option<String>, (StatusCode, String)> {
+    let color = color.trim().to_ascii_lowercase();
+    if color.is_empty() {
+        return Ok(None);
+    }
+    let is_hex = color.len() == 7
+        && color.starts_with('#')
+        && color[1..].chars().all(|c| c.is_ascii_hexdigit());
+    if is_hex || NAMED_COLORS.contains(&color.as_str()) {
+        return Ok(Some(color));
+    }
+    Err((
+        StatusCode::BAD_REQUEST,
+        format!("color must be #rrggbb or one of: {}", NAMED_COLORS.join(", ")),
+    ))
 }
 
-async fn build_pool(database_url: &str) -> anyhow::Result<SqlitePool> {
-    // Handle Windows absolute paths like sqlite://d:/path/finance.db by stripping the scheme
-    // and feeding the remainder into filename(), which avoids URL parsing quirks.
-    let opts = if database_url.starts_with("sqlite://") && !database_url.starts_with("sqlite::") {
-        let path_str = database_url.trim_start_matches("sqlite://");
-        let path = PathBuf::from(path_str);
-        SqliteConnectOptions::default()
-            .filename(path)
-            .create_if_missing(true)
-    } else {
-        SqliteConnectOptions::from_str(database_url)?.create_if_missing(true)
+async fn resolve_to_amount<'c, E>(
+    executor: E,
+    source_id: &str,
+    dest_id: &str,
+    amount: f64,
+    to_amount: Option<f64>,
+) -> Result<Option<f64>, (StatusCode, String)>
+where
+    E: sqlx::Executor<'c, Database = sqlx::Sqlite>,
+{
+    let currencies: Vec<(String, String)> =
+        sqlx::query_as("SELECT id, currency FROM accounts WHERE id IN (?1, ?2)")
+            .bind(source_id)
+            .bind(dest_id)
+            .fetch_all(executor)
+            .await
+            .map_err(internal_error)?;
+    let lookup = |id: &str| {
+        currencies
+            .iter()
+            .find(|(acct, _)| acct == id)
+            .map(|(_, currency)| currency.clone())
+    };
+    let Some(dest_currency) = lookup(dest_id) else {
+        return Err((StatusCode::NOT_FOUND, "destination account not found".into()));
+    };
+    let Some(source_currency) = lookup(source_id) else {
+        return Err((StatusCode::NOT_FOUND, "source account not found".into()));
     };
 
-    SqlitePoolOptions::new()
-        .acquire_timeout(Duration::from_secs(5))
-        .max_connections(5)
-        .connect_with(opts)
-        .await
-        .map_err(anyhow::Error::from)
+    if source_currency == dest_currency {
+        return match to_amount {
+            Some(value) if value != amount => Err((
+                StatusCode::BAD_REQUEST,
+                "to_amount is only allowed for transfers between different currencies".into(),
+            )),
+            _ => Ok(None),
+        };
+    }
+
+    match to_amount {
+        None => Err((
+            StatusCode::BAD_REQUEST,
+            format!("transfer from {source_currency} to {dest_currency} requires to_amount"),
+        )),
+        Some(value) if value < 0.0 => Err((
+            StatusCode::BAD_REQUEST,
+            "to_amount must be non-negative".into(),
+        )),
+        Some(value) => Ok(Some(value)),
+    }
+}
+
+fn period_start(
+    period: &str,
+    now: OffsetDateTime,
+) -> Result<OffsetDateTime, (StatusCode, String)> {
+    let midnight = now.replace_time(time::Time::MIDNIGHT);
+    match period {
+        "7d" => Ok(now - time::Duration::days(7)),
+        "30d" => Ok(now - time::Duration::days(30)),
+        "90d" => Ok(now - time::Duration::days(90)),
+        "mtd" => Ok(midnight.replace_day(1).map_err(internal_error)?),
+        "ytd" => Ok(midnight.replace_ordinal(1).map_err(internal_error)?),
+        other => Err((
+            StatusCode::BAD_REQUEST,
+            format!("unknown period '{other}'; expected one of 7d, 30d, 90d, mtd, ytd"),
+        )),
+    }
+}
+
+// Only rolling windows have a fixed length to average over.
+fn window_days(window: &str) -> Result<i64, (StatusCode, String)> {
+    match window {
+        "7d" => Ok(7),
+        "30d" => Ok(30),
+        "90d" => Ok(90),
+        other => Err((
+            StatusCode::BAD_REQUEST,
+            format!("unknown window '{other}'; expected one of 7d, 30d, 90d"),
+        )),
+    }
+}
+
+fn format_rfc3339(value: OffsetDateTime) -> String {
+    value
+        .format(&time::format_description::well_known::Rfc3339)
+        .unwrap()
 }
 
 fn parse_direction(dir: &str) -> Result<TransactionDirection, (StatusCode, String)> {
@@ -839,6 +6369,7 @@ fn parse_direction(dir: &str) -> Result<TransactionDirection, (StatusCode, Strin
         "income" => Ok(TransactionDirection::Income),
         "expense" => Ok(TransactionDirection::Expense),
         "transfer" => Ok(TransactionDirection::Transfer),
+        "refund" => Ok(TransactionDirection::Refund),
         _ => Err((
             StatusCode::INTERNAL_SERVER_ERROR,
             "invalid direction".into(),
@@ -857,18 +6388,74 @@ async fn init_db(pool: &SqlitePool) -> anyhow::Result<()> {
             id TEXT PRIMARY KEY,
             name TEXT NOT NULL,
             kind TEXT NOT NULL,
+            currency TEXT NOT NULL DEFAULT 'USD',
             balance REAL NOT NULL DEFAULT 0 CHECK (kind IN ('credit', 'investment') OR balance >= 0),
-            created_at TEXT NOT NULL
+            position INTEGER NOT NULL DEFAULT 0,
+            opening_balance REAL NOT NULL DEFAULT 0,
+            color TEXT,
+            created_at TEXT NOT NULL,
+            deleted_at TEXT
         );
         "#,
     )
     .execute(pool)
     .await?;
 
+    let _ = sqlx::query("ALTER TABLE accounts ADD COLUMN currency TEXT NOT NULL DEFAULT 'USD'")
+        .execute(pool)
+        .await;
+    let _ = sqlx::query("ALTER TABLE accounts ADD COLUMN position INTEGER NOT NULL DEFAULT 0")
+        .execute(pool)
+        .await;
+    let _ = sqlx::query("ALTER TABLE accounts ADD COLUMN opening_balance REAL NOT NULL DEFAULT 0")
+        .execute(pool)
+        .await;
+    let _ = sqlx::query("ALTER TABLE accounts ADD COLUMN color TEXT")
+        .execute(pool)
+        .await;
+    let _ = sqlx::query("ALTER TABLE accounts ADD COLUMN deleted_at TEXT")
+        .execute(pool)
+        .await;
+
     // Backfill new transfer target column if migrating from older schema.
     let _ = sqlx::query("ALTER TABLE transactions ADD COLUMN to_account_id TEXT")
         .execute(pool)
         .await;
+    let _ = sqlx::query("ALTER TABLE transactions ADD COLUMN to_amount REAL")
+        .execute(pool)
+        .await;
+    let _ = sqlx::query("ALTER TABLE transactions ADD COLUMN scheduled INTEGER NOT NULL DEFAULT 0")
+        .execute(pool)
+        .await;
+    let _ = sqlx::query("ALTER TABLE transactions ADD COLUMN reconciled INTEGER NOT NULL DEFAULT 0")
+        .execute(pool)
+        .await;
+    let _ = sqlx::query("ALTER TABLE transactions ADD COLUMN payee_id TEXT")
+        .execute(pool)
+        .await;
+    let _ = sqlx::query("ALTER TABLE archived_transactions ADD COLUMN payee_id TEXT")
+        .execute(pool)
+        .await;
+    let _ = sqlx::query("ALTER TABLE transactions ADD COLUMN fee REAL NOT NULL DEFAULT 0")
+        .execute(pool)
+        .await;
+    let _ = sqlx::query("ALTER TABLE archived_transactions ADD COLUMN fee REAL NOT NULL DEFAULT 0")
+        .execute(pool)
+        .await;
+    let _ = sqlx::query("ALTER TABLE transactions ADD COLUMN external_id TEXT")
+        .execute(pool)
+        .await;
+    let _ = sqlx::query("ALTER TABLE archived_transactions ADD COLUMN external_id TEXT")
+        .execute(pool)
+        .await;
+    let _ = sqlx::query("ALTER TABLE transactions ADD COLUMN source TEXT NOT NULL DEFAULT 'manual'")
+        .execute(pool)
+        .await;
+    let _ = sqlx::query(
+        "ALTER TABLE archived_transactions ADD COLUMN source TEXT NOT NULL DEFAULT 'manual'",
+    )
+    .execute(pool)
+    .await;
 
     sqlx::query(
         r#"
@@ -889,9 +6476,16 @@ async fn init_db(pool: &SqlitePool) -> anyhow::Result<()> {
             account_id TEXT NOT NULL,
             to_account_id TEXT,
             amount REAL NOT NULL,
+            to_amount REAL,
+            fee REAL NOT NULL DEFAULT 0,
             direction TEXT NOT NULL,
             description TEXT,
+            payee_id TEXT,
+            external_id TEXT,
             occurred_at TEXT NOT NULL,
+            scheduled INTEGER NOT NULL DEFAULT 0,
+            reconciled INTEGER NOT NULL DEFAULT 0,
+            source TEXT NOT NULL DEFAULT 'manual',
             created_at TEXT NOT NULL,
             updated_at TEXT NOT NULL,
             FOREIGN KEY (account_id) REFERENCES accounts(id) ON DELETE CASCADE,
@@ -901,7 +6495,163 @@ async fn init_db(pool: &SqlitePool) -> anyhow::Result<()> {
     )
     .execute(pool)
     .await?;
+    sqlx::query(
+        "CREATE UNIQUE INDEX IF NOT EXISTS transactions_external_id ON transactions(external_id)",
+    )
+    .execute(pool)
+    .await?;
+
+    // No foreign keys: archived history outlives its accounts and categories.
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS archived_transactions (
+            id TEXT PRIMARY KEY,
+            account_id TEXT NOT NULL,
+            to_account_id TEXT,
+            amount REAL NOT NULL,
+            to_amount REAL,
+            fee REAL NOT NULL DEFAULT 0,
+            direction TEXT NOT NULL,
+            description TEXT,
+            payee_id TEXT,
+            external_id TEXT,
+            occurred_at TEXT NOT NULL,
+            scheduled INTEGER NOT NULL DEFAULT 0,
+            reconciled INTEGER NOT NULL DEFAULT 0,
+            source TEXT NOT NULL DEFAULT 'manual',
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL,
+            archived_at TEXT NOT NULL
+        );
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS archived_transaction_splits (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            transaction_id TEXT NOT NULL,
+            category_id TEXT NOT NULL,
+            amount REAL NOT NULL
+        );
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS alert_rules (
+            id TEXT PRIMARY KEY,
+            direction TEXT NOT NULL,
+            threshold REAL NOT NULL,
+            created_at TEXT NOT NULL
+        );
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS payees (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL UNIQUE COLLATE NOCASE,
+            created_at TEXT NOT NULL
+        );
+        "#,
+    )
+    .execute(pool)
+    .await?;
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS tags (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL UNIQUE COLLATE NOCASE,
+            created_at TEXT NOT NULL
+        );
+        "#,
+    )
+    .execute(pool)
+    .await?;
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS transaction_tags (
+            transaction_id TEXT NOT NULL,
+            tag_id TEXT NOT NULL,
+            PRIMARY KEY (transaction_id, tag_id),
+            FOREIGN KEY (transaction_id) REFERENCES transactions(id) ON DELETE CASCADE,
+            FOREIGN KEY (tag_id) REFERENCES tags(id) ON DELETE CASCADE
+        );
+        "#,
+    )
+    .execute(pool)
+    .await?;
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS categorization_rules (
+            id TEXT PRIMARY KEY,
+            pattern TEXT NOT NULL,
+            is_regex INTEGER NOT NULL DEFAULT 0,
+            category_id TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            FOREIGN KEY (category_id) REFERENCES categories(id) ON DELETE CASCADE
+        );
+        "#,
+    )
+    .execute(pool)
+    .await?;
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS templates (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            account_id TEXT NOT NULL,
+            to_account_id TEXT,
+            amount REAL NOT NULL,
+            direction TEXT NOT NULL,
+            category_id TEXT,
+            description TEXT,
+            created_at TEXT NOT NULL,
+            FOREIGN KEY (account_id) REFERENCES accounts(id) ON DELETE CASCADE,
+            FOREIGN KEY (to_account_id) REFERENCES accounts(id) ON DELETE CASCADE,
+            FOREIGN KEY (category_id) REFERENCES categories(id) ON DELETE SET NULL
+        );
+        "#,
+    )
+    .execute(pool)
+    .await?;
 
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS recurring_transactions (
+            id TEXT PRIMARY KEY,
+            template_id TEXT NOT NULL,
+            cadence TEXT NOT NULL,
+            next_run_at TEXT NOT NULL,
+            paused INTEGER NOT NULL DEFAULT 0,
+            created_at TEXT NOT NULL,
+            FOREIGN KEY (template_id) REFERENCES templates(id) ON DELETE CASCADE
+        );
+        "#,
+    )
+    .execute(pool)
+    .await?;
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS transaction_history (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            transaction_id TEXT NOT NULL,
+            action TEXT NOT NULL,
+            changes TEXT NOT NULL,
+            recorded_at TEXT NOT NULL
+        );
+        "#,
+    )
+    .execute(pool)
+    .await?;
     sqlx::query(
         r#"
         CREATE TABLE IF NOT EXISTS transaction_splits (
@@ -920,7 +6670,35 @@ async fn init_db(pool: &SqlitePool) -> anyhow::Result<()> {
     Ok(())
 }
 
-async fn seed_defaults(pool: &SqlitePool) -> anyhow::Result<()> {
+// Refuse to start on a database missing columns init_db can't add.
+async fn verify_schema(pool: &SqlitePool) -> anyhow::Result<()> {
+    let mut missing = Vec::new();
+    for (table, columns) in EXPECTED_SCHEMA {
+        let present: Vec<(String,)> =
+            sqlx::query_as("SELECT name FROM pragma_table_info(?1)")
+                .bind(table)
+                .fetch_all(pool)
+                .await?;
+        if present.is_empty() {
+            missing.push(format!("table {table}"));
+            continue;
+        }
+        for column in *columns {
+            if !present.iter().any(|(name,)| name == column) {
+                missing.push(format!("{table}.{column}"));
+            }
+        }
+    }
+    if !missing.is_empty() {
+        anyhow::bail!(
+            "database schema is incompatible with this version; missing {}",
+            missing.join(", ")
+        );
+    }
+    Ok(())
+}
+
+async fn seed_defaults(pool: &SqlitePool, currency: &str) -> anyhow::Result<()> {
     let account_count: (i64,) = sqlx::query_as("SELECT COUNT(1) FROM accounts")
         .fetch_one(pool)
         .await?;
@@ -934,11 +6712,12 @@ async fn seed_defaults(pool: &SqlitePool) -> anyhow::Result<()> {
             ("Credit Card", "credit"),
         ] {
             sqlx::query(
-                "INSERT INTO accounts (id, name, kind, balance, created_at) VALUES (?1, ?2, ?3, 0.0, ?4)",
+                "INSERT INTO accounts (id, name, kind, currency, balance, created_at) VALUES (?1, ?2, ?3, ?4, 0.0, ?5)",
             )
             .bind(Uuid::new_v4().to_string())
             .bind(name)
             .bind(kind)
+            .bind(currency)
             .bind(&now)
             .execute(pool)
             .await?;
@@ -960,11 +6739,12 @@ async fn seed_defaults(pool: &SqlitePool) -> anyhow::Result<()> {
                     .await?;
             if exists.0 == 0 {
                 sqlx::query(
-                    "INSERT INTO accounts (id, name, kind, balance, created_at) VALUES (?1, ?2, ?3, 0.0, ?4)",
+                    "INSERT INTO accounts (id, name, kind, currency, balance, created_at) VALUES (?1, ?2, ?3, ?4, 0.0, ?5)",
                 )
                 .bind(Uuid::new_v4().to_string())
                 .bind(name)
                 .bind(kind)
+                .bind(currency)
                 .bind(&now)
                 .execute(pool)
                 .await?;
@@ -991,8 +6771,168 @@ async fn seed_defaults(pool: &SqlitePool) -> anyhow::Result<()> {
     Ok(())
 }
 
+// SEED_DEMO=true fills an empty database with a few months of sample activity.
+async fn seed_demo(state: &AppState) -> anyhow::Result<()> {
+    let pool = &state.pool;
+    let existing: (i64,) = sqlx::query_as(
+        "SELECT (SELECT COUNT(1) FROM transactions) + (SELECT COUNT(1) FROM archived_transactions)",
+    )
+    .fetch_one(pool)
+    .await?;
+    if existing.0 > 0 {
+        return Ok(());
+    }
+
+    let currency = &state.money.currency;
+    let checking = demo_account(pool, "Main Checking", "checking", currency).await?;
+    let savings = demo_account(pool, "Savings", "savings", currency).await?;
+    let credit = demo_account(pool, "Credit Card", "credit", currency).await?;
+    let brokerage = demo_account(pool, "Brokerage", "investment", currency).await?;
+    let income = demo_category(pool, "Income").await?;
+    let groceries = demo_category(pool, "Groceries").await?;
+    let rent = demo_category(pool, "Rent").await?;
+    let utilities = demo_category(pool, "Utilities").await?;
+    let entertainment = demo_category(pool, "Entertainment").await?;
+    let dining = demo_category(pool, "Dining").await?;
+    let transport = demo_category(pool, "Transport").await?;
+
+    let this_month = OffsetDateTime::now_utc().date().replace_day(1)?;
+    let mut seeded = 0;
+    for months_ago in (1..=DEMO_SEED_MONTHS).rev() {
+        let mut month = this_month;
+        for _ in 0..months_ago {
+            month = month
+                .previous_day()
+                .ok_or_else(|| anyhow::anyhow!("demo seed date out of range"))?
+                .replace_day(1)?;
+        }
+        let step = f64::from(months_ago);
+        let entries = [
+            (1, TransactionDirection::Income, &checking, None, 4200.0, Some(&income), "Payroll"),
+            (2, TransactionDirection::Expense, &checking, None, 1650.0, Some(&rent), "Rent"),
+            (4, TransactionDirection::Expense, &credit, None, 86.40 + 7.15 * step, Some(&groceries), "FreshMart"),
+            (6, TransactionDirection::Expense, &checking, None, 92.00 + 11.50 * step, Some(&utilities), "City Power & Water"),
+            (9, TransactionDirection::Expense, &credit, None, 42.75 + 3.20 * step, Some(&dining), "Luigi's Trattoria"),
+            (11, TransactionDirection::Expense, &credit, None, 64.00, Some(&transport), "Metro pass"),
+            (13, TransactionDirection::Expense, &credit, None, 103.20 - 4.60 * step, Some(&groceries), "FreshMart"),
+            (16, TransactionDirection::Expense, &credit, None, 15.99, Some(&entertainment), "StreamFlix"),
+            (18, TransactionDirection::Transfer, &checking, Some(&savings), 500.00, None, "Monthly savings"),
+            (20, TransactionDirection::Expense, &credit, None, 78.35 + 2.90 * step, Some(&groceries), "Corner Grocer"),
+            (23, TransactionDirection::Transfer, &checking, Some(&brokerage), 250.00, None, "Index fund contribution"),
+            (25, TransactionDirection::Expense, &checking, None, 38.00 + 6.00 * step, Some(&entertainment), "Cinema"),
+            (27, TransactionDirection::Transfer, &checking, Some(&credit), 350.00, None, "Card payment"),
+        ];
+        for (day, direction, account, to_account, amount, category, description) in entries {
+            let amount = round_cents(amount);
+            let payload = CreateTransaction {
+                account_id: account.clone(),
+                to_account_id: to_account.cloned(),
+                amount,
+                to_amount: None,
+                fee: None,
+                direction,
+                description: Some(description.to_string()),
+                payee_id: None,
+                external_id: None,
+                occurred_at: Some(format!("{}T12:00:00Z", month.replace_day(day)?)),
+                splits: category.map(|category_id| {
+                    vec![SplitInput {
+                        category_id: category_id.clone(),
+                        amount,
+                        remainder: false,
+                    }]
+                }),
+                split_evenly: None,
+                source: TransactionSource::Manual,
+            };
+            insert_transaction(state, payload)
+                .await
+                .map_err(|(_, message)| anyhow::anyhow!("demo seed failed: {message}"))?;
+            seeded += 1;
+        }
+    }
+    info!("seeded {} demo transaction(s)", seeded);
+    Ok(())
+}
+
+async fn demo_account(pool: &SqlitePool, name: &str, kind: &str, currency: &str) -> anyhow::Result<String> {
+    let existing: Option<(String,)> = sqlx::query_as("SELECT id FROM accounts WHERE name = ?1")
+        .bind(name)
+        .fetch_optional(pool)
+        .await?;
+    if let Some((id,)) = existing {
+        return Ok(id);
+    }
+    let id = Uuid::new_v4().to_string();
+    sqlx::query(
+        "INSERT INTO accounts (id, name, kind, currency, balance, created_at) VALUES (?1, ?2, ?3, ?4, 0.0, ?5)",
+    )
+    .bind(&id)
+    .bind(name)
+    .bind(kind)
+    .bind(currency)
+    .bind(format_rfc3339(OffsetDateTime::now_utc()))
+    .execute(pool)
+    .await?;
+    Ok(id)
+}
+
+async fn demo_category(pool: &SqlitePool, name: &str) -> anyhow::Result<String> {
+    sqlx::query("INSERT OR IGNORE INTO categories (id, name, created_at) VALUES (?1, ?2, ?3)")
+        .bind(Uuid::new_v4().to_string())
+        .bind(name)
+        .bind(format_rfc3339(OffsetDateTime::now_utc()))
+        .execute(pool)
+        .await?;
+    let (id,): (String,) = sqlx::query_as("SELECT id FROM categories WHERE name = ?1")
+        .bind(name)
+        .fetch_one(pool)
+        .await?;
+    Ok(id)
+}
+
 fn internal_error<E: std::fmt::Display>(err: E) -> (StatusCode, String) {
-    (StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
+    let message = err.to_string();
+    if is_busy_message(&message) {
+        return (StatusCode::SERVICE_UNAVAILABLE, BUSY_MESSAGE.to_string());
+    }
+    (StatusCode::INTERNAL_SERVER_ERROR, message)
+}
+
+fn is_busy_message(message: &str) -> bool {
+    message.contains("database is locked") || message.contains("database is busy")
+}
+
+async fn retry_busy<T, F, Fut>(mut op: F) -> Result<T, (StatusCode, String)>
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = Result<T, (StatusCode, String)>>,
+{
+    let mut delay = BUSY_RETRY_BASE_DELAY;
+    let mut attempt = 0;
+    loop {
+        attempt += 1;
+        match op().await {
+            Err((StatusCode::SERVICE_UNAVAILABLE, msg))
+                if msg == BUSY_MESSAGE && attempt < BUSY_RETRY_ATTEMPTS =>
+            {
+                warn!("database busy, retrying (attempt {attempt})");
+                tokio::time::sleep(delay).await;
+                delay *= 2;
+            }
+            result => return result,
+        }
+    }
+}
+
+fn map_foreign_key(err: sqlx::Error) -> (StatusCode, String) {
+    match err {
+        sqlx::Error::Database(db_err) if db_err.message().contains("FOREIGN KEY") => (
+            StatusCode::BAD_REQUEST,
+            "referenced account or category does not exist".to_string(),
+        ),
+        other => internal_error(other),
+    }
 }
 
 fn map_conflict(err: sqlx::Error, message: &str) -> (StatusCode, String) {
@@ -0,0 +1,3729 @@
+use super::*;
+use axum::body::{Body, to_bytes};
+use axum::http::{HeaderMap, Method, Request};
+use serde_json::{Value, json};
+use tower::ServiceExt;
+
+// A backend over a fresh database file with the default seed, driven through the same router
+// `main` serves. The directory is removed when the test ends.
+struct TestApp {
+    router: Router,
+    state: AppState,
+    dir: PathBuf,
+}
+
+struct TestResponse {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Value,
+}
+
+impl TestApp {
+    async fn new() -> Self {
+        Self::with(|_| {}).await
+    }
+
+    // `configure` adjusts the state before seeding and building the router, e.g. to turn on
+    // admin mode or change the base currency.
+    async fn with(configure: impl FnOnce(&mut AppState)) -> Self {
+        let dir = std::env::temp_dir().join(format!("finance-test-{}", Uuid::new_v4()));
+        let pool = build_pool(&format!("sqlite://{}", dir.join("finance.db").display()))
+            .await
+            .unwrap();
+        init_db(&pool).await.unwrap();
+        verify_schema(&pool).await.unwrap();
+        let (notifier, _) = broadcast::channel(32);
+        let mut state = AppState {
+            pool,
+            notifier,
+            admin_enabled: false,
+            read_only: false,
+            api_key: None,
+            snapshot_dir: dir.join("snapshots"),
+            money: MoneyFormat::default(),
+            balance_mode: BalanceMode::Cached,
+            max_transaction_list: DEFAULT_MAX_TRANSACTION_LIST,
+        };
+        configure(&mut state);
+        seed_defaults(&state.pool, &state.money.currency)
+            .await
+            .unwrap();
+        let router = build_router(
+            state.clone(),
+            DEFAULT_MAX_BODY_BYTES,
+            DEFAULT_REQUEST_TIMEOUT,
+        );
+        Self { router, state, dir }
+    }
+
+    async fn send(&self, request: Request<Body>) -> TestResponse {
+        let response = self.router.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let headers = response.headers().clone();
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body = serde_json::from_slice(&bytes)
+            .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(&bytes).into_owned()));
+        TestResponse {
+            status,
+            headers,
+            body,
+        }
+    }
+
+    async fn request(&self, method: Method, uri: &str, body: Option<Value>) -> TestResponse {
+        let builder = Request::builder().method(method).uri(uri);
+        let request = match body {
+            Some(body) => builder
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(body.to_string())),
+            None => builder.body(Body::empty()),
+        };
+        self.send(request.unwrap()).await
+    }
+
+    async fn get(&self, uri: &str) -> TestResponse {
+        self.request(Method::GET, uri, None).await
+    }
+
+    async fn post(&self, uri: &str, body: Value) -> TestResponse {
+        self.request(Method::POST, uri, Some(body)).await
+    }
+
+    async fn put(&self, uri: &str, body: Value) -> TestResponse {
+        self.request(Method::PUT, uri, Some(body)).await
+    }
+
+    async fn patch(&self, uri: &str, body: Value) -> TestResponse {
+        self.request(Method::PATCH, uri, Some(body)).await
+    }
+
+    async fn delete(&self, uri: &str) -> TestResponse {
+        self.request(Method::DELETE, uri, None).await
+    }
+
+    // Id of a seeded account or category by name.
+    async fn account_id(&self, name: &str) -> String {
+        self.id_by_name("/v1/accounts", name).await
+    }
+
+    // Creates an account and returns its id.
+    async fn create_account(&self, body: Value) -> String {
+        let res = self.post("/v1/accounts", body).await;
+        assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+        res.body["id"].as_str().unwrap().to_string()
+    }
+
+    async fn category_id(&self, name: &str) -> String {
+        self.id_by_name("/v1/categories", name).await
+    }
+
+    // Number of items a list endpoint returns.
+    async fn count(&self, uri: &str) -> usize {
+        let res = self.get(uri).await;
+        assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+        res.body.as_array().unwrap().len()
+    }
+
+    // An account as GET /accounts lists it.
+    async fn account(&self, account_id: &str) -> Value {
+        let list = self.get("/v1/accounts").await.body;
+        list.as_array()
+            .unwrap()
+            .iter()
+            .find(|a| a["id"] == account_id)
+            .unwrap_or_else(|| panic!("no account {account_id}"))
+            .clone()
+    }
+
+    async fn balance(&self, account_id: &str) -> f64 {
+        self.account(account_id).await["balance"].as_f64().unwrap()
+    }
+
+    async fn id_by_name(&self, uri: &str, name: &str) -> String {
+        let list = self.get(uri).await.body;
+        list.as_array()
+            .unwrap()
+            .iter()
+            .find(|item| item["name"] == name)
+            .unwrap_or_else(|| panic!("no {name} in {uri}"))["id"]
+            .as_str()
+            .unwrap()
+            .to_string()
+    }
+
+    // Posts a transaction and returns it, failing the test if the backend refuses.
+    async fn create_txn(&self, body: Value) -> Value {
+        let res = self.post("/v1/transactions", body).await;
+        assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+        res.body
+    }
+
+    async fn income(&self, account_id: &str, amount: f64) -> Value {
+        self.create_txn(
+            json!({ "account_id": account_id, "amount": amount, "direction": "income" }),
+        )
+        .await
+    }
+
+    async fn expense(&self, account_id: &str, amount: f64) -> Value {
+        self.create_txn(
+            json!({ "account_id": account_id, "amount": amount, "direction": "expense" }),
+        )
+        .await
+    }
+}
+
+impl Drop for TestApp {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+#[tokio::test]
+async fn amounts_serialize_as_numbers_by_default_and_strings_on_request() {
+    let app = TestApp::new().await;
+    let checking = app.account_id("Main Checking").await;
+    let txn = app.income(&checking, 123.45).await;
+    assert_eq!(txn["amount"], json!(123.45));
+
+    let res = app.get("/v1/transactions").await;
+    assert_eq!(res.body[0]["amount"], json!(123.45));
+    let res = app.get("/v1/transactions?amount_format=string").await;
+    assert_eq!(res.body[0]["amount"], json!("123.45"));
+
+    let accounts = app.get("/v1/accounts?amount_format=string").await.body;
+    let account = accounts
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|a| a["id"] == checking)
+        .unwrap();
+    assert_eq!(account["balance"], json!("123.45"));
+    let accounts = app.get("/v1/accounts").await.body;
+    let account = accounts
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|a| a["id"] == checking)
+        .unwrap();
+    assert_eq!(account["balance"], json!(123.45));
+}
+
+#[tokio::test]
+async fn string_amounts_keep_trailing_zeros() {
+    let app = TestApp::new().await;
+    let checking = app.account_id("Main Checking").await;
+    app.income(&checking, 10.1).await;
+    let res = app.get("/v1/transactions?amount_format=string").await;
+    assert_eq!(res.body[0]["amount"], json!("10.10"));
+}
+
+#[tokio::test]
+async fn batch_delete_reports_missing_ids_and_reverses_balances() {
+    let app = TestApp::new().await;
+    let checking = app.account_id("Main Checking").await;
+    app.income(&checking, 100.0).await;
+    let a = app.expense(&checking, 30.0).await;
+    let b = app.expense(&checking, 20.0).await;
+    assert_eq!(app.balance(&checking).await, 50.0);
+
+    let res = app
+        .post(
+            "/v1/transactions/delete",
+            json!({ "ids": [a["id"], "missing", b["id"]] }),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert_eq!(res.body["deleted"], 2);
+    assert_eq!(res.body["not_found"], json!(["missing"]));
+    assert_eq!(app.balance(&checking).await, 100.0);
+    assert_eq!(
+        app.get("/v1/transactions")
+            .await
+            .body
+            .as_array()
+            .unwrap()
+            .len(),
+        1
+    );
+}
+
+#[tokio::test]
+async fn batch_delete_is_all_or_nothing() {
+    let app = TestApp::new().await;
+    let checking = app.account_id("Main Checking").await;
+    app.income(&checking, 100.0).await;
+    let a = app.expense(&checking, 30.0).await;
+    let locked = app.expense(&checking, 20.0).await;
+    let id = locked["id"].as_str().unwrap();
+    app.post(&format!("/v1/transactions/{id}/reconcile"), json!({}))
+        .await;
+
+    let res = app
+        .post("/v1/transactions/delete", json!({ "ids": [a["id"], id] }))
+        .await;
+    assert_eq!(res.status, StatusCode::CONFLICT);
+    assert_eq!(
+        app.get("/v1/transactions")
+            .await
+            .body
+            .as_array()
+            .unwrap()
+            .len(),
+        3
+    );
+    assert_eq!(app.balance(&checking).await, 50.0);
+}
+
+#[tokio::test]
+async fn admin_reset_is_forbidden_without_the_admin_flag() {
+    let app = TestApp::new().await;
+    let res = app
+        .post("/v1/admin/reset", json!({ "confirm": "RESET" }))
+        .await;
+    assert_eq!(res.status, StatusCode::FORBIDDEN);
+    assert_eq!(app.count("/v1/accounts").await, 3);
+}
+
+#[tokio::test]
+async fn admin_reset_clears_every_table_and_reseeds() {
+    let app = TestApp::with(|state| state.admin_enabled = true).await;
+    let checking = app.account_id("Main Checking").await;
+    let groceries = app.category_id("Groceries").await;
+    app.create_account(json!({ "name": "Extra", "kind": "checking" }))
+        .await;
+    app.income(&checking, 50.0).await;
+    let template = app
+        .post(
+            "/v1/templates",
+            json!({ "name": "Coffee", "account_id": checking, "amount": 4.0,
+                    "direction": "expense", "category_id": groceries }),
+        )
+        .await
+        .body;
+    app.post(
+        "/v1/recurring",
+        json!({ "template_id": template["id"], "cadence": "monthly", "start_on": "2099-01-01" }),
+    )
+    .await;
+    app.post(
+        "/v1/alerts",
+        json!({ "direction": "expense", "threshold": 500.0 }),
+    )
+    .await;
+    app.post(
+        "/v1/categorization-rules",
+        json!({ "pattern": "coffee", "category_id": groceries }),
+    )
+    .await;
+    app.post("/v1/payees", json!({ "name": "Cafe" })).await;
+
+    let res = app
+        .post("/v1/admin/reset", json!({ "confirm": "nope" }))
+        .await;
+    assert_eq!(res.status, StatusCode::BAD_REQUEST);
+
+    let res = app
+        .post("/v1/admin/reset", json!({ "confirm": "RESET" }))
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+    assert_eq!(res.body["accounts_removed"], 4);
+    assert_eq!(res.body["transactions_removed"], 1);
+    assert_eq!(app.count("/v1/accounts").await, 3);
+    assert_eq!(app.count("/v1/categories").await, 5);
+    assert_eq!(app.count("/v1/transactions").await, 0);
+    for uri in [
+        "/v1/templates",
+        "/v1/recurring",
+        "/v1/alerts",
+        "/v1/categorization-rules",
+        "/v1/payees",
+    ] {
+        assert_eq!(app.count(uri).await, 0, "{uri} was not cleared");
+    }
+}
+
+#[tokio::test]
+async fn cross_currency_transfer_applies_each_leg_in_its_own_currency() {
+    let app = TestApp::new().await;
+    let usd = app.account_id("Main Checking").await;
+    let eur = app
+        .create_account(json!({ "name": "Euro", "kind": "savings", "currency": "EUR" }))
+        .await;
+    app.income(&usd, 200.0).await;
+
+    let txn = app
+        .create_txn(
+            json!({ "account_id": usd, "to_account_id": eur, "amount": 100.0,
+                            "to_amount": 92.5, "direction": "transfer" }),
+        )
+        .await;
+    assert_eq!(txn["amount"], json!(100.0));
+    assert_eq!(txn["to_amount"], json!(92.5));
+    assert_eq!(app.balance(&usd).await, 100.0);
+    assert_eq!(app.balance(&eur).await, 92.5);
+}
+
+#[tokio::test]
+async fn cross_currency_transfer_without_to_amount_is_rejected() {
+    let app = TestApp::new().await;
+    let usd = app.account_id("Main Checking").await;
+    let eur = app
+        .create_account(json!({ "name": "Euro", "kind": "savings", "currency": "EUR" }))
+        .await;
+    app.income(&usd, 200.0).await;
+
+    let res = app
+        .post(
+            "/v1/transactions",
+            json!({ "account_id": usd, "to_account_id": eur, "amount": 100.0,
+                    "direction": "transfer" }),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::BAD_REQUEST, "{}", res.body);
+    assert_eq!(app.balance(&usd).await, 200.0);
+}
+
+async fn groceries_template(app: &TestApp) -> (String, String, String) {
+    let checking = app.account_id("Main Checking").await;
+    let groceries = app.category_id("Groceries").await;
+    app.income(&checking, 500.0).await;
+    let res = app
+        .post(
+            "/v1/templates",
+            json!({ "name": "Weekly Groceries", "account_id": checking, "amount": 80.0,
+                    "direction": "expense", "category_id": groceries,
+                    "description": "groceries" }),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+    let id = res.body["id"].as_str().unwrap().to_string();
+    (id, checking, groceries)
+}
+
+#[tokio::test]
+async fn applying_a_template_creates_its_transaction() {
+    let app = TestApp::new().await;
+    let (template, checking, groceries) = groceries_template(&app).await;
+
+    let res = app
+        .request(
+            Method::POST,
+            &format!("/v1/templates/{template}/apply"),
+            None,
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+    assert_eq!(res.body["account_id"], json!(checking));
+    assert_eq!(res.body["amount"], json!(80.0));
+    assert_eq!(res.body["direction"], "expense");
+    assert_eq!(res.body["description"], "groceries");
+    assert_eq!(res.body["splits"][0]["category_id"], json!(groceries));
+    assert_eq!(app.balance(&checking).await, 420.0);
+}
+
+#[tokio::test]
+async fn applying_a_template_honours_overrides() {
+    let app = TestApp::new().await;
+    let (template, checking, groceries) = groceries_template(&app).await;
+
+    let res = app
+        .post(
+            &format!("/v1/templates/{template}/apply"),
+            json!({ "amount": 95.5, "description": "big shop" }),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+    assert_eq!(res.body["amount"], json!(95.5));
+    assert_eq!(res.body["description"], "big shop");
+    // The single split follows the overridden amount.
+    assert_eq!(res.body["splits"][0]["category_id"], json!(groceries));
+    assert_eq!(res.body["splits"][0]["amount"], json!(95.5));
+    assert_eq!(app.balance(&checking).await, 404.5);
+}
+
+#[test]
+fn period_shorthands_start_where_expected() {
+    let now = time::macros::datetime!(2026-03-15 10:30 UTC);
+    let start = |period| period_start(period, now).unwrap();
+    assert_eq!(start("7d"), time::macros::datetime!(2026-03-08 10:30 UTC));
+    assert_eq!(start("30d"), time::macros::datetime!(2026-02-13 10:30 UTC));
+    assert_eq!(start("90d"), time::macros::datetime!(2025-12-15 10:30 UTC));
+    assert_eq!(start("mtd"), time::macros::datetime!(2026-03-01 0:00 UTC));
+    assert_eq!(start("ytd"), time::macros::datetime!(2026-01-01 0:00 UTC));
+    let (status, _) = period_start("1y", now).unwrap_err();
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn period_filter_bounds_the_transaction_list() {
+    let app = TestApp::new().await;
+    let checking = app.account_id("Main Checking").await;
+    let savings = app.account_id("Savings").await;
+    let days_ago = |days| format_rfc3339(OffsetDateTime::now_utc() - time::Duration::days(days));
+    for days in [3, 20, 60] {
+        app.create_txn(
+            json!({ "account_id": checking, "amount": 1.0, "direction": "income",
+                               "occurred_at": days_ago(days) }),
+        )
+        .await;
+    }
+
+    assert_eq!(app.count("/v1/transactions?period=7d").await, 1);
+    assert_eq!(app.count("/v1/transactions?period=30d").await, 2);
+    assert_eq!(app.count("/v1/transactions?period=90d").await, 3);
+    // Composes with the other filters.
+    assert_eq!(
+        app.count(&format!("/v1/transactions?period=30d&account_id={savings}"))
+            .await,
+        0
+    );
+    let res = app.get("/v1/transactions?period=1y").await;
+    assert_eq!(res.status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn merging_accounts_moves_transactions_and_combines_balances() {
+    let app = TestApp::new().await;
+    let checking = app.account_id("Main Checking").await;
+    let duplicate = app
+        .create_account(json!({ "name": "Checking 2", "kind": "checking", "currency": "USD" }))
+        .await;
+    app.income(&checking, 100.0).await;
+    app.income(&duplicate, 50.0).await;
+    app.expense(&duplicate, 20.0).await;
+
+    let res = app
+        .post(
+            &format!("/v1/accounts/{duplicate}/merge"),
+            json!({ "into": checking }),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+    assert_eq!(res.body["balance"], json!(130.0));
+    assert_eq!(app.balance(&checking).await, 130.0);
+    assert_eq!(
+        app.count(&format!("/v1/transactions?account_id={checking}"))
+            .await,
+        3
+    );
+    assert_eq!(app.count("/v1/accounts").await, 3);
+}
+
+#[tokio::test]
+async fn merging_accounts_is_refused_while_transfers_link_them() {
+    let app = TestApp::new().await;
+    let checking = app.account_id("Main Checking").await;
+    let duplicate = app
+        .create_account(json!({ "name": "Checking 2", "kind": "checking", "currency": "USD" }))
+        .await;
+    app.income(&checking, 100.0).await;
+    app.create_txn(json!({ "account_id": checking, "to_account_id": duplicate,
+                           "amount": 40.0, "fee": 1.0, "direction": "transfer" }))
+        .await;
+
+    let res = app
+        .post(
+            &format!("/v1/accounts/{duplicate}/merge"),
+            json!({ "into": checking }),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::CONFLICT, "{}", res.body);
+    assert_eq!(app.balance(&checking).await, 59.0);
+    assert_eq!(app.balance(&duplicate).await, 40.0);
+
+    let res = app
+        .post(
+            &format!("/v1/accounts/{checking}/merge"),
+            json!({ "into": checking }),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::BAD_REQUEST, "{}", res.body);
+}
+
+#[tokio::test]
+async fn merging_categories_repoints_splits_and_folds_collisions() {
+    let app = TestApp::new().await;
+    let checking = app.account_id("Main Checking").await;
+    let groceries = app.category_id("Groceries").await;
+    let res = app
+        .post("/v1/categories", json!({ "name": "groceries" }))
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+    let lowercase = res.body["id"].as_str().unwrap().to_string();
+    app.income(&checking, 100.0).await;
+
+    let only_lowercase = app
+        .create_txn(
+            json!({ "account_id": checking, "amount": 10.0, "direction": "expense",
+                           "splits": [{ "category_id": lowercase, "amount": 10.0 }] }),
+        )
+        .await;
+    let both = app
+        .create_txn(
+            json!({ "account_id": checking, "amount": 50.0, "direction": "expense",
+                           "splits": [{ "category_id": groceries, "amount": 30.0 },
+                                      { "category_id": lowercase, "amount": 20.0 }] }),
+        )
+        .await;
+
+    let res = app
+        .post(
+            &format!("/v1/categories/{lowercase}/merge"),
+            json!({ "into": groceries }),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+    assert_eq!(res.body["name"], "Groceries");
+
+    let splits = app
+        .get(&format!(
+            "/v1/transactions/{}",
+            only_lowercase["id"].as_str().unwrap()
+        ))
+        .await
+        .body["splits"]
+        .clone();
+    assert_eq!(splits.as_array().unwrap().len(), 1);
+    assert_eq!(splits[0]["category_id"], json!(groceries));
+    let splits = app
+        .get(&format!(
+            "/v1/transactions/{}",
+            both["id"].as_str().unwrap()
+        ))
+        .await
+        .body["splits"]
+        .clone();
+    assert_eq!(splits.as_array().unwrap().len(), 1);
+    assert_eq!(splits[0]["category_id"], json!(groceries));
+    assert_eq!(splits[0]["amount"], json!(50.0));
+    assert_eq!(app.count("/v1/categories").await, 5);
+
+    let res = app
+        .post(
+            &format!("/v1/categories/{groceries}/merge"),
+            json!({ "into": groceries }),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::BAD_REQUEST, "{}", res.body);
+}
+
+#[tokio::test]
+async fn scheduling_compares_instants_not_offset_strings() {
+    let app = TestApp::new().await;
+    let checking = app.account_id("Main Checking").await;
+    let minus_ten = time::UtcOffset::from_hms(-10, 0, 0).unwrap();
+    let plus_fourteen = time::UtcOffset::from_hms(14, 0, 0).unwrap();
+
+    // An hour ahead, but written with an offset that sorts before now as a string.
+    let ahead = format_rfc3339(
+        (OffsetDateTime::now_utc() + Duration::from_secs(3600)).to_offset(minus_ten),
+    );
+    let txn = app
+        .create_txn(
+            json!({ "account_id": checking, "amount": 30.0, "direction": "income",
+                           "occurred_at": ahead }),
+        )
+        .await;
+    assert_eq!(txn["scheduled"], json!(true));
+    assert!(txn["occurred_at"].as_str().unwrap().ends_with('Z'), "{txn}");
+    assert_eq!(app.balance(&checking).await, 0.0);
+
+    // An hour ago, written with an offset that sorts after now.
+    let behind = format_rfc3339(
+        (OffsetDateTime::now_utc() - Duration::from_secs(3600)).to_offset(plus_fourteen),
+    );
+    let txn = app
+        .create_txn(
+            json!({ "account_id": checking, "amount": 20.0, "direction": "income",
+                           "occurred_at": behind }),
+        )
+        .await;
+    assert_eq!(txn["scheduled"], json!(false));
+    assert_eq!(app.balance(&checking).await, 20.0);
+
+    let scheduled = app.get("/v1/transactions?scheduled=true").await.body;
+    let scheduled = scheduled.as_array().unwrap();
+    assert_eq!(scheduled.len(), 1);
+    assert_eq!(scheduled[0]["amount"], json!(30.0));
+    let posted = app.get("/v1/transactions?scheduled=false").await.body;
+    assert_eq!(posted[0]["id"], txn["id"]);
+    assert_eq!(posted.as_array().unwrap().len(), 1);
+
+    let res = app
+        .post(
+            "/v1/transactions",
+            json!({ "account_id": checking, "amount": 5.0,
+                                          "direction": "income", "occurred_at": "next tuesday" }),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::BAD_REQUEST, "{}", res.body);
+}
+
+#[tokio::test]
+async fn concurrent_activation_applies_a_due_transaction_once() {
+    let app = TestApp::new().await;
+    let checking = app.account_id("Main Checking").await;
+    let ahead = format_rfc3339(OffsetDateTime::now_utc() + Duration::from_secs(3600));
+    let txn = app
+        .create_txn(
+            json!({ "account_id": checking, "amount": 30.0, "direction": "income",
+                           "occurred_at": ahead }),
+        )
+        .await;
+    assert_eq!(txn["scheduled"], json!(true));
+    sqlx::query("UPDATE transactions SET occurred_at = ?1 WHERE id = ?2")
+        .bind(format_rfc3339(
+            OffsetDateTime::now_utc() - Duration::from_secs(60),
+        ))
+        .bind(txn["id"].as_str().unwrap())
+        .execute(&app.state.pool)
+        .await
+        .unwrap();
+
+    let (first, second) = tokio::join!(
+        activate_due_transactions(&app.state),
+        activate_due_transactions(&app.state)
+    );
+    first.unwrap();
+    second.unwrap();
+    assert_eq!(app.balance(&checking).await, 30.0);
+}
+
+#[tokio::test]
+async fn text_fields_accept_their_limit_and_reject_one_more() {
+    let app = TestApp::new().await;
+    let checking = app.account_id("Main Checking").await;
+    let name = |len: usize| "n".repeat(len);
+
+    let created = app
+        .create_account(json!({ "name": name(MAX_NAME_LEN), "kind": "checking" }))
+        .await;
+    let res = app
+        .post(
+            "/v1/accounts",
+            json!({ "name": name(MAX_NAME_LEN + 1), "kind": "checking" }),
+        )
+        .await;
+    assert_too_long(&res, "name", MAX_NAME_LEN);
+
+    let uri = format!("/v1/accounts/{created}");
+    let res = app
+        .put(&uri, json!({ "name": "s".repeat(MAX_NAME_LEN) }))
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+    let res = app
+        .put(&uri, json!({ "name": "s".repeat(MAX_NAME_LEN + 1) }))
+        .await;
+    assert_too_long(&res, "name", MAX_NAME_LEN);
+
+    let res = app
+        .post("/v1/categories", json!({ "name": name(MAX_NAME_LEN) }))
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+    let res = app
+        .post("/v1/categories", json!({ "name": name(MAX_NAME_LEN + 1) }))
+        .await;
+    assert_too_long(&res, "name", MAX_NAME_LEN);
+
+    let txn = |len: usize| {
+        json!({ "account_id": checking, "amount": 1.0, "direction": "income",
+                "description": "d".repeat(len) })
+    };
+    let created = app.create_txn(txn(MAX_DESCRIPTION_LEN)).await;
+    let res = app
+        .post("/v1/transactions", txn(MAX_DESCRIPTION_LEN + 1))
+        .await;
+    assert_too_long(&res, "description", MAX_DESCRIPTION_LEN);
+
+    let uri = format!("/v1/transactions/{}", created["id"].as_str().unwrap());
+    let res = app.put(&uri, txn(MAX_DESCRIPTION_LEN)).await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+    let res = app.put(&uri, txn(MAX_DESCRIPTION_LEN + 1)).await;
+    assert_too_long(&res, "description", MAX_DESCRIPTION_LEN);
+}
+
+// Form endpoints answer 422 naming the field and its limit.
+fn assert_too_long(res: &TestResponse, field: &str, max: usize) {
+    assert_eq!(res.status, StatusCode::UNPROCESSABLE_ENTITY, "{}", res.body);
+    assert_eq!(
+        res.body["errors"][field],
+        json!(format!("{field} must be at most {max} characters"))
+    );
+}
+
+#[tokio::test]
+async fn summary_matches_the_recorded_month() {
+    let app = TestApp::new().await;
+    let checking = app.account_id("Main Checking").await;
+    let savings = app.account_id("Savings").await;
+    app.income(&checking, 1000.0).await;
+    for (category, amount) in [
+        ("Rent", 500.0),
+        ("Groceries", 80.0),
+        ("Groceries", 40.0),
+        ("Utilities", 60.0),
+        ("Entertainment", 20.0),
+    ] {
+        let category_id = app.category_id(category).await;
+        app.create_txn(
+            json!({ "account_id": checking, "amount": amount, "direction": "expense",
+                               "splits": [{ "category_id": category_id, "amount": amount }] }),
+        )
+        .await;
+    }
+    app.create_txn(
+        json!({ "account_id": checking, "to_account_id": savings, "amount": 100.0,
+                           "fee": 2.0, "direction": "transfer" }),
+    )
+    .await;
+
+    let res = app.get("/v1/summary").await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+    let summary = res.body;
+    assert_eq!(summary["net_worth"], json!(298.0));
+    assert_eq!(summary["account_count"], json!(3));
+    assert_eq!(summary["month_income"], json!(1000.0));
+    assert_eq!(summary["month_expense"], json!(702.0));
+    assert_eq!(summary["month_net"], json!(298.0));
+    let top: Vec<(&str, f64)> = summary["top_categories"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|c| (c["name"].as_str().unwrap(), c["total"].as_f64().unwrap()))
+        .collect();
+    assert_eq!(
+        top,
+        [("Rent", 500.0), ("Groceries", 120.0), ("Utilities", 60.0)]
+    );
+}
+
+#[tokio::test]
+async fn oversized_bodies_are_rejected_except_on_import() {
+    let app = TestApp::new().await;
+    let checking = app.account_id("Main Checking").await;
+    let padding = "x".repeat(DEFAULT_MAX_BODY_BYTES);
+    let res = app
+        .post(
+            "/v1/transactions",
+            json!({ "account_id": checking, "amount": 1.0, "direction": "income",
+                    "description": padding }),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::PAYLOAD_TOO_LARGE, "{}", res.body);
+    assert_eq!(app.count("/v1/transactions").await, 0);
+
+    // The import route has its own, larger limit.
+    let mut csv = String::from(
+        "occurred_at,account,direction,amount\n2024-01-31,Main Checking,income,1.00\n",
+    );
+    csv.push_str(&"\n".repeat(DEFAULT_MAX_BODY_BYTES));
+    let res = app
+        .send(
+            Request::post("/v1/import/transactions.csv")
+                .body(Body::from(csv))
+                .unwrap(),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+    assert_eq!(res.body["inserted"], json!(1));
+}
+
+#[tokio::test]
+async fn reconciled_transactions_refuse_edits_unless_forced() {
+    let app = TestApp::new().await;
+    let checking = app.account_id("Main Checking").await;
+    let txn = app.income(&checking, 100.0).await;
+    let uri = format!("/v1/transactions/{}", txn["id"].as_str().unwrap());
+    let res = app.post(&format!("{uri}/reconcile"), json!({})).await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+    assert_eq!(res.body["reconciled"], json!(true));
+
+    let edit = json!({ "account_id": checking, "amount": 80.0, "direction": "income" });
+    let res = app.put(&uri, edit.clone()).await;
+    assert_eq!(res.status, StatusCode::CONFLICT, "{}", res.body);
+    let res = app.delete(&uri).await;
+    assert_eq!(res.status, StatusCode::CONFLICT, "{}", res.body);
+    assert_eq!(app.balance(&checking).await, 100.0);
+
+    let res = app.put(&format!("{uri}?force=true"), edit).await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+    assert_eq!(res.body["amount"], json!(80.0));
+    assert_eq!(res.body["reconciled"], json!(true));
+    assert_eq!(app.balance(&checking).await, 80.0);
+
+    let res = app.delete(&format!("{uri}?force=true")).await;
+    assert_eq!(res.status, StatusCode::NO_CONTENT, "{}", res.body);
+    assert_eq!(app.balance(&checking).await, 0.0);
+}
+
+#[tokio::test]
+async fn unreconciling_allows_plain_edits_again() {
+    let app = TestApp::new().await;
+    let checking = app.account_id("Main Checking").await;
+    let txn = app.income(&checking, 100.0).await;
+    let uri = format!("/v1/transactions/{}", txn["id"].as_str().unwrap());
+    app.post(&format!("{uri}/reconcile"), json!({})).await;
+    let res = app.post(&format!("{uri}/unreconcile"), json!({})).await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+    assert_eq!(res.body["reconciled"], json!(false));
+
+    let res = app
+        .put(
+            &uri,
+            json!({ "account_id": checking, "amount": 80.0, "direction": "income" }),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+}
+
+#[test]
+fn fiscal_years_starting_in_april_roll_over_on_the_first() {
+    use time::macros::date;
+    assert_eq!(fiscal_year_of(date!(2024 - 03 - 31), Month::April), 2024);
+    assert_eq!(fiscal_year_of(date!(2024 - 04 - 01), Month::April), 2025);
+    assert_eq!(fiscal_year_of(date!(2024 - 12 - 31), Month::April), 2025);
+    assert_eq!(fiscal_year_of(date!(2024 - 12 - 31), Month::January), 2024);
+    assert_eq!(
+        bucket_label(date!(2025 - 03 - 31), Bucket::FiscalYear, Month::April),
+        "FY2025"
+    );
+}
+
+#[test]
+fn iso_weeks_spanning_new_year_take_the_week_based_year() {
+    use time::macros::date;
+    let week = |date| bucket_label(date, Bucket::IsoWeek, Month::January);
+    assert_eq!(week(date!(2024 - 12 - 29)), "2024-W52");
+    assert_eq!(week(date!(2024 - 12 - 30)), "2025-W01");
+    assert_eq!(week(date!(2025 - 01 - 05)), "2025-W01");
+    assert_eq!(week(date!(2021 - 01 - 03)), "2020-W53");
+}
+
+#[tokio::test]
+async fn fiscal_monthly_report_runs_april_to_march() {
+    let app = TestApp::new().await;
+    let checking = app.account_id("Main Checking").await;
+    for (at, amount) in [
+        ("2024-03-31T12:00:00Z", 1.0),
+        ("2024-04-01T12:00:00Z", 10.0),
+        ("2025-03-31T12:00:00Z", 100.0),
+        ("2025-04-01T12:00:00Z", 1000.0),
+    ] {
+        app.create_txn(
+            json!({ "account_id": checking, "amount": amount, "direction": "income",
+                               "occurred_at": at }),
+        )
+        .await;
+    }
+
+    let res = app
+        .get("/v1/reports/monthly?fiscal_year_start_month=4&fiscal_year=2025")
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+    let buckets = res.body.as_array().unwrap();
+    assert_eq!(buckets.len(), 12);
+    assert_eq!(buckets[0]["period"], "2024-04");
+    assert_eq!(buckets[0]["income"], json!(10.0));
+    assert_eq!(buckets[11]["period"], "2025-03");
+    assert_eq!(buckets[11]["income"], json!(100.0));
+    let total: f64 = buckets.iter().map(|b| b["income"].as_f64().unwrap()).sum();
+    assert_eq!(total, 110.0);
+}
+
+#[tokio::test]
+async fn iso_week_trend_groups_across_new_year() {
+    let app = TestApp::new().await;
+    let checking = app.account_id("Main Checking").await;
+    for at in [
+        "2024-12-29T12:00:00Z",
+        "2024-12-30T12:00:00Z",
+        "2025-01-02T12:00:00Z",
+    ] {
+        app.create_txn(
+            json!({ "account_id": checking, "amount": 5.0, "direction": "income",
+                               "occurred_at": at }),
+        )
+        .await;
+    }
+
+    let res = app
+        .get("/v1/reports/trend?bucket=iso_week&from=2024-12-01T00:00:00Z&to=2025-01-31T00:00:00Z")
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+    let buckets: Vec<(&str, f64)> = res
+        .body
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|b| (b["period"].as_str().unwrap(), b["income"].as_f64().unwrap()))
+        .collect();
+    assert_eq!(buckets, [("2024-W52", 5.0), ("2025-W01", 10.0)]);
+}
+
+// The `amount` of every transaction a list returns, smallest first.
+async fn sorted_amounts(app: &TestApp, uri: &str) -> Vec<f64> {
+    let res = app.get(uri).await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+    let mut amounts: Vec<f64> = res
+        .body
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|t| t["amount"].as_f64().unwrap())
+        .collect();
+    amounts.sort_by(f64::total_cmp);
+    amounts
+}
+
+#[tokio::test]
+async fn signed_amounts_follow_the_side_of_a_transfer() {
+    let app = TestApp::new().await;
+    let checking = app.account_id("Main Checking").await;
+    let savings = app.account_id("Savings").await;
+    app.income(&checking, 100.0).await;
+    app.expense(&checking, 10.0).await;
+    let transfer = app
+        .create_txn(
+            json!({ "account_id": checking, "to_account_id": savings, "amount": 40.0,
+                           "fee": 1.0, "direction": "transfer" }),
+        )
+        .await;
+    assert_eq!(transfer["amount"], json!(40.0));
+
+    assert_eq!(
+        sorted_amounts(&app, "/v1/transactions").await,
+        [10.0, 40.0, 100.0]
+    );
+    assert_eq!(
+        sorted_amounts(&app, "/v1/transactions?amount_sign=signed").await,
+        [-41.0, -10.0, 100.0]
+    );
+    // Each account's signed amounts add up to its balance.
+    assert_eq!(
+        sorted_amounts(
+            &app,
+            &format!("/v1/transactions?amount_sign=signed&account_id={checking}")
+        )
+        .await,
+        [-41.0, -10.0, 100.0]
+    );
+    assert_eq!(app.balance(&checking).await, 49.0);
+    assert_eq!(
+        sorted_amounts(
+            &app,
+            &format!("/v1/transactions?amount_sign=signed&account_id={savings}")
+        )
+        .await,
+        [40.0]
+    );
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn concurrent_writes_do_not_fail_on_the_lock() {
+    let app = TestApp::new().await;
+    let checking = app.account_id("Main Checking").await;
+    let writers: Vec<_> = (0..10)
+        .map(|_| {
+            let router = app.router.clone();
+            let checking = checking.clone();
+            tokio::spawn(async move {
+                let mut statuses = Vec::new();
+                for _ in 0..5 {
+                    let body = json!({ "account_id": checking, "amount": 10.0,
+                                       "direction": "income" });
+                    let request = Request::post("/v1/transactions?allow_duplicate=true")
+                        .header(header::CONTENT_TYPE, "application/json")
+                        .body(Body::from(body.to_string()))
+                        .unwrap();
+                    statuses.push(router.clone().oneshot(request).await.unwrap().status());
+                }
+                statuses
+            })
+        })
+        .collect();
+    for writer in writers {
+        for status in writer.await.unwrap() {
+            assert_eq!(status, StatusCode::OK);
+        }
+    }
+    assert_eq!(app.count("/v1/transactions").await, 50);
+    assert_eq!(app.balance(&checking).await, 500.0);
+}
+
+#[tokio::test]
+async fn file_databases_run_in_wal_mode() {
+    let app = TestApp::new().await;
+    let (mode,): (String,) = sqlx::query_as("PRAGMA journal_mode")
+        .fetch_one(&app.state.pool)
+        .await
+        .unwrap();
+    assert_eq!(mode, "wal");
+    // NORMAL is safe under WAL and skips an fsync per commit.
+    let (synchronous,): (i64,) = sqlx::query_as("PRAGMA synchronous")
+        .fetch_one(&app.state.pool)
+        .await
+        .unwrap();
+    assert_eq!(synchronous, 1);
+}
+
+#[tokio::test]
+async fn bad_enum_values_get_a_422_naming_the_field_and_choices() {
+    let app = TestApp::new().await;
+    let res = app
+        .post(
+            "/v1/accounts",
+            json!({ "name": "Wallet", "kind": "crypto" }),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::UNPROCESSABLE_ENTITY, "{}", res.body);
+    let message = res.body.as_str().unwrap();
+    assert!(message.contains("kind"), "{message}");
+    assert!(message.contains("crypto"), "{message}");
+    assert!(message.contains("checking"), "{message}");
+
+    let checking = app.account_id("Main Checking").await;
+    let res = app
+        .post(
+            "/v1/transactions",
+            json!({ "account_id": checking, "amount": 1.0, "direction": "gift" }),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::UNPROCESSABLE_ENTITY, "{}", res.body);
+    let message = res.body.as_str().unwrap();
+    assert!(message.contains("direction"), "{message}");
+    assert!(message.contains("gift"), "{message}");
+    assert!(message.contains("expense"), "{message}");
+}
+
+#[tokio::test]
+async fn snapshot_writes_a_readable_copy_of_the_database() {
+    let app = TestApp::with(|state| state.admin_enabled = true).await;
+    let checking = app.account_id("Main Checking").await;
+    app.income(&checking, 75.0).await;
+
+    let res = app.post("/v1/admin/snapshot", json!({})).await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+    let path = PathBuf::from(res.body["path"].as_str().unwrap());
+    assert!(
+        path.starts_with(app.dir.join("snapshots")),
+        "{}",
+        path.display()
+    );
+    let bytes = std::fs::read(&path).unwrap();
+    assert_eq!(res.body["size_bytes"], json!(bytes.len()));
+    assert!(bytes.starts_with(b"SQLite format 3\0"));
+
+    let copy = build_pool(&format!("sqlite://{}", path.display()))
+        .await
+        .unwrap();
+    verify_schema(&copy).await.unwrap();
+    let (balance,): (f64,) = sqlx::query_as("SELECT balance FROM accounts WHERE id = ?1")
+        .bind(&checking)
+        .fetch_one(&copy)
+        .await
+        .unwrap();
+    assert_eq!(balance, 75.0);
+}
+
+#[tokio::test]
+async fn snapshot_needs_the_admin_flag() {
+    let app = TestApp::new().await;
+    let res = app.post("/v1/admin/snapshot", json!({})).await;
+    assert_eq!(res.status, StatusCode::FORBIDDEN, "{}", res.body);
+    assert!(!app.dir.join("snapshots").exists());
+}
+
+#[tokio::test]
+async fn archiving_moves_old_rows_and_keeps_balances() {
+    let app = TestApp::with(|state| state.balance_mode = BalanceMode::Computed).await;
+    let checking = app.account_id("Main Checking").await;
+    let days_ago =
+        |days: i64| format_rfc3339(OffsetDateTime::now_utc() - time::Duration::days(days));
+    app.create_txn(
+        json!({ "account_id": checking, "amount": 100.0, "direction": "income",
+                           "occurred_at": days_ago(400) }),
+    )
+    .await;
+    app.create_txn(
+        json!({ "account_id": checking, "amount": 30.0, "direction": "expense",
+                           "occurred_at": days_ago(200) }),
+    )
+    .await;
+    let recent = app.income(&checking, 20.0).await;
+    // Written an hour before the cutoff but with an offset that sorts after it as text.
+    let plus_fourteen = time::UtcOffset::from_hms(14, 0, 0).unwrap();
+    let edge = (OffsetDateTime::now_utc() - time::Duration::days(30) - time::Duration::hours(1))
+        .to_offset(plus_fourteen);
+    let edge_txn = app.income(&checking, 5.0).await;
+    sqlx::query("UPDATE transactions SET occurred_at = ?1 WHERE id = ?2")
+        .bind(format_rfc3339(edge))
+        .bind(edge_txn["id"].as_str().unwrap())
+        .execute(&app.state.pool)
+        .await
+        .unwrap();
+    assert_eq!(app.balance(&checking).await, 95.0);
+
+    assert_eq!(archive_old_transactions(&app.state, 30).await.unwrap(), 3);
+    assert_eq!(app.balance(&checking).await, 95.0);
+    let live = app.get("/v1/transactions").await.body;
+    assert_eq!(live.as_array().unwrap().len(), 1);
+    assert_eq!(live[0]["id"], recent["id"]);
+    assert_eq!(app.count("/v1/transactions/archived").await, 3);
+}
+
+#[tokio::test]
+async fn large_expenses_broadcast_an_alert() {
+    let app = TestApp::new().await;
+    let mut events = app.state.notifier.subscribe();
+    let checking = app.account_id("Main Checking").await;
+    app.income(&checking, 1000.0).await;
+
+    let res = app
+        .post(
+            "/v1/alerts",
+            json!({ "direction": "expense", "threshold": 500.0 }),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+    let rule_id = res.body["id"].as_str().unwrap().to_string();
+    app.expense(&checking, 100.0).await;
+    let large = app.expense(&checking, 600.0).await;
+
+    let mut alerts = Vec::new();
+    let mut rule_changed = false;
+    while let Ok(event) = events.try_recv() {
+        match event {
+            ServerEvent::Alert(alert) => alerts.push(alert),
+            ServerEvent::DataChanged => rule_changed = true,
+            _ => {}
+        }
+    }
+    assert!(rule_changed, "creating a rule should notify clients");
+    assert_eq!(alerts.len(), 1);
+    assert_eq!(alerts[0].rule_id, rule_id);
+    assert_eq!(alerts[0].transaction_id, large["id"].as_str().unwrap());
+    assert_eq!(alerts[0].amount, 600.0);
+    assert_eq!(alerts[0].threshold, 500.0);
+}
+
+#[tokio::test]
+async fn editing_alert_rules_notifies_clients() {
+    let app = TestApp::new().await;
+    let res = app
+        .post(
+            "/v1/alerts",
+            json!({ "direction": "expense", "threshold": 500.0 }),
+        )
+        .await;
+    let uri = format!("/v1/alerts/{}", res.body["id"].as_str().unwrap());
+    let mut events = app.state.notifier.subscribe();
+
+    let res = app
+        .put(&uri, json!({ "direction": "income", "threshold": 50.0 }))
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+    assert!(matches!(events.try_recv(), Ok(ServerEvent::DataChanged)));
+    let res = app.delete(&uri).await;
+    assert_eq!(res.status, StatusCode::NO_CONTENT, "{}", res.body);
+    assert!(matches!(events.try_recv(), Ok(ServerEvent::DataChanged)));
+}
+
+#[tokio::test]
+async fn unversioned_paths_alias_v1_and_are_marked_deprecated() {
+    let app = TestApp::new().await;
+    let versioned = app.get("/v1/accounts").await;
+    let unversioned = app.get("/accounts").await;
+    assert_eq!(versioned.status, StatusCode::OK);
+    assert_eq!(unversioned.status, StatusCode::OK);
+    assert_eq!(versioned.body, unversioned.body);
+    assert_eq!(versioned.body.as_array().unwrap().len(), 3);
+    assert!(versioned.headers.get("deprecation").is_none());
+    assert_eq!(unversioned.headers["deprecation"], "true");
+
+    let health = app.get("/v1/health").await;
+    assert_eq!(health.body["api_version"], API_VERSION);
+}
+
+#[tokio::test]
+async fn account_colors_round_trip() {
+    let app = TestApp::new().await;
+    let id = app
+        .create_account(json!({ "name": "Wallet", "kind": "checking", "color": "#1A2B3C" }))
+        .await;
+    assert_eq!(app.account(&id).await["color"], json!("#1a2b3c"));
+
+    let uri = format!("/v1/accounts/{id}");
+    let res = app.put(&uri, json!({ "color": "cyan" })).await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+    assert_eq!(res.body["color"], json!("cyan"));
+    assert_eq!(app.account(&id).await["color"], json!("cyan"));
+
+    // Leaving the field out keeps it; an empty string clears it.
+    app.put(&uri, json!({ "name": "Pocket" })).await;
+    assert_eq!(app.account(&id).await["color"], json!("cyan"));
+    app.put(&uri, json!({ "color": "" })).await;
+    assert_eq!(app.account(&id).await["color"], Value::Null);
+
+    let res = app.put(&uri, json!({ "color": "#12345" })).await;
+    assert_eq!(res.status, StatusCode::UNPROCESSABLE_ENTITY, "{}", res.body);
+}
+
+#[tokio::test]
+async fn integrity_check_reports_balance_drift() {
+    let app = TestApp::with(|state| state.admin_enabled = true).await;
+    let checking = app.account_id("Main Checking").await;
+    app.income(&checking, 100.0).await;
+    assert_eq!(app.count("/v1/admin/integrity").await, 0);
+
+    sqlx::query("UPDATE accounts SET balance = balance + 12.5 WHERE id = ?1")
+        .bind(&checking)
+        .execute(&app.state.pool)
+        .await
+        .unwrap();
+    let res = app.get("/v1/admin/integrity").await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+    assert_eq!(
+        res.body,
+        json!([{ "account_id": checking, "name": "Main Checking", "stored": 112.5,
+                 "computed": 100.0, "delta": 12.5 }])
+    );
+}
+
+// Records one of each kind of entry the export has to carry, including splits, a fee and
+// a cross-currency leg.
+async fn varied_ledger(app: &TestApp) {
+    let checking = app.account_id("Main Checking").await;
+    let savings = app.account_id("Savings").await;
+    let euro = app.account_id("Euro").await;
+    let income = app.category_id("Income").await;
+    let groceries = app.category_id("Groceries").await;
+    let rent = app.category_id("Rent").await;
+    app.create_txn(
+        json!({ "account_id": checking, "amount": 12345.5, "direction": "income",
+                           "description": "Salary, March",
+                           "splits": [{ "category_id": income, "amount": 12345.5 }] }),
+    )
+    .await;
+    app.create_txn(
+        json!({ "account_id": checking, "amount": 50.0, "direction": "expense",
+                           "splits": [{ "category_id": groceries, "amount": 30.25 },
+                                      { "category_id": rent, "amount": 19.75 }] }),
+    )
+    .await;
+    app.create_txn(
+        json!({ "account_id": checking, "to_account_id": savings, "amount": 100.0,
+                           "fee": 1.5, "direction": "transfer" }),
+    )
+    .await;
+    app.create_txn(
+        json!({ "account_id": checking, "to_account_id": euro, "amount": 200.0,
+                           "to_amount": 185.4, "direction": "transfer" }),
+    )
+    .await;
+}
+
+// Every split on the live ledger as (category name, amount), sorted.
+async fn named_splits(app: &TestApp) -> Vec<(String, f64)> {
+    let categories = app.get("/v1/categories").await.body;
+    let name = |id: &Value| {
+        categories
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|c| &c["id"] == id)
+            .unwrap()["name"]
+            .as_str()
+            .unwrap()
+            .to_string()
+    };
+    let mut splits = Vec::new();
+    for txn in app.get("/v1/transactions").await.body.as_array().unwrap() {
+        for split in txn["splits"].as_array().unwrap() {
+            splits.push((
+                name(&split["category_id"]),
+                split["amount"].as_f64().unwrap(),
+            ));
+        }
+    }
+    splits.sort_by(|a, b| a.0.cmp(&b.0));
+    splits
+}
+
+async fn export_then_import(locale: Locale) {
+    let configure = |state: &mut AppState| state.money.locale = locale;
+    let source = TestApp::with(configure).await;
+    let target = TestApp::with(configure).await;
+    for app in [&source, &target] {
+        app.create_account(json!({ "name": "Euro", "kind": "savings", "currency": "EUR" }))
+            .await;
+    }
+    varied_ledger(&source).await;
+
+    let csv = source.get("/v1/export/transactions.csv").await;
+    assert_eq!(csv.status, StatusCode::OK);
+    let csv = csv.body.as_str().unwrap().to_string();
+    let res = target
+        .send(
+            Request::post("/v1/import/transactions.csv")
+                .body(Body::from(csv.clone()))
+                .unwrap(),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+    assert_eq!(res.body["inserted"], json!(4), "{}\n{csv}", res.body);
+
+    for name in ["Main Checking", "Savings", "Euro"] {
+        let (from, to) = (source.account_id(name).await, target.account_id(name).await);
+        assert_eq!(
+            source.balance(&from).await,
+            target.balance(&to).await,
+            "{name}"
+        );
+    }
+    assert_eq!(named_splits(&source).await, named_splits(&target).await);
+    assert_eq!(named_splits(&target).await.len(), 3);
+    // Exporting the imported ledger gives the same amounts back.
+    let again = target.get("/v1/export/transactions.csv").await.body;
+    let amounts = |csv: &str| -> Vec<String> {
+        csv.lines()
+            .skip(1)
+            .map(|line| line.split_once(',').unwrap().1.to_string())
+            .collect()
+    };
+    assert_eq!(amounts(&csv), amounts(again.as_str().unwrap()));
+}
+
+#[tokio::test]
+async fn exported_csv_imports_back_unchanged() {
+    export_then_import(Locale::EnUs).await;
+}
+
+#[tokio::test]
+async fn exported_csv_imports_back_unchanged_in_a_comma_decimal_locale() {
+    export_then_import(Locale::DeDe).await;
+}
+
+#[tokio::test]
+async fn refunds_reduce_category_spend_and_raise_the_balance() {
+    let app = TestApp::new().await;
+    let checking = app.account_id("Main Checking").await;
+    let groceries = app.category_id("Groceries").await;
+    app.income(&checking, 200.0).await;
+    app.create_txn(
+        json!({ "account_id": checking, "amount": 80.0, "direction": "expense",
+                           "splits": [{ "category_id": groceries, "amount": 80.0 }] }),
+    )
+    .await;
+    app.create_txn(
+        json!({ "account_id": checking, "amount": 25.0, "direction": "refund",
+                           "splits": [{ "category_id": groceries, "amount": 25.0 }] }),
+    )
+    .await;
+
+    assert_eq!(app.balance(&checking).await, 145.0);
+    let res = app
+        .get(&format!("/v1/categories/{groceries}/accounts"))
+        .await;
+    assert_eq!(res.body[0]["total"], json!(55.0));
+    assert_eq!(res.body[0]["count"], json!(2));
+    let summary = app.get("/v1/summary").await.body;
+    assert_eq!(summary["month_expense"], json!(55.0));
+    assert_eq!(summary["top_categories"][0]["total"], json!(55.0));
+}
+
+#[tokio::test]
+async fn exports_use_the_configured_currency_and_locale() {
+    let app = TestApp::with(|state| {
+        state.money = MoneyFormat {
+            currency: "EUR".into(),
+            locale: Locale::DeDe,
+        }
+    })
+    .await;
+    let checking = app.account_id("Main Checking").await;
+    app.income(&checking, 1234.5).await;
+
+    let csv = app.get("/v1/export/transactions.csv").await.body;
+    let row = csv.as_str().unwrap().lines().nth(1).unwrap().to_string();
+    assert!(row.contains("\"1.234,50 €\""), "{row}");
+    let res = app.get("/v1/transactions?amount_format=display").await;
+    assert_eq!(res.body[0]["amount"], json!("1.234,50 €"));
+}
+
+#[tokio::test]
+async fn schema_check_lists_missing_columns_until_migrated() {
+    let app = TestApp::new().await;
+    let pool = &app.state.pool;
+    sqlx::query("ALTER TABLE accounts DROP COLUMN color")
+        .execute(pool)
+        .await
+        .unwrap();
+    sqlx::query("DROP TABLE alert_rules")
+        .execute(pool)
+        .await
+        .unwrap();
+
+    let err = verify_schema(pool).await.unwrap_err().to_string();
+    assert!(err.contains("accounts.color"), "{err}");
+    assert!(err.contains("table alert_rules"), "{err}");
+
+    // Startup migrates an older database forward before checking it.
+    init_db(pool).await.unwrap();
+    verify_schema(pool).await.unwrap();
+}
+
+#[tokio::test]
+async fn substring_rules_categorize_only_matching_transactions() {
+    let app = TestApp::new().await;
+    let checking = app.account_id("Main Checking").await;
+    let groceries = app.category_id("Groceries").await;
+    app.income(&checking, 100.0).await;
+    let res = app
+        .post(
+            "/v1/categorization-rules",
+            json!({ "pattern": "market", "category_id": groceries }),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+    let matching = app
+        .create_txn(
+            json!({ "account_id": checking, "amount": 12.0, "direction": "expense",
+                           "description": "Corner MARKET #12" }),
+        )
+        .await;
+    let other = app
+        .create_txn(
+            json!({ "account_id": checking, "amount": 40.0, "direction": "expense",
+                           "description": "Cinema tickets" }),
+        )
+        .await;
+
+    let res = app.post("/v1/transactions/apply-rules", json!({})).await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+    assert_eq!(res.body["categorized"], json!(1));
+    let uri = |txn: &Value| format!("/v1/transactions/{}", txn["id"].as_str().unwrap());
+    let res = app.get(&uri(&matching)).await;
+    assert_eq!(res.body["splits"][0]["category_id"], json!(groceries));
+    assert_eq!(res.body["splits"][0]["amount"], json!(12.0));
+    let res = app.get(&uri(&other)).await;
+    assert_eq!(res.body["splits"], json!([]));
+}
+
+#[tokio::test]
+async fn merging_categories_repoints_their_rules() {
+    let app = TestApp::new().await;
+    let groceries = app.category_id("Groceries").await;
+    let res = app.post("/v1/categories", json!({ "name": "Food" })).await;
+    let food = res.body["id"].as_str().unwrap().to_string();
+    app.post(
+        "/v1/categorization-rules",
+        json!({ "pattern": "market", "category_id": food }),
+    )
+    .await;
+
+    let res = app
+        .post(
+            &format!("/v1/categories/{food}/merge"),
+            json!({ "into": groceries }),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+    let rules = app.get("/v1/categorization-rules").await.body;
+    assert_eq!(rules.as_array().unwrap().len(), 1);
+    assert_eq!(rules[0]["category_id"], json!(groceries));
+}
+
+#[cfg(not(feature = "sqlcipher"))]
+#[tokio::test]
+async fn plain_builds_refuse_an_encryption_key() {
+    let app = TestApp::new().await;
+    let url = format!("sqlite://{}", app.dir.join("encrypted.db").display());
+    let err = build_pool_with_key(&url, Some("correct horse"))
+        .await
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("--features sqlcipher"), "{err}");
+}
+
+#[cfg(feature = "sqlcipher")]
+#[tokio::test]
+async fn encrypted_databases_are_not_readable_as_plain_sqlite() {
+    let app = TestApp::new().await;
+    let path = app.dir.join("encrypted.db");
+    let url = format!("sqlite://{}", path.display());
+    let pool = build_pool_with_key(&url, Some("correct horse"))
+        .await
+        .unwrap();
+    init_db(&pool).await.unwrap();
+    seed_defaults(&pool, "USD").await.unwrap();
+    pool.close().await;
+
+    let bytes = std::fs::read(&path).unwrap();
+    assert!(!bytes.starts_with(b"SQLite format 3\0"));
+    // Without the key the header cannot be read at all.
+    assert!(build_pool_with_key(&url, None).await.is_err());
+    let reopened = build_pool_with_key(&url, Some("correct horse"))
+        .await
+        .unwrap();
+    let (count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM accounts")
+        .fetch_one(&reopened)
+        .await
+        .unwrap();
+    assert_eq!(count, 3);
+}
+
+#[tokio::test]
+async fn payees_attach_to_transactions_and_group_the_merchant_report() {
+    let app = TestApp::new().await;
+    let checking = app.account_id("Main Checking").await;
+    app.income(&checking, 500.0).await;
+    let res = app
+        .post("/v1/payees", json!({ "name": "Corner Market" }))
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+    let market = res.body["id"].as_str().unwrap().to_string();
+    app.post("/v1/payees", json!({ "name": "Cinema" })).await;
+
+    let found = app.get("/v1/payees?q=corn").await.body;
+    assert_eq!(found.as_array().unwrap().len(), 1);
+    assert_eq!(found[0]["id"], json!(market));
+
+    // Differently worded descriptions still land on one payee.
+    for (description, amount) in [("CORNER MKT 0412", 20.0), ("corner market", 15.0)] {
+        let txn = app
+            .create_txn(json!({ "account_id": checking, "amount": amount,
+                               "direction": "expense", "description": description,
+                               "payee_id": market }))
+            .await;
+        assert_eq!(txn["payee_id"], json!(market));
+    }
+    app.create_txn(
+        json!({ "account_id": checking, "amount": 50.0, "direction": "expense",
+                           "description": "Rent" }),
+    )
+    .await;
+
+    let report = app.get("/v1/reports/merchants").await.body;
+    assert_eq!(
+        report,
+        json!([
+            { "payee_id": null, "merchant": "rent", "total": 50.0, "count": 1 },
+            { "payee_id": market, "merchant": "Corner Market", "total": 35.0, "count": 2 },
+        ])
+    );
+}
+
+#[tokio::test]
+async fn updated_since_returns_only_what_changed_after_the_cursor() {
+    let app = TestApp::new().await;
+    let checking = app.account_id("Main Checking").await;
+    app.income(&checking, 10.0).await;
+    let edited = app.income(&checking, 20.0).await;
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    // The cursor is written with an offset; it still means the same instant.
+    let plus_five = time::UtcOffset::from_hms(5, 0, 0).unwrap();
+    let cursor = format_rfc3339(OffsetDateTime::now_utc().to_offset(plus_five));
+    let cursor = cursor.replace('+', "%2B");
+    tokio::time::sleep(Duration::from_millis(20)).await;
+
+    let uri = format!("/v1/transactions/{}", edited["id"].as_str().unwrap());
+    let res = app
+        .put(
+            &uri,
+            json!({ "account_id": checking, "amount": 25.0, "direction": "income" }),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+
+    let changed = app
+        .get(&format!("/v1/transactions?updated_since={cursor}"))
+        .await
+        .body;
+    assert_eq!(changed.as_array().unwrap().len(), 1);
+    assert_eq!(changed[0]["id"], edited["id"]);
+    assert_eq!(
+        app.count(&format!("/v1/transactions?created_since={cursor}"))
+            .await,
+        0
+    );
+
+    let res = app
+        .get("/v1/transactions?updated_since=2024-01-01junk")
+        .await;
+    assert_eq!(res.status, StatusCode::BAD_REQUEST, "{}", res.body);
+}
+
+#[tokio::test]
+async fn skipping_a_recurring_entry_advances_it_without_posting() {
+    let app = TestApp::new().await;
+    let (template, _, _) = groceries_template(&app).await;
+    let res = app
+        .post(
+            "/v1/recurring",
+            json!({ "template_id": template, "cadence": "monthly", "start_on": "2030-01-15" }),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+    let id = res.body["id"].as_str().unwrap().to_string();
+    assert_eq!(res.body["next_run_at"], "2030-01-15T00:00:00Z");
+
+    let res = app
+        .post(&format!("/v1/recurring/{id}/skip"), json!({}))
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+    assert_eq!(res.body["next_run_at"], "2030-02-15T00:00:00Z");
+    let res = app
+        .post(&format!("/v1/recurring/{id}/skip"), json!({}))
+        .await;
+    assert_eq!(res.body["next_run_at"], "2030-03-15T00:00:00Z");
+    // Only the seed income from the template helper.
+    assert_eq!(app.count("/v1/transactions").await, 1);
+}
+
+#[tokio::test]
+async fn paused_recurring_entries_are_not_generated() {
+    let app = TestApp::new().await;
+    let (template, checking, _) = groceries_template(&app).await;
+    let res = app
+        .post(
+            "/v1/recurring",
+            json!({ "template_id": template, "cadence": "weekly" }),
+        )
+        .await;
+    let id = res.body["id"].as_str().unwrap().to_string();
+    let res = app
+        .post(&format!("/v1/recurring/{id}/pause"), json!({}))
+        .await;
+    assert_eq!(res.body["paused"], json!(true));
+
+    run_due_recurring(&app.state).await.unwrap();
+    assert_eq!(app.count("/v1/transactions").await, 1);
+    assert_eq!(app.balance(&checking).await, 500.0);
+
+    let res = app
+        .post(&format!("/v1/recurring/{id}/resume"), json!({}))
+        .await;
+    assert_eq!(res.body["paused"], json!(false));
+    run_due_recurring(&app.state).await.unwrap();
+    assert_eq!(app.count("/v1/transactions").await, 2);
+    assert_eq!(app.balance(&checking).await, 420.0);
+}
+
+#[tokio::test]
+async fn credit_card_debt_reduces_net_worth() {
+    let app = TestApp::new().await;
+    let checking = app.account_id("Main Checking").await;
+    let card = app.account_id("Credit Card").await;
+    app.income(&checking, 1000.0).await;
+    app.expense(&card, 250.0).await;
+    assert_eq!(app.balance(&card).await, -250.0);
+
+    let report = app.get("/v1/reports/networth").await.body;
+    assert_eq!(report["assets"], json!(1000.0));
+    assert_eq!(report["liabilities"], json!(250.0));
+    assert_eq!(report["net_worth"], json!(750.0));
+    let card_row = report["accounts"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|a| a["id"] == card.as_str())
+        .unwrap()
+        .clone();
+    assert_eq!(card_row["owed"], json!(250.0));
+}
+
+#[tokio::test]
+async fn near_duplicates_are_refused_unless_allowed() {
+    let app = TestApp::new().await;
+    let checking = app.account_id("Main Checking").await;
+    let savings = app.account_id("Savings").await;
+    app.income(&checking, 500.0).await;
+    let body = json!({ "account_id": checking, "amount": 42.0, "direction": "expense",
+                       "occurred_at": "2024-05-01T12:00:00Z" });
+    let first = app.create_txn(body.clone()).await;
+
+    let res = app.post("/v1/transactions", body.clone()).await;
+    assert_eq!(res.status, StatusCode::CONFLICT, "{}", res.body);
+    assert!(
+        res.body
+            .as_str()
+            .unwrap()
+            .contains(first["id"].as_str().unwrap()),
+        "{}",
+        res.body
+    );
+    assert_eq!(app.balance(&checking).await, 458.0);
+
+    // A different account is not a duplicate.
+    app.income(&savings, 100.0).await;
+    app.create_txn(
+        json!({ "account_id": savings, "amount": 42.0, "direction": "expense",
+                           "occurred_at": "2024-05-01T12:00:00Z" }),
+    )
+    .await;
+
+    let res = app
+        .post("/v1/transactions?allow_duplicate=true", body)
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+    assert_eq!(app.balance(&checking).await, 416.0);
+}
+
+#[test]
+fn rounding_follows_the_written_decimal_not_float_noise() {
+    assert_eq!(round_cents(0.1 + 0.2), 0.3);
+    assert_eq!(round_cents(1.005), 1.01);
+    assert_eq!(round_cents(-2.675), -2.68);
+    assert_eq!(round_cents(1.004999), 1.0);
+    // Just under a half cent on a large amount must not be pushed over it.
+    assert_eq!(round_cents(123456.784999999), 123456.78);
+    assert_eq!(round_cents(-0.001), 0.0);
+    assert!(round_cents(-0.001).is_sign_positive());
+    assert_eq!(round_in_currency(1.0005, "BHD"), 1.001);
+    assert_eq!(round_in_currency(1234.5, "JPY"), 1235.0);
+    let drifted: f64 = std::iter::repeat_n(0.1, 10).sum();
+    assert_ne!(drifted, 1.0);
+    assert_eq!(round_cents(drifted), 1.0);
+}
+
+#[tokio::test]
+async fn patching_one_field_leaves_the_rest_untouched() {
+    let app = TestApp::new().await;
+    let checking = app.account_id("Main Checking").await;
+    let groceries = app.category_id("Groceries").await;
+    app.income(&checking, 500.0).await;
+    let txn = app
+        .create_txn(
+            json!({ "account_id": checking, "amount": 80.0, "direction": "expense",
+                            "description": "Weekly shop", "occurred_at": "2024-05-01T12:00:00Z",
+                            "splits": [{ "category_id": groceries, "amount": 80.0 }] }),
+        )
+        .await;
+    let uri = format!("/v1/transactions/{}", txn["id"].as_str().unwrap());
+
+    let res = app.patch(&uri, json!({ "description": "Market" })).await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+    assert_eq!(res.body["description"], "Market");
+    assert_eq!(res.body["amount"], json!(80.0));
+    assert_eq!(res.body["occurred_at"], txn["occurred_at"]);
+    assert_eq!(res.body["splits"][0]["category_id"], json!(groceries));
+    assert_eq!(app.balance(&checking).await, 420.0);
+
+    let res = app.patch(&uri, json!({ "amount": 95.0 })).await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+    assert_eq!(res.body["amount"], json!(95.0));
+    assert_eq!(res.body["description"], "Market");
+    assert_eq!(res.body["splits"][0]["amount"], json!(95.0));
+    assert_eq!(app.balance(&checking).await, 405.0);
+}
+
+// Runs creates, an update and a delete, then returns every account's listed balance by name.
+async fn balances_after_edits(mode: BalanceMode) -> (TestApp, Vec<(String, f64)>) {
+    let app = TestApp::with(|state| state.balance_mode = mode).await;
+    app.create_account(json!({ "name": "Euro", "kind": "savings", "currency": "EUR" }))
+        .await;
+    varied_ledger(&app).await;
+    let checking = app.account_id("Main Checking").await;
+    let card = app.account_id("Credit Card").await;
+    let edited = app.expense(&checking, 40.0).await;
+    let res = app
+        .patch(
+            &format!("/v1/transactions/{}", edited["id"].as_str().unwrap()),
+            json!({ "amount": 65.0 }),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+    let removed = app.expense(&card, 30.0).await;
+    let res = app
+        .delete(&format!(
+            "/v1/transactions/{}",
+            removed["id"].as_str().unwrap()
+        ))
+        .await;
+    assert_eq!(res.status, StatusCode::NO_CONTENT, "{}", res.body);
+    app.expense(&card, 12.5).await;
+
+    let mut balances: Vec<(String, f64)> = app
+        .get("/v1/accounts")
+        .await
+        .body
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|a| {
+            (
+                a["name"].as_str().unwrap().to_string(),
+                a["balance"].as_f64().unwrap(),
+            )
+        })
+        .collect();
+    balances.sort_by(|a, b| a.0.cmp(&b.0));
+    (app, balances)
+}
+
+#[tokio::test]
+async fn cached_and_computed_balances_agree() {
+    let (_, cached) = balances_after_edits(BalanceMode::Cached).await;
+    let (computed_app, computed) = balances_after_edits(BalanceMode::Computed).await;
+    assert_eq!(cached, computed);
+    assert!(
+        cached.contains(&("Main Checking".to_string(), 11929.0)),
+        "{cached:?}"
+    );
+
+    // Computed mode never writes the stored column.
+    let drifted: i64 =
+        sqlx::query_scalar("SELECT COUNT(*) FROM accounts WHERE balance != opening_balance")
+            .fetch_one(&computed_app.state.pool)
+            .await
+            .unwrap();
+    assert_eq!(drifted, 0);
+}
+
+#[tokio::test]
+async fn importing_the_same_file_twice_adds_nothing_the_second_time() {
+    let app = TestApp::new().await;
+    let checking = app.account_id("Main Checking").await;
+    let csv = "occurred_at,account,direction,amount,category,description,external_id\n\
+               2024-04-01T09:00:00Z,Main Checking,income,1000,Income,Salary,BANK-001\n\
+               2024-04-02T09:00:00Z,Main Checking,expense,42.5,Groceries,Market,BANK-002\n\
+               2024-04-03T09:00:00Z,Main Checking,expense,18,Utilities,Water,BANK-003\n";
+    let import = || async {
+        let res = app
+            .send(
+                Request::post("/v1/import/transactions.csv")
+                    .body(Body::from(csv))
+                    .unwrap(),
+            )
+            .await;
+        assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+        res.body
+    };
+
+    let first = import().await;
+    assert_eq!(first["inserted"], 3, "{first}");
+    assert_eq!(first["skipped"], 0, "{first}");
+    let second = import().await;
+    assert_eq!(second["inserted"], 0, "{second}");
+    assert_eq!(second["skipped"], 3, "{second}");
+    assert_eq!(second["errors"], json!([]), "{second}");
+
+    assert_eq!(app.count("/v1/transactions").await, 3);
+    assert_eq!(app.balance(&checking).await, 939.5);
+}
+
+#[tokio::test]
+async fn demo_seed_fills_an_empty_ledger_once() {
+    let app = TestApp::new().await;
+    let counts = || async {
+        let (accounts, categories, transactions, transfers): (i64, i64, i64, i64) = sqlx::query_as(
+            "SELECT (SELECT COUNT(*) FROM accounts), (SELECT COUNT(*) FROM categories), \
+                 (SELECT COUNT(*) FROM transactions), \
+                 (SELECT COUNT(*) FROM transactions WHERE direction = 'transfer')",
+        )
+        .fetch_one(&app.state.pool)
+        .await
+        .unwrap();
+        (accounts, categories, transactions, transfers)
+    };
+
+    seed_demo(&app.state).await.unwrap();
+    let months = i64::from(DEMO_SEED_MONTHS);
+    // The three default accounts plus Brokerage; five default categories plus two.
+    assert_eq!(counts().await, (4, 7, 13 * months, 3 * months));
+    let scheduled: i64 =
+        sqlx::query_scalar("SELECT COUNT(*) FROM transactions WHERE scheduled = 1")
+            .fetch_one(&app.state.pool)
+            .await
+            .unwrap();
+    assert_eq!(scheduled, 0);
+    let brokerage = app.account_id("Brokerage").await;
+    assert_eq!(app.balance(&brokerage).await, 250.0 * months as f64);
+
+    seed_demo(&app.state).await.unwrap();
+    assert_eq!(counts().await, (4, 7, 13 * months, 3 * months));
+}
+
+#[tokio::test]
+async fn history_records_creation_and_each_edit_as_a_diff() {
+    let app = TestApp::new().await;
+    let checking = app.account_id("Main Checking").await;
+    app.income(&checking, 500.0).await;
+    let txn = app
+        .create_txn(
+            json!({ "account_id": checking, "amount": 25.0, "direction": "expense",
+                            "description": "Lunch" }),
+        )
+        .await;
+    let id = txn["id"].as_str().unwrap();
+    let res = app
+        .patch(
+            &format!("/v1/transactions/{id}"),
+            json!({ "amount": 27.5, "description": "Lunch with tip" }),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+
+    let history = app.get(&format!("/v1/transactions/{id}/history")).await;
+    assert_eq!(history.status, StatusCode::OK, "{}", history.body);
+    let entries = history.body.as_array().unwrap();
+    assert_eq!(entries.len(), 2, "{}", history.body);
+    assert_eq!(entries[0]["action"], "created");
+    assert_eq!(entries[0]["changes"]["amount"]["after"], json!(25.0));
+    assert_eq!(entries[1]["action"], "updated");
+    assert_eq!(
+        entries[1]["changes"]["amount"],
+        json!({ "before": 25.0, "after": 27.5 })
+    );
+    assert_eq!(
+        entries[1]["changes"]["description"],
+        json!({ "before": "Lunch", "after": "Lunch with tip" })
+    );
+    // Only what changed is listed.
+    assert!(entries[1]["changes"].get("account_id").is_none());
+    assert!(entries[1]["recorded_at"].as_str().is_some());
+
+    assert_eq!(
+        app.delete(&format!("/v1/transactions/{id}")).await.status,
+        StatusCode::NO_CONTENT
+    );
+    let history = app.get(&format!("/v1/transactions/{id}/history")).await;
+    assert_eq!(history.body.as_array().unwrap().len(), 3);
+    assert_eq!(history.body[2]["action"], "deleted");
+
+    let missing = app.get("/v1/transactions/nope/history").await;
+    assert_eq!(missing.status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn balance_as_of_counts_only_what_happened_by_then() {
+    let app = TestApp::new().await;
+    let checking = app.account_id("Main Checking").await;
+    let savings = app.account_id("Savings").await;
+    let at = |amount: f64, direction: &str, occurred_at: &str| {
+        json!({ "account_id": checking, "amount": amount, "direction": direction,
+                "occurred_at": occurred_at })
+    };
+    app.create_txn(at(1000.0, "income", "2024-01-10T09:00:00Z"))
+        .await;
+    app.create_txn(at(200.0, "expense", "2024-01-31T22:00:00Z"))
+        .await;
+    app.create_txn(
+        json!({ "account_id": checking, "to_account_id": savings, "amount": 300.0,
+                           "direction": "transfer", "occurred_at": "2024-02-01T08:00:00Z" }),
+    )
+    .await;
+    app.create_txn(at(50.0, "expense", "2024-02-15T12:00:00Z"))
+        .await;
+
+    let client = &app;
+    let as_of = |account: &str, when: &str| {
+        let uri = format!("/v1/accounts/{account}/balance?as_of={when}");
+        async move { client.get(&uri).await }
+    };
+    let res = as_of(&checking, "2024-01-31").await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+    assert_eq!(res.body["balance"], json!(800.0));
+    // 23:30 on the 31st in UTC-1 is already February 1st in UTC.
+    let res = as_of(&checking, "2024-01-31T23:30:00-01:00").await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+    assert_eq!(res.body["balance"], json!(800.0));
+    let res = as_of(&checking, "2024-02-01T09:00:00Z").await;
+    assert_eq!(res.body["balance"], json!(500.0));
+    let res = as_of(&savings, "2024-02-01T09:00:00Z").await;
+    assert_eq!(res.body["balance"], json!(300.0));
+    let res = as_of(&checking, "2023-12-31").await;
+    assert_eq!(res.body["balance"], json!(0.0));
+    assert_eq!(app.balance(&checking).await, 450.0);
+
+    for bad in ["2024-01-31garbage", "2024-13-01", "yesterday"] {
+        let res = as_of(&checking, bad).await;
+        assert_eq!(res.status, StatusCode::BAD_REQUEST, "{bad}: {}", res.body);
+    }
+    assert_eq!(
+        as_of("nope", "2024-01-31").await.status,
+        StatusCode::NOT_FOUND
+    );
+    let spare = app
+        .create_account(json!({ "name": "Spare", "kind": "checking" }))
+        .await;
+    assert_eq!(
+        app.delete(&format!("/v1/accounts/{spare}")).await.status,
+        StatusCode::NO_CONTENT
+    );
+    assert_eq!(
+        as_of(&spare, "2024-01-31").await.status,
+        StatusCode::NOT_FOUND
+    );
+}
+
+#[test]
+fn windows_style_database_urls_keep_their_drive_letter() {
+    let opts = connect_options("sqlite://d:/finance/data/finance.db").unwrap();
+    assert_eq!(
+        opts.get_filename(),
+        std::path::Path::new("d:/finance/data/finance.db")
+    );
+    let opts = connect_options("sqlite://C:\\Users\\me\\finance.db").unwrap();
+    assert_eq!(
+        opts.get_filename(),
+        std::path::Path::new("C:\\Users\\me\\finance.db")
+    );
+
+    let err = connect_options("sqlite://d:/finance.db?mode=rwc").unwrap_err();
+    assert!(
+        err.to_string().contains("takes no query parameters"),
+        "{err}"
+    );
+    assert!(connect_options("sqlite:finance.db?mode=rwc").is_ok());
+}
+
+#[test]
+fn database_file_parents_are_created_and_bad_paths_explained() {
+    let dir = std::env::temp_dir().join(format!("finance-test-{}", Uuid::new_v4()));
+    let path = dir.join("nested").join("deeper").join("finance.db");
+    prepare_database_file(&path).unwrap();
+    assert!(path.is_file());
+
+    let err = prepare_database_file(&dir.join("nested")).unwrap_err();
+    assert!(err.to_string().contains("it is a directory"), "{err}");
+    let err = prepare_database_file(&path.join("finance.db")).unwrap_err();
+    assert!(err.to_string().contains("is not a directory"), "{err}");
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn suggested_direction_follows_recent_use_then_kind() {
+    use TransactionDirection::{Expense, Income};
+    assert_eq!(suggested_direction("checking", 0, 0), Expense);
+    assert_eq!(suggested_direction("credit", 0, 0), Expense);
+    assert_eq!(suggested_direction("savings", 0, 0), Income);
+    assert_eq!(suggested_direction("investment", 2, 2), Income);
+    assert_eq!(suggested_direction("savings", 1, 3), Expense);
+    assert_eq!(suggested_direction("credit", 4, 1), Income);
+}
+
+#[tokio::test]
+async fn accounts_list_their_suggested_direction() {
+    let app = TestApp::new().await;
+    let checking = app.account_id("Main Checking").await;
+    let savings = app.account_id("Savings").await;
+    let card = app.account_id("Credit Card").await;
+    assert_eq!(app.account(&checking).await["default_direction"], "expense");
+    assert_eq!(app.account(&savings).await["default_direction"], "income");
+    assert_eq!(app.account(&card).await["default_direction"], "expense");
+
+    app.income(&checking, 100.0).await;
+    app.income(&checking, 120.0).await;
+    app.expense(&checking, 10.0).await;
+    assert_eq!(app.account(&checking).await["default_direction"], "income");
+
+    let created = app
+        .post(
+            "/v1/accounts",
+            json!({ "name": "Broker", "kind": "investment" }),
+        )
+        .await;
+    assert_eq!(created.body["default_direction"], "income");
+}
+
+#[tokio::test]
+async fn transaction_lists_above_the_cap_are_flagged_truncated() {
+    let app = TestApp::with(|state| state.max_transaction_list = 3).await;
+    let checking = app.account_id("Main Checking").await;
+    for amount in [10.0, 20.0, 30.0, 40.0, 50.0] {
+        app.income(&checking, amount).await;
+    }
+    let list = |uri: &'static str| async {
+        let res = app.get(uri).await;
+        assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+        let truncated = res.headers[TRUNCATED_HEADER].to_str().unwrap().to_string();
+        assert_eq!(res.headers[TOTAL_COUNT_HEADER], "5");
+        (res.body.as_array().unwrap().len(), truncated)
+    };
+
+    assert_eq!(list("/v1/transactions").await, (3, "true".into()));
+    assert_eq!(
+        list("/v1/transactions?limit=10000").await,
+        (3, "true".into())
+    );
+    // Paging below the cap is not truncation, nor is a last page the cap didn't shorten.
+    assert_eq!(list("/v1/transactions?limit=2").await, (2, "false".into()));
+    assert_eq!(
+        list("/v1/transactions?limit=10&offset=3").await,
+        (2, "false".into())
+    );
+}
+
+// Event kinds broadcast since the last call, in order.
+fn drain_events(events: &mut broadcast::Receiver<ServerEvent>) -> Vec<String> {
+    std::iter::from_fn(|| events.try_recv().ok())
+        .map(|event| format!("{event:?}"))
+        .collect()
+}
+
+#[tokio::test]
+async fn account_and_transaction_changes_broadcast_their_entity() {
+    let app = TestApp::new().await;
+    let checking = app.account_id("Main Checking").await;
+    let groceries = app.category_id("Groceries").await;
+    let mut events = app.state.notifier.subscribe();
+
+    let spare = app
+        .create_account(json!({ "name": "Spare", "kind": "checking" }))
+        .await;
+    assert_eq!(drain_events(&mut events), ["AccountsChanged"]);
+    let res = app
+        .put(
+            &format!("/v1/accounts/{spare}"),
+            json!({ "name": "Spare cash" }),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+    assert_eq!(drain_events(&mut events), ["AccountsChanged"]);
+    // Deleting an account also hides its transactions from the lists.
+    let res = app.delete(&format!("/v1/accounts/{spare}")).await;
+    assert_eq!(res.status, StatusCode::NO_CONTENT, "{}", res.body);
+    assert_eq!(
+        drain_events(&mut events),
+        ["TransactionsChanged", "AccountsChanged"]
+    );
+
+    // Anything that moves money changes both the list and the balances.
+    let txn = app.income(&checking, 100.0).await;
+    let uri = format!("/v1/transactions/{}", txn["id"].as_str().unwrap());
+    assert_eq!(
+        drain_events(&mut events),
+        ["TransactionsChanged", "AccountsChanged"]
+    );
+    let res = app.patch(&uri, json!({ "amount": 90.0 })).await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+    assert_eq!(
+        drain_events(&mut events),
+        ["TransactionsChanged", "AccountsChanged"]
+    );
+
+    // Recategorizing leaves every balance as it was.
+    let res = app
+        .put(
+            &format!("{uri}/splits"),
+            json!({ "splits": [{ "category_id": groceries, "amount": 90.0 }] }),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+    assert_eq!(drain_events(&mut events), ["TransactionsChanged"]);
+
+    assert_eq!(app.delete(&uri).await.status, StatusCode::NO_CONTENT);
+    assert_eq!(
+        drain_events(&mut events),
+        ["TransactionsChanged", "AccountsChanged"]
+    );
+}
+
+#[tokio::test]
+async fn total_amount_header_sums_every_filtered_row_not_just_the_page() {
+    let app = TestApp::new().await;
+    let checking = app.account_id("Main Checking").await;
+    let savings = app.account_id("Savings").await;
+    app.income(&checking, 1000.0).await;
+    app.expense(&checking, 120.25).await;
+    app.expense(&checking, 30.5).await;
+    app.income(&savings, 75.0).await;
+    app.create_txn(
+        json!({ "account_id": checking, "to_account_id": savings, "amount": 200.0,
+                           "fee": 2.0, "direction": "transfer" }),
+    )
+    .await;
+
+    // Every row of the filter, fetched one small page at a time and summed by hand.
+    let manual_sum = |account: &str| {
+        let account = account.to_string();
+        let client = &app;
+        async move {
+            let mut sum = 0.0;
+            let mut offset = 0;
+            loop {
+                let res = client
+                    .get(&format!(
+                        "/v1/transactions?account_id={account}&limit=2&offset={offset}"
+                    ))
+                    .await;
+                let page = res.body.as_array().unwrap().clone();
+                if page.is_empty() {
+                    break;
+                }
+                for txn in &page {
+                    let amount = txn["amount"].as_f64().unwrap();
+                    let fee = txn["fee"].as_f64().unwrap_or(0.0);
+                    sum += match txn["direction"].as_str().unwrap() {
+                        "income" | "refund" => amount,
+                        "expense" => -amount,
+                        _ if txn["account_id"] == account.as_str() => -(amount + fee),
+                        _ => amount,
+                    };
+                }
+                offset += page.len();
+            }
+            sum
+        }
+    };
+    let header = |account: &str| {
+        let uri = format!("/v1/transactions?account_id={account}&limit=2");
+        let client = &app;
+        async move {
+            let res = client.get(&uri).await;
+            assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+            res.headers[TOTAL_AMOUNT_HEADER]
+                .to_str()
+                .unwrap()
+                .parse::<f64>()
+                .unwrap()
+        }
+    };
+
+    assert_eq!(header(&checking).await, 647.25);
+    assert_eq!(header(&checking).await, manual_sum(&checking).await);
+    assert_eq!(header(&savings).await, 275.0);
+    assert_eq!(header(&savings).await, manual_sum(&savings).await);
+    // Unfiltered, a transfer moves money between the user's own accounts: only its fee leaves.
+    let res = app.get("/v1/transactions?limit=1").await;
+    assert_eq!(res.headers[TOTAL_AMOUNT_HEADER], "922.25");
+}
+
+#[tokio::test]
+async fn invalid_forms_report_every_bad_field_at_once() {
+    let app = TestApp::new().await;
+    let checking = app.account_id("Main Checking").await;
+
+    let res = app
+        .post(
+            "/v1/accounts",
+            json!({ "name": " ", "kind": "checking", "currency": "dollars", "color": "teal" }),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::UNPROCESSABLE_ENTITY, "{}", res.body);
+    let errors = res.body["errors"].as_object().unwrap();
+    assert_eq!(errors.len(), 3, "{}", res.body);
+    assert_eq!(errors["name"], "name must not be empty");
+    assert_eq!(
+        errors["currency"],
+        "currency must be a 3-letter ISO 4217 code"
+    );
+    assert!(errors.contains_key("color"), "{}", res.body);
+
+    let res = app
+        .post(
+            "/v1/transactions",
+            json!({ "account_id": checking, "amount": -5.0, "direction": "transfer",
+                    "description": "x".repeat(MAX_DESCRIPTION_LEN + 1) }),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::UNPROCESSABLE_ENTITY, "{}", res.body);
+    let errors = res.body["errors"].as_object().unwrap();
+    assert_eq!(errors["amount"], "amount must be non-negative");
+    assert_eq!(
+        errors["to_account_id"],
+        "transfer requires destination account"
+    );
+    assert_too_long(&res, "description", MAX_DESCRIPTION_LEN);
+    assert_eq!(app.count("/v1/transactions").await, 0);
+
+    let res = app.post("/v1/categories", json!({ "name": "" })).await;
+    assert_eq!(res.status, StatusCode::UNPROCESSABLE_ENTITY, "{}", res.body);
+    assert_eq!(res.body["errors"]["name"], "name must not be empty");
+}
+
+#[tokio::test]
+async fn text_amounts_use_each_accounts_currency_precision() {
+    let app = TestApp::new().await;
+    let checking = app.account_id("Main Checking").await;
+    let yen = app
+        .create_account(json!({ "name": "Yen", "kind": "checking", "currency": "JPY" }))
+        .await;
+    let dinar = app
+        .create_account(json!({ "name": "Dinar", "kind": "checking", "currency": "BHD" }))
+        .await;
+    app.income(&checking, 100.0).await;
+    app.income(&yen, 1500.0).await;
+    app.income(&dinar, 12.345).await;
+    app.expense(&dinar, 0.5).await;
+    app.create_txn(
+        json!({ "account_id": checking, "to_account_id": yen, "amount": 10.0,
+                           "to_amount": 1480.0, "direction": "transfer" }),
+    )
+    .await;
+
+    let accounts = app.get("/v1/accounts?amount_format=string").await.body;
+    let balance = |id: &str| {
+        accounts
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|a| a["id"] == id)
+            .unwrap()["balance"]
+            .clone()
+    };
+    assert_eq!(balance(&checking), "90.00");
+    assert_eq!(balance(&yen), "2980");
+    assert_eq!(balance(&dinar), "11.845");
+    let accounts = app.get("/v1/accounts?amount_format=display").await.body;
+    let shown: Vec<&Value> = accounts
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|a| a["id"] == yen || a["id"] == dinar)
+        .map(|a| &a["balance"])
+        .collect();
+    assert_eq!(shown, [&json!("¥2,980"), &json!("BHD 11.845")]);
+
+    // A transfer's legs each follow their own account.
+    let res = app
+        .get(&format!(
+            "/v1/transactions?account_id={yen}&amount_format=string"
+        ))
+        .await;
+    let transfer = res
+        .body
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|t| t["direction"] == "transfer")
+        .unwrap()
+        .clone();
+    assert_eq!(transfer["amount"], "10.00");
+    assert_eq!(transfer["to_amount"], "1480");
+    assert_eq!(transfer["fee"], "0.00");
+    assert_eq!(res.headers[TOTAL_AMOUNT_HEADER], "2980");
+    let res = app
+        .get(&format!(
+            "/v1/transactions?account_id={yen}&amount_format=string&amount_sign=signed"
+        ))
+        .await;
+    assert!(
+        res.body
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|t| t["amount"] == "1480"),
+        "{}",
+        res.body
+    );
+
+    let res = app
+        .get(&format!(
+            "/v1/transactions?account_id={dinar}&amount_format=string"
+        ))
+        .await;
+    let amounts: Vec<&Value> = res
+        .body
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|t| &t["amount"])
+        .collect();
+    assert_eq!(amounts.len(), 2);
+    assert!(amounts.contains(&&json!("12.345")), "{amounts:?}");
+    assert!(amounts.contains(&&json!("0.500")), "{amounts:?}");
+    // The header is text in every format.
+    let res = app
+        .get(&format!("/v1/transactions?account_id={dinar}"))
+        .await;
+    assert_eq!(res.headers[TOTAL_AMOUNT_HEADER], "11.845");
+
+    let res = app
+        .get(&format!(
+            "/v1/accounts/{yen}/balance?as_of=2999-12-31&amount_format=string"
+        ))
+        .await;
+    assert_eq!(res.body["balance"], "2980");
+}
+
+#[tokio::test]
+async fn normalizing_descriptions_spares_reconciled_rows_and_records_history() {
+    let app = TestApp::new().await;
+    let checking = app.account_id("Main Checking").await;
+    app.income(&checking, 500.0).await;
+    let txn = |amount: f64, description: &str| {
+        json!({ "account_id": checking, "amount": amount, "direction": "expense",
+                "description": description })
+    };
+    let coffee = app.create_txn(txn(4.5, "SQ *COFFEE 123")).await;
+    let settled = app.create_txn(txn(3.75, "SQ *COFFEE 456")).await;
+    let books = app.create_txn(txn(20.0, "AMZN Mktp books")).await;
+    let settled_id = settled["id"].as_str().unwrap();
+    let res = app
+        .post(
+            &format!("/v1/transactions/{settled_id}/reconcile"),
+            json!({}),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+
+    let rules = json!({ "rules": [
+        { "find": "AMZN Mktp", "replace": "Amazon" },
+        { "find": "^SQ \\*COFFEE \\d+$", "replace": "Coffee", "regex": true },
+    ] });
+    let client = &app;
+    let description = |id: Value| async move {
+        let res = client
+            .get(&format!("/v1/transactions/{}", id.as_str().unwrap()))
+            .await;
+        res.body["description"].clone()
+    };
+
+    let res = app
+        .post(
+            "/v1/transactions/normalize-descriptions?dry_run=true",
+            rules.clone(),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+    assert_eq!(res.body["changed"], 0);
+    assert_eq!(res.body["changes"].as_array().unwrap().len(), 2);
+    assert_eq!(res.body["skipped_reconciled"], 1);
+    assert_eq!(description(coffee["id"].clone()).await, "SQ *COFFEE 123");
+
+    let res = app
+        .post("/v1/transactions/normalize-descriptions", rules.clone())
+        .await;
+    assert_eq!(res.body["changed"], 2, "{}", res.body);
+    assert_eq!(description(coffee["id"].clone()).await, "Coffee");
+    assert_eq!(description(books["id"].clone()).await, "Amazon books");
+    assert_eq!(description(settled["id"].clone()).await, "SQ *COFFEE 456");
+
+    let history = app
+        .get(&format!(
+            "/v1/transactions/{}/history",
+            coffee["id"].as_str().unwrap()
+        ))
+        .await
+        .body;
+    let last = history.as_array().unwrap().last().unwrap();
+    assert_eq!(last["action"], "updated");
+    assert_eq!(
+        last["changes"]["description"],
+        json!({ "before": "SQ *COFFEE 123", "after": "Coffee" })
+    );
+
+    let res = app
+        .post("/v1/transactions/normalize-descriptions?force=true", rules)
+        .await;
+    assert_eq!(res.body["changed"], 1, "{}", res.body);
+    assert_eq!(res.body["skipped_reconciled"], 0);
+    assert_eq!(description(settled["id"].clone()).await, "Coffee");
+}
+
+#[tokio::test]
+async fn deleting_an_account_hides_it_until_restored() {
+    let app = TestApp::new().await;
+    let checking = app.account_id("Main Checking").await;
+    let spare = app
+        .create_account(json!({ "name": "Spare", "kind": "checking" }))
+        .await;
+    app.income(&checking, 200.0).await;
+    app.income(&spare, 100.0).await;
+    app.expense(&spare, 30.0).await;
+    app.create_txn(
+        json!({ "account_id": checking, "to_account_id": spare, "amount": 50.0,
+                           "direction": "transfer" }),
+    )
+    .await;
+    assert_eq!(app.count("/v1/transactions").await, 4);
+
+    let res = app.delete(&format!("/v1/accounts/{spare}")).await;
+    assert_eq!(res.status, StatusCode::NO_CONTENT, "{}", res.body);
+    let accounts = app.get("/v1/accounts").await.body;
+    assert!(
+        accounts
+            .as_array()
+            .unwrap()
+            .iter()
+            .all(|a| a["id"] != spare),
+        "{accounts}"
+    );
+    // Everything touching the account is hidden, the transfer included, but kept.
+    assert_eq!(app.count("/v1/transactions").await, 1);
+    let kept: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM transactions")
+        .fetch_one(&app.state.pool)
+        .await
+        .unwrap();
+    assert_eq!(kept, 4);
+
+    let res = app
+        .post(&format!("/v1/accounts/{spare}/restore"), json!({}))
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+    assert_eq!(res.body["balance"], json!(120.0));
+    assert_eq!(app.balance(&spare).await, 120.0);
+    assert_eq!(app.balance(&checking).await, 150.0);
+    assert_eq!(app.count("/v1/transactions").await, 4);
+
+    let res = app
+        .post(&format!("/v1/accounts/{spare}/restore"), json!({}))
+        .await;
+    assert_eq!(res.status, StatusCode::CONFLICT);
+    let res = app.post("/v1/accounts/nope/restore", json!({})).await;
+    assert_eq!(res.status, StatusCode::NOT_FOUND);
+    let res = app.delete(&format!("/v1/accounts/{checking}")).await;
+    assert_eq!(res.status, StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn kind_report_totals_live_accounts_by_kind() {
+    let app = TestApp::new().await;
+    let checking = app.account_id("Main Checking").await;
+    let savings = app.account_id("Savings").await;
+    let card = app.account_id("Credit Card").await;
+    let second = app
+        .create_account(json!({ "name": "Joint", "kind": "checking" }))
+        .await;
+    let closed = app
+        .create_account(json!({ "name": "Old savings", "kind": "savings" }))
+        .await;
+    app.income(&checking, 1200.0).await;
+    app.income(&second, 300.5).await;
+    app.income(&savings, 2500.0).await;
+    app.income(&closed, 999.0).await;
+    app.expense(&card, 85.25).await;
+    assert_eq!(
+        app.delete(&format!("/v1/accounts/{closed}")).await.status,
+        StatusCode::NO_CONTENT
+    );
+
+    let res = app.get("/v1/reports/by-kind").await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+    assert_eq!(
+        res.body,
+        json!([
+            { "kind": "checking", "account_count": 2, "balance": 1500.5 },
+            { "kind": "savings", "account_count": 1, "balance": 2500.0 },
+            // Credit keeps its negative sign while money is owed.
+            { "kind": "credit", "account_count": 1, "balance": -85.25 },
+            { "kind": "investment", "account_count": 0, "balance": 0.0 },
+        ])
+    );
+}
+
+#[tokio::test]
+async fn read_only_mode_refuses_writes_and_activates_nothing() {
+    let app = TestApp::new().await;
+    let checking = app.account_id("Main Checking").await;
+    app.income(&checking, 100.0).await;
+    let later = app
+        .create_txn(
+            json!({ "account_id": checking, "amount": 40.0, "direction": "expense",
+                            "occurred_at": "2999-01-01T00:00:00Z" }),
+        )
+        .await;
+    // Bring the scheduled entry due without activating it.
+    sqlx::query("UPDATE transactions SET occurred_at = '2020-01-01T00:00:00Z' WHERE id = ?1")
+        .bind(later["id"].as_str().unwrap())
+        .execute(&app.state.pool)
+        .await
+        .unwrap();
+
+    let read_only = TestApp {
+        router: build_router(
+            AppState {
+                read_only: true,
+                ..app.state.clone()
+            },
+            DEFAULT_MAX_BODY_BYTES,
+            DEFAULT_REQUEST_TIMEOUT,
+        ),
+        state: app.state.clone(),
+        dir: app.dir.join("read-only"),
+    };
+    let res = read_only.get("/v1/accounts").await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+    assert_eq!(read_only.balance(&checking).await, 100.0);
+    let res = read_only.get("/v1/transactions").await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+    let scheduled: bool = sqlx::query_scalar("SELECT scheduled FROM transactions WHERE id = ?1")
+        .bind(later["id"].as_str().unwrap())
+        .fetch_one(&app.state.pool)
+        .await
+        .unwrap();
+    assert!(scheduled);
+
+    let res = read_only
+        .post(
+            "/v1/transactions",
+            json!({ "account_id": checking, "amount": 5.0, "direction": "expense" }),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::FORBIDDEN, "{}", res.body);
+    let res = read_only
+        .delete(&format!(
+            "/v1/transactions/{}",
+            later["id"].as_str().unwrap()
+        ))
+        .await;
+    assert_eq!(res.status, StatusCode::FORBIDDEN, "{}", res.body);
+    assert_eq!(app.count("/v1/transactions").await, 2);
+
+    // The writable server posts it on its next read.
+    assert_eq!(app.balance(&checking).await, 60.0);
+}
+
+#[tokio::test]
+async fn api_key_is_required_everywhere_but_health() {
+    let app = TestApp::with(|state| state.api_key = Some("s3cret-key".into())).await;
+    let with_key = |uri: &str, key: &str| {
+        Request::get(uri)
+            .header(API_KEY_HEADER, key)
+            .body(Body::empty())
+            .unwrap()
+    };
+
+    assert_eq!(
+        app.get("/v1/accounts").await.status,
+        StatusCode::UNAUTHORIZED
+    );
+    let res = app.send(with_key("/v1/accounts", "s3cret-kez")).await;
+    assert_eq!(res.status, StatusCode::UNAUTHORIZED);
+    let res = app.send(with_key("/v1/accounts", "s3cret")).await;
+    assert_eq!(res.status, StatusCode::UNAUTHORIZED);
+    let res = app.send(with_key("/v1/accounts", "s3cret-key")).await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+    assert_eq!(app.get("/v1/health").await.status, StatusCode::OK);
+
+    // Only the websocket takes the key from the query string.
+    let res = app.get("/v1/accounts?api_key=s3cret-key").await;
+    assert_eq!(res.status, StatusCode::UNAUTHORIZED);
+    let res = app.get("/v1/events?api_key=wrong").await;
+    assert_eq!(res.status, StatusCode::UNAUTHORIZED);
+    let res = app.get("/v1/events?api_key=s3cret-key").await;
+    assert_ne!(res.status, StatusCode::UNAUTHORIZED, "{}", res.body);
+}
+
+#[tokio::test]
+async fn transactions_filter_to_several_accounts() {
+    let app = TestApp::new().await;
+    let checking = app.account_id("Main Checking").await;
+    let savings = app.account_id("Savings").await;
+    let card = app.account_id("Credit Card").await;
+    app.income(&checking, 300.0).await;
+    app.income(&savings, 200.0).await;
+    app.expense(&card, 45.0).await;
+    app.expense(&card, 12.0).await;
+    app.create_txn(
+        json!({ "account_id": card, "to_account_id": savings, "amount": 20.0,
+                           "direction": "transfer" }),
+    )
+    .await;
+
+    let client = &app;
+    let listed = |uri: String| async move {
+        let res = client.get(&uri).await;
+        assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+        let mut amounts: Vec<f64> = res
+            .body
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|t| t["amount"].as_f64().unwrap())
+            .collect();
+        amounts.sort_by(f64::total_cmp);
+        amounts
+    };
+    // The transfer touches savings, so it is in; the card's own expenses are not.
+    assert_eq!(
+        listed(format!("/v1/transactions?account_id={checking},{savings}")).await,
+        [20.0, 200.0, 300.0]
+    );
+    // Spaces and repeats are tolerated.
+    assert_eq!(
+        listed(format!(
+            "/v1/transactions?account_id={checking},%20{checking}"
+        ))
+        .await,
+        [300.0]
+    );
+
+    let res = app
+        .get(&format!("/v1/transactions?account_id={checking},nope"))
+        .await;
+    assert_eq!(res.status, StatusCode::BAD_REQUEST);
+    assert!(res.body.as_str().unwrap().contains("nope"), "{}", res.body);
+}
+
+#[tokio::test]
+async fn transactions_record_how_they_were_created() {
+    let app = TestApp::new().await;
+    let (template, _, _) = groceries_template(&app).await;
+    let res = app
+        .post(
+            "/v1/recurring",
+            json!({ "template_id": template, "cadence": "weekly" }),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+    run_due_recurring(&app.state).await.unwrap();
+    let csv = "occurred_at,account,direction,amount,description\n\
+               2024-03-01T00:00:00Z,Savings,income,42.00,imported\n";
+    let res = app
+        .send(
+            Request::post("/v1/import/transactions.csv")
+                .body(Body::from(csv))
+                .unwrap(),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+
+    let recurring = app.get("/v1/transactions?source=recurring").await.body;
+    let recurring = recurring.as_array().unwrap();
+    assert_eq!(recurring.len(), 1);
+    assert_eq!(recurring[0]["source"], "recurring");
+    assert_eq!(recurring[0]["description"], "groceries");
+    let imported = app.get("/v1/transactions?source=import").await.body;
+    let imported = imported.as_array().unwrap();
+    assert_eq!(imported.len(), 1);
+    assert_eq!(imported[0]["description"], "imported");
+    // The seed income from the template helper went through the API by hand.
+    assert_eq!(app.count("/v1/transactions?source=manual").await, 1);
+    assert_eq!(app.count("/v1/transactions").await, 3);
+
+    let res = app.get("/v1/transactions?source=bank").await;
+    assert_eq!(res.status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn odd_cent_balances_come_out_exactly_rounded() {
+    let amounts = [0.1, 0.2, 10.07, 0.33];
+    // Adding these as floats drifts off the cent.
+    assert_ne!(amounts.iter().sum::<f64>(), 10.7);
+    for mode in [BalanceMode::Cached, BalanceMode::Computed] {
+        let app = TestApp::with(|state| state.balance_mode = mode).await;
+        let checking = app.account_id("Main Checking").await;
+        for amount in amounts {
+            app.income(&checking, amount).await;
+        }
+        assert_eq!(app.balance(&checking).await, 10.7, "{mode:?}");
+        let summary = app.get("/v1/summary").await.body;
+        assert_eq!(summary["net_worth"], json!(10.7), "{mode:?}");
+    }
+}
+
+#[tokio::test]
+async fn average_spend_counts_what_the_summary_counts() {
+    let app = TestApp::new().await;
+    let checking = app.account_id("Main Checking").await;
+    let savings = app.account_id("Savings").await;
+    let days_ago =
+        |days: u64| format_rfc3339(OffsetDateTime::now_utc() - Duration::from_secs(days * 86_400));
+    app.income(&checking, 1000.0).await;
+    app.expense(&checking, 90.0).await;
+    app.expense(&checking, 60.0).await;
+    app.create_txn(json!({ "account_id": checking, "amount": 15.0, "direction": "refund" }))
+        .await;
+    // Only the fee of a transfer is spent.
+    app.create_txn(
+        json!({ "account_id": checking, "to_account_id": savings, "amount": 200.0,
+                           "fee": 3.0, "direction": "transfer" }),
+    )
+    .await;
+    // Outside the window.
+    app.create_txn(
+        json!({ "account_id": checking, "amount": 40.0, "direction": "expense",
+                           "occurred_at": days_ago(40) }),
+    )
+    .await;
+    // Still scheduled, then one that falls due before the report runs.
+    let ahead = format_rfc3339(OffsetDateTime::now_utc() + Duration::from_secs(3600));
+    app.create_txn(
+        json!({ "account_id": checking, "amount": 70.0, "direction": "expense",
+                           "occurred_at": ahead }),
+    )
+    .await;
+    let due = app
+        .create_txn(
+            json!({ "account_id": checking, "amount": 12.0, "direction": "expense",
+                           "occurred_at": ahead }),
+        )
+        .await;
+    sqlx::query("UPDATE transactions SET occurred_at = ?1 WHERE id = ?2")
+        .bind(days_ago(1))
+        .bind(due["id"].as_str().unwrap())
+        .execute(&app.state.pool)
+        .await
+        .unwrap();
+    // Spending on a deleted account drops out with it.
+    let spare = app
+        .create_account(json!({ "name": "Spare", "kind": "checking" }))
+        .await;
+    app.income(&spare, 50.0).await;
+    app.expense(&spare, 33.0).await;
+    let res = app.delete(&format!("/v1/accounts/{spare}")).await;
+    assert_eq!(res.status, StatusCode::NO_CONTENT, "{}", res.body);
+
+    let res = app.get("/v1/reports/average-spend?window=30d").await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+    assert_eq!(res.body["days"], json!(30));
+    assert_eq!(res.body["total"], json!(150.0));
+    assert_eq!(res.body["daily"], json!(5.0));
+    assert_eq!(res.body["weekly"], json!(35.0));
+    assert_eq!(res.body["projected_monthly"], json!(152.19));
+}
+
+#[tokio::test]
+async fn replacing_tags_records_the_old_and_new_sets() {
+    let app = TestApp::new().await;
+    let checking = app.account_id("Main Checking").await;
+    let txn = app.income(&checking, 75.0).await;
+    let uri = format!("/v1/transactions/{}/tags", txn["id"].as_str().unwrap());
+
+    let res = app.put(&uri, json!({ "tags": ["Work", "trip"] })).await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+    let res = app
+        .put(&uri, json!({ "tags": ["trip", "Food", "food"] }))
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+    assert_eq!(res.body["tags"], json!(["Food", "trip"]));
+    // The same set again changes nothing worth recording.
+    app.put(&uri, json!({ "tags": ["Food", "trip"] })).await;
+
+    let history = app
+        .get(&format!(
+            "/v1/transactions/{}/history",
+            txn["id"].as_str().unwrap()
+        ))
+        .await
+        .body;
+    let entries = history.as_array().unwrap();
+    assert_eq!(entries.len(), 3, "{history}");
+    assert_eq!(entries[1]["action"], "updated");
+    assert_eq!(
+        entries[1]["changes"],
+        json!({ "tags": { "before": [], "after": ["trip", "Work"] } })
+    );
+    assert_eq!(
+        entries[2]["changes"],
+        json!({ "tags": { "before": ["trip", "Work"], "after": ["Food", "trip"] } })
+    );
+}
+
+#[tokio::test]
+async fn request_ids_are_echoed_and_included_in_error_bodies() {
+    let app = TestApp::new().await;
+    let checking = app.account_id("Main Checking").await;
+    let with_id = |uri: &str, body: Value| {
+        Request::post(uri)
+            .header(header::CONTENT_TYPE, "application/json")
+            .header("x-request-id", "trace-42")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    };
+
+    let res = app
+        .send(with_id(
+            "/v1/transactions",
+            json!({ "account_id": checking, "amount": -5.0, "direction": "expense" }),
+        ))
+        .await;
+    assert_eq!(res.status, StatusCode::UNPROCESSABLE_ENTITY, "{}", res.body);
+    assert_eq!(res.headers["x-request-id"], "trace-42");
+    assert!(res.body["errors"]["amount"].is_string(), "{}", res.body);
+    assert_eq!(res.body["request_id"], "trace-42");
+
+    let res = app
+        .send(
+            Request::get("/v1/transactions?source=bank")
+                .header("x-request-id", "trace-43")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::BAD_REQUEST, "{}", res.body);
+    assert_eq!(res.headers["x-request-id"], "trace-43");
+    assert!(
+        res.body
+            .as_str()
+            .unwrap()
+            .ends_with("(request id: trace-43)"),
+        "{}",
+        res.body
+    );
+
+    // Successful responses carry the header but keep their body as is.
+    let res = app
+        .send(with_id(
+            "/v1/transactions",
+            json!({ "account_id": checking, "amount": 5.0, "direction": "income" }),
+        ))
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+    assert_eq!(res.headers["x-request-id"], "trace-42");
+    assert!(res.body.get("request_id").is_none());
+}
+
+#[tokio::test]
+async fn transfers_list_both_account_names_within_the_range() {
+    let app = TestApp::new().await;
+    let checking = app.account_id("Main Checking").await;
+    let savings = app.account_id("Savings").await;
+    app.income(&checking, 1000.0).await;
+    let transfer = |amount: f64, to: &str, occurred_at: &str| {
+        json!({ "account_id": checking, "to_account_id": to, "amount": amount,
+                "direction": "transfer", "occurred_at": occurred_at })
+    };
+    app.create_txn(transfer(100.0, &savings, "2024-03-01T12:00:00Z"))
+        .await;
+    app.create_txn(transfer(50.0, &savings, "2024-03-10T12:00:00Z"))
+        .await;
+    let spare = app
+        .create_account(json!({ "name": "Spare", "kind": "savings" }))
+        .await;
+    app.create_txn(transfer(25.0, &spare, "2024-03-05T12:00:00Z"))
+        .await;
+
+    let res = app.get("/v1/transfers").await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+    let list = res.body.as_array().unwrap();
+    assert_eq!(list.len(), 3);
+    assert_eq!(list[0]["amount"], json!(50.0));
+    assert_eq!(list[0]["from_account"], "Main Checking");
+    assert_eq!(list[0]["to_account"], "Savings");
+    assert_eq!(list[1]["to_account"], "Spare");
+
+    // Bounds are compared as instants: 15:00+05:00 is 10:00 UTC, before the noon transfer.
+    let res = app
+        .get("/v1/transfers?from=2024-03-05T15:00:00%2B05:00&to=2024-03-09T00:00:00Z")
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+    assert_eq!(res.body.as_array().unwrap().len(), 1);
+    assert_eq!(res.body[0]["amount"], json!(25.0));
+    let res = app.get("/v1/transfers?from=March").await;
+    assert_eq!(res.status, StatusCode::BAD_REQUEST, "{}", res.body);
+
+    // Deleting an account hides the transfers it took part in.
+    let res = app.delete(&format!("/v1/accounts/{spare}")).await;
+    assert_eq!(res.status, StatusCode::NO_CONTENT, "{}", res.body);
+    assert_eq!(app.count("/v1/transfers").await, 2);
+}
+
+#[tokio::test]
+async fn merchant_report_groups_repeated_descriptions() {
+    let app = TestApp::new().await;
+    let checking = app.account_id("Main Checking").await;
+    app.income(&checking, 500.0).await;
+    let expense = |amount: f64, description: Option<&str>| {
+        json!({ "account_id": checking, "amount": amount, "direction": "expense",
+                "description": description, "occurred_at": "2024-05-10T12:00:00Z" })
+    };
+    app.create_txn(expense(4.5, Some("Coffee Shop"))).await;
+    app.create_txn(expense(5.25, Some(" coffee shop "))).await;
+    app.create_txn(expense(3.0, Some("COFFEE SHOP"))).await;
+    app.create_txn(expense(20.0, Some(""))).await;
+    app.create_txn(expense(7.0, None)).await;
+    let spare = app
+        .create_account(json!({ "name": "Spare", "kind": "checking" }))
+        .await;
+    app.income(&spare, 100.0).await;
+    app.expense(&spare, 60.0).await;
+    let res = app.delete(&format!("/v1/accounts/{spare}")).await;
+    assert_eq!(res.status, StatusCode::NO_CONTENT, "{}", res.body);
+
+    let report = app.get("/v1/reports/merchants").await.body;
+    assert_eq!(
+        report,
+        json!([
+            { "payee_id": null, "merchant": "Unlabeled", "total": 27.0, "count": 2 },
+            { "payee_id": null, "merchant": "coffee shop", "total": 12.75, "count": 3 },
+        ])
+    );
+
+    let res = app
+        .get("/v1/reports/merchants?from=2024-05-10T15:00:00%2B05:00")
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+    assert_eq!(res.body.as_array().unwrap().len(), 2);
+    let res = app
+        .get("/v1/reports/merchants?to=2024-05-10T10:00:00Z")
+        .await;
+    assert_eq!(res.body, json!([]));
+    let res = app.get("/v1/reports/merchants?from=yesterday").await;
+    assert_eq!(res.status, StatusCode::BAD_REQUEST, "{}", res.body);
+}
+
+#[tokio::test]
+async fn rules_spare_reconciled_transactions_and_record_history() {
+    let app = TestApp::new().await;
+    let checking = app.account_id("Main Checking").await;
+    let groceries = app.category_id("Groceries").await;
+    app.income(&checking, 100.0).await;
+    app.post(
+        "/v1/categorization-rules",
+        json!({ "pattern": "market", "category_id": groceries }),
+    )
+    .await;
+    let expense = |amount: f64| {
+        json!({ "account_id": checking, "amount": amount, "direction": "expense",
+                "description": "Corner Market" })
+    };
+    let open = app.create_txn(expense(12.0)).await;
+    let settled = app.create_txn(expense(8.0)).await;
+    let settled_id = settled["id"].as_str().unwrap();
+    let res = app
+        .post(
+            &format!("/v1/transactions/{settled_id}/reconcile"),
+            json!({}),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+
+    let res = app.post("/v1/transactions/apply-rules", json!({})).await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+    assert_eq!(res.body["categorized"], json!(1));
+    assert_eq!(res.body["skipped_reconciled"], json!(1));
+    assert_eq!(res.body["assignments"][0]["transaction_id"], open["id"]);
+    let res = app.get(&format!("/v1/transactions/{settled_id}")).await;
+    assert_eq!(res.body["splits"], json!([]));
+
+    let history = app
+        .get(&format!(
+            "/v1/transactions/{}/history",
+            open["id"].as_str().unwrap()
+        ))
+        .await
+        .body;
+    assert_eq!(history.as_array().unwrap().len(), 2, "{history}");
+    assert_eq!(
+        history[1]["changes"]["splits"],
+        json!({ "before": [], "after": [{ "category_id": groceries, "amount": 12.0 }] })
+    );
+
+    let res = app
+        .post("/v1/transactions/apply-rules?force=true", json!({}))
+        .await;
+    assert_eq!(res.body["categorized"], json!(1));
+    assert_eq!(res.body["skipped_reconciled"], json!(0));
+    let res = app.get(&format!("/v1/transactions/{settled_id}")).await;
+    assert_eq!(res.body["splits"][0]["category_id"], json!(groceries));
+}
+
+#[tokio::test]
+async fn dry_run_rules_report_the_plan_without_writing() {
+    let app = TestApp::new().await;
+    let checking = app.account_id("Main Checking").await;
+    let groceries = app.category_id("Groceries").await;
+    app.income(&checking, 100.0).await;
+    app.post(
+        "/v1/categorization-rules",
+        json!({ "pattern": "market", "category_id": groceries }),
+    )
+    .await;
+    let txn = app
+        .create_txn(
+            json!({ "account_id": checking, "amount": 12.0, "direction": "expense",
+                    "description": "Corner Market" }),
+        )
+        .await;
+    let uri = format!("/v1/transactions/{}", txn["id"].as_str().unwrap());
+
+    let res = app
+        .post("/v1/transactions/apply-rules?dry_run=true", json!({}))
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+    assert_eq!(res.body["dry_run"], json!(true));
+    assert_eq!(res.body["categorized"], json!(0));
+    assert_eq!(
+        res.body["assignments"],
+        json!([{ "transaction_id": txn["id"], "description": "Corner Market",
+                 "category_id": groceries }])
+    );
+    let res = app.get(&uri).await;
+    assert_eq!(res.body["splits"], json!([]));
+    assert_eq!(res.body["updated_at"], txn["updated_at"]);
+    assert_eq!(app.count(&format!("{uri}/history")).await, 1);
+}
+
+#[tokio::test]
+async fn reports_and_exports_leave_out_deleted_accounts() {
+    let app = TestApp::new().await;
+    let checking = app.account_id("Main Checking").await;
+    let groceries = app.category_id("Groceries").await;
+    let spare = app
+        .create_account(json!({ "name": "Spare", "kind": "checking" }))
+        .await;
+    for (account, income, expense) in [(&checking, 500.0, 30.0), (&spare, 100.0, 40.0)] {
+        app.create_txn(
+            json!({ "account_id": account, "amount": income, "direction": "income",
+                               "occurred_at": "2024-05-01T12:00:00Z" }),
+        )
+        .await;
+        app.create_txn(
+            json!({ "account_id": account, "amount": expense, "direction": "expense",
+                               "occurred_at": "2024-05-02T12:00:00Z",
+                               "splits": [{ "category_id": groceries, "amount": expense }] }),
+        )
+        .await;
+    }
+    let res = app.delete(&format!("/v1/accounts/{spare}")).await;
+    assert_eq!(res.status, StatusCode::NO_CONTENT, "{}", res.body);
+
+    let trend = app.get("/v1/reports/trend?bucket=fiscal_year").await.body;
+    assert_eq!(trend[0]["income"], json!(500.0), "{trend}");
+    assert_eq!(trend[0]["expense"], json!(30.0), "{trend}");
+    let monthly = app.get("/v1/reports/monthly?fiscal_year=2024").await.body;
+    let may = monthly
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|bucket| bucket["period"] == "2024-05")
+        .unwrap();
+    assert_eq!(may["expense"], json!(30.0), "{monthly}");
+    let res = app
+        .get(&format!("/v1/categories/{groceries}/accounts"))
+        .await;
+    assert_eq!(res.body.as_array().unwrap().len(), 1, "{}", res.body);
+    assert_eq!(res.body[0]["name"], "Main Checking");
+    let csv = app.get("/v1/export/transactions.csv").await.body;
+    let csv = csv.as_str().unwrap();
+    assert_eq!(csv.lines().count(), 3, "{csv}");
+    assert!(!csv.contains("Spare"), "{csv}");
+}
+
+#[tokio::test]
+async fn reports_round_to_the_currency_minor_unit() {
+    let app = TestApp::with(|state| state.money.currency = "BHD".into()).await;
+    let checking = app.account_id("Main Checking").await;
+    let groceries = app.category_id("Groceries").await;
+    app.income(&checking, 10.004).await;
+    app.create_txn(
+        json!({ "account_id": checking, "amount": 1.125, "direction": "expense",
+                "splits": [{ "category_id": groceries, "amount": 1.125 }] }),
+    )
+    .await;
+
+    assert_eq!(app.balance(&checking).await, 8.879);
+    let summary = app.get("/v1/summary").await.body;
+    assert_eq!(summary["net_worth"], json!(8.879));
+    assert_eq!(summary["month_income"], json!(10.004));
+    assert_eq!(summary["month_expense"], json!(1.125));
+    assert_eq!(summary["top_categories"][0]["total"], json!(1.125));
+    let res = app
+        .get(&format!("/v1/categories/{groceries}/accounts"))
+        .await;
+    assert_eq!(res.body[0]["total"], json!(1.125));
+    let history = app
+        .get(&format!("/v1/accounts/{checking}/balance-history?days=1"))
+        .await;
+    assert_eq!(history.status, StatusCode::OK, "{}", history.body);
+    assert_eq!(history.body["points"][0]["balance"], json!(8.879));
+    let net_worth = app.get("/v1/reports/networth").await.body;
+    assert_eq!(net_worth["net_worth"], json!(8.879));
+    let kinds = app.get("/v1/reports/by-kind").await.body;
+    assert_eq!(kinds[0]["balance"], json!(8.879));
+    let merchants = app.get("/v1/reports/merchants").await.body;
+    assert_eq!(merchants[0]["total"], json!(1.125));
+    let trend = app.get("/v1/reports/trend").await.body;
+    assert_eq!(trend[0]["income"], json!(10.004));
+}
+
+#[tokio::test]
+async fn split_evenly_gives_the_first_category_the_leftover_cent() {
+    let app = TestApp::new().await;
+    let checking = app.account_id("Main Checking").await;
+    app.income(&checking, 100.0).await;
+    let categories = [
+        app.category_id("Groceries").await,
+        app.category_id("Utilities").await,
+        app.category_id("Entertainment").await,
+    ];
+
+    let txn = app
+        .create_txn(
+            json!({ "account_id": checking, "amount": 10.0, "direction": "expense",
+                    "split_evenly": categories }),
+        )
+        .await;
+    let mut splits: Vec<(String, f64)> = txn["splits"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|s| {
+            (
+                s["category_id"].as_str().unwrap().to_string(),
+                s["amount"].as_f64().unwrap(),
+            )
+        })
+        .collect();
+    let mut expected = vec![
+        (categories[0].clone(), 3.34),
+        (categories[1].clone(), 3.33),
+        (categories[2].clone(), 3.33),
+    ];
+    expected.sort_by(|a, b| a.0.cmp(&b.0));
+    splits.sort_by(|a, b| a.0.cmp(&b.0));
+    assert_eq!(splits, expected);
+
+    let res = app
+        .post(
+            "/v1/transactions",
+            json!({ "account_id": checking, "amount": 9.0, "direction": "expense",
+                    "split_evenly": categories,
+                    "splits": [{ "category_id": categories[0], "amount": 9.0 }] }),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::BAD_REQUEST, "{}", res.body);
+    assert!(
+        res.body.as_str().unwrap().contains("not both"),
+        "{}",
+        res.body
+    );
+}
+
+#[tokio::test]
+async fn remainder_split_takes_what_the_others_leave() {
+    let app = TestApp::new().await;
+    let checking = app.account_id("Main Checking").await;
+    app.income(&checking, 100.0).await;
+    let groceries = app.category_id("Groceries").await;
+    let utilities = app.category_id("Utilities").await;
+    let rent = app.category_id("Rent").await;
+    let expense = |amount: f64, splits: Value| {
+        json!({ "account_id": checking, "amount": amount, "direction": "expense",
+                "splits": splits })
+    };
+
+    let txn = app
+        .create_txn(expense(
+            10.0,
+            json!([{ "category_id": groceries, "amount": 3.33 },
+                   { "category_id": utilities, "amount": 2.2 },
+                   { "category_id": rent, "remainder": true }]),
+        ))
+        .await;
+    let rent_split = txn["splits"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|s| s["category_id"] == rent.as_str())
+        .unwrap();
+    assert_eq!(rent_split["amount"], json!(4.47));
+
+    let res = app
+        .post(
+            "/v1/transactions",
+            expense(
+                11.0,
+                json!([{ "category_id": groceries, "remainder": true },
+                       { "category_id": rent, "remainder": true }]),
+            ),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::BAD_REQUEST, "{}", res.body);
+    assert!(
+        res.body.as_str().unwrap().contains("only one split"),
+        "{}",
+        res.body
+    );
+
+    let res = app
+        .post(
+            "/v1/transactions",
+            expense(
+                12.0,
+                json!([{ "category_id": groceries, "amount": 8.0 },
+                       { "category_id": utilities, "amount": 4.01 },
+                       { "category_id": rent, "remainder": true }]),
+            ),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::BAD_REQUEST, "{}", res.body);
+    assert!(
+        res.body
+            .as_str()
+            .unwrap()
+            .contains("more than the transaction amount"),
+        "{}",
+        res.body
+    );
+    assert_eq!(app.balance(&checking).await, 90.0);
+}
+
+#[tokio::test]
+async fn splits_may_not_repeat_a_category() {
+    let app = TestApp::new().await;
+    let checking = app.account_id("Main Checking").await;
+    app.income(&checking, 100.0).await;
+    let groceries = app.category_id("Groceries").await;
+    let twice = json!([{ "category_id": groceries, "amount": 5.0 },
+                       { "category_id": groceries, "amount": 7.0 }]);
+
+    let res = app
+        .post(
+            "/v1/transactions",
+            json!({ "account_id": checking, "amount": 12.0, "direction": "expense",
+                    "splits": twice }),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::UNPROCESSABLE_ENTITY, "{}", res.body);
+    assert!(
+        res.body["errors"]["splits"]
+            .as_str()
+            .unwrap()
+            .contains("more than one split"),
+        "{}",
+        res.body
+    );
+
+    let txn = app.expense(&checking, 12.0).await;
+    let uri = format!("/v1/transactions/{}", txn["id"].as_str().unwrap());
+    let res = app
+        .put(&format!("{uri}/splits"), json!({ "splits": twice }))
+        .await;
+    assert_eq!(res.status, StatusCode::BAD_REQUEST, "{}", res.body);
+    assert_eq!(app.get(&uri).await.body["splits"], json!([]));
+}
+
+#[tokio::test]
+async fn reordering_accounts_changes_the_list_order() {
+    let app = TestApp::new().await;
+    let names = |list: &Value| -> Vec<String> {
+        list.as_array()
+            .unwrap()
+            .iter()
+            .map(|a| a["name"].as_str().unwrap().to_string())
+            .collect()
+    };
+    let checking = app.account_id("Main Checking").await;
+    let savings = app.account_id("Savings").await;
+    let card = app.account_id("Credit Card").await;
+
+    let res = app
+        .put("/v1/accounts/reorder", json!({ "ids": [card, savings] }))
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+    assert_eq!(
+        names(&res.body),
+        ["Credit Card", "Savings", "Main Checking"]
+    );
+    assert_eq!(names(&app.get("/v1/accounts").await.body), names(&res.body));
+
+    let res = app
+        .put("/v1/accounts/reorder", json!({ "ids": [checking] }))
+        .await;
+    assert_eq!(
+        names(&res.body),
+        ["Main Checking", "Credit Card", "Savings"]
+    );
+
+    let res = app
+        .put("/v1/accounts/reorder", json!({ "ids": [savings, savings] }))
+        .await;
+    assert_eq!(res.status, StatusCode::BAD_REQUEST, "{}", res.body);
+    let res = app
+        .put("/v1/accounts/reorder", json!({ "ids": ["nope"] }))
+        .await;
+    assert_eq!(res.status, StatusCode::BAD_REQUEST, "{}", res.body);
+}
+
+#[tokio::test]
+async fn slow_requests_time_out_except_on_untimed_routes() {
+    async fn slow() -> &'static str {
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        "done"
+    }
+    let app = TestApp::new().await;
+    let router = with_request_timeout(
+        Router::new().route("/slow", get(slow)),
+        Router::new().route("/stream", get(slow)),
+        Duration::from_millis(20),
+    )
+    .with_state(app.state.clone());
+    let status = |uri: &'static str| {
+        let router = router.clone();
+        async move {
+            let request = Request::get(uri).body(Body::empty()).unwrap();
+            router.oneshot(request).await.unwrap().status()
+        }
+    };
+
+    assert_eq!(status("/slow").await, StatusCode::REQUEST_TIMEOUT);
+    assert_eq!(status("/stream").await, StatusCode::OK);
+}
+
+// Collects everything a log subscriber writes.
+#[derive(Clone, Default)]
+struct CapturedLogs(Arc<std::sync::Mutex<Vec<u8>>>);
+
+impl std::io::Write for CapturedLogs {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> tracing_subscriber::fmt::MakeWriter<'a> for CapturedLogs {
+    type Writer = CapturedLogs;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        self.clone()
+    }
+}
+
+fn log_line(format: LogFormat) -> String {
+    let logs = CapturedLogs::default();
+    tracing::subscriber::with_default(log_subscriber(format, logs.clone()), || {
+        info!(request_id = "abc", "request finished");
+    });
+    String::from_utf8(logs.0.lock().unwrap().clone()).unwrap()
+}
+
+#[test]
+fn log_format_selects_json_or_pretty_output() {
+    assert_eq!(LogFormat::parse("json"), Some(LogFormat::Json));
+    assert_eq!(LogFormat::parse("pretty"), Some(LogFormat::Pretty));
+    assert_eq!(LogFormat::parse(""), Some(LogFormat::Pretty));
+    assert_eq!(LogFormat::parse("xml"), None);
+
+    let json = log_line(LogFormat::Json);
+    let line: Value = serde_json::from_str(json.trim()).unwrap();
+    assert_eq!(line["level"], "INFO");
+    assert_eq!(line["fields"]["message"], "request finished");
+    assert_eq!(line["fields"]["request_id"], "abc");
+
+    let pretty = log_line(LogFormat::Pretty);
+    assert!(
+        serde_json::from_str::<Value>(pretty.trim()).is_err(),
+        "{pretty}"
+    );
+    assert!(pretty.contains("request finished"), "{pretty}");
+    assert!(pretty.contains("INFO"), "{pretty}");
+}
+
+#[tokio::test]
+async fn uncategorized_filter_lists_only_rows_without_splits() {
+    let app = TestApp::new().await;
+    let checking = app.account_id("Main Checking").await;
+    let savings = app.account_id("Savings").await;
+    let salary = app.category_id("Income").await;
+    app.create_txn(
+        json!({ "account_id": checking, "amount": 300.0, "direction": "income",
+                "splits": [{ "category_id": salary, "amount": 300.0 }] }),
+    )
+    .await;
+    let loose = app.expense(&checking, 20.0).await;
+    app.create_txn(
+        json!({ "account_id": checking, "to_account_id": savings, "amount": 50.0,
+                "direction": "transfer" }),
+    )
+    .await;
+
+    let res = app.get("/v1/transactions?uncategorized=true").await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+    let list = res.body.as_array().unwrap();
+    assert_eq!(list.len(), 1, "{}", res.body);
+    assert_eq!(list[0]["id"], loose["id"]);
+    assert_eq!(app.count("/v1/transactions?uncategorized=false").await, 3);
+}
+
+#[tokio::test]
+async fn category_accounts_total_each_account_separately() {
+    let app = TestApp::new().await;
+    let checking = app.account_id("Main Checking").await;
+    let card = app.account_id("Credit Card").await;
+    let groceries = app.category_id("Groceries").await;
+    let utilities = app.category_id("Utilities").await;
+    app.income(&checking, 500.0).await;
+    let expense = |account: &str, amount: f64, category: &str| {
+        json!({ "account_id": account, "amount": amount, "direction": "expense",
+                "splits": [{ "category_id": category, "amount": amount }] })
+    };
+    app.create_txn(expense(&checking, 40.0, &groceries)).await;
+    app.create_txn(expense(&checking, 25.5, &groceries)).await;
+    app.create_txn(expense(&card, 80.0, &groceries)).await;
+    app.create_txn(expense(&card, 60.0, &utilities)).await;
+
+    let res = app
+        .get(&format!("/v1/categories/{groceries}/accounts"))
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+    assert_eq!(
+        res.body,
+        json!([
+            { "account_id": card, "name": "Credit Card", "kind": "credit",
+              "total": 80.0, "count": 1 },
+            { "account_id": checking, "name": "Main Checking", "kind": "checking",
+              "total": 65.5, "count": 2 },
+        ])
+    );
+    let res = app.get("/v1/categories/nope/accounts").await;
+    assert_eq!(res.status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn category_duplicates_group_case_and_spacing_variants() {
+    let app = TestApp::new().await;
+    let checking = app.account_id("Main Checking").await;
+    app.income(&checking, 100.0).await;
+    for name in ["Dining", "Groceries ", " GROCERIES"] {
+        let res = app.post("/v1/categories", json!({ "name": name })).await;
+        assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+    }
+    let groceries = app.category_id("Groceries").await;
+    app.create_txn(
+        json!({ "account_id": checking, "amount": 9.0, "direction": "expense",
+                "splits": [{ "category_id": groceries, "amount": 9.0 }] }),
+    )
+    .await;
+
+    let res = app.get("/v1/categories/duplicates").await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+    let groups = res.body.as_array().unwrap();
+    assert_eq!(groups.len(), 1, "{}", res.body);
+    assert_eq!(groups[0]["normalized_name"], "groceries");
+    assert_eq!(groups[0]["categories"].as_array().unwrap().len(), 3);
+    // The variant already in use is the one to keep.
+    assert_eq!(groups[0]["suggested_id"], json!(groceries));
+}
+
+#[tokio::test]
+async fn categories_list_in_each_order() {
+    let app = TestApp::new().await;
+    let checking = app.account_id("Main Checking").await;
+    app.income(&checking, 100.0).await;
+    for (name, created_at) in [
+        ("Rent", "2020-01-01T00:00:00Z"),
+        ("Utilities", "2020-02-01T00:00:00Z"),
+        ("Income", "2020-03-01T00:00:00Z"),
+        ("Groceries", "2020-04-01T00:00:00Z"),
+        ("Entertainment", "2020-05-01T00:00:00Z"),
+    ] {
+        sqlx::query("UPDATE categories SET created_at = ?1 WHERE name = ?2")
+            .bind(created_at)
+            .bind(name)
+            .execute(&app.state.pool)
+            .await
+            .unwrap();
+    }
+    for (amount, name) in [(5.0, "Groceries"), (6.0, "Groceries"), (7.0, "Utilities")] {
+        let category = app.category_id(name).await;
+        app.create_txn(
+            json!({ "account_id": checking, "amount": amount, "direction": "expense",
+                    "splits": [{ "category_id": category, "amount": amount }] }),
+        )
+        .await;
+    }
+    let names = |list: Value| -> Vec<String> {
+        list.as_array()
+            .unwrap()
+            .iter()
+            .map(|c| c["name"].as_str().unwrap().to_string())
+            .collect()
+    };
+
+    let alphabetical = ["Entertainment", "Groceries", "Income", "Rent", "Utilities"];
+    assert_eq!(names(app.get("/v1/categories").await.body), alphabetical);
+    assert_eq!(
+        names(app.get("/v1/categories?order=name").await.body),
+        alphabetical
+    );
+    assert_eq!(
+        names(app.get("/v1/categories?order=created").await.body),
+        ["Rent", "Utilities", "Income", "Groceries", "Entertainment"]
+    );
+    assert_eq!(
+        names(app.get("/v1/categories?order=usage").await.body),
+        ["Groceries", "Utilities", "Entertainment", "Income", "Rent"]
+    );
+    let res = app.get("/v1/categories?order=size").await;
+    assert_eq!(res.status, StatusCode::BAD_REQUEST, "{}", res.body);
+}
+
+#[tokio::test]
+async fn accounts_sort_and_filter_by_creation() {
+    let app = TestApp::new().await;
+    let checking = app.account_id("Main Checking").await;
+    let savings = app.account_id("Savings").await;
+    let card = app.account_id("Credit Card").await;
+    let brokerage = app
+        .create_account(json!({ "name": "brokerage", "kind": "investment" }))
+        .await;
+    for (id, created_at) in [
+        (&savings, "2024-01-01T00:00:00Z"),
+        (&card, "2024-02-01T00:00:00Z"),
+        (&checking, "2024-03-01T00:00:00Z"),
+        (&brokerage, "2024-04-01T00:00:00Z"),
+    ] {
+        sqlx::query("UPDATE accounts SET created_at = ?1 WHERE id = ?2")
+            .bind(created_at)
+            .bind(id)
+            .execute(&app.state.pool)
+            .await
+            .unwrap();
+    }
+    app.income(&checking, 500.0).await;
+    app.income(&savings, 200.0).await;
+    app.expense(&card, 50.0).await;
+    let names = |list: Value| -> Vec<String> {
+        list.as_array()
+            .unwrap()
+            .iter()
+            .map(|a| a["name"].as_str().unwrap().to_string())
+            .collect()
+    };
+    let res = app
+        .put(
+            "/v1/accounts/reorder",
+            json!({ "ids": [card, brokerage, checking, savings] }),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+
+    let by_position = ["Credit Card", "brokerage", "Main Checking", "Savings"];
+    assert_eq!(names(app.get("/v1/accounts").await.body), by_position);
+    assert_eq!(
+        names(app.get("/v1/accounts?sort=position").await.body),
+        by_position
+    );
+    assert_eq!(
+        names(app.get("/v1/accounts?sort=name").await.body),
+        ["brokerage", "Credit Card", "Main Checking", "Savings"]
+    );
+    assert_eq!(
+        names(app.get("/v1/accounts?sort=created").await.body),
+        ["brokerage", "Main Checking", "Credit Card", "Savings"]
+    );
+    assert_eq!(
+        names(app.get("/v1/accounts?sort=balance").await.body),
+        ["Main Checking", "Savings", "brokerage", "Credit Card"]
+    );
+    let res = app.get("/v1/accounts?sort=color").await;
+    assert_eq!(res.status, StatusCode::BAD_REQUEST, "{}", res.body);
+
+    // Bounds are inclusive and may carry any offset.
+    assert_eq!(
+        names(
+            app.get(
+                "/v1/accounts?sort=created&created_from=2024-02-01T00:00:00Z\
+                 &created_to=2024-03-01T02:00:00%2B02:00"
+            )
+            .await
+            .body
+        ),
+        ["Main Checking", "Credit Card"]
+    );
+    assert_eq!(
+        names(
+            app.get("/v1/accounts?sort=created&created_to=2024-01-31T23:59:59Z")
+                .await
+                .body
+        ),
+        ["Savings"]
+    );
+    let res = app
+        .get("/v1/accounts?created_from=2024-03-01T00:00:00Z&created_to=2024-02-01T00:00:00Z")
+        .await;
+    assert_eq!(res.status, StatusCode::BAD_REQUEST, "{}", res.body);
+    let res = app.get("/v1/accounts?created_from=March").await;
+    assert_eq!(res.status, StatusCode::BAD_REQUEST, "{}", res.body);
+}
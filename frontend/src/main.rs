@@ -1,7 +1,7 @@
 mod utils;
 
 use anyhow::Result;
-use utils::{App, refresh, restore_terminal, run_app, setup_terminal};
+use utils::{App, reconnect, restore_terminal, run_app, setup_terminal};
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -10,7 +10,8 @@ async fn main() -> Result<()> {
 
     let mut app = App::new(backend_url);
     app.status = "Loading data...".into();
-    refresh(&mut app).await?;
+    // A backend that is still starting shouldn't stop the TUI; it opens offline instead.
+    reconnect(&mut app).await;
 
     let mut terminal = setup_terminal()?;
     let res = run_app(&mut terminal, &mut app).await;
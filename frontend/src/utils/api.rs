@@ -1,8 +1,45 @@
 use anyhow::Result;
 use serde_json::json;
+use time::OffsetDateTime;
 
-use super::app::{App, InputState, Mode};
-use super::model::{Account, Category, CreateSplit, CreateTransaction, DirectionKind, Transaction};
+use super::app::{App, GET_ATTEMPTS, GET_RETRY_DELAY, InputState, Mode, TXN_PAGE_SIZE, View};
+use super::model::{
+    Account, Category, CreateSplit, CreateTransaction, DirectionKind, ImportResult, Summary, Tag,
+    Template, Transaction,
+};
+
+// Every request carries API_KEY, when set, the way the backend's API_KEY expects it.
+pub fn api_key() -> Option<String> {
+    std::env::var("API_KEY").ok().filter(|key| !key.is_empty())
+}
+
+// Built once and kept on `App` so connections are reused across requests.
+pub fn build_client() -> reqwest::Client {
+    let mut headers = reqwest::header::HeaderMap::new();
+    if let Some(value) = api_key().and_then(|key| reqwest::header::HeaderValue::from_str(&key).ok())
+    {
+        headers.insert("x-api-key", value);
+    }
+    reqwest::Client::builder()
+        .default_headers(headers)
+        .build()
+        .unwrap_or_default()
+}
+
+// Only GETs are retried; a retried write could apply twice.
+async fn get_with_retry(client: &reqwest::Client, url: &str) -> Result<reqwest::Response> {
+    let mut delay = GET_RETRY_DELAY;
+    for _ in 1..GET_ATTEMPTS {
+        match client.get(url).send().await {
+            Ok(res) if !res.status().is_server_error() => return Ok(res),
+            Err(err) if !err.is_connect() && !err.is_timeout() => return Err(err.into()),
+            _ => {}
+        }
+        tokio::time::sleep(delay).await;
+        delay *= 2;
+    }
+    Ok(client.get(url).send().await?)
+}
 
 pub async fn submit_transaction(app: &mut App) -> Result<()> {
     let amount: f64 = app
@@ -12,7 +49,7 @@ pub async fn submit_transaction(app: &mut App) -> Result<()> {
         .map_err(|_| anyhow::anyhow!("Invalid amount"))?;
 
     if amount < 0.0 {
-        app.status = "Amount must be non-negative".into();
+        app.set_error("Amount must be non-negative");
         return Ok(());
     }
 
@@ -22,8 +59,7 @@ pub async fn submit_transaction(app: &mut App) -> Result<()> {
         Some(app.input.description.clone())
     };
 
-    let client = reqwest::Client::new();
-    let res = if app.mode == Mode::Transfer {
+    let payload = if app.mode == Mode::Transfer {
         let from = app
             .accounts
             .get(app.input.account_idx)
@@ -33,10 +69,10 @@ pub async fn submit_transaction(app: &mut App) -> Result<()> {
             .get(app.input.to_account_idx)
             .ok_or_else(|| anyhow::anyhow!("No destination account available"))?;
         if from.id == to.id {
-            app.status = "Source and destination must differ".into();
+            app.set_error("Source and destination must differ");
             return Ok(());
         }
-        let payload = CreateTransaction {
+        CreateTransaction {
             account_id: from.id.clone(),
             to_account_id: Some(to.id.clone()),
             amount,
@@ -44,19 +80,6 @@ pub async fn submit_transaction(app: &mut App) -> Result<()> {
             description,
             occurred_at: None,
             splits: None,
-        };
-        if let Some(edit_id) = app.editing_txn_id.clone() {
-            client
-                .put(format!("{}/transactions/{}", app.backend_url, edit_id))
-                .json(&payload)
-                .send()
-                .await?
-        } else {
-            client
-                .post(format!("{}/transactions", app.backend_url))
-                .json(&payload)
-                .send()
-                .await?
         }
     } else {
         let account = app
@@ -68,7 +91,7 @@ pub async fn submit_transaction(app: &mut App) -> Result<()> {
             .get(app.input.category_idx)
             .ok_or_else(|| anyhow::anyhow!("No category available"))?;
 
-        let payload = CreateTransaction {
+        CreateTransaction {
             account_id: account.id.clone(),
             to_account_id: None,
             amount,
@@ -79,22 +102,30 @@ pub async fn submit_transaction(app: &mut App) -> Result<()> {
                 category_id: category.id.clone(),
                 amount,
             }]),
-        };
-        if let Some(edit_id) = app.editing_txn_id.clone() {
-            client
-                .put(format!("{}/transactions/{}", app.backend_url, edit_id))
-                .json(&payload)
-                .send()
-                .await?
-        } else {
-            client
-                .post(format!("{}/transactions", app.backend_url))
-                .json(&payload)
-                .send()
-                .await?
         }
     };
 
+    // Submitting the same entry again after a duplicate warning confirms it.
+    let payload_json = serde_json::to_value(&payload)?;
+    let allow_duplicate = app.input.duplicate_warning.as_ref() == Some(&payload_json);
+    let client = app.http.clone();
+    let res = if let Some(edit_id) = app.editing_txn_id.clone() {
+        client
+            .put(format!("{}/transactions/{}", app.api_url, edit_id))
+            .json(&payload)
+            .send()
+            .await?
+    } else {
+        client
+            .post(format!(
+                "{}/transactions?allow_duplicate={allow_duplicate}",
+                app.api_url
+            ))
+            .json(&payload)
+            .send()
+            .await?
+    };
+
     if res.status().is_success() {
         app.status = if app.editing_txn_id.is_some() {
             "Transaction updated".into()
@@ -108,64 +139,243 @@ pub async fn submit_transaction(app: &mut App) -> Result<()> {
         };
         app.mode = Mode::Normal;
         refresh(app).await?;
+    } else if res.status() == reqwest::StatusCode::CONFLICT && app.editing_txn_id.is_none() {
+        app.input.duplicate_warning = Some(payload_json);
+        app.set_error(
+            "Looks like a duplicate of a recent transaction: press Enter again to save it anyway",
+        );
     } else {
-        let text = res.text().await.unwrap_or_else(|_| "unknown error".into());
-        app.status = format!("Failed to create: {text}");
+        let text = error_text(res).await;
+        app.set_error(format!("Failed to create: {text}"));
     }
     Ok(())
 }
 
+async fn error_text(res: reqwest::Response) -> String {
+    let text = res.text().await.unwrap_or_else(|_| "unknown error".into());
+    match serde_json::from_str::<serde_json::Value>(&text) {
+        Ok(serde_json::Value::Object(body)) => match body.get("errors") {
+            Some(serde_json::Value::Object(errors)) => {
+                let messages = errors
+                    .values()
+                    .filter_map(|message| message.as_str())
+                    .collect::<Vec<_>>()
+                    .join("; ");
+                match body.get("request_id").and_then(|id| id.as_str()) {
+                    Some(id) => format!("{messages} (request id: {id})"),
+                    None => messages,
+                }
+            }
+            _ => text,
+        },
+        _ => text,
+    }
+}
+
 pub async fn refresh(app: &mut App) -> Result<()> {
-    let client = reqwest::Client::new();
-    let accounts: Vec<Account> = client
-        .get(format!("{}/accounts", app.backend_url))
-        .send()
+    let client = app.http.clone();
+    let categories: Vec<Category> = get_with_retry(&client, &format!("{}/categories", app.api_url))
         .await?
+        .error_for_status()?
         .json()
         .await?;
-    let categories: Vec<Category> = client
-        .get(format!("{}/categories", app.backend_url))
-        .send()
+    let templates: Vec<Template> = get_with_retry(&client, &format!("{}/templates", app.api_url))
         .await?
+        .error_for_status()?
         .json()
         .await?;
-    let transactions: Vec<Transaction> = client
-        .get(format!("{}/transactions", app.backend_url))
-        .send()
+    let tags: Vec<Tag> = get_with_retry(&client, &format!("{}/tags", app.api_url))
         .await?
+        .error_for_status()?
         .json()
         .await?;
 
-    app.accounts = accounts;
     app.categories = categories;
+    app.templates = templates;
+    app.tags = tags;
+    app.selected_template_idx = app
+        .selected_template_idx
+        .min(app.templates.len().saturating_sub(1));
+    app.input.category_idx = app
+        .input
+        .category_idx
+        .min(app.categories.len().saturating_sub(1));
+    let category_count = app.categories.len();
+    app.input
+        .command_choices
+        .retain(|&idx| idx < category_count);
+    refresh_accounts(app).await?;
+    refresh_transactions(app).await
+}
+
+// Failure leaves the TUI running offline until a retry succeeds.
+pub async fn reconnect(app: &mut App) {
+    match refresh(app).await {
+        Ok(()) => {
+            let was_offline = app.offline.take().is_some();
+            load_server_info(app).await;
+            if app.read_only {
+                app.status = "Server is read-only: browsing only, changes are disabled".into();
+            } else if was_offline {
+                app.status = "Reconnected to the backend".into();
+            }
+        }
+        Err(err) => go_offline(app, &err),
+    }
+}
+
+pub fn go_offline(app: &mut App, err: &anyhow::Error) {
+    let reason = match err.downcast_ref::<reqwest::Error>() {
+        Some(err) if err.is_connect() => format!("can't reach the backend at {}", app.api_url),
+        Some(err) if err.is_timeout() => "the backend didn't answer in time".to_string(),
+        Some(err) => match err.status() {
+            Some(status) => format!("the backend answered {status}"),
+            None => err.to_string(),
+        },
+        None => err.to_string(),
+    };
+    app.offline = Some(reason);
+    app.mode = Mode::Normal;
+    app.status = "Offline — press r to retry, q to quit".into();
+}
+
+// Older or unreachable backends are treated as writable.
+pub async fn load_server_info(app: &mut App) {
+    let info: Option<serde_json::Value> =
+        match app.http.get(format!("{}/health", app.api_url)).send().await {
+            Ok(res) => res.json().await.ok(),
+            Err(_) => None,
+        };
+    app.read_only = info
+        .and_then(|info| info.get("read_only").and_then(|v| v.as_bool()))
+        .unwrap_or(false);
+}
+
+// Fetched once per account and window; refresh_accounts clears the marker.
+pub async fn load_balance_history(app: &mut App) {
+    if app.view != View::Account || app.offline.is_some() {
+        return;
+    }
+    let Some(account) = app.accounts.get(app.selected_account_idx) else {
+        app.balance_history = None;
+        return;
+    };
+    let wanted = (account.id.clone(), app.history_days);
+    if app.history_requested.as_ref() == Some(&wanted) {
+        return;
+    }
+    let res = get_with_retry(
+        &app.http,
+        &format!(
+            "{}/accounts/{}/balance-history?days={}",
+            app.api_url, wanted.0, wanted.1
+        ),
+    )
+    .await;
+    app.balance_history = match res {
+        Ok(res) if res.status().is_success() => res.json().await.ok(),
+        Ok(res) => {
+            let text = error_text(res).await;
+            app.set_error(format!("Failed to load balance history: {text}"));
+            None
+        }
+        Err(err) => {
+            go_offline(app, &err);
+            None
+        }
+    };
+    app.history_requested = Some(wanted);
+}
+
+// Re-pulls only the accounts table, e.g. after a server "accounts" event.
+pub async fn refresh_accounts(app: &mut App) -> Result<()> {
+    let client = app.http.clone();
+    let accounts: Vec<Account> = get_with_retry(&client, &format!("{}/accounts", app.api_url))
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+
+    app.set_accounts(accounts);
+    // Older backends lack /summary; the dashboard falls back to local figures.
+    app.summary = fetch_summary(&client, &app.api_url).await.ok();
+    app.history_requested = None;
+    set_counts_status(app);
+    Ok(())
+}
+
+// Re-pulls only the loaded page of transactions, e.g. after a server "transactions" event.
+pub async fn refresh_transactions(app: &mut App) -> Result<()> {
+    let client = app.http.clone();
+    let res = get_with_retry(
+        &client,
+        &format!("{}/transactions?limit={}", app.api_url, app.txn_limit),
+    )
+    .await?
+    .error_for_status()?;
+    let total = res
+        .headers()
+        .get("x-total-count")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<usize>().ok());
+    let transactions: Vec<Transaction> = res.json().await?;
+
+    // Backends without paging return everything and no count.
+    app.txn_total = total.unwrap_or(transactions.len());
     app.transactions = transactions;
+    app.summary = fetch_summary(&client, &app.api_url).await.ok();
     if !app.transactions.is_empty() {
-        app.selected_txn_idx = app.selected_txn_idx.min(app.transactions.len().saturating_sub(1));
+        app.selected_txn_idx = app
+            .selected_txn_idx
+            .min(app.transactions.len().saturating_sub(1));
     } else {
         app.selected_txn_idx = 0;
     }
+    set_counts_status(app);
+    Ok(())
+}
+
+fn set_counts_status(app: &mut App) {
     app.status = format!(
-        "{} accounts | {} categories | {} transactions",
+        "{} accounts | {} categories | showing {} of {} transactions",
         app.accounts.len(),
         app.categories.len(),
-        app.transactions.len()
+        app.transactions.len(),
+        app.txn_total
     );
-    Ok(())
+}
+
+pub async fn load_more_transactions(app: &mut App) -> Result<()> {
+    if !app.has_more_transactions() {
+        app.status = format!("All {} transactions loaded", app.txn_total);
+        return Ok(());
+    }
+    app.txn_limit += TXN_PAGE_SIZE;
+    refresh(app).await
+}
+
+async fn fetch_summary(client: &reqwest::Client, api_url: &str) -> Result<Summary> {
+    let summary = get_with_retry(client, &format!("{}/summary", api_url))
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    Ok(summary)
 }
 
 pub async fn create_account(app: &mut App, name: &str, kind: &str) -> Result<()> {
     if name.trim().is_empty() {
-        app.status = "Account name cannot be empty".into();
+        app.set_error("Account name cannot be empty");
         return Ok(());
     }
 
-    let client = reqwest::Client::new();
+    let client = app.http.clone();
     let payload = json!({
         "name": name,
         "kind": kind,
     });
     let res = client
-        .post(format!("{}/accounts", app.backend_url))
+        .post(format!("{}/accounts", app.api_url))
         .json(&payload)
         .send()
         .await?;
@@ -173,40 +383,271 @@ pub async fn create_account(app: &mut App, name: &str, kind: &str) -> Result<()>
         refresh(app).await?;
         app.status = format!("Account \"{}\" created", name);
     } else {
-        let text = res.text().await.unwrap_or_else(|_| "unknown error".into());
-        app.status = format!("Failed to create account: {text}");
+        let text = error_text(res).await;
+        app.set_error(format!("Failed to create account: {text}"));
     }
     Ok(())
 }
 
 pub async fn delete_transaction(app: &mut App, txn_id: &str) -> Result<()> {
-    let client = reqwest::Client::new();
+    let client = app.http.clone();
     let res = client
-        .delete(format!("{}/transactions/{}", app.backend_url, txn_id))
+        .delete(format!("{}/transactions/{}", app.api_url, txn_id))
         .send()
         .await?;
     if res.status().is_success() {
         refresh(app).await?;
         app.status = "Transaction deleted".into();
     } else {
-        let text = res.text().await.unwrap_or_else(|_| "unknown error".into());
-        app.status = format!("Failed to delete transaction: {text}");
+        let text = error_text(res).await;
+        app.set_error(format!("Failed to delete transaction: {text}"));
     }
     Ok(())
 }
 
 pub async fn delete_account(app: &mut App, account_id: &str) -> Result<()> {
-    let client = reqwest::Client::new();
+    let client = app.http.clone();
     let res = client
-        .delete(format!("{}/accounts/{}", app.backend_url, account_id))
+        .delete(format!("{}/accounts/{}", app.api_url, account_id))
         .send()
         .await?;
     if res.status().is_success() {
         refresh(app).await?;
         app.status = "Account deleted".into();
     } else {
-        let text = res.text().await.unwrap_or_else(|_| "unknown error".into());
-        app.status = format!("Failed to delete account: {text}");
+        let text = error_text(res).await;
+        app.set_error(format!("Failed to delete account: {text}"));
+    }
+    Ok(())
+}
+
+// Saves the backend's CSV export as transactions-<timestamp>.csv in the working directory.
+pub async fn export_csv(app: &mut App) -> Result<()> {
+    let client = app.http.clone();
+    let res = match client
+        .get(format!("{}/export/transactions.csv", app.api_url))
+        .send()
+        .await
+    {
+        Ok(res) => res,
+        Err(err) => {
+            app.set_error(format!("Export failed: {err}"));
+            return Ok(());
+        }
+    };
+    if res.status() == reqwest::StatusCode::NOT_FOUND {
+        app.set_error("Export failed: this backend has no CSV export");
+        return Ok(());
+    }
+    if !res.status().is_success() {
+        let text = error_text(res).await;
+        app.set_error(format!("Export failed: {text}"));
+        return Ok(());
+    }
+
+    let body = res.bytes().await?;
+    let now = OffsetDateTime::now_utc();
+    let path = format!(
+        "transactions-{:04}{:02}{:02}-{:02}{:02}{:02}.csv",
+        now.year(),
+        u8::from(now.month()),
+        now.day(),
+        now.hour(),
+        now.minute(),
+        now.second()
+    );
+    match tokio::fs::write(&path, &body).await {
+        Ok(()) => app.status = format!("Exported {} bytes to {path}", body.len()),
+        Err(err) => app.set_error(format!("Could not write {path}: {err}")),
+    }
+    Ok(())
+}
+
+// Sends a CSV file to the backend and opens the summary popup with any rows it rejected.
+pub async fn import_csv(app: &mut App, path: &str) -> Result<()> {
+    let path = path.trim();
+    if path.is_empty() {
+        app.set_error("Type the path of a CSV file to import");
+        return Ok(());
+    }
+    let body = match tokio::fs::read_to_string(path).await {
+        Ok(body) => body,
+        Err(err) => {
+            app.set_error(format!("Could not read {path}: {err}"));
+            return Ok(());
+        }
+    };
+
+    let client = app.http.clone();
+    let res = match client
+        .post(format!("{}/import/transactions.csv", app.api_url))
+        .header(reqwest::header::CONTENT_TYPE, "text/csv")
+        .body(body)
+        .send()
+        .await
+    {
+        Ok(res) => res,
+        Err(err) => {
+            app.set_error(format!("Import failed: {err}"));
+            return Ok(());
+        }
+    };
+    if res.status() == reqwest::StatusCode::NOT_FOUND {
+        app.set_error("Import failed: this backend has no CSV import");
+        return Ok(());
+    }
+    if !res.status().is_success() {
+        let text = error_text(res).await;
+        app.set_error(format!("Import failed: {text}"));
+        return Ok(());
+    }
+
+    let report: ImportResult = res.json().await?;
+    refresh(app).await?;
+    app.status = format!(
+        "Imported {} | skipped {} | {} failed",
+        report.inserted,
+        report.skipped,
+        report.errors.len()
+    );
+    app.import_report = Some(report);
+    app.import_scroll = 0;
+    app.mode = Mode::ImportReport;
+    Ok(())
+}
+
+pub async fn save_splits(app: &mut App, txn_id: &str, splits: Vec<CreateSplit>) -> Result<()> {
+    let client = app.http.clone();
+    let res = client
+        .put(format!("{}/transactions/{}/splits", app.api_url, txn_id))
+        .json(&json!({ "splits": splits }))
+        .send()
+        .await?;
+    if res.status().is_success() {
+        app.mode = Mode::Normal;
+        app.editing_txn_id = None;
+        app.split_drafts.clear();
+        refresh(app).await?;
+        app.status = "Splits saved".into();
+    } else {
+        let text = error_text(res).await;
+        app.set_error(format!("Failed to save splits: {text}"));
+    }
+    Ok(())
+}
+
+pub async fn set_tags(app: &mut App, txn_id: &str, tags: Vec<String>, done: String) -> Result<()> {
+    let client = app.http.clone();
+    let res = client
+        .put(format!("{}/transactions/{}/tags", app.api_url, txn_id))
+        .json(&json!({ "tags": tags }))
+        .send()
+        .await?;
+    if res.status().is_success() {
+        app.input.tag_query.clear();
+        app.input.tag_idx = 0;
+        refresh(app).await?;
+        app.status = done;
+    } else {
+        let text = error_text(res).await;
+        app.set_error(format!("Failed to save tags: {text}"));
+    }
+    Ok(())
+}
+
+// Re-files a transaction under another account; the backend moves its balance effect along.
+pub async fn move_transaction(app: &mut App, txn_id: &str, account_id: &str) -> Result<()> {
+    let name_of = |app: &App, id: &str| {
+        app.accounts
+            .iter()
+            .find(|a| a.id == id)
+            .map(|a| a.name.clone())
+            .unwrap_or_else(|| "unknown".into())
+    };
+    let from = app
+        .transactions
+        .iter()
+        .find(|t| t.id == txn_id)
+        .map(|t| name_of(app, &t.account_id))
+        .unwrap_or_else(|| "unknown".into());
+    let to = name_of(app, account_id);
+    let client = app.http.clone();
+    let res = client
+        .patch(format!("{}/transactions/{}", app.api_url, txn_id))
+        .json(&json!({ "account_id": account_id }))
+        .send()
+        .await?;
+    if res.status().is_success() {
+        app.mode = Mode::Normal;
+        app.editing_txn_id = None;
+        refresh(app).await?;
+        app.status = format!("Moved transaction from {from} to {to}");
+    } else {
+        let text = error_text(res).await;
+        app.set_error(format!("Failed to move transaction: {text}"));
+    }
+    Ok(())
+}
+
+pub async fn set_reconciled(app: &mut App, txn_id: &str, reconciled: bool) -> Result<()> {
+    let action = if reconciled {
+        "reconcile"
+    } else {
+        "unreconcile"
+    };
+    let client = app.http.clone();
+    let res = client
+        .post(format!("{}/transactions/{}/{action}", app.api_url, txn_id))
+        .send()
+        .await?;
+    if res.status().is_success() {
+        refresh(app).await?;
+        app.status = if reconciled {
+            "Transaction marked reconciled".into()
+        } else {
+            "Transaction marked unreconciled".into()
+        };
+    } else {
+        let text = error_text(res).await;
+        app.set_error(format!("Failed to {action} transaction: {text}"));
+    }
+    Ok(())
+}
+
+pub async fn reorder_accounts(app: &mut App, ids: &[String]) -> Result<()> {
+    let client = app.http.clone();
+    let res = client
+        .put(format!("{}/accounts/reorder", app.api_url))
+        .json(&json!({ "ids": ids }))
+        .send()
+        .await?;
+    if res.status().is_success() {
+        refresh(app).await?;
+        app.status = "Account order saved".into();
+    } else {
+        let text = error_text(res).await;
+        app.set_error(format!("Failed to reorder accounts: {text}"));
+    }
+    Ok(())
+}
+
+pub async fn apply_template(app: &mut App, template_id: &str, amount: Option<f64>) -> Result<()> {
+    let client = app.http.clone();
+    let payload = match amount {
+        Some(amount) => json!({ "amount": amount }),
+        None => json!({}),
+    };
+    let res = client
+        .post(format!("{}/templates/{}/apply", app.api_url, template_id))
+        .json(&payload)
+        .send()
+        .await?;
+    if res.status().is_success() {
+        refresh(app).await?;
+        app.status = "Template applied".into();
+    } else {
+        let text = error_text(res).await;
+        app.set_error(format!("Failed to apply template: {text}"));
     }
     Ok(())
 }
@@ -1,4 +1,26 @@
-use super::model::{Account, Category, DirectionKind, Transaction};
+use time::OffsetDateTime;
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use super::api::build_client;
+use super::model::{
+    Account, BalanceHistory, Category, CategoryTotal, DirectionKind, ImportResult, Summary, Tag,
+    Template, Transaction,
+};
+use super::money::MoneyFormat;
+
+// Backend API version this client speaks.
+const API_VERSION: &str = "v1";
+// How long an error stays in the red message area before it clears itself.
+const ERROR_DISPLAY_TIME: Duration = Duration::from_secs(5);
+// Newest transactions fetched per page; older ones load on demand.
+pub const TXN_PAGE_SIZE: usize = 100;
+// Tries per GET before a refresh gives up, and the pause before the first retry.
+pub const GET_ATTEMPTS: u32 = 3;
+pub const GET_RETRY_DELAY: Duration = Duration::from_millis(200);
+// Day counts the account view's balance sparkline steps through with [ and ].
+pub const HISTORY_WINDOWS: [i64; 6] = [7, 14, 30, 90, 180, 365];
 
 #[derive(Clone)]
 pub struct InputState {
@@ -11,12 +33,19 @@ pub struct InputState {
     pub to_account_idx: usize,
     pub new_account_name: String,
     pub new_account_kind_idx: usize,
-}
-
-impl Default for ActiveField {
-    fn default() -> Self {
-        ActiveField::Amount
-    }
+    pub account_query: String,
+    pub category_query: String,
+    pub tag_query: String,
+    pub tag_idx: usize,
+    pub command: String,
+    pub import_path: String,
+    // Parse or lookup failure for the command bar, shown under the typed line.
+    pub command_error: Option<String>,
+    // Category indexes offered when the command's category is ambiguous; 1-9 picks one.
+    pub command_choices: Vec<usize>,
+    // Payload the backend flagged as a likely duplicate; resubmitting it unchanged confirms.
+    pub duplicate_warning: Option<serde_json::Value>,
+    pub split_idx: usize,
 }
 
 impl Default for InputState {
@@ -31,12 +60,97 @@ impl Default for InputState {
             to_account_idx: 0,
             new_account_name: String::new(),
             new_account_kind_idx: 0,
+            account_query: String::new(),
+            category_query: String::new(),
+            tag_query: String::new(),
+            tag_idx: 0,
+            command: String::new(),
+            import_path: String::new(),
+            command_error: None,
+            command_choices: Vec::new(),
+            duplicate_warning: None,
+            split_idx: 0,
         }
     }
 }
 
-#[derive(Clone, Copy, PartialEq, Eq)]
+impl InputState {
+    // Returns false when the text is not a number.
+    pub fn adjust_amount(&mut self, delta: f64) -> bool {
+        let current = if self.amount.is_empty() {
+            0.0
+        } else {
+            match self.amount.parse::<f64>() {
+                Ok(value) => value,
+                Err(_) => return false,
+            }
+        };
+        let next = (current + delta).max(0.0);
+        self.amount = if next.fract() == 0.0 {
+            format!("{next:.0}")
+        } else {
+            format!("{next:.2}")
+        };
+        true
+    }
+}
+
+// One row of the split editor; the amount stays text while it is being typed.
+#[derive(Clone)]
+pub struct SplitDraft {
+    pub category_idx: usize,
+    pub amount: String,
+}
+
+// Parsed from a command-bar line such as "expense 42.50 groceries coffee beans".
+pub struct QuickEntry {
+    pub direction: DirectionKind,
+    pub amount: f64,
+    pub category: String,
+    pub description: Option<String>,
+}
+
+impl QuickEntry {
+    pub fn parse(line: &str) -> Result<Self, String> {
+        let mut words = line.split_whitespace().peekable();
+        let direction = match words.peek().map(|w| w.to_lowercase()).as_deref() {
+            None => return Err("Type e.g. \"expense 42.50 groceries coffee\"".into()),
+            Some("expense" | "e") => DirectionKind::Expense,
+            Some("income" | "i") => DirectionKind::Income,
+            Some("refund" | "r") => DirectionKind::Refund,
+            Some(word) if word.parse::<f64>().is_ok() => DirectionKind::Expense,
+            Some(word) => {
+                return Err(format!(
+                    "Unknown direction \"{word}\": use expense, income or refund"
+                ));
+            }
+        };
+        if words.peek().is_some_and(|w| w.parse::<f64>().is_err()) {
+            words.next();
+        }
+        let amount = match words.next() {
+            Some(word) => word
+                .parse::<f64>()
+                .map_err(|_| format!("Invalid amount \"{word}\""))?,
+            None => return Err("Missing amount".into()),
+        };
+        if !amount.is_finite() || amount < 0.0 {
+            return Err("Amount must be non-negative".into());
+        }
+        let category = words.next().ok_or("Missing category")?.to_string();
+        let description = words.collect::<Vec<_>>().join(" ");
+        Ok(Self {
+            direction,
+            amount,
+            category,
+            description: (!description.is_empty()).then_some(description),
+        })
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
 pub enum ActiveField {
+    #[default]
     Amount,
     Description,
     AccountName,
@@ -51,35 +165,402 @@ pub enum Mode {
     AddAccount,
     DeleteAccount,
     DeleteTransaction,
+    Templates,
+    ReorderAccounts,
+    JumpAccount,
+    Command,
+    Splits,
+    Categorize,
+    MoveTransaction,
+    Tag,
+    Import,
+    ImportReport,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum View {
+    Transactions,
+    Dashboard,
+    Account,
+}
+
+impl View {
+    // Top-level views in Tab order; number keys 1.. select them directly.
+    pub const ALL: [View; 3] = [View::Transactions, View::Dashboard, View::Account];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            View::Transactions => "Transactions",
+            View::Dashboard => "Dashboard",
+            View::Account => "Account",
+        }
+    }
+
+    pub fn next(self) -> View {
+        let idx = View::ALL.iter().position(|v| *v == self).unwrap_or(0);
+        View::ALL[(idx + 1) % View::ALL.len()]
+    }
 }
 
 pub struct App {
-    pub backend_url: String,
+    pub http: reqwest::Client,
+    // Versioned base for REST and websocket calls, e.g. http://host:8080/v1.
+    pub api_url: String,
     pub accounts: Vec<Account>,
     pub categories: Vec<Category>,
+    // Every tag on the backend, for autocompleting the tag prompt.
+    pub tags: Vec<Tag>,
     pub transactions: Vec<Transaction>,
+    // How many of the newest transactions refresh fetches, and how many exist in total.
+    pub txn_limit: usize,
+    pub txn_total: usize,
+    pub templates: Vec<Template>,
     pub selected_txn_idx: usize,
+    pub selected_template_idx: usize,
+    // Account the add/transfer forms start on; set from the quick-switcher.
+    pub selected_account_idx: usize,
     pub editing_txn_id: Option<String>,
     pub status: String,
+    // Last failure and when it happened; shown apart from `status` so it doesn't clobber it.
+    pub error: Option<(String, Instant)>,
+    // Set while a key's backend request is outstanding, e.g. "Saving...".
+    pub in_flight: Option<&'static str>,
+    // The backend runs with READ_ONLY, so keys that would change data are refused up front.
+    pub read_only: bool,
+    // Why the backend couldn't be reached; set until a full refresh succeeds again.
+    pub offline: Option<String>,
     pub mode: Mode,
     pub input: InputState,
+    pub hide_zero_accounts: bool,
+    pub view: View,
+    pub summary: Option<Summary>,
+    // Daily balances of the selected account for the account view, over `history_days`.
+    pub balance_history: Option<BalanceHistory>,
+    pub history_days: i64,
+    // Account and window last asked for, so the history loads once per selection.
+    pub history_requested: Option<(String, i64)>,
+    pub money: MoneyFormat,
+    // Splits being edited for the transaction in `editing_txn_id`.
+    pub split_drafts: Vec<SplitDraft>,
+    // Outcome of the last CSV import, shown in a popup until dismissed.
+    pub import_report: Option<ImportResult>,
+    pub import_scroll: u16,
+    // Kept open: on X11 copied text lives only as long as its handle.
+    pub clipboard: Option<arboard::Clipboard>,
 }
 
 impl App {
     pub fn new(backend_url: String) -> Self {
         Self {
-            backend_url,
+            http: build_client(),
+            api_url: format!("{}/{API_VERSION}", backend_url.trim_end_matches('/')),
             accounts: Vec::new(),
             categories: Vec::new(),
+            tags: Vec::new(),
             transactions: Vec::new(),
+            txn_limit: TXN_PAGE_SIZE,
+            txn_total: 0,
+            templates: Vec::new(),
             selected_txn_idx: 0,
+            selected_template_idx: 0,
+            selected_account_idx: 0,
             editing_txn_id: None,
-            status: "Press a add txn, t transfer, n new acct, x delete acct, e edit txn, d delete txn, q quit".to_string(),
+            status: "Press a add txn, t transfer, : quick entry, n new acct, x delete acct, e edit txn, s splits, c categorize, M move txn, T tags, Y copy txn id, r reconcile txn, d delete txn, p templates, I import csv, Tab/1-3 switch view, [ ] history window, z hide zero accts, q quit".to_string(),
+            error: None,
+            in_flight: None,
+            read_only: false,
+            offline: None,
             mode: Mode::Normal,
             input: InputState {
                 direction: DirectionKind::Expense,
                 ..Default::default()
             },
+            hide_zero_accounts: false,
+            view: View::Transactions,
+            summary: None,
+            balance_history: None,
+            history_days: 30,
+            history_requested: None,
+            money: MoneyFormat::from_env(),
+            split_drafts: Vec::new(),
+            import_report: None,
+            import_scroll: 0,
+            clipboard: None,
         }
     }
 }
+
+impl App {
+    pub fn has_more_transactions(&self) -> bool {
+        self.transactions.len() < self.txn_total
+    }
+
+    pub fn set_error(&mut self, message: impl Into<String>) {
+        self.error = Some((message.into(), Instant::now()));
+    }
+
+    pub fn set_accounts(&mut self, accounts: Vec<Account>) {
+        self.accounts = accounts;
+        let last_account = self.accounts.len().saturating_sub(1);
+        self.selected_account_idx = self.selected_account_idx.min(last_account);
+        self.input.account_idx = self.input.account_idx.min(last_account);
+        self.input.to_account_idx = self.input.to_account_idx.min(last_account);
+    }
+
+    // Amount of the transaction whose splits are being edited.
+    pub fn split_total(&self) -> f64 {
+        self.editing_txn_id
+            .as_ref()
+            .and_then(|id| self.transactions.iter().find(|t| &t.id == id))
+            .map(|t| t.amount)
+            .unwrap_or(0.0)
+    }
+
+    // Formatting in the currency of the transaction whose splits are being edited.
+    pub fn split_money(&self) -> MoneyFormat {
+        self.editing_txn_id
+            .as_ref()
+            .and_then(|id| self.transactions.iter().find(|t| &t.id == id))
+            .map(|t| self.money_for(&t.account_id))
+            .unwrap_or_else(|| self.money.clone())
+    }
+
+    // Formatting for one account's amounts, in its own currency.
+    pub fn money_for(&self, account_id: &str) -> MoneyFormat {
+        self.accounts
+            .iter()
+            .find(|a| a.id == account_id)
+            .map(|a| self.money.in_currency(&a.currency))
+            .unwrap_or_else(|| self.money.clone())
+    }
+
+    // Sum of the split drafts, treating amounts still being typed (or empty) as zero.
+    pub fn split_allocated(&self) -> f64 {
+        self.split_drafts
+            .iter()
+            .map(|d| d.amount.parse::<f64>().unwrap_or(0.0))
+            .sum()
+    }
+
+    // Copies to the system clipboard, opening it on first use.
+    pub fn copy_to_clipboard(&mut self, text: &str) -> Result<(), arboard::Error> {
+        let clipboard = match &mut self.clipboard {
+            Some(clipboard) => clipboard,
+            None => self.clipboard.insert(arboard::Clipboard::new()?),
+        };
+        clipboard.set_text(text)
+    }
+
+    pub fn active_error(&self) -> Option<&str> {
+        self.error
+            .as_ref()
+            .filter(|(_, at)| at.elapsed() < ERROR_DISPLAY_TIME)
+            .map(|(message, _)| message.as_str())
+    }
+
+    // Indexes of accounts whose name contains the quick-switcher query, ignoring case.
+    pub fn matching_accounts(&self) -> Vec<usize> {
+        let query = self.input.account_query.to_lowercase();
+        self.accounts
+            .iter()
+            .enumerate()
+            .filter(|(_, a)| a.name.to_lowercase().contains(&query))
+            .map(|(idx, _)| idx)
+            .collect()
+    }
+
+    // Only the best tier: exact, then prefix, substring, letters in order.
+    pub fn matching_categories(&self, query: &str) -> Vec<usize> {
+        let query = query.to_lowercase();
+        let tier = |name: &str| {
+            let name = name.to_lowercase();
+            if name == query {
+                Some(0)
+            } else if name.starts_with(&query) {
+                Some(1)
+            } else if name.contains(&query) {
+                Some(2)
+            } else {
+                let mut letters = name.chars();
+                query.chars().all(|c| letters.any(|n| n == c)).then_some(3)
+            }
+        };
+        let tiers: Vec<(usize, u8)> = self
+            .categories
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, c)| tier(&c.name).map(|t| (idx, t)))
+            .collect();
+        let best = tiers.iter().map(|(_, t)| *t).min();
+        tiers
+            .into_iter()
+            .filter(|(_, t)| Some(*t) == best)
+            .map(|(idx, _)| idx)
+            .collect()
+    }
+
+    pub fn tag_suggestions(&self) -> Vec<String> {
+        let query = self.input.tag_query.trim();
+        let lower = query.to_lowercase();
+        let mut names: Vec<String> = self
+            .tags
+            .iter()
+            .filter(|t| t.name.to_lowercase().contains(&lower))
+            .map(|t| t.name.clone())
+            .collect();
+        if !query.is_empty() && !names.iter().any(|n| n.to_lowercase() == lower) {
+            names.push(query.to_string());
+        }
+        names
+    }
+
+    pub fn is_new_tag(&self, name: &str) -> bool {
+        !self.tags.iter().any(|t| t.name.eq_ignore_ascii_case(name))
+    }
+
+    // Only a lower bound while more pages remain.
+    pub fn unreconciled_count(&self, account_id: &str) -> usize {
+        self.transactions
+            .iter()
+            .filter(|t| !t.reconciled)
+            .filter(|t| {
+                t.account_id == account_id || t.to_account_id.as_deref() == Some(account_id)
+            })
+            .count()
+    }
+
+    pub fn default_direction(&self, account_idx: usize) -> DirectionKind {
+        self.accounts
+            .get(account_idx)
+            .and_then(|a| a.default_direction.clone())
+            .filter(|d| matches!(d, DirectionKind::Income | DirectionKind::Expense))
+            .unwrap_or(DirectionKind::Expense)
+    }
+
+    // Total owed across credit accounts.
+    pub fn debt(&self) -> f64 {
+        self.accounts.iter().filter_map(|a| a.owed()).sum()
+    }
+
+    // Assets less debt, i.e. the same figure as /reports/networth.
+    pub fn net_worth(&self) -> f64 {
+        let assets: f64 = self
+            .accounts
+            .iter()
+            .filter(|a| a.owed().is_none())
+            .map(|a| a.balance)
+            .sum();
+        assets - self.debt()
+    }
+
+    pub fn today_net(&self) -> f64 {
+        let today = OffsetDateTime::now_utc().date().to_string();
+        self.transactions
+            .iter()
+            .filter(|t| t.occurred_at.starts_with(&today))
+            .map(|t| match t.direction {
+                DirectionKind::Income | DirectionKind::Refund => t.amount,
+                DirectionKind::Expense => -t.amount,
+                DirectionKind::Transfer => 0.0,
+            })
+            .sum()
+    }
+
+    // Fallback without /summary, built from the loaded page only.
+    pub fn local_summary(&self) -> Summary {
+        let now = OffsetDateTime::now_utc();
+        let month_prefix = format!("{:04}-{:02}", now.year(), u8::from(now.month()));
+        let mut month_income = 0.0;
+        let mut month_expense = 0.0;
+        let mut by_category: HashMap<String, f64> = HashMap::new();
+        for t in self
+            .transactions
+            .iter()
+            .filter(|t| t.occurred_at.starts_with(&month_prefix))
+        {
+            match t.direction {
+                DirectionKind::Income => month_income += t.amount,
+                DirectionKind::Expense => {
+                    month_expense += t.amount;
+                    for split in &t.splits {
+                        *by_category.entry(split.category_id.clone()).or_insert(0.0) +=
+                            split.amount;
+                    }
+                }
+                // Refunds net against the category they were spent in.
+                DirectionKind::Refund => {
+                    month_expense -= t.amount;
+                    for split in &t.splits {
+                        *by_category.entry(split.category_id.clone()).or_insert(0.0) -=
+                            split.amount;
+                    }
+                }
+                // Only the fee leaves the user's accounts.
+                DirectionKind::Transfer => month_expense += t.fee,
+            }
+        }
+
+        let mut top_categories: Vec<CategoryTotal> = by_category
+            .into_iter()
+            .map(|(category_id, total)| CategoryTotal {
+                name: self
+                    .categories
+                    .iter()
+                    .find(|c| c.id == category_id)
+                    .map(|c| c.name.clone())
+                    .unwrap_or_else(|| "unknown".into()),
+                category_id,
+                total,
+            })
+            .filter(|c| c.total > 0.0)
+            .collect();
+        top_categories.sort_by(|a, b| b.total.total_cmp(&a.total));
+        top_categories.truncate(3);
+
+        Summary {
+            net_worth: self.net_worth(),
+            account_count: self.accounts.len() as i64,
+            month_income,
+            month_expense,
+            month_net: month_income - month_expense,
+            top_categories,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn account(id: &str) -> Account {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "name": id,
+            "kind": "checking",
+            "balance": 0.0,
+            "color": null,
+            "created_at": "2024-01-01T00:00:00Z",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn deleting_the_selected_last_account_moves_the_selection_up() {
+        let mut app = App::new("http://localhost:8080".into());
+        app.set_accounts(vec![account("a"), account("b"), account("c")]);
+        app.selected_account_idx = 2;
+        app.input.account_idx = 2;
+        app.input.to_account_idx = 1;
+
+        app.set_accounts(vec![account("a"), account("b")]);
+        assert_eq!(app.selected_account_idx, 1);
+        assert_eq!(app.accounts[app.selected_account_idx].id, "b");
+        assert_eq!(app.input.account_idx, 1);
+        assert_eq!(app.input.to_account_idx, 1);
+
+        app.set_accounts(Vec::new());
+        assert_eq!(app.selected_account_idx, 0);
+        assert_eq!(app.input.account_idx, 0);
+        assert!(app.accounts.get(app.selected_account_idx).is_none());
+    }
+}
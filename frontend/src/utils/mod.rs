@@ -1,9 +1,10 @@
 pub mod api;
 pub mod app;
 pub mod model;
+pub mod money;
 pub mod terminal;
 pub mod ui;
 
-pub use api::refresh;
+pub use api::reconnect;
 pub use app::App;
 pub use terminal::{restore_terminal, run_app, setup_terminal};
@@ -6,8 +6,27 @@ pub struct Account {
     pub id: String,
     pub name: String,
     pub kind: String,
+    // ISO 4217 code the balance and the account's transactions are in.
+    #[serde(default)]
+    pub currency: String,
     pub balance: f64,
+    pub color: Option<String>,
     pub created_at: String,
+    // Suggested by the backend from the account's kind and recent entries.
+    #[serde(default)]
+    pub default_direction: Option<DirectionKind>,
+    // Posted entries on the account and what they moved it by this month.
+    #[serde(default)]
+    pub transaction_count: Option<i64>,
+    #[serde(default)]
+    pub month_net: Option<f64>,
+}
+
+impl Account {
+    // Credit balances go negative as the card is used.
+    pub fn owed(&self) -> Option<f64> {
+        (self.kind == "credit").then_some(-self.balance)
+    }
 }
 
 #[allow(dead_code)]
@@ -18,12 +37,33 @@ pub struct Category {
     pub created_at: String,
 }
 
+#[allow(dead_code)]
+#[derive(Debug, Deserialize, Clone)]
+pub struct Tag {
+    pub id: String,
+    pub name: String,
+    pub created_at: String,
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
 #[serde(rename_all = "lowercase")]
 pub enum DirectionKind {
     Income,
     Expense,
     Transfer,
+    Refund,
+}
+
+impl DirectionKind {
+    // Lowercase name as the backend spells it, for labels and status messages.
+    pub fn label(&self) -> &'static str {
+        match self {
+            DirectionKind::Income => "income",
+            DirectionKind::Expense => "expense",
+            DirectionKind::Transfer => "transfer",
+            DirectionKind::Refund => "refund",
+        }
+    }
 }
 
 #[allow(dead_code)]
@@ -41,14 +81,84 @@ pub struct Transaction {
     pub account_id: String,
     pub to_account_id: Option<String>,
     pub amount: f64,
+    pub to_amount: Option<f64>,
+    // Charged to the source account on transfers; absent from older backends.
+    #[serde(default)]
+    pub fee: f64,
     pub direction: DirectionKind,
     pub description: Option<String>,
     pub occurred_at: String,
+    // Older backends don't send the flag; treat their rows as unreconciled.
+    #[serde(default)]
+    pub reconciled: bool,
     pub splits: Vec<TransactionSplit>,
+    #[serde(default)]
+    pub tags: Vec<String>,
     pub created_at: String,
     pub updated_at: String,
 }
 
+#[allow(dead_code)]
+#[derive(Debug, Deserialize, Clone)]
+pub struct Template {
+    pub id: String,
+    pub name: String,
+    pub account_id: String,
+    pub to_account_id: Option<String>,
+    pub amount: f64,
+    pub direction: DirectionKind,
+    pub category_id: Option<String>,
+    pub description: Option<String>,
+    pub created_at: String,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize, Clone)]
+pub struct CategoryTotal {
+    pub category_id: String,
+    pub name: String,
+    pub total: f64,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize, Clone)]
+pub struct BalanceHistory {
+    pub account_id: String,
+    pub points: Vec<DailyBalance>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct DailyBalance {
+    pub date: String,
+    pub balance: f64,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct Summary {
+    pub net_worth: f64,
+    pub account_count: i64,
+    pub month_income: f64,
+    pub month_expense: f64,
+    pub month_net: f64,
+    pub top_categories: Vec<CategoryTotal>,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize, Clone)]
+pub struct Alert {
+    pub rule_id: String,
+    pub transaction_id: String,
+    pub direction: DirectionKind,
+    pub amount: f64,
+    pub threshold: f64,
+}
+
+// Pushed over /events alongside the plain "refresh" notifications.
+#[derive(Debug, Deserialize)]
+pub struct AlertMessage {
+    pub alert: Alert,
+}
+
 #[derive(Serialize)]
 pub struct CreateTransaction {
     pub account_id: String,
@@ -60,6 +170,21 @@ pub struct CreateTransaction {
     pub splits: Option<Vec<CreateSplit>>,
 }
 
+// Answer to POST /import/transactions.csv; rows that failed are listed by file line.
+#[derive(Debug, Deserialize, Clone)]
+pub struct ImportResult {
+    pub inserted: usize,
+    pub skipped: usize,
+    #[serde(default)]
+    pub errors: Vec<ImportRowError>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct ImportRowError {
+    pub line: usize,
+    pub message: String,
+}
+
 #[derive(Serialize)]
 pub struct CreateSplit {
     pub category_id: String,
@@ -0,0 +1,117 @@
+// Display formatting from BASE_CURRENCY and LOCALE, matching the backend.
+#[derive(Clone, Debug)]
+pub struct MoneyFormat {
+    symbol: String,
+    digits: usize,
+    thousands: char,
+    decimal: char,
+    symbol_after: bool,
+}
+
+impl Default for MoneyFormat {
+    fn default() -> Self {
+        Self::new("USD", "en-US")
+    }
+}
+
+impl MoneyFormat {
+    pub fn new(currency: &str, locale: &str) -> Self {
+        let (symbol, digits) = currency_style(currency);
+        let (thousands, decimal, symbol_after) = match locale
+            .trim()
+            .replace('_', "-")
+            .to_ascii_lowercase()
+            .as_str()
+        {
+            "de-de" => ('.', ',', true),
+            "fr-fr" => ('\u{a0}', ',', true),
+            _ => (',', '.', false),
+        };
+        Self {
+            symbol,
+            digits,
+            thousands,
+            decimal,
+            symbol_after,
+        }
+    }
+
+    pub fn from_env() -> Self {
+        let currency = std::env::var("BASE_CURRENCY").unwrap_or_else(|_| "USD".into());
+        let locale = std::env::var("LOCALE").unwrap_or_else(|_| "en-US".into());
+        Self::new(&currency, &locale)
+    }
+
+    // An empty code, as from an older backend, keeps the base currency.
+    pub fn in_currency(&self, currency: &str) -> Self {
+        if currency.trim().is_empty() {
+            return self.clone();
+        }
+        let (symbol, digits) = currency_style(currency);
+        Self {
+            symbol,
+            digits,
+            ..self.clone()
+        }
+    }
+
+    pub fn format(&self, amount: f64) -> String {
+        self.render(amount, false)
+    }
+
+    // Always carries a sign, for net figures such as "+$12.00" or "-$3.50".
+    pub fn format_signed(&self, amount: f64) -> String {
+        self.render(amount, true)
+    }
+
+    fn render(&self, amount: f64, always_sign: bool) -> String {
+        let digits = self.digits;
+        let fixed = format!("{:.digits$}", amount.abs());
+        let (whole, minor) = fixed.split_once('.').unwrap_or((&fixed, ""));
+        let mut grouped = String::new();
+        for (idx, digit) in whole.chars().enumerate() {
+            if idx > 0 && (whole.len() - idx) % 3 == 0 {
+                grouped.push(self.thousands);
+            }
+            grouped.push(digit);
+        }
+        let rounds_to_zero = fixed.chars().all(|c| c == '0' || c == '.');
+        let sign = if amount < 0.0 && !rounds_to_zero {
+            "-"
+        } else if always_sign {
+            "+"
+        } else {
+            ""
+        };
+        let number = if minor.is_empty() {
+            grouped
+        } else {
+            format!("{grouped}{}{minor}", self.decimal)
+        };
+        if self.symbol_after {
+            format!("{sign}{number} {}", self.symbol)
+        } else if self.symbol.chars().count() > 1 {
+            format!("{sign}{} {number}", self.symbol)
+        } else {
+            format!("{sign}{}{number}", self.symbol)
+        }
+    }
+}
+
+// (symbol, decimal places) for an ISO 4217 code; codes without a symbol show themselves.
+fn currency_style(currency: &str) -> (String, usize) {
+    let currency = currency.trim().to_ascii_uppercase();
+    let digits = match currency.as_str() {
+        "JPY" | "KRW" | "VND" | "CLP" | "ISK" | "UGX" | "PYG" | "XAF" | "XOF" => 0,
+        "BHD" | "KWD" | "OMR" | "JOD" | "TND" | "LYD" | "IQD" => 3,
+        _ => 2,
+    };
+    let symbol = match currency.as_str() {
+        "USD" | "CAD" | "AUD" => "$".to_string(),
+        "EUR" => "€".to_string(),
+        "GBP" => "£".to_string(),
+        "JPY" => "¥".to_string(),
+        _ => currency,
+    };
+    (symbol, digits)
+}
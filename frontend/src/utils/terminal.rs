@@ -2,7 +2,7 @@ use std::io::{Stdout, stdout};
 
 use anyhow::Result;
 use crossterm::ExecutableCommand;
-use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
 use crossterm::terminal::{
     EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode,
 };
@@ -13,8 +13,14 @@ use tokio::sync::mpsc;
 use tokio::time::{Duration, sleep};
 use tokio_tungstenite::connect_async;
 
-use super::api::{create_account, delete_account, delete_transaction, refresh, submit_transaction};
-use super::app::{ActiveField, App, Mode};
+use super::api::{
+    api_key, apply_template, create_account, delete_account, delete_transaction, export_csv,
+    go_offline, import_csv, load_balance_history, load_more_transactions, move_transaction,
+    reconnect, refresh_accounts, refresh_transactions, reorder_accounts, save_splits,
+    set_reconciled, set_tags, submit_transaction,
+};
+use super::app::{ActiveField, App, HISTORY_WINDOWS, Mode, QuickEntry, SplitDraft, View};
+use super::model::{AlertMessage, CreateSplit, DirectionKind};
 use super::ui::ui;
 
 pub fn setup_terminal() -> Result<Terminal<CrosstermBackend<Stdout>>> {
@@ -36,18 +42,57 @@ pub async fn run_app(
     app: &mut App,
 ) -> Result<()> {
     let (ws_tx, mut ws_rx) = mpsc::unbounded_channel();
-    let events_url = format!(
+    let mut events_url = format!(
         "{}/events",
-        app.backend_url
+        app.api_url
             .replace("http://", "ws://")
             .replace("https://", "wss://")
     );
+    // Websocket upgrades can't rely on custom headers, so the key rides in the query string.
+    if let Some(key) = api_key()
+        && let Ok(url) = reqwest::Url::parse_with_params(&events_url, [("api_key", key)])
+    {
+        events_url = url.to_string();
+    }
     tokio::spawn(start_event_listener(events_url, ws_tx));
 
     loop {
-        while ws_rx.try_recv().is_ok() {
-            refresh(app).await?;
+        // A transfer arrives as both "transactions" and "accounts"; refresh each table once.
+        let (mut accounts_stale, mut transactions_stale, mut all_stale) = (false, false, false);
+        while let Ok(message) = ws_rx.try_recv() {
+            match message.as_str() {
+                "accounts" => accounts_stale = true,
+                "transactions" => transactions_stale = true,
+                // Sent by the listener on every (re)connect; events may have been missed.
+                "connected" => all_stale = true,
+                _ => match serde_json::from_str::<AlertMessage>(&message) {
+                    Ok(AlertMessage { alert }) => {
+                        app.status = format!(
+                            "Alert: {} of {} exceeds the {} threshold",
+                            alert.direction.label(),
+                            app.money.format(alert.amount),
+                            app.money.format(alert.threshold)
+                        );
+                    }
+                    Err(_) => all_stale = true,
+                },
+            }
         }
+        if all_stale {
+            reconnect(app).await;
+        } else if app.offline.is_none() {
+            let mut result = Ok(());
+            if accounts_stale {
+                result = refresh_accounts(app).await;
+            }
+            if transactions_stale && result.is_ok() {
+                result = refresh_transactions(app).await;
+            }
+            if let Err(err) = result {
+                go_offline(app, &err);
+            }
+        }
+        load_balance_history(app).await;
 
         terminal.draw(|f| ui(f, app))?;
 
@@ -59,14 +104,42 @@ pub async fn run_app(
             if key.kind == KeyEventKind::Release {
                 continue;
             }
-            match app.mode {
-                Mode::Normal => handle_normal_mode(key.code, app)?,
-                Mode::Input => handle_transaction_mode(key.code, app).await?,
-                Mode::Transfer => handle_transfer_mode(key.code, app).await?,
-                Mode::AddAccount => handle_add_account_mode(key.code, app).await?,
-                Mode::DeleteAccount => handle_delete_account_mode(key.code, app).await?,
-                Mode::DeleteTransaction => handle_delete_transaction_mode(key.code, app).await?,
+            // Show the request as pending before the handler blocks on it.
+            app.in_flight = in_flight_label(app, key);
+            if app.in_flight.is_some() {
+                terminal.draw(|f| ui(f, app))?;
+            }
+            let result = match app.mode {
+                Mode::Normal => handle_normal_mode(key.code, app).await,
+                Mode::Input => handle_transaction_mode(key.code, app).await,
+                Mode::Transfer => handle_transfer_mode(key.code, app).await,
+                Mode::AddAccount => handle_add_account_mode(key.code, app).await,
+                Mode::DeleteAccount => handle_delete_account_mode(key.code, app).await,
+                Mode::DeleteTransaction => handle_delete_transaction_mode(key.code, app).await,
+                Mode::Templates => handle_templates_mode(key.code, app).await,
+                Mode::ReorderAccounts => handle_reorder_accounts_mode(key, app).await,
+                Mode::JumpAccount => handle_jump_account_mode(key.code, app),
+                Mode::Command => handle_command_mode(key.code, app).await,
+                Mode::Splits => handle_splits_mode(key.code, app).await,
+                Mode::Categorize => handle_categorize_mode(key.code, app).await,
+                Mode::MoveTransaction => handle_move_transaction_mode(key.code, app).await,
+                Mode::Tag => handle_tag_mode(key.code, app).await,
+                Mode::Import => handle_import_mode(key.code, app).await,
+                Mode::ImportReport => handle_import_report_mode(key.code, app),
             };
+            if let Err(err) = result {
+                // Losing the backend mid-request shouldn't take the TUI down with it.
+                if err.downcast_ref::<reqwest::Error>().is_none() {
+                    return Err(err);
+                }
+                go_offline(app, &err);
+            }
+            if app.in_flight.take().is_some() {
+                // Drop keys typed while waiting so an impatient second Enter can't resubmit.
+                while event::poll(Duration::ZERO)? {
+                    event::read()?;
+                }
+            }
             if app.mode == Mode::Normal && matches!(key.code, KeyCode::Char('q')) {
                 break;
             }
@@ -75,23 +148,75 @@ pub async fn run_app(
     Ok(())
 }
 
-fn handle_normal_mode(code: KeyCode, app: &mut App) -> Result<()> {
+// Keys whose handlers wait on the backend, with what to show meanwhile.
+fn in_flight_label(app: &App, key: KeyEvent) -> Option<&'static str> {
+    if app.offline.is_some() {
+        return (key.code == KeyCode::Char('r')).then_some("Connecting...");
+    }
+    match (&app.mode, key.code) {
+        (Mode::Normal, KeyCode::Char('E')) => Some("Exporting..."),
+        (Mode::Normal, KeyCode::Char('m')) => Some("Loading..."),
+        (Mode::Normal, KeyCode::Char('r')) => Some("Saving..."),
+        (Mode::Import, KeyCode::Enter) => Some("Importing..."),
+        (Mode::DeleteAccount | Mode::DeleteTransaction, KeyCode::Enter) => Some("Deleting..."),
+        (Mode::Templates, KeyCode::Enter) => Some("Applying template..."),
+        (
+            Mode::Input
+            | Mode::Transfer
+            | Mode::AddAccount
+            | Mode::Command
+            | Mode::Splits
+            | Mode::Categorize
+            | Mode::MoveTransaction
+            | Mode::Tag,
+            KeyCode::Enter,
+        ) => Some("Saving..."),
+        (Mode::ReorderAccounts, KeyCode::Up | KeyCode::Down)
+            if key.modifiers.contains(KeyModifiers::SHIFT) =>
+        {
+            Some("Saving order...")
+        }
+        _ => None,
+    }
+}
+
+async fn handle_normal_mode(code: KeyCode, app: &mut App) -> Result<()> {
     match code {
         KeyCode::Char('q') => {}
-        KeyCode::Up => {
-            if !app.transactions.is_empty() {
-                app.selected_txn_idx =
-                    (app.selected_txn_idx + app.transactions.len() - 1) % app.transactions.len();
-            }
+        KeyCode::Char('r') if app.offline.is_some() => reconnect(app).await,
+        _ if app.offline.is_some() => {
+            app.set_error("Offline — press r to retry, q to quit");
         }
-        KeyCode::Down => {
-            if !app.transactions.is_empty() {
-                app.selected_txn_idx = (app.selected_txn_idx + 1) % app.transactions.len();
-            }
+        KeyCode::Char(
+            'a' | 't' | ':' | 'n' | 'I' | 'r' | 'o' | 'x' | 'e' | 's' | 'c' | 'M' | 'T' | 'p' | 'd',
+        ) if app.read_only => {
+            app.set_error("Server is read-only; changes are disabled");
+        }
+        KeyCode::Up if !app.transactions.is_empty() => {
+            app.selected_txn_idx =
+                (app.selected_txn_idx + app.transactions.len() - 1) % app.transactions.len();
+        }
+        // Scrolling past the last loaded row pulls in the next page before wrapping around.
+        KeyCode::Down
+            if app.selected_txn_idx + 1 == app.transactions.len()
+                && app.has_more_transactions() =>
+        {
+            load_more_transactions(app).await?;
+            app.selected_txn_idx = (app.selected_txn_idx + 1).min(app.transactions.len() - 1);
+        }
+        KeyCode::Down if !app.transactions.is_empty() => {
+            app.selected_txn_idx = (app.selected_txn_idx + 1) % app.transactions.len();
+        }
+        KeyCode::Char('m') => load_more_transactions(app).await?,
+        // Transactions need an account; the empty-state hint points at 'n'.
+        KeyCode::Char('a') | KeyCode::Char('t') | KeyCode::Char(':') if app.accounts.is_empty() => {
+            app.set_error("Create an account first: press n");
         }
         KeyCode::Char('a') => {
             app.mode = Mode::Input;
             app.input = Default::default();
+            app.input.account_idx = app.selected_account_idx;
+            app.input.direction = app.default_direction(app.input.account_idx);
             app.status =
                 "Add transaction: amount/description, Tab switches fields, Enter to submit".into();
             app.editing_txn_id = None;
@@ -99,9 +224,18 @@ fn handle_normal_mode(code: KeyCode, app: &mut App) -> Result<()> {
         KeyCode::Char('t') => {
             app.mode = Mode::Transfer;
             app.input = Default::default();
+            app.input.account_idx = app.selected_account_idx;
             app.input.direction = super::model::DirectionKind::Transfer;
-            app.status = "Transfer: left/right source, up/down destination, amount then Enter"
-                .into();
+            app.status =
+                "Transfer: left/right source, up/down destination, amount then Enter".into();
+            app.editing_txn_id = None;
+        }
+        KeyCode::Char(':') => {
+            app.mode = Mode::Command;
+            app.input = Default::default();
+            app.status =
+                "Quick entry: [expense|income|refund] amount category [description], Enter submits"
+                    .into();
             app.editing_txn_id = None;
         }
         KeyCode::Char('n') => {
@@ -110,6 +244,52 @@ fn handle_normal_mode(code: KeyCode, app: &mut App) -> Result<()> {
             app.input.active_field = ActiveField::AccountName;
             app.status = "New account: type name, Tab to change type, Enter to save".into();
         }
+        KeyCode::Char('E') => export_csv(app).await?,
+        KeyCode::Char('I') => {
+            app.mode = Mode::Import;
+            app.input = Default::default();
+            app.status = "Import CSV: type the file path, Enter imports, Esc cancels".into();
+        }
+        KeyCode::Char('Y') => match app.transactions.get(app.selected_txn_idx) {
+            Some(txn) => {
+                let id = txn.id.clone();
+                // Headless sessions have no clipboard; show the id so it can still be copied.
+                app.status = match app.copy_to_clipboard(&id) {
+                    Ok(()) => format!("Copied transaction id {id}"),
+                    Err(_) => format!("No clipboard available; transaction id: {id}"),
+                };
+            }
+            None => app.status = "No transaction selected to copy".into(),
+        },
+        KeyCode::Char('r') => match app.transactions.get(app.selected_txn_idx) {
+            Some(txn) => {
+                let (id, reconciled) = (txn.id.clone(), txn.reconciled);
+                set_reconciled(app, &id, !reconciled).await?;
+            }
+            None => app.status = "No transaction selected to reconcile".into(),
+        },
+        KeyCode::Char('g') => {
+            if app.accounts.is_empty() {
+                app.status = "No accounts to jump to".into();
+            } else {
+                app.mode = Mode::JumpAccount;
+                app.input = Default::default();
+                app.status =
+                    "Jump to account: type to filter, Up/Down to choose, Enter selects, Esc cancels"
+                        .into();
+            }
+        }
+        KeyCode::Char('o') => {
+            if app.accounts.is_empty() {
+                app.status = "No accounts to reorder".into();
+            } else {
+                app.mode = Mode::ReorderAccounts;
+                app.input = Default::default();
+                app.status =
+                    "Reorder accounts: Up/Down to choose, Shift+Up/Down to move, Esc when done"
+                        .into();
+            }
+        }
         KeyCode::Char('x') => {
             app.mode = Mode::DeleteAccount;
             app.status = "Delete account: left/right to pick (defaults locked), Enter to delete, Esc to cancel".into();
@@ -122,11 +302,13 @@ fn handle_normal_mode(code: KeyCode, app: &mut App) -> Result<()> {
                 if let Some(idx) = app.accounts.iter().position(|a| a.id == txn.account_id) {
                     app.input.account_idx = idx;
                 }
-                if let Some(split) = txn.splits.first() {
-                    if let Some(idx) = app.categories.iter().position(|c| c.id == split.category_id)
-                    {
-                        app.input.category_idx = idx;
-                    }
+                if let Some(split) = txn.splits.first()
+                    && let Some(idx) = app
+                        .categories
+                        .iter()
+                        .position(|c| c.id == split.category_id)
+                {
+                    app.input.category_idx = idx;
                 }
                 app.input.direction = txn.direction.clone();
                 app.input.amount = format!("{}", txn.amount);
@@ -148,6 +330,148 @@ fn handle_normal_mode(code: KeyCode, app: &mut App) -> Result<()> {
                 app.status = "No transaction selected to edit".into();
             }
         }
+        KeyCode::Char('s') => match app.transactions.get(app.selected_txn_idx).cloned() {
+            None => app.status = "No transaction selected".into(),
+            Some(txn) if matches!(txn.direction, DirectionKind::Transfer) => {
+                app.status = "Transfers have no splits".into();
+            }
+            Some(_) if app.categories.is_empty() => {
+                app.set_error("Create a category before splitting");
+            }
+            Some(txn) => {
+                let mut drafts: Vec<SplitDraft> = txn
+                    .splits
+                    .iter()
+                    .map(|s| SplitDraft {
+                        category_idx: app
+                            .categories
+                            .iter()
+                            .position(|c| c.id == s.category_id)
+                            .unwrap_or(0),
+                        amount: s.amount.to_string(),
+                    })
+                    .collect();
+                // Uncategorized entries start as one split for the full amount.
+                if drafts.is_empty() {
+                    drafts.push(SplitDraft {
+                        category_idx: 0,
+                        amount: txn.amount.to_string(),
+                    });
+                }
+                app.split_drafts = drafts;
+                app.editing_txn_id = Some(txn.id);
+                app.input = Default::default();
+                app.mode = Mode::Splits;
+                app.status =
+                    "Splits: Up/Down choose, Left/Right category, type amount, + add, - remove, Enter saves"
+                        .into();
+            }
+        },
+        KeyCode::Char('c') => match app.transactions.get(app.selected_txn_idx) {
+            None => app.status = "No transaction selected".into(),
+            Some(txn) if matches!(txn.direction, DirectionKind::Transfer) => {
+                app.status = "Transfers have no category".into();
+            }
+            Some(txn) if txn.splits.len() > 1 => {
+                app.set_error("Transaction is split across categories; press s to edit its splits");
+            }
+            Some(_) if app.categories.is_empty() => {
+                app.set_error("Create a category before categorizing");
+            }
+            Some(txn) => {
+                app.editing_txn_id = Some(txn.id.clone());
+                app.input = Default::default();
+                app.mode = Mode::Categorize;
+                app.status =
+                    "Categorize: type to filter, Up/Down to choose, Enter assigns, Esc cancels"
+                        .into();
+            }
+        },
+        KeyCode::Char('T') => match app.transactions.get(app.selected_txn_idx) {
+            None => app.status = "No transaction selected".into(),
+            Some(txn) => {
+                app.editing_txn_id = Some(txn.id.clone());
+                app.input = Default::default();
+                app.mode = Mode::Tag;
+                app.status =
+                    "Tags: type to filter or name a new tag, Up/Down to choose, Enter adds/removes, Esc closes"
+                        .into();
+            }
+        },
+        KeyCode::Char('M') => match app.transactions.get(app.selected_txn_idx) {
+            None => app.status = "No transaction selected".into(),
+            Some(_) if app.accounts.len() < 2 => {
+                app.status = "No other account to move to".into();
+            }
+            Some(txn) => {
+                let current = app
+                    .accounts
+                    .iter()
+                    .position(|a| a.id == txn.account_id)
+                    .unwrap_or(0);
+                app.editing_txn_id = Some(txn.id.clone());
+                app.input = Default::default();
+                // Start on the next account so Enter right away is never a no-op.
+                app.input.account_idx = (current + 1) % app.accounts.len();
+                app.mode = Mode::MoveTransaction;
+                app.status =
+                    "Move transaction: Left/Right to choose account, Enter moves, Esc cancels"
+                        .into();
+            }
+        },
+        KeyCode::Char('p') => {
+            if app.templates.is_empty() {
+                app.status = "No templates saved".into();
+            } else {
+                app.mode = Mode::Templates;
+                app.input = Default::default();
+                app.status =
+                    "Templates: Up/Down to choose, type an amount to override, Enter applies, Esc cancels"
+                        .into();
+            }
+        }
+        // 'v' predates Tab and is kept as an alias.
+        KeyCode::Tab | KeyCode::Char('v') => {
+            app.view = app.view.next();
+            app.status = format!("View: {}", app.view.name());
+        }
+        KeyCode::Char(c @ '1'..='9') => {
+            let idx = c as usize - '1' as usize;
+            if let Some(view) = View::ALL.get(idx) {
+                app.view = *view;
+                app.status = format!("View: {}", app.view.name());
+            }
+        }
+        // The account view shows the selected account; Left/Right step through them.
+        KeyCode::Left | KeyCode::Right if app.view == View::Account && !app.accounts.is_empty() => {
+            let len = app.accounts.len();
+            app.selected_account_idx = if code == KeyCode::Left {
+                (app.selected_account_idx + len - 1) % len
+            } else {
+                (app.selected_account_idx + 1) % len
+            };
+        }
+        KeyCode::Char('[') | KeyCode::Char(']') if app.view == View::Account => {
+            let current = HISTORY_WINDOWS
+                .iter()
+                .position(|days| *days == app.history_days)
+                .unwrap_or(2);
+            let next = if code == KeyCode::Char('[') {
+                current.saturating_sub(1)
+            } else {
+                (current + 1).min(HISTORY_WINDOWS.len() - 1)
+            };
+            app.history_days = HISTORY_WINDOWS[next];
+            app.status = format!("Balance history: last {} days", app.history_days);
+        }
+        KeyCode::Char('z') => {
+            app.hide_zero_accounts = !app.hide_zero_accounts;
+            app.status = if app.hide_zero_accounts {
+                "Hiding zero-balance accounts".into()
+            } else {
+                "Showing all accounts".into()
+            };
+        }
         KeyCode::Char('d') => {
             if app.transactions.is_empty() {
                 app.status = "No transaction to delete".into();
@@ -162,32 +486,30 @@ fn handle_normal_mode(code: KeyCode, app: &mut App) -> Result<()> {
     Ok(())
 }
 
-async fn start_event_listener(url: String, tx: mpsc::UnboundedSender<()>) {
+async fn start_event_listener(url: String, tx: mpsc::UnboundedSender<String>) {
     loop {
-        match connect_async(&url).await {
-            Ok((stream, _)) => {
-                let (mut write, mut read) = stream.split();
-                // Send a ping to keep the connection alive on some servers.
-                let _ = write
-                    .send(tokio_tungstenite::tungstenite::Message::Ping(vec![]))
-                    .await;
-
-                while let Some(msg) = read.next().await {
-                    match msg {
-                        Ok(tokio_tungstenite::tungstenite::Message::Text(_)) => {
-                            let _ = tx.send(());
-                        }
-                        Ok(tokio_tungstenite::tungstenite::Message::Ping(data)) => {
-                            let _ = write
-                                .send(tokio_tungstenite::tungstenite::Message::Pong(data))
-                                .await;
-                        }
-                        Ok(_) => {}
-                        Err(_) => break,
+        if let Ok((stream, _)) = connect_async(&url).await {
+            let _ = tx.send("connected".into());
+            let (mut write, mut read) = stream.split();
+            // Send a ping to keep the connection alive on some servers.
+            let _ = write
+                .send(tokio_tungstenite::tungstenite::Message::Ping(vec![]))
+                .await;
+
+            while let Some(msg) = read.next().await {
+                match msg {
+                    Ok(tokio_tungstenite::tungstenite::Message::Text(text)) => {
+                        let _ = tx.send(text);
                     }
+                    Ok(tokio_tungstenite::tungstenite::Message::Ping(data)) => {
+                        let _ = write
+                            .send(tokio_tungstenite::tungstenite::Message::Pong(data))
+                            .await;
+                    }
+                    Ok(_) => {}
+                    Err(_) => break,
                 }
             }
-            Err(_) => {}
         }
         sleep(Duration::from_secs(1)).await;
     }
@@ -206,32 +528,30 @@ pub async fn handle_transaction_mode(code: KeyCode, app: &mut App) -> Result<()>
                 _ => ActiveField::Amount,
             };
         }
-        KeyCode::Left => {
-            if !app.accounts.is_empty() {
-                app.input.account_idx =
-                    (app.input.account_idx + app.accounts.len() - 1) % app.accounts.len();
-            }
-        }
-        KeyCode::Right => {
-            if !app.accounts.is_empty() {
-                app.input.account_idx = (app.input.account_idx + 1) % app.accounts.len();
+        KeyCode::Left | KeyCode::Right if !app.accounts.is_empty() => {
+            let len = app.accounts.len();
+            app.input.account_idx = if code == KeyCode::Left {
+                (app.input.account_idx + len - 1) % len
+            } else {
+                (app.input.account_idx + 1) % len
+            };
+            // A new entry follows the account's usual direction; an edit keeps its own.
+            if app.editing_txn_id.is_none() {
+                app.input.direction = app.default_direction(app.input.account_idx);
             }
         }
-        KeyCode::Up => {
-            if !app.categories.is_empty() {
-                app.input.category_idx =
-                    (app.input.category_idx + app.categories.len() - 1) % app.categories.len();
-            }
+        KeyCode::Up if !app.categories.is_empty() => {
+            app.input.category_idx =
+                (app.input.category_idx + app.categories.len() - 1) % app.categories.len();
         }
-        KeyCode::Down => {
-            if !app.categories.is_empty() {
-                app.input.category_idx = (app.input.category_idx + 1) % app.categories.len();
-            }
+        KeyCode::Down if !app.categories.is_empty() => {
+            app.input.category_idx = (app.input.category_idx + 1) % app.categories.len();
         }
         KeyCode::Char('d') => {
             use super::model::DirectionKind;
             app.input.direction = match app.input.direction {
                 DirectionKind::Expense => DirectionKind::Income,
+                DirectionKind::Income => DirectionKind::Refund,
                 _ => DirectionKind::Expense,
             };
         }
@@ -247,13 +567,17 @@ pub async fn handle_transaction_mode(code: KeyCode, app: &mut App) -> Result<()>
             }
             _ => {}
         },
+        KeyCode::Char(c)
+            if app.input.active_field == ActiveField::Amount && QUICK_AMOUNT_KEYS.contains(c) =>
+        {
+            quick_amount(app, c);
+        }
         KeyCode::Char(c) => match app.input.active_field {
-            ActiveField::Amount => {
+            ActiveField::Amount
                 // Amounts are non-negative: allow digits and a single decimal point.
-                if c.is_ascii_digit() || (c == '.' && !app.input.amount.contains('.')) {
+                if (c.is_ascii_digit() || (c == '.' && !app.input.amount.contains('.'))) => {
                     app.input.amount.push(c);
                 }
-            }
             ActiveField::Description => {
                 app.input.description.push(c);
             }
@@ -264,6 +588,27 @@ pub async fn handle_transaction_mode(code: KeyCode, app: &mut App) -> Result<()>
     Ok(())
 }
 
+// '+'/'-' step by 1, '>'/'<' by 10, '*' appends "00".
+const QUICK_AMOUNT_KEYS: &str = "+-<>*";
+
+fn quick_amount(app: &mut App, key: char) {
+    let ok = match key {
+        '+' => app.input.adjust_amount(1.0),
+        '-' => app.input.adjust_amount(-1.0),
+        '>' => app.input.adjust_amount(10.0),
+        '<' => app.input.adjust_amount(-10.0),
+        _ => {
+            if !app.input.amount.is_empty() && !app.input.amount.contains('.') {
+                app.input.amount.push_str("00");
+            }
+            true
+        }
+    };
+    if !ok {
+        app.set_error(format!("Invalid amount: {}", app.input.amount));
+    }
+}
+
 pub async fn handle_transfer_mode(code: KeyCode, app: &mut App) -> Result<()> {
     match code {
         KeyCode::Esc => {
@@ -277,27 +622,19 @@ pub async fn handle_transfer_mode(code: KeyCode, app: &mut App) -> Result<()> {
                 _ => ActiveField::Amount,
             };
         }
-        KeyCode::Left => {
-            if !app.accounts.is_empty() {
-                app.input.account_idx =
-                    (app.input.account_idx + app.accounts.len() - 1) % app.accounts.len();
-            }
+        KeyCode::Left if !app.accounts.is_empty() => {
+            app.input.account_idx =
+                (app.input.account_idx + app.accounts.len() - 1) % app.accounts.len();
         }
-        KeyCode::Right => {
-            if !app.accounts.is_empty() {
-                app.input.account_idx = (app.input.account_idx + 1) % app.accounts.len();
-            }
+        KeyCode::Right if !app.accounts.is_empty() => {
+            app.input.account_idx = (app.input.account_idx + 1) % app.accounts.len();
         }
-        KeyCode::Up => {
-            if !app.accounts.is_empty() {
-                app.input.to_account_idx =
-                    (app.input.to_account_idx + app.accounts.len() - 1) % app.accounts.len();
-            }
+        KeyCode::Up if !app.accounts.is_empty() => {
+            app.input.to_account_idx =
+                (app.input.to_account_idx + app.accounts.len() - 1) % app.accounts.len();
         }
-        KeyCode::Down => {
-            if !app.accounts.is_empty() {
-                app.input.to_account_idx = (app.input.to_account_idx + 1) % app.accounts.len();
-            }
+        KeyCode::Down if !app.accounts.is_empty() => {
+            app.input.to_account_idx = (app.input.to_account_idx + 1) % app.accounts.len();
         }
         KeyCode::Enter => {
             submit_transaction(app).await?;
@@ -311,11 +648,16 @@ pub async fn handle_transfer_mode(code: KeyCode, app: &mut App) -> Result<()> {
             }
             _ => {}
         },
+        KeyCode::Char(c)
+            if app.input.active_field == ActiveField::Amount && QUICK_AMOUNT_KEYS.contains(c) =>
+        {
+            quick_amount(app, c);
+        }
         KeyCode::Char(c) => match app.input.active_field {
-            ActiveField::Amount => {
-                if c.is_ascii_digit() || (c == '.' && !app.input.amount.contains('.')) {
-                    app.input.amount.push(c);
-                }
+            ActiveField::Amount
+                if (c.is_ascii_digit() || (c == '.' && !app.input.amount.contains('.'))) =>
+            {
+                app.input.amount.push(c);
             }
             ActiveField::Description => {
                 app.input.description.push(c);
@@ -340,17 +682,13 @@ pub async fn handle_add_account_mode(code: KeyCode, app: &mut App) -> Result<()>
                 _ => ActiveField::AccountName,
             };
         }
-        KeyCode::Left | KeyCode::Up => {
-            if app.input.active_field == ActiveField::AccountKind {
-                app.input.new_account_kind_idx =
-                    (app.input.new_account_kind_idx + ACCOUNT_KINDS.len() - 1) % ACCOUNT_KINDS.len();
-            }
+        KeyCode::Left | KeyCode::Up if app.input.active_field == ActiveField::AccountKind => {
+            app.input.new_account_kind_idx =
+                (app.input.new_account_kind_idx + ACCOUNT_KINDS.len() - 1) % ACCOUNT_KINDS.len();
         }
-        KeyCode::Right | KeyCode::Down => {
-            if app.input.active_field == ActiveField::AccountKind {
-                app.input.new_account_kind_idx =
-                    (app.input.new_account_kind_idx + 1) % ACCOUNT_KINDS.len();
-            }
+        KeyCode::Right | KeyCode::Down if app.input.active_field == ActiveField::AccountKind => {
+            app.input.new_account_kind_idx =
+                (app.input.new_account_kind_idx + 1) % ACCOUNT_KINDS.len();
         }
         KeyCode::Enter => {
             let kind = ACCOUNT_KINDS[app.input.new_account_kind_idx];
@@ -360,15 +698,11 @@ pub async fn handle_add_account_mode(code: KeyCode, app: &mut App) -> Result<()>
                 app.mode = Mode::Normal;
             }
         }
-        KeyCode::Backspace => {
-            if app.input.active_field == ActiveField::AccountName {
-                app.input.new_account_name.pop();
-            }
+        KeyCode::Backspace if app.input.active_field == ActiveField::AccountName => {
+            app.input.new_account_name.pop();
         }
-        KeyCode::Char(c) => {
-            if app.input.active_field == ActiveField::AccountName {
-                app.input.new_account_name.push(c);
-            }
+        KeyCode::Char(c) if app.input.active_field == ActiveField::AccountName => {
+            app.input.new_account_name.push(c);
         }
         _ => {}
     }
@@ -381,16 +715,12 @@ pub async fn handle_delete_account_mode(code: KeyCode, app: &mut App) -> Result<
             app.mode = Mode::Normal;
             app.status = "Cancelled".into();
         }
-        KeyCode::Left => {
-            if !app.accounts.is_empty() {
-                app.input.account_idx =
-                    (app.input.account_idx + app.accounts.len() - 1) % app.accounts.len();
-            }
+        KeyCode::Left if !app.accounts.is_empty() => {
+            app.input.account_idx =
+                (app.input.account_idx + app.accounts.len() - 1) % app.accounts.len();
         }
-        KeyCode::Right => {
-            if !app.accounts.is_empty() {
-                app.input.account_idx = (app.input.account_idx + 1) % app.accounts.len();
-            }
+        KeyCode::Right if !app.accounts.is_empty() => {
+            app.input.account_idx = (app.input.account_idx + 1) % app.accounts.len();
         }
         KeyCode::Enter => {
             if let Some(account) = app.accounts.get(app.input.account_idx) {
@@ -406,23 +736,462 @@ pub async fn handle_delete_account_mode(code: KeyCode, app: &mut App) -> Result<
     Ok(())
 }
 
-pub async fn handle_delete_transaction_mode(code: KeyCode, app: &mut App) -> Result<()> {
+pub async fn handle_tag_mode(code: KeyCode, app: &mut App) -> Result<()> {
+    let suggestions = app.tag_suggestions();
     match code {
         KeyCode::Esc => {
             app.mode = Mode::Normal;
+            app.editing_txn_id = None;
+            app.status = "Done tagging".into();
+        }
+        KeyCode::Up if !suggestions.is_empty() => {
+            app.input.tag_idx = (app.input.tag_idx + suggestions.len() - 1) % suggestions.len();
+        }
+        KeyCode::Down if !suggestions.is_empty() => {
+            app.input.tag_idx = (app.input.tag_idx + 1) % suggestions.len();
+        }
+        KeyCode::Enter => {
+            let Some(name) = suggestions.get(app.input.tag_idx) else {
+                app.status = "Type a tag name".into();
+                return Ok(());
+            };
+            let Some(txn) = app
+                .editing_txn_id
+                .as_ref()
+                .and_then(|id| app.transactions.iter().find(|t| t.id == *id))
+            else {
+                app.mode = Mode::Normal;
+                app.editing_txn_id = None;
+                app.set_error("Transaction is no longer loaded");
+                return Ok(());
+            };
+            let mut tags = txn.tags.clone();
+            let done = if let Some(pos) = tags.iter().position(|t| t.eq_ignore_ascii_case(name)) {
+                tags.remove(pos);
+                format!("Removed tag {name}")
+            } else {
+                tags.push(name.clone());
+                if app.is_new_tag(name) {
+                    format!("Created tag {name} and added it")
+                } else {
+                    format!("Tagged {name}")
+                }
+            };
+            let txn_id = txn.id.clone();
+            set_tags(app, &txn_id, tags, done).await?;
+        }
+        KeyCode::Backspace => {
+            app.input.tag_query.pop();
+            app.input.tag_idx = 0;
+        }
+        KeyCode::Char(c) => {
+            app.input.tag_query.push(c);
+            app.input.tag_idx = 0;
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+pub async fn handle_move_transaction_mode(code: KeyCode, app: &mut App) -> Result<()> {
+    match code {
+        KeyCode::Esc => {
+            app.mode = Mode::Normal;
+            app.editing_txn_id = None;
             app.status = "Cancelled".into();
         }
-        KeyCode::Up => {
-            if !app.transactions.is_empty() {
-                app.selected_txn_idx =
-                    (app.selected_txn_idx + app.transactions.len() - 1) % app.transactions.len();
+        KeyCode::Left if !app.accounts.is_empty() => {
+            app.input.account_idx =
+                (app.input.account_idx + app.accounts.len() - 1) % app.accounts.len();
+        }
+        KeyCode::Right if !app.accounts.is_empty() => {
+            app.input.account_idx = (app.input.account_idx + 1) % app.accounts.len();
+        }
+        KeyCode::Enter => {
+            let txn = app
+                .editing_txn_id
+                .as_ref()
+                .and_then(|id| app.transactions.iter().find(|t| t.id == *id));
+            let (Some(txn), Some(target)) = (txn, app.accounts.get(app.input.account_idx)) else {
+                app.mode = Mode::Normal;
+                app.editing_txn_id = None;
+                app.set_error("Transaction is no longer loaded");
+                return Ok(());
+            };
+            if txn.account_id == target.id {
+                app.set_error(format!("Transaction is already in {}", target.name));
+            } else if txn.to_account_id.as_deref() == Some(target.id.as_str()) {
+                app.set_error(format!("{} is this transfer's destination", target.name));
+            } else {
+                let (txn_id, target_id) = (txn.id.clone(), target.id.clone());
+                move_transaction(app, &txn_id, &target_id).await?;
             }
         }
-        KeyCode::Down => {
-            if !app.transactions.is_empty() {
-                app.selected_txn_idx = (app.selected_txn_idx + 1) % app.transactions.len();
+        _ => {}
+    }
+    Ok(())
+}
+
+// `input.account_idx` indexes into the filtered matches while the switcher is open.
+pub fn handle_jump_account_mode(code: KeyCode, app: &mut App) -> Result<()> {
+    let matches = app.matching_accounts();
+    match code {
+        KeyCode::Esc => {
+            app.mode = Mode::Normal;
+            app.status = "Cancelled".into();
+        }
+        KeyCode::Up if !matches.is_empty() => {
+            app.input.account_idx = (app.input.account_idx + matches.len() - 1) % matches.len();
+        }
+        KeyCode::Down if !matches.is_empty() => {
+            app.input.account_idx = (app.input.account_idx + 1) % matches.len();
+        }
+        KeyCode::Enter => match matches.get(app.input.account_idx) {
+            Some(&idx) => {
+                app.selected_account_idx = idx;
+                app.mode = Mode::Normal;
+                app.status = format!("Selected account {}", app.accounts[idx].name);
+            }
+            None => app.status = "No account matches".into(),
+        },
+        KeyCode::Backspace => {
+            app.input.account_query.pop();
+            app.input.account_idx = 0;
+        }
+        KeyCode::Char(c) => {
+            app.input.account_query.push(c);
+            app.input.account_idx = 0;
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+pub async fn handle_categorize_mode(code: KeyCode, app: &mut App) -> Result<()> {
+    let matches = app.matching_categories(&app.input.category_query);
+    match code {
+        KeyCode::Esc => {
+            app.mode = Mode::Normal;
+            app.editing_txn_id = None;
+            app.status = "Cancelled".into();
+        }
+        KeyCode::Up if !matches.is_empty() => {
+            app.input.category_idx = (app.input.category_idx + matches.len() - 1) % matches.len();
+        }
+        KeyCode::Down if !matches.is_empty() => {
+            app.input.category_idx = (app.input.category_idx + 1) % matches.len();
+        }
+        KeyCode::Enter => {
+            let Some(&idx) = matches.get(app.input.category_idx) else {
+                app.status = "No category matches".into();
+                return Ok(());
+            };
+            let Some(txn) = app
+                .editing_txn_id
+                .as_ref()
+                .and_then(|id| app.transactions.iter().find(|t| t.id == *id))
+            else {
+                app.mode = Mode::Normal;
+                app.editing_txn_id = None;
+                app.set_error("Transaction is no longer loaded");
+                return Ok(());
+            };
+            let (txn_id, amount) = (txn.id.clone(), txn.amount);
+            let category = app.categories[idx].clone();
+            let split = CreateSplit {
+                category_id: category.id,
+                amount,
+            };
+            save_splits(app, &txn_id, vec![split]).await?;
+            if app.mode == Mode::Normal {
+                app.status = format!("Categorized as {}", category.name);
+            }
+        }
+        KeyCode::Backspace => {
+            app.input.category_query.pop();
+            app.input.category_idx = 0;
+        }
+        KeyCode::Char(c) => {
+            app.input.category_query.push(c);
+            app.input.category_idx = 0;
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+pub async fn handle_import_mode(code: KeyCode, app: &mut App) -> Result<()> {
+    match code {
+        KeyCode::Esc => {
+            app.mode = Mode::Normal;
+            app.status = "Cancelled".into();
+        }
+        KeyCode::Enter => {
+            let path = app.input.import_path.clone();
+            import_csv(app, &path).await?;
+        }
+        KeyCode::Backspace => {
+            app.input.import_path.pop();
+        }
+        KeyCode::Char(c) => app.input.import_path.push(c),
+        _ => {}
+    }
+    Ok(())
+}
+
+pub fn handle_import_report_mode(code: KeyCode, app: &mut App) -> Result<()> {
+    let last_line = app
+        .import_report
+        .as_ref()
+        .map_or(0, |report| report.errors.len() as u16);
+    match code {
+        KeyCode::Esc | KeyCode::Enter => {
+            app.mode = Mode::Normal;
+            app.import_report = None;
+        }
+        KeyCode::Up => app.import_scroll = app.import_scroll.saturating_sub(1),
+        KeyCode::Down => app.import_scroll = (app.import_scroll + 1).min(last_line),
+        KeyCode::PageUp => app.import_scroll = app.import_scroll.saturating_sub(10),
+        KeyCode::PageDown => app.import_scroll = (app.import_scroll + 10).min(last_line),
+        _ => {}
+    }
+    Ok(())
+}
+
+// Command-bar lines go to the selected account through the regular add-transaction path.
+pub async fn handle_command_mode(code: KeyCode, app: &mut App) -> Result<()> {
+    match code {
+        KeyCode::Esc => {
+            app.mode = Mode::Normal;
+            app.status = "Cancelled".into();
+        }
+        KeyCode::Char(c @ '1'..='9') if !app.input.command_choices.is_empty() => {
+            let pick = c as usize - '1' as usize;
+            if let Some(&category_idx) = app.input.command_choices.get(pick) {
+                submit_command(app, category_idx).await?;
             }
         }
+        KeyCode::Enter => {
+            let entry = match QuickEntry::parse(&app.input.command) {
+                Ok(entry) => entry,
+                Err(message) => {
+                    app.input.command_error = Some(message);
+                    return Ok(());
+                }
+            };
+            let matches = app.matching_categories(&entry.category);
+            match matches.as_slice() {
+                [] => {
+                    app.input.command_error =
+                        Some(format!("No category matches \"{}\"", entry.category));
+                }
+                [category_idx] => submit_command(app, *category_idx).await?,
+                _ => {
+                    app.input.command_error = None;
+                    app.input.command_choices = matches.into_iter().take(9).collect();
+                    app.status = format!(
+                        "\"{}\" matches several categories: press 1-{} to pick one",
+                        entry.category,
+                        app.input.command_choices.len()
+                    );
+                }
+            }
+        }
+        KeyCode::Backspace => {
+            app.input.command.pop();
+            app.input.command_error = None;
+            app.input.command_choices.clear();
+        }
+        KeyCode::Char(c) => {
+            app.input.command.push(c);
+            app.input.command_error = None;
+            app.input.command_choices.clear();
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+async fn submit_command(app: &mut App, category_idx: usize) -> Result<()> {
+    let entry = match QuickEntry::parse(&app.input.command) {
+        Ok(entry) => entry,
+        Err(message) => {
+            app.input.command_error = Some(message);
+            return Ok(());
+        }
+    };
+    app.input.account_idx = app.selected_account_idx;
+    app.input.category_idx = category_idx;
+    app.input.direction = entry.direction;
+    app.input.amount = entry.amount.to_string();
+    app.input.description = entry.description.unwrap_or_default();
+    app.input.command_choices.clear();
+    submit_transaction(app).await
+}
+
+pub async fn handle_splits_mode(code: KeyCode, app: &mut App) -> Result<()> {
+    let len = app.split_drafts.len();
+    let categories = app.categories.len();
+    let idx = app.input.split_idx.min(len.saturating_sub(1));
+    match code {
+        KeyCode::Esc => {
+            app.mode = Mode::Normal;
+            app.editing_txn_id = None;
+            app.split_drafts.clear();
+            app.status = "Cancelled".into();
+        }
+        KeyCode::Up if len > 0 => app.input.split_idx = (idx + len - 1) % len,
+        KeyCode::Down if len > 0 => app.input.split_idx = (idx + 1) % len,
+        KeyCode::Left | KeyCode::Right if len > 0 && categories > 0 => {
+            let draft = &mut app.split_drafts[idx];
+            draft.category_idx = if code == KeyCode::Left {
+                (draft.category_idx + categories - 1) % categories
+            } else {
+                (draft.category_idx + 1) % categories
+            };
+        }
+        // New splits take whatever is still unallocated.
+        KeyCode::Char('+') => {
+            let remaining = app.split_total() - app.split_allocated();
+            let category_idx = app
+                .split_drafts
+                .last()
+                .map(|d| (d.category_idx + 1) % categories.max(1))
+                .unwrap_or(0);
+            app.split_drafts.push(SplitDraft {
+                category_idx,
+                amount: if remaining > 0.0 {
+                    format!("{remaining:.2}")
+                } else {
+                    String::new()
+                },
+            });
+            app.input.split_idx = app.split_drafts.len() - 1;
+        }
+        KeyCode::Char('-') | KeyCode::Delete => {
+            if len > 1 {
+                app.split_drafts.remove(idx);
+                app.input.split_idx = idx.min(len - 2);
+            } else {
+                app.set_error("A transaction keeps at least one split");
+            }
+        }
+        KeyCode::Backspace if len > 0 => {
+            app.split_drafts[idx].amount.pop();
+        }
+        KeyCode::Char(c)
+            if len > 0
+                && (c.is_ascii_digit()
+                    || (c == '.' && !app.split_drafts[idx].amount.contains('.'))) =>
+        {
+            app.split_drafts[idx].amount.push(c);
+        }
+        KeyCode::Enter => {
+            let Some(txn_id) = app.editing_txn_id.clone() else {
+                app.mode = Mode::Normal;
+                return Ok(());
+            };
+            let mut splits = Vec::with_capacity(len);
+            for (pos, draft) in app.split_drafts.iter().enumerate() {
+                let Ok(amount) = draft.amount.parse::<f64>() else {
+                    app.set_error(format!("Invalid amount in split {}", pos + 1));
+                    return Ok(());
+                };
+                let Some(category) = app.categories.get(draft.category_idx) else {
+                    app.set_error(format!("Split {} has no category", pos + 1));
+                    return Ok(());
+                };
+                if splits
+                    .iter()
+                    .any(|s: &CreateSplit| s.category_id == category.id)
+                {
+                    app.set_error(format!("{} is used by more than one split", category.name));
+                    return Ok(());
+                }
+                splits.push(CreateSplit {
+                    category_id: category.id.clone(),
+                    amount,
+                });
+            }
+            let (allocated, total) = (app.split_allocated(), app.split_total());
+            if (allocated - total).abs() > 0.005 {
+                app.set_error(format!(
+                    "Splits add up to {} but the transaction is {}",
+                    app.split_money().format(allocated),
+                    app.split_money().format(total)
+                ));
+                return Ok(());
+            }
+            save_splits(app, &txn_id, splits).await?;
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+// Takes the whole key event because moving an account needs the Shift modifier.
+pub async fn handle_reorder_accounts_mode(key: KeyEvent, app: &mut App) -> Result<()> {
+    let len = app.accounts.len();
+    let shift = key.modifiers.contains(KeyModifiers::SHIFT);
+    match key.code {
+        KeyCode::Esc | KeyCode::Enter => {
+            app.mode = Mode::Normal;
+            app.status = "Done reordering".into();
+        }
+        KeyCode::Up | KeyCode::Down if len == 0 => {}
+        KeyCode::Up if shift => move_account(app, -1).await?,
+        KeyCode::Down if shift => move_account(app, 1).await?,
+        KeyCode::Up => {
+            app.input.account_idx = (app.input.account_idx + len - 1) % len;
+        }
+        KeyCode::Down => {
+            app.input.account_idx = (app.input.account_idx + 1) % len;
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+async fn move_account(app: &mut App, offset: isize) -> Result<()> {
+    let from = app.input.account_idx;
+    let Some(to) = from
+        .checked_add_signed(offset)
+        .filter(|idx| *idx < app.accounts.len())
+    else {
+        app.status = if offset < 0 {
+            "Account is already at the top".into()
+        } else {
+            "Account is already at the bottom".into()
+        };
+        return Ok(());
+    };
+
+    let moved_id = app.accounts[from].id.clone();
+    let mut ids: Vec<String> = app.accounts.iter().map(|a| a.id.clone()).collect();
+    ids.swap(from, to);
+    reorder_accounts(app, &ids).await?;
+    // Follow the moved account even if the refreshed list differs from our local guess.
+    app.input.account_idx = app
+        .accounts
+        .iter()
+        .position(|a| a.id == moved_id)
+        .unwrap_or(to);
+    Ok(())
+}
+
+pub async fn handle_delete_transaction_mode(code: KeyCode, app: &mut App) -> Result<()> {
+    match code {
+        KeyCode::Esc => {
+            app.mode = Mode::Normal;
+            app.status = "Cancelled".into();
+        }
+        KeyCode::Up if !app.transactions.is_empty() => {
+            app.selected_txn_idx =
+                (app.selected_txn_idx + app.transactions.len() - 1) % app.transactions.len();
+        }
+        KeyCode::Down if !app.transactions.is_empty() => {
+            app.selected_txn_idx = (app.selected_txn_idx + 1) % app.transactions.len();
+        }
         KeyCode::Enter => {
             if let Some(txn) = app.transactions.get(app.selected_txn_idx) {
                 let id = txn.id.clone();
@@ -439,3 +1208,48 @@ pub async fn handle_delete_transaction_mode(code: KeyCode, app: &mut App) -> Res
     }
     Ok(())
 }
+
+pub async fn handle_templates_mode(code: KeyCode, app: &mut App) -> Result<()> {
+    match code {
+        KeyCode::Esc => {
+            app.mode = Mode::Normal;
+            app.status = "Cancelled".into();
+        }
+        KeyCode::Up if !app.templates.is_empty() => {
+            app.selected_template_idx =
+                (app.selected_template_idx + app.templates.len() - 1) % app.templates.len();
+        }
+        KeyCode::Down if !app.templates.is_empty() => {
+            app.selected_template_idx = (app.selected_template_idx + 1) % app.templates.len();
+        }
+        KeyCode::Enter => {
+            let amount = if app.input.amount.is_empty() {
+                None
+            } else {
+                match app.input.amount.parse::<f64>() {
+                    Ok(value) => Some(value),
+                    Err(_) => {
+                        app.set_error("Invalid amount");
+                        return Ok(());
+                    }
+                }
+            };
+            if let Some(template) = app.templates.get(app.selected_template_idx) {
+                let id = template.id.clone();
+                apply_template(app, &id, amount).await?;
+                app.mode = Mode::Normal;
+                app.input = Default::default();
+            } else {
+                app.status = "No template selected".into();
+            }
+        }
+        KeyCode::Backspace => {
+            app.input.amount.pop();
+        }
+        KeyCode::Char(c) if c.is_ascii_digit() || (c == '.' && !app.input.amount.contains('.')) => {
+            app.input.amount.push(c);
+        }
+        _ => {}
+    }
+    Ok(())
+}
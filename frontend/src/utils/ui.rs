@@ -1,17 +1,20 @@
-use ratatui::layout::{Constraint, Direction, Layout};
+use ratatui::layout::{Alignment, Constraint, Direction, Layout};
 use ratatui::style::{Color, Modifier, Style};
 use ratatui::text::{Line, Span};
-use ratatui::widgets::{Block, Borders, Cell, Paragraph, Row, Table};
+use ratatui::widgets::{
+    Block, Borders, Cell, Clear, Gauge, Paragraph, Row, Sparkline, Table, Wrap,
+};
 
-use super::app::{ActiveField, App, Mode};
+use super::app::{ActiveField, App, Mode, View};
 use super::model::{Account, Category, DirectionKind, Transaction};
+use super::money::MoneyFormat;
 
 pub fn ui(f: &mut ratatui::Frame, app: &mut App) {
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints(
             [
-                Constraint::Length(3),
+                Constraint::Length(5),
                 Constraint::Min(10),
                 Constraint::Length(7),
             ]
@@ -19,8 +22,60 @@ pub fn ui(f: &mut ratatui::Frame, app: &mut App) {
         )
         .split(f.area());
 
-    let status = Paragraph::new(app.status.clone())
-        .block(Block::default().borders(Borders::ALL).title("Status"));
+    let today_net = app.today_net();
+    let today_style = if today_net > 0.0 {
+        Style::default().fg(Color::Green)
+    } else if today_net < 0.0 {
+        Style::default().fg(Color::Red)
+    } else {
+        Style::default()
+    };
+    let status = Paragraph::new(vec![
+        Line::raw(app.status.clone()),
+        Line::from(vec![
+            Span::raw(format!(
+                "Net worth: {} | Today: ",
+                app.money.format(app.net_worth())
+            )),
+            Span::styled(app.money.format_signed(today_net), today_style),
+            Span::raw(
+                app.accounts
+                    .get(app.selected_account_idx)
+                    .map(|a| {
+                        format!(
+                            " | {}: {}{} unreconciled",
+                            a.name,
+                            app.unreconciled_count(&a.id),
+                            if app.has_more_transactions() { "+" } else { "" }
+                        )
+                    })
+                    .unwrap_or_default(),
+            ),
+        ]),
+        // Errors get their own line; a pending request takes it over.
+        match (app.in_flight, app.active_error()) {
+            (Some(label), _) => Line::styled(
+                label,
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            (None, _) if app.offline.is_some() => Line::styled(
+                format!("Offline: {}", app.offline.as_deref().unwrap_or_default()),
+                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            ),
+            (None, Some(error)) => Line::styled(
+                error.to_string(),
+                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            ),
+            (None, None) => Line::raw(""),
+        },
+    ])
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(format!("Status | {} view", app.view.name())),
+    );
     f.render_widget(status, chunks[0]);
 
     let main_chunks = Layout::default()
@@ -28,40 +83,196 @@ pub fn ui(f: &mut ratatui::Frame, app: &mut App) {
         .constraints([Constraint::Percentage(30), Constraint::Percentage(70)].as_ref())
         .split(chunks[1]);
 
-    render_accounts(f, main_chunks[0], &app.accounts);
-    render_transactions(
+    let selected_account_idx = match app.mode {
+        Mode::ReorderAccounts => Some(app.input.account_idx),
+        Mode::JumpAccount => app.matching_accounts().get(app.input.account_idx).copied(),
+        _ => Some(app.selected_account_idx),
+    };
+    let selected_account = selected_account_idx
+        .and_then(|idx| app.accounts.get(idx))
+        .map(|a| a.id.as_str());
+    render_accounts(
         f,
-        main_chunks[1],
-        &app.transactions,
-        &app.categories,
+        main_chunks[0],
         &app.accounts,
-        app.selected_txn_idx,
+        app.hide_zero_accounts,
+        selected_account,
+        &app.money,
     );
+    match app.view {
+        View::Transactions => {
+            let [table_area, detail_area] = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Min(5), Constraint::Length(3)])
+                .areas(main_chunks[1]);
+            render_transactions(
+                f,
+                table_area,
+                &app.transactions,
+                &app.categories,
+                &app.accounts,
+                app.selected_txn_idx,
+                &app.money,
+            );
+            render_transaction_detail(f, detail_area, app);
+        }
+        View::Dashboard => render_dashboard(f, main_chunks[1], app),
+        View::Account => render_account_view(f, main_chunks[1], app),
+    }
 
     render_input(f, chunks[2], app);
+    if app.mode == Mode::ImportReport {
+        render_import_report(f, app);
+    }
+}
+
+// Popup over the middle of the screen listing each rejected row by its line in the file.
+fn render_import_report(f: &mut ratatui::Frame, app: &App) {
+    let Some(report) = &app.import_report else {
+        return;
+    };
+    let [_, middle, _] = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage(20),
+            Constraint::Percentage(60),
+            Constraint::Percentage(20),
+        ])
+        .areas(f.area());
+    let [_, area, _] = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage(15),
+            Constraint::Percentage(70),
+            Constraint::Percentage(15),
+        ])
+        .areas(middle);
+
+    let mut lines = vec![
+        Line::raw(format!(
+            "Imported {} | skipped {} already imported | {} row(s) failed",
+            report.inserted,
+            report.skipped,
+            report.errors.len()
+        )),
+        Line::raw(""),
+    ];
+    if report.errors.is_empty() {
+        lines.push(Line::styled(
+            "Every row was imported or skipped.",
+            Style::default().fg(Color::Green),
+        ));
+    }
+    for error in &report.errors {
+        lines.push(Line::from(vec![
+            Span::styled(
+                format!("line {}: ", error.line),
+                Style::default().add_modifier(Modifier::BOLD),
+            ),
+            Span::styled(error.message.clone(), Style::default().fg(Color::Red)),
+        ]));
+    }
+
+    let popup = Paragraph::new(lines)
+        .wrap(Wrap { trim: false })
+        .scroll((app.import_scroll, 0))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Import summary | Up/Down/PgUp/PgDn scroll, Esc closes"),
+        );
+    f.render_widget(Clear, area);
+    f.render_widget(popup, area);
 }
 
-fn render_accounts(f: &mut ratatui::Frame, area: ratatui::layout::Rect, accounts: &[Account]) {
-    let rows: Vec<Row> = accounts
+fn render_accounts(
+    f: &mut ratatui::Frame,
+    area: ratatui::layout::Rect,
+    accounts: &[Account],
+    hide_zero: bool,
+    selected_id: Option<&str>,
+    money: &MoneyFormat,
+) {
+    if accounts.is_empty() {
+        render_empty_state(
+            f,
+            area,
+            "Accounts",
+            "No accounts yet — press n to create one",
+        );
+        return;
+    }
+    // Filtering is display-only; pickers keep indexing into the full account list.
+    let visible: Vec<&Account> = accounts
+        .iter()
+        .filter(|a| !hide_zero || a.balance != 0.0)
+        .collect();
+    let hidden = accounts.len() - visible.len();
+    let title = if hide_zero {
+        format!("Accounts ({} zero-balance hidden)", hidden)
+    } else {
+        "Accounts".to_string()
+    };
+    let rows: Vec<Row> = visible
         .iter()
         .map(|a| {
+            // Unset or unrecognised colors fall back to the terminal's default foreground.
+            let name_style = a
+                .color
+                .as_deref()
+                .and_then(|c| c.parse::<Color>().ok())
+                .map(|c| Style::default().fg(c))
+                .unwrap_or_default();
+            let money = money.in_currency(&a.currency);
+            let count = a
+                .transaction_count
+                .map(|n| n.to_string())
+                .unwrap_or_else(|| "-".into());
+            let month_net = match a.month_net {
+                Some(net) if net > 0.0 => {
+                    Cell::from(money.format_signed(net)).style(Style::default().fg(Color::Green))
+                }
+                Some(net) if net < 0.0 => {
+                    Cell::from(money.format_signed(net)).style(Style::default().fg(Color::Red))
+                }
+                Some(_) => {
+                    Cell::from(money.format(0.0)).style(Style::default().fg(Color::DarkGray))
+                }
+                None => Cell::from("-"),
+            };
             Row::new(vec![
-                Cell::from(a.name.clone()),
+                Cell::from(a.name.clone()).style(name_style),
                 Cell::from(a.kind.clone()),
-                Cell::from(format!("{:.2}", a.balance)),
+                Cell::from(count),
+                month_net,
+                match a.owed() {
+                    Some(owed) if owed > 0.0 => Cell::from(format!("{} owed", money.format(owed)))
+                        .style(Style::default().fg(Color::Red)),
+                    _ => Cell::from(money.format(a.balance)),
+                },
             ])
+            .style(if selected_id == Some(a.id.as_str()) {
+                Style::default().fg(Color::Cyan)
+            } else {
+                Style::default()
+            })
         })
         .collect();
     let table = Table::new(
         rows,
         [
-            Constraint::Percentage(40),
-            Constraint::Percentage(30),
-            Constraint::Percentage(30),
+            Constraint::Percentage(28),
+            Constraint::Percentage(14),
+            Constraint::Percentage(10),
+            Constraint::Percentage(22),
+            Constraint::Percentage(26),
         ],
     )
-    .block(Block::default().title("Accounts").borders(Borders::ALL))
-    .header(Row::new(vec!["Name", "Type", "Balance"]).style(Style::default().fg(Color::Yellow)))
+    .block(Block::default().title(title).borders(Borders::ALL))
+    .header(
+        Row::new(vec!["Name", "Type", "Txns", "This month", "Balance"])
+            .style(Style::default().fg(Color::Yellow)),
+    )
     .column_spacing(1);
     f.render_widget(table, area);
 }
@@ -73,48 +284,80 @@ fn render_transactions(
     categories: &[Category],
     accounts: &[Account],
     selected_idx: usize,
+    money: &MoneyFormat,
 ) {
+    if txns.is_empty() {
+        let hint = if accounts.is_empty() {
+            "No transactions yet — create an account first (press n)"
+        } else {
+            "No transactions yet — press a to add one or t for a transfer"
+        };
+        render_empty_state(f, area, "Transactions", hint);
+        return;
+    }
     let rows: Vec<Row> = txns
         .iter()
         .enumerate()
         .map(|(idx, t)| {
-            let account = accounts
-                .iter()
-                .find(|a| a.id == t.account_id)
+            let source = accounts.iter().find(|a| a.id == t.account_id);
+            let account = source
                 .map(|a| a.name.clone())
                 .unwrap_or_else(|| "unknown".into());
+            let source_money = source
+                .map(|a| money.in_currency(&a.currency))
+                .unwrap_or_else(|| money.clone());
+            // Outflow in Amount, inflow next to the "To" name.
             let to_account = t
                 .to_account_id
                 .as_ref()
                 .and_then(|id| accounts.iter().find(|a| a.id == *id))
-                .map(|a| a.name.clone())
+                .map(|a| match t.direction {
+                    DirectionKind::Transfer => {
+                        format!(
+                            "{} {}",
+                            a.name,
+                            money
+                                .in_currency(&a.currency)
+                                .format_signed(t.to_amount.unwrap_or(t.amount))
+                        )
+                    }
+                    _ => a.name.clone(),
+                })
                 .unwrap_or_else(|| "-".into());
-            let category = t
+            // Entries still waiting for a category stand out; transfers never get one.
+            let category = match t
                 .splits
                 .first()
                 .and_then(|s| categories.iter().find(|c| c.id == s.category_id))
-                .map(|c| c.name.clone())
-                .unwrap_or_else(|| "-".into());
+            {
+                Some(c) => Cell::from(c.name.clone()),
+                None if matches!(t.direction, DirectionKind::Transfer) => Cell::from("-"),
+                None => Cell::from("-").style(
+                    Style::default()
+                        .fg(Color::Magenta)
+                        .add_modifier(Modifier::BOLD),
+                ),
+            };
             let signed_amount = match t.direction {
-                DirectionKind::Income => t.amount,
+                DirectionKind::Income | DirectionKind::Refund => t.amount,
                 DirectionKind::Expense => -t.amount,
-                DirectionKind::Transfer => t.amount,
+                DirectionKind::Transfer => -t.amount,
             };
             Row::new(vec![
+                Cell::from(if t.reconciled { "✓" } else { "" }),
                 Cell::from(account),
-                Cell::from(format!("{:+.2}", signed_amount)),
-                Cell::from(match t.direction {
-                    DirectionKind::Income => "income",
-                    DirectionKind::Expense => "expense",
-                    DirectionKind::Transfer => "transfer",
-                }),
+                Cell::from(source_money.format_signed(signed_amount)),
+                Cell::from(t.direction.label()),
                 Cell::from(to_account),
-                Cell::from(category),
+                category,
                 Cell::from(t.description.clone().unwrap_or_else(|| "".into())),
                 Cell::from(t.occurred_at.clone()),
             ])
+            // Reconciled rows are settled, so they recede behind the ones still to check.
             .style(if idx == selected_idx {
                 Style::default().fg(Color::Cyan)
+            } else if t.reconciled {
+                Style::default().fg(Color::DarkGray)
             } else {
                 Style::default()
             })
@@ -124,6 +367,7 @@ fn render_transactions(
     let table = Table::new(
         rows,
         [
+            Constraint::Length(1),
             Constraint::Percentage(14),
             Constraint::Percentage(10),
             Constraint::Percentage(10),
@@ -136,6 +380,7 @@ fn render_transactions(
     .block(Block::default().title("Transactions").borders(Borders::ALL))
     .header(
         Row::new(vec![
+            "",
             "Account",
             "Amount",
             "Dir",
@@ -155,6 +400,261 @@ fn render_transactions(
     f.render_widget(table, area);
 }
 
+// Tags of the selected transaction as chips under the table.
+fn render_transaction_detail(f: &mut ratatui::Frame, area: ratatui::layout::Rect, app: &App) {
+    let line = match app.transactions.get(app.selected_txn_idx) {
+        None => Line::raw(""),
+        Some(txn) if txn.tags.is_empty() => Line::styled(
+            "No tags — press T to add one",
+            Style::default().fg(Color::DarkGray),
+        ),
+        Some(txn) => Line::from(
+            txn.tags
+                .iter()
+                .flat_map(|name| [tag_chip(name), Span::raw(" ")])
+                .collect::<Vec<_>>(),
+        ),
+    };
+    let paragraph =
+        Paragraph::new(line).block(Block::default().title("Details").borders(Borders::ALL));
+    f.render_widget(paragraph, area);
+}
+
+// Each tag keeps the same color wherever it is shown, picked from its name.
+fn tag_chip(name: &str) -> Span<'static> {
+    const COLORS: [Color; 6] = [
+        Color::Blue,
+        Color::Green,
+        Color::Magenta,
+        Color::Cyan,
+        Color::Yellow,
+        Color::Red,
+    ];
+    let hash = name.to_lowercase().bytes().fold(0usize, |acc, b| {
+        acc.wrapping_mul(31).wrapping_add(b as usize)
+    });
+    Span::styled(
+        format!(" {name} "),
+        Style::default()
+            .fg(Color::Black)
+            .bg(COLORS[hash % COLORS.len()]),
+    )
+}
+
+// Shown in place of an empty table so a fresh database tells the user what to do next.
+fn render_empty_state(
+    f: &mut ratatui::Frame,
+    area: ratatui::layout::Rect,
+    title: &str,
+    hint: &str,
+) {
+    let paragraph = Paragraph::new(vec![Line::raw(""), Line::raw(hint.to_string())])
+        .style(Style::default().fg(Color::DarkGray))
+        .alignment(Alignment::Center)
+        .wrap(Wrap { trim: true })
+        .block(
+            Block::default()
+                .title(title.to_string())
+                .borders(Borders::ALL),
+        );
+    f.render_widget(paragraph, area);
+}
+
+fn render_dashboard(f: &mut ratatui::Frame, area: ratatui::layout::Rect, app: &App) {
+    let (summary, source) = match &app.summary {
+        Some(summary) => (summary.clone(), "Dashboard"),
+        None => (app.local_summary(), "Dashboard (local)"),
+    };
+    let block = Block::default().title(source).borders(Borders::ALL);
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let sections = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(
+            [
+                Constraint::Length(5),
+                Constraint::Length(3),
+                Constraint::Min(3),
+            ]
+            .as_ref(),
+        )
+        .split(inner);
+
+    let net_style = if summary.month_net < 0.0 {
+        Style::default().fg(Color::Red)
+    } else {
+        Style::default().fg(Color::Green)
+    };
+    let headline = Paragraph::new(vec![
+        Line::from(vec![
+            Span::raw("Net worth: "),
+            Span::styled(
+                app.money.format(summary.net_worth),
+                Style::default().add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(format!(
+                " across {} accounts (debt {})",
+                summary.account_count,
+                app.money.format(app.debt())
+            )),
+        ]),
+        Line::raw(""),
+        Line::raw(format!(
+            "This month: income {} | expense {}",
+            app.money.format(summary.month_income),
+            app.money.format(summary.month_expense)
+        )),
+        Line::from(vec![
+            Span::raw("This month net: "),
+            Span::styled(app.money.format_signed(summary.month_net), net_style),
+        ]),
+    ]);
+    f.render_widget(headline, sections[0]);
+
+    let ratio = if summary.month_income > 0.0 {
+        (summary.month_expense / summary.month_income).clamp(0.0, 1.0)
+    } else if summary.month_expense > 0.0 {
+        1.0
+    } else {
+        0.0
+    };
+    let gauge = Gauge::default()
+        .block(
+            Block::default()
+                .title("Spent of income")
+                .borders(Borders::ALL),
+        )
+        .gauge_style(Style::default().fg(if ratio >= 1.0 {
+            Color::Red
+        } else {
+            Color::Yellow
+        }))
+        .ratio(ratio);
+    f.render_widget(gauge, sections[1]);
+
+    let mut lines = vec![Line::styled(
+        "Top spending categories this month",
+        Style::default()
+            .fg(Color::Yellow)
+            .add_modifier(Modifier::BOLD),
+    )];
+    if summary.top_categories.is_empty() {
+        lines.push(Line::raw("No expenses recorded this month"));
+    }
+    for (rank, category) in summary.top_categories.iter().enumerate() {
+        lines.push(Line::raw(format!(
+            "{}. {} {}",
+            rank + 1,
+            category.name,
+            app.money.format(category.total)
+        )));
+    }
+    f.render_widget(Paragraph::new(lines), sections[2]);
+}
+
+// The selected account's figures and a sparkline of its end-of-day balance.
+fn render_account_view(f: &mut ratatui::Frame, area: ratatui::layout::Rect, app: &App) {
+    let Some(account) = app.accounts.get(app.selected_account_idx) else {
+        render_empty_state(
+            f,
+            area,
+            "Account",
+            "No accounts yet — press n to create one",
+        );
+        return;
+    };
+    let money = app.money.in_currency(&account.currency);
+    let block = Block::default()
+        .title(format!("Account: {} (Left/Right to switch)", account.name))
+        .borders(Borders::ALL);
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let sections = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(4), Constraint::Min(3)].as_ref())
+        .split(inner);
+
+    let month_net = account.month_net.unwrap_or(0.0);
+    let net_style = if month_net < 0.0 {
+        Style::default().fg(Color::Red)
+    } else {
+        Style::default().fg(Color::Green)
+    };
+    let headline = Paragraph::new(vec![
+        Line::from(vec![
+            Span::raw("Balance: "),
+            Span::styled(
+                money.format(account.balance),
+                Style::default().add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(format!(" ({})", account.kind)),
+        ]),
+        Line::raw(format!(
+            "Transactions: {}",
+            account.transaction_count.unwrap_or(0)
+        )),
+        Line::from(vec![
+            Span::raw("This month net: "),
+            Span::styled(money.format_signed(month_net), net_style),
+        ]),
+    ]);
+    f.render_widget(headline, sections[0]);
+
+    // Only show a history that belongs to this account; another may still be loading.
+    let points = app
+        .balance_history
+        .as_ref()
+        .filter(|h| h.account_id == account.id)
+        .map(|h| h.points.as_slice())
+        .unwrap_or_default();
+    // A window longer than the pane is wide keeps its most recent days.
+    let width = sections[1].width.saturating_sub(2) as usize;
+    let points = &points[points.len().saturating_sub(width)..];
+    let (Some(first), Some(last)) = (points.first(), points.last()) else {
+        let hint = format!("No balance history for the last {} days", app.history_days);
+        f.render_widget(
+            Paragraph::new(hint).block(Block::default().borders(Borders::ALL)),
+            sections[1],
+        );
+        return;
+    };
+    let low = points
+        .iter()
+        .map(|p| p.balance)
+        .fold(f64::INFINITY, f64::min);
+    let high = points
+        .iter()
+        .map(|p| p.balance)
+        .fold(f64::NEG_INFINITY, f64::max);
+    // Sparkline bars can't go below zero, so heights are measured up from the lowest day.
+    let data: Vec<u64> = points
+        .iter()
+        .map(|p| ((p.balance - low) * 100.0).round() as u64)
+        .collect();
+    let sparkline = Sparkline::default()
+        .block(
+            Block::default()
+                .title(format!(
+                    "Balance {} to {} ([ ] {} days): low {}, high {}",
+                    first.date,
+                    last.date,
+                    app.history_days,
+                    money.format(low),
+                    money.format(high)
+                ))
+                .borders(Borders::ALL),
+        )
+        .data(&data)
+        .style(Style::default().fg(if last.balance < first.balance {
+            Color::Red
+        } else {
+            Color::Green
+        }));
+    f.render_widget(sparkline, sections[1]);
+}
+
 fn render_input(f: &mut ratatui::Frame, area: ratatui::layout::Rect, app: &App) {
     let mode_label = if app.editing_txn_id.is_some() {
         match app.mode {
@@ -163,6 +663,16 @@ fn render_input(f: &mut ratatui::Frame, area: ratatui::layout::Rect, app: &App)
             Mode::AddAccount => "New Account",
             Mode::DeleteAccount => "Delete Account",
             Mode::DeleteTransaction => "Delete Txn",
+            Mode::Templates => "Templates",
+            Mode::ReorderAccounts => "Reorder Accounts",
+            Mode::JumpAccount => "Jump to Account",
+            Mode::Command => "Quick Entry",
+            Mode::Splits => "Edit Splits",
+            Mode::Categorize => "Categorize",
+            Mode::MoveTransaction => "Move Txn",
+            Mode::Tag => "Tags",
+            Mode::Import => "Import CSV",
+            Mode::ImportReport => "Import Summary",
             Mode::Normal => "Normal",
         }
     } else {
@@ -173,13 +683,25 @@ fn render_input(f: &mut ratatui::Frame, area: ratatui::layout::Rect, app: &App)
             Mode::AddAccount => "New Account",
             Mode::DeleteAccount => "Delete Account",
             Mode::DeleteTransaction => "Delete Txn",
+            Mode::Templates => "Templates",
+            Mode::ReorderAccounts => "Reorder Accounts",
+            Mode::JumpAccount => "Jump to Account",
+            Mode::Command => "Quick Entry",
+            Mode::Splits => "Edit Splits",
+            Mode::Categorize => "Categorize",
+            Mode::MoveTransaction => "Move Txn",
+            Mode::Tag => "Tags",
+            Mode::Import => "Import CSV",
+            Mode::ImportReport => "Import Summary",
         }
     };
 
     let mut lines = vec![Line::from(vec![
         Span::raw("Mode: "),
         Span::styled(mode_label, Style::default().fg(Color::Cyan)),
-        Span::raw(" | q quit | a add | t transfer | n new acct | x delete acct | e edit txn | d delete txn | arrows choose txn"),
+        Span::raw(
+            " | q quit | a add | t transfer | : quick entry | n new acct | g jump acct | o reorder accts | x delete acct | e edit txn | s splits | c categorize | M move txn | T tags | Y copy txn id | r reconcile txn | d delete txn | p templates | E export csv | I import csv | Tab/1-3 views | [ ] history window | z hide zero accts | m more txns | arrows choose txn",
+        ),
     ])];
 
     if app.mode == Mode::Input {
@@ -224,7 +746,9 @@ fn render_input(f: &mut ratatui::Frame, area: ratatui::layout::Rect, app: &App)
                 format!("Description: {}", app.input.description),
                 desc_style,
             ),
-            Span::raw(" | Tab switches fields | Enter to submit, Esc to cancel"),
+            Span::raw(
+                " | Tab switches fields | +/- adjust by 1, </> by 10, * appends 00 | Enter to submit, Esc to cancel",
+            ),
         ]));
     } else if app.mode == Mode::Transfer {
         let from_name = app
@@ -264,7 +788,9 @@ fn render_input(f: &mut ratatui::Frame, area: ratatui::layout::Rect, app: &App)
                 format!("Description: {}", app.input.description),
                 desc_style,
             ),
-            Span::raw(" | Tab switches fields | Enter to submit, Esc to cancel"),
+            Span::raw(
+                " | Tab switches fields | +/- adjust by 1, </> by 10, * appends 00 | Enter to submit, Esc to cancel",
+            ),
         ]));
     } else if app.mode == Mode::AddAccount {
         let name_style = if app.input.active_field == ActiveField::AccountName {
@@ -287,13 +813,12 @@ fn render_input(f: &mut ratatui::Frame, area: ratatui::layout::Rect, app: &App)
             .copied()
             .unwrap_or("checking");
         lines.push(Line::from(vec![
-            Span::styled(
-                format!("Name: {}", app.input.new_account_name),
-                name_style,
-            ),
+            Span::styled(format!("Name: {}", app.input.new_account_name), name_style),
             Span::raw(" | "),
             Span::styled(format!("Type: {}", current_kind), kind_style),
-            Span::raw(" | Tab switches fields | Up/Down change type | Enter to save, Esc to cancel"),
+            Span::raw(
+                " | Tab switches fields | Up/Down change type | Enter to save, Esc to cancel",
+            ),
         ]));
     } else if app.mode == Mode::DeleteAccount {
         let account_name = app
@@ -305,16 +830,272 @@ fn render_input(f: &mut ratatui::Frame, area: ratatui::layout::Rect, app: &App)
             "Select account to delete (defaults locked): {} (left/right, Enter confirms, Esc cancels)",
             account_name
         )));
+    } else if app.mode == Mode::MoveTransaction {
+        let from = app
+            .editing_txn_id
+            .as_ref()
+            .and_then(|id| app.transactions.iter().find(|t| t.id == *id))
+            .and_then(|t| app.accounts.iter().find(|a| a.id == t.account_id))
+            .map(|a| a.name.clone())
+            .unwrap_or_else(|| "unknown".into());
+        let to = app
+            .accounts
+            .get(app.input.account_idx)
+            .map(|a| a.name.clone())
+            .unwrap_or_else(|| "<no accounts>".into());
+        lines.push(Line::raw(format!(
+            "Move from {from} to: {to} (left/right, Enter moves, Esc cancels)"
+        )));
+    } else if app.mode == Mode::JumpAccount {
+        let matches = app.matching_accounts();
+        let names: Vec<Span> = matches
+            .iter()
+            .enumerate()
+            .map(|(pos, &idx)| {
+                let style = if pos == app.input.account_idx {
+                    Style::default()
+                        .fg(Color::Green)
+                        .add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default()
+                };
+                Span::styled(format!(" {} ", app.accounts[idx].name), style)
+            })
+            .collect();
+        lines.push(Line::raw(format!(
+            "Jump to: {} ({} match{})",
+            app.input.account_query,
+            matches.len(),
+            if matches.len() == 1 { "" } else { "es" }
+        )));
+        lines.push(Line::from(names));
+    } else if app.mode == Mode::Categorize {
+        let matches = app.matching_categories(&app.input.category_query);
+        let names: Vec<Span> = matches
+            .iter()
+            .enumerate()
+            .map(|(pos, &idx)| {
+                let style = if pos == app.input.category_idx {
+                    Style::default()
+                        .fg(Color::Green)
+                        .add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default()
+                };
+                Span::styled(format!(" {} ", app.categories[idx].name), style)
+            })
+            .collect();
+        lines.push(Line::raw(format!(
+            "Category: {} ({} match{})",
+            app.input.category_query,
+            matches.len(),
+            if matches.len() == 1 { "" } else { "es" }
+        )));
+        lines.push(Line::from(names));
+    } else if app.mode == Mode::Tag {
+        let suggestions = app.tag_suggestions();
+        let current = app
+            .editing_txn_id
+            .as_ref()
+            .and_then(|id| app.transactions.iter().find(|t| t.id == *id))
+            .map(|t| t.tags.clone())
+            .unwrap_or_default();
+        let names: Vec<Span> = suggestions
+            .iter()
+            .enumerate()
+            .map(|(pos, name)| {
+                let style = if pos == app.input.tag_idx {
+                    Style::default()
+                        .fg(Color::Green)
+                        .add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default()
+                };
+                let label = if current.iter().any(|t| t.eq_ignore_ascii_case(name)) {
+                    format!(" ✓{name} ")
+                } else if app.is_new_tag(name) {
+                    format!(" +{name} (new) ")
+                } else {
+                    format!(" {name} ")
+                };
+                Span::styled(label, style)
+            })
+            .collect();
+        lines.push(Line::raw(format!(
+            "Tag: {} (✓ on this transaction, Enter adds/removes, Esc closes)",
+            app.input.tag_query
+        )));
+        lines.push(Line::from(names));
+    } else if app.mode == Mode::Command {
+        let account_name = app
+            .accounts
+            .get(app.selected_account_idx)
+            .map(|a| a.name.clone())
+            .unwrap_or_else(|| "<no accounts>".into());
+        lines.push(Line::from(vec![
+            Span::styled(
+                format!(":{}", app.input.command),
+                Style::default()
+                    .fg(Color::Green)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(format!(
+                " | into {account_name} | Enter submits, Esc cancels"
+            )),
+        ]));
+        if let Some(message) = &app.input.command_error {
+            lines.push(Line::styled(
+                message.clone(),
+                Style::default().fg(Color::Red),
+            ));
+        } else if !app.input.command_choices.is_empty() {
+            let choices: Vec<String> = app
+                .input
+                .command_choices
+                .iter()
+                .enumerate()
+                .map(|(pos, &idx)| format!("{} {}", pos + 1, app.categories[idx].name))
+                .collect();
+            lines.push(Line::raw(format!(
+                "Pick a category: {}",
+                choices.join(" | ")
+            )));
+        }
+    } else if app.mode == Mode::Import {
+        lines.push(Line::from(vec![
+            Span::styled(
+                format!("CSV file: {}", app.input.import_path),
+                Style::default()
+                    .fg(Color::Green)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(
+                " | columns occurred_at, account, direction, amount | Enter imports, Esc cancels",
+            ),
+        ]));
+    } else if app.mode == Mode::Splits {
+        let drafts: Vec<Span> = app
+            .split_drafts
+            .iter()
+            .enumerate()
+            .map(|(pos, draft)| {
+                let style = if pos == app.input.split_idx {
+                    Style::default()
+                        .fg(Color::Green)
+                        .add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default()
+                };
+                let category = app
+                    .categories
+                    .get(draft.category_idx)
+                    .map(|c| c.name.as_str())
+                    .unwrap_or("<no category>");
+                Span::styled(format!(" {category}: {} ", draft.amount), style)
+            })
+            .collect();
+        lines.push(Line::from(drafts));
+
+        let money = app.split_money();
+        let (allocated, total) = (app.split_allocated(), app.split_total());
+        let remaining = total - allocated;
+        let (note, style) = if remaining.abs() <= 0.005 {
+            ("balanced".to_string(), Style::default().fg(Color::Green))
+        } else if remaining > 0.0 {
+            (
+                format!("{} left", money.format(remaining)),
+                Style::default().fg(Color::Yellow),
+            )
+        } else {
+            (
+                format!("over by {}", money.format(-remaining)),
+                Style::default().fg(Color::Red),
+            )
+        };
+        lines.push(Line::from(vec![
+            Span::raw(format!(
+                "Allocated {} of {} ",
+                money.format(allocated),
+                money.format(total)
+            )),
+            Span::styled(format!("({note})"), style),
+            Span::raw(
+                " | Up/Down choose, Left/Right category, + add, - remove, Enter saves, Esc cancels",
+            ),
+        ]));
+    } else if app.mode == Mode::ReorderAccounts {
+        let account_name = app
+            .accounts
+            .get(app.input.account_idx)
+            .map(|a| a.name.clone())
+            .unwrap_or_else(|| "<no accounts>".into());
+        lines.push(Line::raw(format!(
+            "Selected: {} (Up/Down chooses, Shift+Up/Down moves, Esc when done)",
+            account_name
+        )));
     } else if app.mode == Mode::DeleteTransaction {
         let txn_desc = app
             .transactions
             .get(app.selected_txn_idx)
-            .map(|t| t.description.clone().unwrap_or_else(|| format!("{:?}", t.direction)))
+            .map(|t| {
+                t.description
+                    .clone()
+                    .unwrap_or_else(|| format!("{:?}", t.direction))
+            })
             .unwrap_or_else(|| "<no transaction>".into());
         lines.push(Line::raw(format!(
             "Select transaction to delete: {} (Up/Down moves, Enter deletes, Esc cancels)",
             txn_desc
         )));
+    } else if app.mode == Mode::Templates {
+        let names: Vec<Span> = app
+            .templates
+            .iter()
+            .enumerate()
+            .map(|(idx, t)| {
+                let style = if idx == app.selected_template_idx {
+                    Style::default()
+                        .fg(Color::Green)
+                        .add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default()
+                };
+                Span::styled(format!(" {} ", t.name), style)
+            })
+            .collect();
+        let template_desc = app
+            .templates
+            .get(app.selected_template_idx)
+            .map(|t| {
+                let account = app
+                    .accounts
+                    .iter()
+                    .find(|a| a.id == t.account_id)
+                    .map(|a| a.name.as_str())
+                    .unwrap_or("unknown");
+                format!(
+                    "{} {} from {account}",
+                    app.money_for(&t.account_id).format(t.amount),
+                    t.direction.label()
+                )
+            })
+            .unwrap_or_else(|| "<no templates>".into());
+        lines.push(Line::raw(format!(
+            "Template: {} (up/down, {} of {})",
+            template_desc,
+            app.selected_template_idx + 1,
+            app.templates.len()
+        )));
+        lines.push(Line::from(names));
+        lines.push(Line::from(vec![
+            Span::styled(
+                format!("Amount override: {}", app.input.amount),
+                Style::default()
+                    .fg(Color::Green)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(" | leave empty to use the template amount | Enter applies, Esc cancels"),
+        ]));
     }
 
     let paragraph =